@@ -0,0 +1,46 @@
+/* Generated with cbindgen from src/lib.rs; regenerate with
+ * `cbindgen --config cbindgen.toml --output include/putao.h`. */
+
+#ifndef PUTAO_H
+#define PUTAO_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Opaque handle to a compiled pattern.
+ */
+typedef struct PutaoRegex PutaoRegex;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/**
+ * Compiles a NUL-terminated UTF-8 pattern; returns NULL if it is invalid.
+ */
+PutaoRegex *putao_compile(const char *pattern);
+
+/**
+ * Reports whether the pattern matches anywhere in the `len` bytes at `hay`.
+ */
+bool putao_is_match(const PutaoRegex *re, const uint8_t *hay, size_t len);
+
+/**
+ * Finds the leftmost match, storing its byte offsets in `start` and `end`.
+ *
+ * Returns false, leaving the outputs untouched, if there is no match.
+ */
+bool putao_find(const PutaoRegex *re, const uint8_t *hay, size_t len, size_t *start, size_t *end);
+
+/**
+ * Frees a pattern returned by `putao_compile`; NULL is ignored.
+ */
+void putao_free(PutaoRegex *re);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* PUTAO_H */
@@ -0,0 +1,89 @@
+//! C bindings for the putao regex engine; see `include/putao.h`.
+
+use std::ffi::{c_char, CStr};
+use std::slice;
+
+use putao_cf::bytes::Regex;
+
+/// Opaque handle to a compiled pattern.
+pub struct PutaoRegex(Regex);
+
+/// Compiles a NUL-terminated UTF-8 pattern; returns NULL if it is invalid.
+///
+/// # Safety
+///
+/// `pattern` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn putao_compile(pattern: *const c_char) -> *mut PutaoRegex {
+    if pattern.is_null() {
+        return std::ptr::null_mut();
+    }
+    match CStr::from_ptr(pattern).to_str().map(Regex::new) {
+        Ok(Ok(re)) => Box::into_raw(Box::new(PutaoRegex(re))),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Reports whether the pattern matches anywhere in the `len` bytes at `hay`.
+///
+/// # Safety
+///
+/// `re` must come from [`putao_compile`] and `hay` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn putao_is_match(re: *const PutaoRegex, hay: *const u8, len: usize) -> bool {
+    match re.as_ref() {
+        Some(re) => re.0.is_match(haystack(hay, len)),
+        None => false,
+    }
+}
+
+/// Finds the leftmost match, storing its byte offsets in `start` and `end`.
+///
+/// Returns false, leaving the outputs untouched, if there is no match.
+///
+/// # Safety
+///
+/// As for [`putao_is_match`]; `start` and `end` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn putao_find(
+    re: *const PutaoRegex,
+    hay: *const u8,
+    len: usize,
+    start: *mut usize,
+    end: *mut usize,
+) -> bool {
+    let Some(re) = re.as_ref() else {
+        return false;
+    };
+    let Some(m) = re.0.find(haystack(hay, len)) else {
+        return false;
+    };
+    if !start.is_null() {
+        *start = m.start();
+    }
+    if !end.is_null() {
+        *end = m.end();
+    }
+    true
+}
+
+/// Frees a pattern returned by [`putao_compile`]; NULL is ignored.
+///
+/// # Safety
+///
+/// `re` must be NULL or come from [`putao_compile`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn putao_free(re: *mut PutaoRegex) {
+    if !re.is_null() {
+        drop(Box::from_raw(re));
+    }
+}
+
+/// Borrows a C buffer, treating NULL as empty.
+unsafe fn haystack<'a>(hay: *const u8, len: usize) -> &'a [u8] {
+    if hay.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(hay, len)
+    }
+}
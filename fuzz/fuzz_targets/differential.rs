@@ -0,0 +1,145 @@
+//! Cross-checks `is_match` against the `regex` crate on patterns built from
+//! the syntax both engines agree on.
+//!
+//! Run with `cargo +nightly fuzz run differential`.
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Leaf {
+    Lit(u8),
+    Dot,
+    Digit,
+    Word,
+    Class(bool, Vec<u8>),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Quant {
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+    Exactly(u8),
+    AtLeast(u8),
+    Bounded(u8, u8),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Pat {
+    Leaf(Leaf),
+    Concat(Vec<Pat>),
+    Group(Vec<Pat>),
+    Repeat(Box<Pat>, Quant),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    start: bool,
+    pat: Pat,
+    end: bool,
+    hay: Vec<u8>,
+}
+
+/// Chars patterns are built from; `-` is left out of classes, where the
+/// `regex` crate reads it as a range.
+const CHARS: &[u8] = b"abc1_ -";
+const HAY: &[u8] = b"abc1_ -\n";
+
+fn lit(b: u8) -> char {
+    CHARS[b as usize % CHARS.len()] as char
+}
+
+fn class_lit(b: u8) -> char {
+    CHARS[b as usize % (CHARS.len() - 1)] as char
+}
+
+/// Renders a pattern, returning whether it can match the empty string.
+///
+/// Nullable sub-patterns are never quantified, since unbounded repetition
+/// of them does not terminate.
+fn render(p: &Pat, depth: usize, out: &mut String) -> bool {
+    match p {
+        Pat::Leaf(Leaf::Lit(b)) => out.push(lit(*b)),
+        Pat::Leaf(Leaf::Dot) => out.push('.'),
+        Pat::Leaf(Leaf::Digit) => out.push_str(r"\d"),
+        Pat::Leaf(Leaf::Word) => out.push_str(r"\w"),
+        Pat::Leaf(Leaf::Class(neg, cs)) => {
+            out.push_str(if *neg { "[^" } else { "[" });
+            out.push(class_lit(0));
+            cs.iter().take(3).for_each(|&b| out.push(class_lit(b)));
+            out.push(']');
+        }
+        Pat::Concat(ps) if depth < 4 && !ps.is_empty() => {
+            return ps
+                .iter()
+                .take(4)
+                .fold(true, |all, p| render(p, depth + 1, out) && all);
+        }
+        Pat::Group(ps) if depth < 4 && !ps.is_empty() => {
+            out.push('(');
+            let mut any = false;
+            for (i, p) in ps.iter().take(3).enumerate() {
+                if i > 0 {
+                    out.push('|');
+                }
+                any |= render(p, depth + 1, out);
+            }
+            out.push(')');
+            return any;
+        }
+        Pat::Repeat(p, q) if depth < 4 => {
+            let mut inner = String::new();
+            if render(p, depth + 1, &mut inner) {
+                out.push_str(&inner);
+                return true;
+            }
+            out.push('(');
+            out.push_str(&inner);
+            out.push(')');
+            let (s, nullable) = match *q {
+                Quant::ZeroOrOne => ("?".to_string(), true),
+                Quant::ZeroOrMore => ("*".to_string(), true),
+                Quant::OneOrMore => ("+".to_string(), false),
+                Quant::Exactly(n) => (format!("{{{}}}", n % 4), n % 4 == 0),
+                Quant::AtLeast(n) => (format!("{{{},}}", n % 4), n % 4 == 0),
+                Quant::Bounded(n, m) => {
+                    let (n, m) = (n % 4, n % 4 + m % 3);
+                    (format!("{{{},{}}}", n, m), n == 0)
+                }
+            };
+            out.push_str(&s);
+            return nullable;
+        }
+        _ => out.push(lit(0)),
+    }
+    false
+}
+
+fuzz_target!(|input: Input| {
+    let mut pattern = String::new();
+    if input.start {
+        pattern.push('^');
+    }
+    render(&input.pat, 0, &mut pattern);
+    if input.end {
+        pattern.push('$');
+    }
+    let hay: String = input
+        .hay
+        .iter()
+        .take(16)
+        .map(|&b| HAY[b as usize % HAY.len()] as char)
+        .collect();
+    let ours = putao_cf::Regex::new(&pattern).unwrap();
+    let oracle = regex::Regex::new(&pattern).unwrap();
+    assert_eq!(
+        ours.is_match(&hay),
+        oracle.is_match(&hay),
+        "pattern {:?} on {:?}",
+        pattern,
+        hay
+    );
+});
@@ -0,0 +1,4 @@
+//! Python bindings for the putao regex engine: the library's `python`
+//! module, built as the `putao_cf` extension module by maturin.
+
+pub use putao_cf::python::*;
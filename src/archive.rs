@@ -0,0 +1,245 @@
+//! Reading the files inside zip and tar archives, so each is searched as
+//! if it were on disk.
+//!
+//! Every archive may unpack to at most a limit, so a small archive
+//! expanding to far more than it holds, a decompression bomb, is cut short
+//! with an error rather than read to the end.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::inflate::inflate;
+use crate::pipe::decompressor;
+
+/// The kinds of archive searched inside.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Zip,
+    Tar,
+    /// A tar archive put through a compressor, such as `.tar.gz`.
+    CompressedTar,
+}
+
+/// Returns the kind of archive the file at path is, going by its name; a
+/// compressed tar archive is one only if its decompressor is known.
+pub fn kind(path: &Path) -> Option<Kind> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    let (stem, ext) = name.rsplit_once('.')?;
+    match ext {
+        "zip" => Some(Kind::Zip),
+        "tar" => Some(Kind::Tar),
+        _ if !stem.ends_with(".tar") && !matches!(ext, "tgz" | "tbz2" | "txz") => None,
+        _ => decompressor(path).map(|_| Kind::CompressedTar),
+    }
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid {} archive", what),
+    )
+}
+
+fn too_large(limit: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unpacks to more than {} bytes", limit),
+    )
+}
+
+/// How much of a GNU long name or pax header is kept.
+const HEADER_LIMIT: u64 = 1 << 20;
+
+/// Calls f with the name and contents of each file in the tar archive
+/// read from rdr, until it returns false; fails once more than limit bytes
+/// of files were unpacked.
+pub fn tar<R, F>(mut rdr: R, limit: u64, mut f: F) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(&str, &mut dyn Read) -> bool,
+{
+    let mut unpacked = 0u64;
+    // A name given by the GNU or pax header before the entry it is for.
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; 512];
+    loop {
+        if !read_block(&mut rdr, &mut header)? || header.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = tar_size(&header[124..136]).ok_or_else(|| invalid("tar"))?;
+        let padded = size.div_ceil(512) * 512;
+        let kind = header[156];
+        if matches!(kind, b'L' | b'x') {
+            // Only the start of a longer header is kept; the rest is read
+            // past, so the next header is still found where it starts.
+            let mut data = Vec::new();
+            (&mut rdr)
+                .take(size.min(HEADER_LIMIT))
+                .read_to_end(&mut data)?;
+            let rest = padded - data.len() as u64;
+            io::copy(&mut (&mut rdr).take(rest), &mut io::sink())?;
+            long_name = match kind {
+                b'L' => Some(field(&data)),
+                _ => pax_path(&data).or(long_name),
+            };
+            continue;
+        }
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let (name, prefix) = (field(&header[..100]), field(&header[345..500]));
+                let ustar = &header[257..262] == b"ustar";
+                match ustar && !prefix.is_empty() {
+                    true => format!("{}/{}", prefix, name),
+                    false => name,
+                }
+            }
+        };
+        // `tar -C dir .` names everything below `./`.
+        let name = name.strip_prefix("./").unwrap_or(&name);
+        let mut entry = (&mut rdr).take(size);
+        if matches!(kind, b'0' | b'\0' | b'7') {
+            unpacked += size;
+            if unpacked > limit {
+                return Err(too_large(limit));
+            }
+            if !f(name, &mut entry) {
+                return Ok(());
+            }
+        }
+        io::copy(&mut entry, &mut io::sink())?;
+        io::copy(&mut (&mut rdr).take(padded - size), &mut io::sink())?;
+    }
+}
+
+/// Fills block, returning false if rdr ended before anything was read.
+fn read_block(rdr: &mut impl Read, block: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < block.len() {
+        match rdr.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(false),
+            0 => return Err(invalid("tar")),
+            n => filled += n,
+        }
+    }
+    Ok(true)
+}
+
+/// Reads the size in a tar header, in octal or GNU base-256.
+fn tar_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        return field[1..]
+            .iter()
+            .try_fold(0u64, |n, &b| n.checked_mul(256).map(|n| n + u64::from(b)));
+    }
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// Returns the text of a NUL-padded header field.
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Returns the path a pax extended header gives, if it does: its records
+/// are `LEN path=VALUE\n`.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    text.lines()
+        .filter_map(|record| record.split_once(' ')?.1.strip_prefix("path="))
+        .next_back()
+        .map(str::to_string)
+}
+
+/// Calls f with the name and contents of each file in the zip archive
+/// file, until it returns false; fails once more than limit bytes of
+/// files were unpacked. Files stored or deflated are read; those packed
+/// any other way, or encrypted, are skipped.
+pub fn zip<F>(mut file: File, limit: u64, mut f: F) -> io::Result<()>
+where
+    F: FnMut(&str, &mut dyn Read) -> bool,
+{
+    let (count, offset) = zip_directory(&mut file)?;
+    let mut directory = Vec::new();
+    file.seek(SeekFrom::Start(offset))?;
+    let mut header = [0u8; 46];
+    for _ in 0..count {
+        file.read_exact(&mut header).map_err(|_| invalid("zip"))?;
+        if u32_at(&header, 0) != 0x0201_4b50 {
+            return Err(invalid("zip"));
+        }
+        let mut name = vec![0; usize::from(u16_at(&header, 28))];
+        file.read_exact(&mut name)?;
+        let skip = i64::from(u16_at(&header, 30)) + i64::from(u16_at(&header, 32));
+        file.seek(SeekFrom::Current(skip))?;
+        directory.push((header, String::from_utf8_lossy(&name).into_owned()));
+    }
+    let mut unpacked = 0u64;
+    for (header, name) in directory {
+        let flags = u16_at(&header, 8);
+        let method = u16_at(&header, 10);
+        let packed = u64::from(u32_at(&header, 20));
+        let size = u64::from(u32_at(&header, 24));
+        let local = u64::from(u32_at(&header, 42));
+        if name.ends_with('/') || flags & 1 != 0 || !matches!(method, 0 | 8) {
+            continue;
+        }
+        // Sizes this large are kept in zip64 fields, which are not read.
+        if packed == 0xFFFF_FFFF || size == 0xFFFF_FFFF || local == 0xFFFF_FFFF {
+            continue;
+        }
+        unpacked += size;
+        if unpacked > limit {
+            return Err(too_large(limit));
+        }
+        let mut start = [0u8; 30];
+        file.seek(SeekFrom::Start(local))?;
+        file.read_exact(&mut start).map_err(|_| invalid("zip"))?;
+        if u32_at(&start, 0) != 0x0403_4b50 {
+            return Err(invalid("zip"));
+        }
+        let skip = i64::from(u16_at(&start, 26)) + i64::from(u16_at(&start, 28));
+        file.seek(SeekFrom::Current(skip))?;
+        let mut data = Vec::new();
+        (&mut file).take(packed).read_to_end(&mut data)?;
+        if method == 8 {
+            // The size given is only trusted to bound what is unpacked.
+            data = inflate(&data, size)?;
+        }
+        if !f(&name, &mut data.as_slice()) {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Finds the end of central directory record of a zip archive, returning
+/// how many entries the directory has and where it starts.
+fn zip_directory(file: &mut File) -> io::Result<(u16, u64)> {
+    let len = file.seek(SeekFrom::End(0))?;
+    // The record is 22 bytes, followed by a comment of up to 64 KiB.
+    let tail = len.min(22 + 0xFFFF);
+    file.seek(SeekFrom::Start(len - tail))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    let at = (0..buf.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&buf, i) == 0x0605_4b50)
+        .ok_or_else(|| invalid("zip"))?;
+    let record = &buf[at..];
+    Ok((u16_at(record, 10), u64::from(u32_at(record, 16))))
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
@@ -0,0 +1,278 @@
+//! The parsed form of a pattern, for tools that want to inspect patterns.
+//!
+//! ```
+//! use putao_cf::ast::{self, Ast, Visitor};
+//!
+//! struct Reps(usize);
+//!
+//! impl Visitor for Reps {
+//!     fn visit_pre(&mut self, ast: &Ast) {
+//!         if let Ast::Repetition(_) = ast {
+//!             self.0 += 1;
+//!         }
+//!     }
+//! }
+//!
+//! let ast = ast::parse(r"(a+)*b").unwrap();
+//! let mut reps = Reps(0);
+//! ast.visit(&mut reps);
+//! assert_eq!(reps.0, 2);
+//! assert_eq!(ast.to_string(), r"(a+)*b");
+//! ```
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{fmt, mem};
+
+use crate::error::Error;
+use crate::parse;
+
+/// A node of a parsed pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ast {
+    /// Matches the empty string.
+    Empty,
+    /// A single literal char.
+    Literal(char),
+    /// `.`, any char except (by default) newline.
+    Dot,
+    /// A Perl class such as `\d`.
+    Perl(ClassPerl),
+    /// A bracketed class such as `[abc]` or `[^abc]`.
+    Class(Class),
+    /// A zero-width assertion such as `^`.
+    Assertion(Assertion),
+    /// A quantified sub-pattern such as `a+`.
+    Repetition(Repetition),
+    /// A capturing group, optionally named.
+    Group(Group),
+    /// A backreference to a capturing group, such as `\1`.
+    Backref(usize),
+    /// A lookahead or lookbehind such as `(?=a)`.
+    Lookaround(Lookaround),
+    /// `\K`, which leaves the text matched before it out of the match.
+    Keep,
+    /// Alternatives separated by `|`.
+    Alternation(Vec<Ast>),
+    /// A sequence matched one after another.
+    Concat(Vec<Ast>),
+}
+
+/// A Perl class escape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassPerl {
+    /// `\d`
+    Digit,
+    /// `\w`
+    Word,
+    /// `\s`
+    Space,
+    /// `\D`
+    NotDigit,
+    /// `\W`
+    NotWord,
+    /// `\S`
+    NotSpace,
+}
+
+/// A bracketed class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Class {
+    /// Whether the class was written `[^...]`.
+    pub negated: bool,
+    /// The single chars listed between the brackets.
+    pub chars: String,
+    /// The inclusive ranges of chars listed, such as `a-z`, by scalar value.
+    pub ranges: Vec<(char, char)>,
+}
+
+/// A zero-width assertion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assertion {
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `\b`, between a word char and a non-word char or either end.
+    WordBoundary,
+    /// `\B`, anywhere `\b` does not match.
+    NotWordBoundary,
+}
+
+/// A quantified sub-pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repetition {
+    /// The quantifier applied.
+    pub op: RepetitionOp,
+    /// Whether it repeats as often as it can, rather than as rarely as
+    /// with a trailing `?`.
+    pub greedy: bool,
+    /// The quantified sub-pattern.
+    pub ast: Box<Ast>,
+}
+
+/// A quantifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepetitionOp {
+    /// `?`
+    ZeroOrOne,
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+    /// `{n}`
+    Exactly(usize),
+    /// `{n,}`
+    AtLeast(usize),
+    /// `{n,m}`
+    Bounded(usize, usize),
+}
+
+/// A zero-width assertion that a sub-pattern matches next to the position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lookaround {
+    /// Which side is looked at and whether the sub-pattern must fail.
+    pub kind: LookaroundKind,
+    /// The sub-pattern looked for.
+    pub ast: Box<Ast>,
+}
+
+/// The kind of a lookaround.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LookaroundKind {
+    /// `(?=...)`
+    Ahead,
+    /// `(?!...)`
+    NotAhead,
+    /// `(?<=...)`
+    Behind,
+    /// `(?<!...)`
+    NotBehind,
+}
+
+/// A capturing group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    /// The 1-based capture index.
+    pub index: usize,
+    /// The name given with `(?<name>...)`, if any.
+    pub name: Option<String>,
+    /// The grouped sub-pattern.
+    pub ast: Box<Ast>,
+}
+
+/// Callbacks invoked while walking an [`Ast`] depth-first.
+pub trait Visitor {
+    /// Called before the children of a node are visited.
+    fn visit_pre(&mut self, _ast: &Ast) {}
+
+    /// Called after the children of a node are visited.
+    fn visit_post(&mut self, _ast: &Ast) {}
+}
+
+impl Ast {
+    /// Walks this node and its children depth-first.
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_pre(self);
+        match self {
+            Ast::Repetition(r) => r.ast.visit(visitor),
+            Ast::Group(g) => g.ast.visit(visitor),
+            Ast::Lookaround(l) => l.ast.visit(visitor),
+            Ast::Alternation(v) | Ast::Concat(v) => v.iter().for_each(|a| a.visit(visitor)),
+            _ => {}
+        }
+        visitor.visit_post(self);
+    }
+}
+
+/// Writes an equivalent pattern; parsing the output yields the same AST.
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ast::Empty => Ok(()),
+            Ast::Literal('\n') => write!(f, "\\n"),
+            Ast::Literal(c) if parse::is_meta(*c) => write!(f, "\\{}", c),
+            Ast::Literal(c) => write!(f, "{}", c),
+            Ast::Dot => f.write_str("."),
+            Ast::Perl(ClassPerl::Digit) => f.write_str("\\d"),
+            Ast::Perl(ClassPerl::Word) => f.write_str("\\w"),
+            Ast::Perl(ClassPerl::Space) => f.write_str("\\s"),
+            Ast::Perl(ClassPerl::NotDigit) => f.write_str("\\D"),
+            Ast::Perl(ClassPerl::NotWord) => f.write_str("\\W"),
+            Ast::Perl(ClassPerl::NotSpace) => f.write_str("\\S"),
+            Ast::Class(c) => {
+                f.write_str(if c.negated { "[^" } else { "[" })?;
+                let mut first = !c.negated;
+                for ch in c.chars.chars() {
+                    class_char(f, ch, mem::take(&mut first))?;
+                }
+                for &(lo, hi) in &c.ranges {
+                    class_char(f, lo, mem::take(&mut first))?;
+                    f.write_str("-")?;
+                    class_char(f, hi, false)?;
+                }
+                f.write_str("]")
+            }
+            Ast::Assertion(Assertion::Start) => f.write_str("^"),
+            Ast::Assertion(Assertion::End) => f.write_str("$"),
+            Ast::Assertion(Assertion::WordBoundary) => f.write_str("\\b"),
+            Ast::Assertion(Assertion::NotWordBoundary) => f.write_str("\\B"),
+            Ast::Repetition(r) => {
+                write!(f, "{}", r.ast)?;
+                match r.op {
+                    RepetitionOp::ZeroOrOne => f.write_str("?"),
+                    RepetitionOp::ZeroOrMore => f.write_str("*"),
+                    RepetitionOp::OneOrMore => f.write_str("+"),
+                    RepetitionOp::Exactly(n) => write!(f, "{{{}}}", n),
+                    RepetitionOp::AtLeast(n) => write!(f, "{{{},}}", n),
+                    RepetitionOp::Bounded(n, m) => write!(f, "{{{},{}}}", n, m),
+                }?;
+                if !r.greedy {
+                    f.write_str("?")?;
+                }
+                Ok(())
+            }
+            Ast::Group(g) => match &g.name {
+                Some(name) => write!(f, "(?<{}>{})", name, g.ast),
+                None => write!(f, "({})", g.ast),
+            },
+            Ast::Backref(n) => write!(f, "\\{}", n),
+            Ast::Lookaround(l) => {
+                let open = match l.kind {
+                    LookaroundKind::Ahead => "(?=",
+                    LookaroundKind::NotAhead => "(?!",
+                    LookaroundKind::Behind => "(?<=",
+                    LookaroundKind::NotBehind => "(?<!",
+                };
+                write!(f, "{}{})", open, l.ast)
+            }
+            Ast::Keep => f.write_str("\\K"),
+            Ast::Alternation(v) => {
+                for (i, a) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    write!(f, "{}", a)?;
+                }
+                Ok(())
+            }
+            Ast::Concat(v) => v.iter().try_for_each(|a| write!(f, "{}", a)),
+        }
+    }
+}
+
+/// Parses a pattern into its AST.
+pub fn parse(pattern: &str) -> Result<Ast, Error> {
+    parse::parse(pattern, false)
+}
+
+/// Writes a char listed in a class, escaped if it would otherwise read as
+/// part of the class syntax; a `^` only needs that first in the class.
+fn class_char(f: &mut fmt::Formatter<'_>, ch: char, first: bool) -> fmt::Result {
+    match ch {
+        '\\' | ']' | '-' => write!(f, "\\{}", ch),
+        '^' if first => f.write_str("\\^"),
+        '\n' => f.write_str("\\n"),
+        _ => write!(f, "{}", ch),
+    }
+}
@@ -0,0 +1,92 @@
+//! `putao bench`: timing a search, so how fast it is, and how the pattern
+//! was compiled to make it so, can be compared from build to build.
+//!
+//! The search is run as `-c` would run it, with the counts going nowhere,
+//! at least [`MIN_RUNS`] times and then until [`BUDGET`] is spent or
+//! [`MAX_RUNS`] runs were made; the fastest and median runs are reported.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+use crate::progress::bytes;
+use crate::{grep_inputs, Args, Directories, Mode};
+
+const MIN_RUNS: usize = 3;
+const MAX_RUNS: usize = 20;
+const BUDGET: Duration = Duration::from_secs(3);
+
+/// The synopsis of `putao bench`.
+pub const USAGE: &str = "Usage: putao bench [OPTION]... PATTERNS PATH...";
+
+/// Runs `putao bench` with the arguments after `bench`.
+pub fn run(argv: impl Iterator<Item = String>) -> Result<i32> {
+    let mut args = Args::parse_for(argv, false)?;
+    if args.paths.is_empty() {
+        bail!("{}\nTry 'putao --help' for more information.", USAGE);
+    }
+    // Directories are searched whole, as a benchmark over one means to.
+    args.directories = Directories::Recurse;
+    args.mode = Mode::Count;
+    args.stats = true;
+    let builder = args.regex();
+    let mut compiles = Vec::new();
+    let re = loop {
+        let started = Instant::now();
+        let re = builder.build()?;
+        compiles.push(started.elapsed());
+        if compiles.len() == MIN_RUNS {
+            break re;
+        }
+    };
+    let mut runs = Vec::new();
+    let mut searched = None;
+    let started = Instant::now();
+    while runs.len() < MIN_RUNS || (runs.len() < MAX_RUNS && started.elapsed() < BUDGET) {
+        let mut grep = args.grep_with(re.clone(), Box::new(io::sink()))?;
+        let run = Instant::now();
+        grep_inputs(&args, &mut grep)?;
+        runs.push(run.elapsed());
+        searched = grep.stats.take();
+        // What cannot be read was said so the first time.
+        args.no_messages = true;
+    }
+    let stats = searched.unwrap_or_default();
+    let (best, middle) = (fastest(&runs), median(&mut runs));
+    let mut out = io::stdout().lock();
+    for pattern in &args.patterns {
+        writeln!(out, "pattern: {:?}", pattern)?;
+    }
+    for line in re.explain().lines().take_while(|l| *l != "program:") {
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out, "compile: {:.2?}", median(&mut compiles))?;
+    writeln!(
+        out,
+        "searched: {} file{}, {}, {} matching lines",
+        stats.searches,
+        if stats.searches == 1 { "" } else { "s" },
+        bytes(stats.bytes_searched as f64),
+        stats.matched_lines
+    )?;
+    writeln!(
+        out,
+        "runs: {}, fastest {:.2?}, median {:.2?}",
+        runs.len(),
+        best,
+        middle
+    )?;
+    let rate = stats.bytes_searched as f64 / middle.as_secs_f64().max(1e-9);
+    writeln!(out, "throughput: {}/s", bytes(rate))?;
+    Ok(0)
+}
+
+fn fastest(times: &[Duration]) -> Duration {
+    times.iter().copied().min().unwrap_or_default()
+}
+
+fn median(times: &mut [Duration]) -> Duration {
+    times.sort();
+    times.get(times.len() / 2).copied().unwrap_or_default()
+}
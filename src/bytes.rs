@@ -0,0 +1,212 @@
+//! Byte-oriented regexes for haystacks that may not be valid UTF-8.
+//!
+//! Invalid UTF-8 sequences are matched one byte at a time as if they were
+//! U+FFFD, so `.` and negated classes still step over them.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::exec::Caps;
+use crate::regex::Searches;
+use crate::Error;
+
+/// A compiled pattern matched against `&[u8]`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Regex(crate::Regex);
+
+impl Regex {
+    /// Compiles a pattern with default options.
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        crate::Regex::new(pattern).map(Regex)
+    }
+
+    /// Returns the pattern this regex was compiled from.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Returns the number of capture groups, including the implicit group 0.
+    pub fn captures_len(&self) -> usize {
+        self.0.captures_len()
+    }
+
+    /// Returns the index of the group with the given name.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        self.0.capture_index(name)
+    }
+
+    /// Reports whether the pattern matches anywhere in the haystack.
+    pub fn is_match(&self, hay: &[u8]) -> bool {
+        self.0.search(hay, 0).is_some()
+    }
+
+    /// Like [`is_match`](Regex::is_match), but fails instead of reporting
+    /// no match if the search runs past the state limit.
+    pub fn try_is_match(&self, hay: &[u8]) -> Result<bool, Error> {
+        Ok(self.0.try_search(hay, 0)?.is_some())
+    }
+
+    /// Returns the leftmost match in the haystack.
+    pub fn find<'h>(&self, hay: &'h [u8]) -> Option<Match<'h>> {
+        let (start, end) = self.0.search(hay, 0)?[0].unwrap();
+        Some(Match { hay, start, end })
+    }
+
+    /// Iterates over successive non-overlapping matches.
+    pub fn find_iter<'r, 'h>(&'r self, hay: &'h [u8]) -> Matches<'r, 'h> {
+        Matches(hay, Searches::new(&self.0, hay))
+    }
+
+    /// Returns the capture groups of the leftmost match.
+    pub fn captures<'h>(&self, hay: &'h [u8]) -> Option<Captures<'h>> {
+        self.0.search(hay, 0).map(|slots| Captures { hay, slots })
+    }
+
+    /// Iterates over the capture groups of successive non-overlapping matches.
+    pub fn captures_iter<'r, 'h>(&'r self, hay: &'h [u8]) -> CaptureMatches<'r, 'h> {
+        CaptureMatches(hay, Searches::new(&self.0, hay))
+    }
+
+    /// Appends replacement to dst with each `$1`, `${1}`, `$name` or
+    /// `${name}` replaced by the text of that group of caps, or by nothing
+    /// if it did not match; `$$` is a literal `$`.
+    ///
+    /// ```
+    /// use putao_cf::bytes::Regex;
+    ///
+    /// let re = Regex::new(r"(?<key>\w+)=(\w+)").unwrap();
+    /// let caps = re.captures(b"a=1").unwrap();
+    /// let mut dst = Vec::new();
+    /// re.expand(&caps, "${2}:$key", &mut dst);
+    /// assert_eq!(dst, b"1:a");
+    /// ```
+    pub fn expand(&self, caps: &Captures<'_>, replacement: &str, dst: &mut Vec<u8>) {
+        let mut rest = replacement;
+        while let Some(i) = rest.find('$') {
+            dst.extend_from_slice(&rest.as_bytes()[..i]);
+            rest = &rest[i + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                dst.push(b'$');
+                rest = after;
+                continue;
+            }
+            let (name, after) = match rest.strip_prefix('{') {
+                Some(braced) => match braced.split_once('}') {
+                    Some(split) => split,
+                    None => ("", rest),
+                },
+                None => {
+                    let end = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    rest.split_at(end)
+                }
+            };
+            // A `$` not followed by a group reference is kept as is.
+            if name.is_empty() {
+                dst.push(b'$');
+                continue;
+            }
+            rest = after;
+            let group = name.parse().ok().or_else(|| self.0.capture_index(name));
+            if let Some(m) = group.and_then(|i| caps.get(i)) {
+                dst.extend_from_slice(m.as_bytes());
+            }
+        }
+        dst.extend_from_slice(rest.as_bytes());
+    }
+}
+
+/// A single match of a byte regex in a haystack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match<'h> {
+    hay: &'h [u8],
+    start: usize,
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    /// Returns the byte offset where the match starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the byte offset just past the end of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the byte range of the match.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the matched bytes.
+    pub fn as_bytes(&self) -> &'h [u8] {
+        &self.hay[self.range()]
+    }
+}
+
+/// The capture groups of a single match of a byte regex.
+#[derive(Clone, Debug)]
+pub struct Captures<'h> {
+    hay: &'h [u8],
+    slots: Caps,
+}
+
+impl<'h> Captures<'h> {
+    /// Returns group i, or `None` if it did not participate in the match.
+    pub fn get(&self, i: usize) -> Option<Match<'h>> {
+        let (start, end) = self.slots.get(i).copied().flatten()?;
+        Some(Match {
+            hay: self.hay,
+            start,
+            end,
+        })
+    }
+
+    /// Returns the number of groups, including group 0.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Always false: group 0 is always present.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Iterator returned by [`Regex::find_iter`].
+#[derive(Debug)]
+pub struct Matches<'r, 'h>(&'h [u8], Searches<'r, 'h>);
+
+impl<'h> Iterator for Matches<'_, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        let hay = self.0;
+        let (start, end) = self.1.next_slots()?[0].unwrap();
+        Some(Match { hay, start, end })
+    }
+}
+
+/// Iterator returned by [`Regex::captures_iter`].
+#[derive(Debug)]
+pub struct CaptureMatches<'r, 'h>(&'h [u8], Searches<'r, 'h>);
+
+impl<'h> Iterator for CaptureMatches<'_, 'h> {
+    type Item = Captures<'h>;
+
+    fn next(&mut self) -> Option<Captures<'h>> {
+        let hay = self.0;
+        self.1.next_slots().map(|slots| Captures { hay, slots })
+    }
+}
+
+/// Reuses a regex configured through [`crate::RegexBuilder`] for byte haystacks.
+impl From<crate::Regex> for Regex {
+    fn from(re: crate::Regex) -> Self {
+        Regex(re)
+    }
+}
@@ -0,0 +1,50 @@
+//! Compile-once helpers for patterns used repeatedly.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::{Error, Regex};
+
+/// Compiles a literal pattern on first use and returns the same `&'static Regex` after.
+///
+/// Panics if the pattern is invalid.
+///
+/// ```
+/// use putao_cf::putao_regex;
+///
+/// for line in ["a1", "b", "c22"] {
+///     if putao_regex!(r"\d+").is_match(line) {
+///         println!("{}", line);
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! putao_regex {
+    ($pattern:literal $(,)?) => {{
+        static RE: ::std::sync::OnceLock<$crate::Regex> = ::std::sync::OnceLock::new();
+        RE.get_or_init(|| $crate::Regex::new($pattern).expect("invalid pattern"))
+    }};
+}
+
+fn cache() -> &'static Mutex<HashMap<String, Arc<Regex>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<Regex>>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+impl Regex {
+    /// Compiles a pattern with default options, reusing an earlier compilation
+    /// of the same pattern from a process-wide cache.
+    ///
+    /// Compiled patterns stay cached for the life of the process, so this is
+    /// meant for a bounded set of hot patterns rather than arbitrary input.
+    pub fn cached(pattern: &str) -> Result<Arc<Regex>, Error> {
+        if let Some(re) = cache().lock().ok().and_then(|c| c.get(pattern).cloned()) {
+            return Ok(re);
+        }
+        let re = Arc::new(Regex::new(pattern)?);
+        if let Ok(mut c) = cache().lock() {
+            c.entry(pattern.to_string()).or_insert_with(|| re.clone());
+        }
+        Ok(re)
+    }
+}
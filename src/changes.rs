@@ -0,0 +1,157 @@
+//! `--changed`: searching only the lines added or changed since a git
+//! revision, so what a branch introduces can be told apart from what was
+//! there before it.
+//!
+//! The lines come from the hunks of `git diff -U0` between the point the
+//! revision and `HEAD` branched off, and the working tree; files git does
+//! not track yet, and does not ignore, count as added whole.
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Result};
+
+/// The lines added or changed in each file below the working directory.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// The files changed, relative to the working directory, as git named
+    /// them.
+    paths: Vec<String>,
+    /// The lines of each file changed, by number, by its canonical path.
+    lines: HashMap<PathBuf, Vec<Range<u64>>>,
+}
+
+impl Changes {
+    /// Finds what changed below the working directory since base.
+    pub fn since(base: &str) -> Result<Changes> {
+        let base = commit(base)?;
+        let diff = git(&[
+            "diff",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            "--relative",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            "--merge-base",
+            "--end-of-options",
+            &base,
+            "--",
+        ])?;
+        let mut changes = Changes::default();
+        let mut file: Option<(String, Vec<Range<u64>>)> = None;
+        for line in diff.lines() {
+            if let Some(name) = line.strip_prefix("+++ ") {
+                changes.add(file.take());
+                file = path_name(name).map(|name| (name, Vec::new()));
+            } else if let (Some(hunk), Some((_, lines))) = (line.strip_prefix("@@ "), &mut file) {
+                lines.extend(added(hunk));
+            }
+        }
+        changes.add(file);
+        let untracked = git(&["ls-files", "-z", "--others", "--exclude-standard"])?;
+        let whole = 1..u64::MAX;
+        for name in untracked.split('\0').filter(|n| !n.is_empty()) {
+            changes.add(Some((name.to_string(), vec![whole.clone()])));
+        }
+        Ok(changes)
+    }
+
+    /// Records the lines changed in a file, if any.
+    fn add(&mut self, file: Option<(String, Vec<Range<u64>>)>) {
+        let Some((name, lines)) = file.filter(|(_, lines)| !lines.is_empty()) else {
+            return;
+        };
+        if let Ok(path) = fs::canonicalize(&name) {
+            self.paths.push(name);
+            self.lines.insert(path, lines);
+        }
+    }
+
+    /// Returns the files changed, relative to the working directory.
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    /// Returns the lines changed in the file at path, if it changed.
+    pub fn lines(&self, path: &Path) -> Option<&[Range<u64>]> {
+        let path = fs::canonicalize(path).ok()?;
+        self.lines.get(&path).map(Vec::as_slice)
+    }
+}
+
+/// Resolves a revision to the commit it names, refusing one that git
+/// would read as an option.
+fn commit(base: &str) -> Result<String> {
+    if base.starts_with('-') {
+        bail!("invalid revision: {}", base);
+    }
+    let rev = format!("{}^{{commit}}", base);
+    let sha = git(&["rev-parse", "--verify", "--quiet", "--end-of-options", &rev])
+        .map_err(|_| anyhow!("unknown revision: {}", base))?;
+    Ok(sha.trim().to_string())
+}
+
+/// Runs git with args, returning what it printed.
+fn git(args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("cannot run git: {}", e))?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr);
+        let err = err.lines().next().unwrap_or("git diff failed");
+        bail!("{}", err.strip_prefix("fatal: ").unwrap_or(err));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Returns the lines a hunk header, after its `@@ `, says were added: in
+/// `-12,3 +14,5 @@`, lines 14 to 18.
+fn added(hunk: &str) -> Option<Range<u64>> {
+    let new = hunk.split_whitespace().find_map(|s| s.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse::<u64>().ok()?, count.parse::<u64>().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+    (count > 0).then(|| start..start + count)
+}
+
+/// Returns the path a `+++ b/PATH` line names, without the prefix and
+/// unquoted if git quoted it; a file deleted, `+++ /dev/null`, names none.
+fn path_name(name: &str) -> Option<String> {
+    let name = unquote(name).unwrap_or_else(|| name.to_string());
+    name.strip_prefix("b/").map(str::to_string)
+}
+
+/// Decodes a name git quoted, as it does one with a quote, backslash or
+/// control character in it; returns None if name is not quoted.
+fn unquote(name: &str) -> Option<String> {
+    let inner = name.strip_prefix('"')?.strip_suffix('"')?;
+    let mut bytes = Vec::new();
+    let mut rest = inner.bytes();
+    while let Some(b) = rest.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match rest.next()? {
+            b't' => bytes.push(b'\t'),
+            b'n' => bytes.push(b'\n'),
+            b'r' => bytes.push(b'\r'),
+            d @ b'0'..=b'7' => {
+                let mut n = u32::from(d - b'0');
+                for _ in 0..2 {
+                    n = n * 8 + u32::from(rest.next()?.checked_sub(b'0')?);
+                }
+                bytes.push(u8::try_from(n).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
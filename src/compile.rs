@@ -0,0 +1,190 @@
+//! Lowers the public [`Ast`] into the node tree executed by the matcher.
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+
+use crate::ast::{Assertion, Ast, ClassPerl, LookaroundKind, RepetitionOp};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Node {
+    Lit(char),
+    /// Matches a char of a Perl class such as `\d`.
+    Perl(ClassPerl),
+    Any,
+    /// Matches a char listed, or in one of the inclusive ranges.
+    Pos(String, Vec<(char, char)>),
+    /// Matches a char neither listed nor in any of the ranges.
+    Neg(String, Vec<(char, char)>),
+    Opt(Box<Node>),
+    Plus(Box<Node>),
+    Star(Box<Node>),
+    Rep(Box<Node>, usize),
+    MinRep(Box<Node>, usize),
+    RangeRep(Box<Node>, usize, usize),
+    /// Repeats between min and, if bounded, max times, as few as possible.
+    Lazy(Box<Node>, usize, Option<usize>),
+    Cap(usize, Vec<Vec<Node>>),
+    /// Tries each branch in order without capturing.
+    Alt(Vec<Vec<Node>>),
+    Ref(usize),
+    /// Asserts the position is at the start (of a line, in multi-line mode).
+    Start,
+    /// Asserts the position is at the end (of a line, in multi-line mode).
+    End,
+    /// Asserts one of the branches matches at the position or, if false, none does.
+    Ahead(bool, Vec<Vec<Node>>),
+    /// Like `Ahead` for matches ending at the position, which start at most
+    /// the given number of chars before it if that is bounded.
+    Behind(bool, Vec<Vec<Node>>, Option<usize>),
+    /// Starts the reported match at the position.
+    Keep,
+    /// Asserts the char before the position, if any, is not a word char.
+    NotWordBefore,
+    /// Asserts the char at the position, if any, is not a word char.
+    NotWordAfter,
+    /// Asserts the position is, if true, or is not, a word boundary.
+    Boundary(bool),
+    /// Ends the top-level sequence; true if it must sit at the end (of a line, in multi-line mode).
+    Accept(bool),
+}
+
+/// Lowers a parsed pattern into matcher nodes and the start anchor flag.
+///
+/// An empty pattern lowers to a lone accept, matching everywhere.
+pub(crate) fn compile(ast: &Ast) -> (Vec<Node>, bool) {
+    let mut items = match ast {
+        Ast::Empty => &[],
+        Ast::Concat(v) => &v[..],
+        a => core::slice::from_ref(a),
+    };
+    let start = matches!(items.first(), Some(Ast::Assertion(Assertion::Start)));
+    if start {
+        items = &items[1..];
+    }
+    let end = matches!(items.last(), Some(Ast::Assertion(Assertion::End)));
+    if end {
+        items = &items[..items.len() - 1];
+    }
+    let mut nodes: Vec<Node> = items.iter().map(node).collect();
+    nodes.push(Node::Accept(end));
+    (nodes, start)
+}
+
+/// Lowers text into nodes matching it literally, each line an alternative.
+///
+/// Longer lines are tried first, so the longest line matching at a
+/// position wins as in grep.
+pub(crate) fn literal(text: &str) -> Vec<Node> {
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    lines.sort_by_key(|l| core::cmp::Reverse(l.len()));
+    let mut nodes = match &lines[..] {
+        [line] => line.chars().map(Node::Lit).collect(),
+        _ => vec![Node::Alt(
+            lines
+                .iter()
+                .map(|l| l.chars().map(Node::Lit).collect())
+                .collect(),
+        )],
+    };
+    nodes.push(Node::Accept(false));
+    nodes
+}
+
+/// Lowers a group body or alternation branch into a sequence.
+fn seq(ast: &Ast) -> Vec<Node> {
+    match ast {
+        Ast::Empty => Vec::new(),
+        Ast::Concat(v) => v.iter().map(node).collect(),
+        a => vec![node(a)],
+    }
+}
+
+fn node(ast: &Ast) -> Node {
+    match ast {
+        Ast::Literal(c) => Node::Lit(*c),
+        Ast::Dot => Node::Any,
+        Ast::Perl(p) => Node::Perl(*p),
+        Ast::Class(c) if c.negated => Node::Neg(c.chars.clone(), c.ranges.clone()),
+        Ast::Class(c) => Node::Pos(c.chars.clone(), c.ranges.clone()),
+        Ast::Backref(n) => Node::Ref(*n),
+        Ast::Keep => Node::Keep,
+        Ast::Repetition(r) if !r.greedy => {
+            let inner = Box::new(node(&r.ast));
+            let (min, max) = match r.op {
+                RepetitionOp::ZeroOrOne => (0, Some(1)),
+                RepetitionOp::ZeroOrMore => (0, None),
+                RepetitionOp::OneOrMore => (1, None),
+                RepetitionOp::Exactly(n) => (n, Some(n)),
+                RepetitionOp::AtLeast(n) => (n, None),
+                RepetitionOp::Bounded(n, m) => (n, Some(m)),
+            };
+            Node::Lazy(inner, min, max)
+        }
+        Ast::Repetition(r) => {
+            let inner = Box::new(node(&r.ast));
+            match r.op {
+                RepetitionOp::ZeroOrOne => Node::Opt(inner),
+                RepetitionOp::ZeroOrMore => Node::Star(inner),
+                RepetitionOp::OneOrMore => Node::Plus(inner),
+                RepetitionOp::Exactly(n) => Node::Rep(inner, n),
+                RepetitionOp::AtLeast(n) => Node::MinRep(inner, n),
+                RepetitionOp::Bounded(n, m) => Node::RangeRep(inner, n, m),
+            }
+        }
+        Ast::Group(g) => {
+            let brs = match &*g.ast {
+                Ast::Alternation(v) => v.iter().map(seq).collect(),
+                a => vec![seq(a)],
+            };
+            Node::Cap(g.index, brs)
+        }
+        Ast::Lookaround(l) => {
+            let brs: Vec<Vec<Node>> = match &*l.ast {
+                Ast::Alternation(v) => v.iter().map(seq).collect(),
+                a => vec![seq(a)],
+            };
+            match l.kind {
+                LookaroundKind::Ahead => Node::Ahead(true, brs),
+                LookaroundKind::NotAhead => Node::Ahead(false, brs),
+                LookaroundKind::Behind | LookaroundKind::NotBehind => {
+                    let max = brs.iter().try_fold(0, |w, b| Some(w.max(width(b)?)));
+                    Node::Behind(l.kind == LookaroundKind::Behind, brs, max)
+                }
+            }
+        }
+        Ast::Assertion(Assertion::Start) => Node::Start,
+        Ast::Assertion(Assertion::End) => Node::End,
+        Ast::Assertion(Assertion::WordBoundary) => Node::Boundary(true),
+        Ast::Assertion(Assertion::NotWordBoundary) => Node::Boundary(false),
+        Ast::Alternation(v) => Node::Alt(v.iter().map(seq).collect()),
+        Ast::Empty | Ast::Concat(_) => {
+            unreachable!("parser only produces these at the top level or inside groups")
+        }
+    }
+}
+
+/// Returns the most chars a sequence can match, or None if unbounded.
+fn width(nodes: &[Node]) -> Option<usize> {
+    nodes
+        .iter()
+        .try_fold(0usize, |sum, n| sum.checked_add(node_width(n)?))
+}
+
+fn node_width(node: &Node) -> Option<usize> {
+    match node {
+        Node::Lit(_) | Node::Perl(_) | Node::Any | Node::Pos(..) | Node::Neg(..) => {
+            Some(1)
+        }
+        Node::Opt(n) => node_width(n),
+        Node::Rep(n, k) | Node::RangeRep(n, _, k) | Node::Lazy(n, _, Some(k)) => {
+            node_width(n)?.checked_mul(*k)
+        }
+        Node::Plus(_)
+        | Node::Star(_)
+        | Node::MinRep(..)
+        | Node::Lazy(_, _, None)
+        | Node::Ref(_) => None,
+        Node::Cap(_, brs) | Node::Alt(brs) => brs.iter().try_fold(0, |w, b| Some(w.max(width(b)?))),
+        _ => Some(0),
+    }
+}
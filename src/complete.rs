@@ -0,0 +1,159 @@
+//! Shell completion scripts, generated from [`FLAGS`].
+
+use anyhow::{bail, Result};
+
+use crate::flags::{Flag, FLAGS};
+
+/// Returns the completion script for shell: `bash`, `zsh`, `fish` or
+/// `powershell`.
+pub fn generate(shell: &str) -> Result<String> {
+    match shell {
+        "bash" => Ok(bash()),
+        "zsh" => Ok(zsh()),
+        "fish" => Ok(fish()),
+        "powershell" => Ok(powershell()),
+        _ => bail!("invalid argument '{}' for '--generate-completions'", shell),
+    }
+}
+
+/// Returns every name a flag is given by, its short one first.
+fn names(flag: &Flag) -> Vec<String> {
+    let short = flag.short.map(|c| format!("-{}", c));
+    let longs = [flag.long].into_iter().chain(flag.aliases.iter().copied());
+    short
+        .into_iter()
+        .chain(longs.map(|l| format!("--{}", l)))
+        .collect()
+}
+
+fn bash() -> String {
+    let all: Vec<_> = FLAGS.iter().flat_map(names).collect();
+    let mut out = String::from(
+        "_putao() {\n    \
+         local cur prev\n    \
+         cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    \
+         prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    \
+         if [[ $prev == = ]]; then\n        \
+         prev=\"${COMP_WORDS[COMP_CWORD-2]}\"\n    \
+         elif [[ $cur == = ]]; then\n        \
+         cur=\n    \
+         fi\n    \
+         case \"$prev\" in\n",
+    );
+    for flag in FLAGS.iter().filter(|f| !f.values.is_empty()) {
+        out += &format!(
+            "        {})\n            \
+             COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            \
+             return;;\n",
+            names(flag).join("|"),
+            flag.values.join(" "),
+        );
+    }
+    out += "    esac\n    if [[ $cur == -* ]]; then\n        ";
+    out += &format!(
+        "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+        all.join(" ")
+    );
+    out += "    fi\n}\ncomplete -o default -F _putao putao\n";
+    out
+}
+
+fn zsh() -> String {
+    // Inside brackets, `]` and `:` would end the description early.
+    let desc = |f: &Flag| {
+        f.help
+            .replace('\'', "'\\''")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let mut out = String::from("#compdef putao\n\n_arguments -s -S \\\n");
+    for flag in FLAGS {
+        let value = flag.value.map(|v| {
+            let action = match (flag.values, v) {
+                ([], "FILE") => "_files".to_string(),
+                ([], _) => String::new(),
+                (values, _) => format!("({})", values.join(" ")),
+            };
+            format!(":{}:{}", v, action)
+        });
+        for name in names(flag) {
+            let sep = match (&value, flag.optional, name.starts_with("--")) {
+                (None, _, _) => "",
+                (Some(_), true, _) => "=-",
+                (Some(_), false, true) => "=",
+                (Some(_), false, false) => "+",
+            };
+            out += &format!(
+                "  '{}{}[{}]{}' \\\n",
+                name,
+                sep,
+                desc(flag),
+                value.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    out += "  '*:file:_files'\n";
+    out
+}
+
+fn fish() -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut out = String::new();
+    for flag in FLAGS {
+        let longs = [flag.long].into_iter().chain(flag.aliases.iter().copied());
+        for (i, long) in longs.enumerate() {
+            out += "complete -c putao";
+            if let Some(c) = flag.short.filter(|_| i == 0) {
+                out += &format!(" -s {}", c);
+            }
+            out += &format!(" -l {}", long);
+            if flag.value.is_some() && !flag.optional {
+                out += " -r";
+            }
+            if !flag.values.is_empty() {
+                out += &format!(" -f -a {}", quote(&flag.values.join(" ")));
+            }
+            out += &format!(" -d {}\n", quote(flag.help));
+        }
+    }
+    out
+}
+
+fn powershell() -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let mut out = String::from(
+        "Register-ArgumentCompleter -Native -CommandName putao -ScriptBlock {\n    \
+         param($wordToComplete, $commandAst, $cursorPosition)\n    \
+         $prev = $commandAst.CommandElements |\n        \
+         Where-Object { $_.Extent.EndOffset -lt $cursorPosition } |\n        \
+         Select-Object -Last 1\n    \
+         $values = switch ($prev.Extent.Text) {\n",
+    );
+    for flag in FLAGS.iter().filter(|f| !f.values.is_empty()) {
+        let names: Vec<_> = names(flag).iter().map(|n| quote(n)).collect();
+        let values: Vec<_> = flag.values.iter().map(|v| quote(v)).collect();
+        out += &format!(
+            "        {{ $_ -in {} }} {{ {} }}\n",
+            names.join(", "),
+            values.join(", ")
+        );
+    }
+    out += "    }\n    if ($values) {\n        \
+            $values | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n            \
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n        \
+            }\n        \
+            return\n    \
+            }\n    \
+            $flags = @(\n";
+    for flag in FLAGS {
+        for name in names(flag) {
+            out += &format!("        ,@({}, {})\n", quote(&name), quote(flag.help));
+        }
+    }
+    out += "    )\n    \
+            $flags | Where-Object { $_[0] -like \"$wordToComplete*\" } | ForEach-Object {\n        \
+            [System.Management.Automation.CompletionResult]::new($_[0], $_[0], 'ParameterName', $_[1])\n    \
+            }\n\
+            }\n";
+    out
+}
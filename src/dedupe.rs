@@ -0,0 +1,119 @@
+//! `--dedupe`: leaving out files found by recursion whose contents are
+//! those of one already searched, as copies of vendored sources are.
+//!
+//! Files are first told apart by length, so one of a length no other file
+//! has is not read at all; a link to a file already searched is known by
+//! its inode. Contents hashing the same are compared whole before a file
+//! is taken for a copy, so none is left out for a collision.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// A file searched.
+struct Searched {
+    path: PathBuf,
+    /// Identifies the file itself, where the platform says how.
+    id: Option<(u64, u64)>,
+    /// The hash of its contents, once another file is as long.
+    hash: Option<u64>,
+}
+
+/// The files searched so far, by length.
+#[derive(Default)]
+pub struct Dedupe {
+    by_len: HashMap<u64, Vec<Searched>>,
+}
+
+impl Dedupe {
+    /// Reports whether the file at path has the contents of one already
+    /// searched, recording it as searched if not.
+    pub fn duplicate(&mut self, path: &Path) -> io::Result<bool> {
+        let md = fs::metadata(path)?;
+        let id = id(&md);
+        let files = self.by_len.entry(md.len()).or_default();
+        if id.is_some() && files.iter().any(|f| f.id == id) {
+            return Ok(true);
+        }
+        let mut hash = None;
+        for file in files.iter_mut() {
+            let theirs = match file.hash {
+                Some(h) => h,
+                None => *file.hash.insert(hash_file(&file.path)?),
+            };
+            let ours = match hash {
+                Some(h) => h,
+                None => *hash.insert(hash_file(path)?),
+            };
+            if ours == theirs && same_contents(path, &file.path)? {
+                return Ok(true);
+            }
+        }
+        files.push(Searched {
+            path: path.to_path_buf(),
+            id,
+            hash,
+        });
+        Ok(false)
+    }
+}
+
+/// Returns the hash of the contents of the file at path.
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut rdr = File::open(path)?;
+    let mut hasher = DefaultHasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        match rdr.read(&mut buf)? {
+            0 => return Ok(hasher.finish()),
+            n => hasher.write(&buf[..n]),
+        }
+    }
+}
+
+/// Reports whether the files at a and b, as long as each other, hold the
+/// same bytes.
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (
+        BufReader::new(File::open(a)?),
+        BufReader::new(File::open(b)?),
+    );
+    let (mut x, mut y) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = read_full(&mut a, &mut x)?;
+        if n != read_full(&mut b, &mut y)? || x[..n] != y[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads into buf until it is full or the input ends, returning how much
+/// was read.
+fn read_full(rdr: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match rdr.read(&mut buf[n..])? {
+            0 => break,
+            m => n += m,
+        }
+    }
+    Ok(n)
+}
+
+/// Identifies the file md describes by device and inode, on Unix.
+fn id(md: &fs::Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    let id = {
+        use std::os::unix::fs::MetadataExt;
+        Some((md.dev(), md.ino()))
+    };
+    #[cfg(not(unix))]
+    let id = None;
+    id
+}
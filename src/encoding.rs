@@ -0,0 +1,247 @@
+//! Transcoding of UTF-16 and Latin-1 input to UTF-8 before it is searched,
+//! and guessing which of them input without a byte order mark is in.
+
+use std::io::{self, Read};
+
+/// How much of the start of input [`Encoding::detect`] is given.
+pub const DETECT_LEN: usize = 8 * 1024;
+
+/// A text encoding input can be transcoded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, passed through as is apart from a leading BOM.
+    Utf8,
+    /// Little-endian UTF-16.
+    Utf16Le,
+    /// Big-endian UTF-16.
+    Utf16Be,
+    /// ISO-8859-1, every byte standing for the code point of its value.
+    Latin1,
+}
+
+impl Encoding {
+    /// Looks up an encoding by a label such as `utf-16le` or `latin1`,
+    /// ignoring case.
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        let enc = match label.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "utf-16le" | "utf16le" => Encoding::Utf16Le,
+            "utf-16be" | "utf16be" => Encoding::Utf16Be,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            _ => return None,
+        };
+        Some(enc)
+    }
+
+    /// Returns the encoding the byte order mark at the start of bytes
+    /// names, with the length of the mark.
+    pub fn sniff(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        if bytes.starts_with(b"\xEF\xBB\xBF") {
+            Some((Encoding::Utf8, 3))
+        } else if bytes.starts_with(b"\xFF\xFE") {
+            Some((Encoding::Utf16Le, 2))
+        } else if bytes.starts_with(b"\xFE\xFF") {
+            Some((Encoding::Utf16Be, 2))
+        } else {
+            None
+        }
+    }
+
+    /// Guesses the encoding of text without a byte order mark from its
+    /// first bytes: UTF-16 if every other byte is NUL, as it is for ASCII
+    /// text written in it, UTF-8 if the bytes are valid as that, and else
+    /// Latin-1. Returns None for what, with NULs elsewhere, looks binary.
+    /// A head [`DETECT_LEN`] long is taken to be cut from longer input, so
+    /// may end partway through a character.
+    ///
+    /// ```
+    /// use putao_cf::encoding::Encoding;
+    ///
+    /// assert_eq!(Encoding::detect(b"h\0i\0"), Some(Encoding::Utf16Le));
+    /// assert_eq!(Encoding::detect("café".as_bytes()), Some(Encoding::Utf8));
+    /// assert_eq!(Encoding::detect(b"caf\xE9"), Some(Encoding::Latin1));
+    /// assert_eq!(Encoding::detect(b"\x7FELF\0\0\x01"), None);
+    /// ```
+    pub fn detect(head: &[u8]) -> Option<Encoding> {
+        let pairs = head.len() / 2;
+        let nuls = |parity| {
+            let at = head.iter().skip(parity).step_by(2);
+            at.take(pairs).filter(|&&b| b == 0).count()
+        };
+        let (even, odd) = (nuls(0), nuls(1));
+        // Allowing for characters outside ASCII, whose units have no NUL.
+        if pairs > 0 && odd * 2 > pairs && even * 10 < pairs {
+            return Some(Encoding::Utf16Le);
+        }
+        if pairs > 0 && even * 2 > pairs && odd * 10 < pairs {
+            return Some(Encoding::Utf16Be);
+        }
+        if head.contains(&0) {
+            return None;
+        }
+        match std::str::from_utf8(head) {
+            Ok(_) => Some(Encoding::Utf8),
+            Err(e) if e.error_len().is_none() && head.len() >= DETECT_LEN => Some(Encoding::Utf8),
+            Err(_) => Some(Encoding::Latin1),
+        }
+    }
+}
+
+/// A reader yielding the UTF-8 transcoding of what it reads.
+///
+/// Without an encoding, a byte order mark picks one and input without a
+/// mark passes through unchanged, unless [`Decoder::detect`] has its
+/// encoding guessed. Invalid UTF-16 becomes U+FFFD.
+///
+/// ```
+/// use std::io::Read;
+/// use putao_cf::encoding::Decoder;
+///
+/// let mut text = String::new();
+/// Decoder::new(&b"\xFF\xFEh\0i\0"[..], None).read_to_string(&mut text)?;
+/// assert_eq!(text, "hi");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Decoder<R> {
+    rdr: R,
+    encoding: Option<Encoding>,
+    /// Whether input without an encoding or mark has its encoding guessed.
+    detect: bool,
+    /// Whether the start of the input has been checked for a mark.
+    sniffed: bool,
+    /// Bytes read but not yet decoded: part of a code unit or a surrogate
+    /// pair, or the start of the input while sniffing.
+    raw: Vec<u8>,
+    /// Decoded bytes not yet handed out.
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Creates a decoder reading rdr as encoding, or as its mark says.
+    pub fn new(rdr: R, encoding: Option<Encoding>) -> Self {
+        Decoder {
+            rdr,
+            encoding,
+            detect: false,
+            sniffed: false,
+            raw: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Guesses the encoding of input with neither an encoding set nor a
+    /// byte order mark from its start, as [`Encoding::detect`] does.
+    pub fn detect(&mut self, yes: bool) -> &mut Self {
+        self.detect = yes;
+        self
+    }
+
+    /// Reads the first bytes of the input and strips a mark from them,
+    /// settling the encoding.
+    fn sniff(&mut self) -> io::Result<()> {
+        self.sniffed = true;
+        let guess = self.detect && self.encoding.is_none();
+        let mut head = vec![0; if guess { DETECT_LEN } else { 3 }];
+        let mut n = 0;
+        while n < head.len() {
+            match self.rdr.read(&mut head[n..])? {
+                0 => break,
+                m => n += m,
+            }
+        }
+        let head = &head[..n];
+        match Encoding::sniff(head) {
+            Some((enc, len)) if self.encoding.map_or(true, |e| e == enc) => {
+                self.encoding = Some(enc);
+                self.raw.extend_from_slice(&head[len..]);
+            }
+            _ => {
+                if guess {
+                    self.encoding = Encoding::detect(head);
+                }
+                self.raw.extend_from_slice(head);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the next chunk of input into out, returning false at its end.
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut chunk = [0; 8 * 1024];
+        let n = self.rdr.read(&mut chunk)?;
+        self.raw.extend_from_slice(&chunk[..n]);
+        self.out.clear();
+        self.pos = 0;
+        let eof = n == 0;
+        let used = match self.encoding {
+            None | Some(Encoding::Utf8) => {
+                self.out.extend_from_slice(&self.raw);
+                self.raw.len()
+            }
+            Some(Encoding::Latin1) => {
+                self.out
+                    .extend(self.raw.iter().flat_map(|&b| utf8(char::from(b))));
+                self.raw.len()
+            }
+            Some(enc) => utf16(&self.raw, enc == Encoding::Utf16Be, eof, &mut self.out),
+        };
+        self.raw.drain(..used);
+        Ok(!(eof && self.out.is_empty()))
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.sniffed {
+            self.sniff()?;
+        }
+        while self.pos == self.out.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.out.len() - self.pos);
+        buf[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Returns the UTF-8 encoding of c.
+fn utf8(c: char) -> impl Iterator<Item = u8> {
+    let mut buf = [0; 4];
+    let len = c.encode_utf8(&mut buf).len();
+    buf.into_iter().take(len)
+}
+
+/// Decodes the UTF-16 in raw into out, returning how many bytes it used;
+/// an odd byte or a high surrogate at the end is left over unless eof.
+fn utf16(raw: &[u8], big_endian: bool, eof: bool, out: &mut Vec<u8>) -> usize {
+    let mut units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|b| {
+            let b = [b[0], b[1]];
+            if big_endian {
+                u16::from_be_bytes(b)
+            } else {
+                u16::from_le_bytes(b)
+            }
+        })
+        .collect();
+    let mut used = units.len() * 2;
+    if !eof && units.last().is_some_and(|u| (0xD800..0xDC00).contains(u)) {
+        units.pop();
+        used -= 2;
+    }
+    for c in char::decode_utf16(units) {
+        out.extend(utf8(c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+    }
+    if eof && used < raw.len() {
+        out.extend(utf8(char::REPLACEMENT_CHARACTER));
+        used = raw.len();
+    }
+    used
+}
@@ -0,0 +1,177 @@
+//! Errors reported while compiling a pattern or matching it.
+
+use alloc::string::String;
+use core::fmt;
+
+/// An error compiling a pattern, or running out of room matching one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The pattern is malformed at the given char offset; pattern is empty
+    /// unless the error came from compiling a pattern.
+    Syntax {
+        kind: ErrorKind,
+        offset: usize,
+        pattern: String,
+    },
+    /// The compiled pattern would exceed the configured size limit.
+    SizeLimit { limit: usize },
+    /// A search needed more backtracking state than the configured limit.
+    StateLimit { limit: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax {
+                kind,
+                offset,
+                pattern,
+            } if pattern.is_empty() => write!(f, "{} at offset {}", kind, offset),
+            Error::Syntax {
+                kind,
+                offset,
+                pattern,
+            } => {
+                // Tabs stay tabs so the caret lines up however wide they are.
+                let pad: String = pattern
+                    .chars()
+                    .take(*offset)
+                    .map(|c| if c == '\t' { c } else { ' ' })
+                    .collect();
+                write!(
+                    f,
+                    "regex parse error:\n    {}\n    {}^\nerror: {}",
+                    pattern, pad, kind
+                )
+            }
+            Error::SizeLimit { limit } => {
+                write!(f, "compiled pattern exceeds size limit of {} bytes", limit)
+            }
+            Error::StateLimit { limit } => {
+                write!(f, "match state exceeds size limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl Error {
+    pub(crate) fn syntax(kind: ErrorKind, offset: usize) -> Self {
+        Error::Syntax {
+            kind,
+            offset,
+            pattern: String::new(),
+        }
+    }
+
+    /// Attaches the pattern a syntax error was found in, moving its offset
+    /// from the text parsed to the pattern as given with at.
+    pub(crate) fn in_pattern(self, text: &str, at: impl Fn(usize) -> usize) -> Self {
+        match self {
+            Error::Syntax { kind, offset, .. } => Error::Syntax {
+                kind,
+                offset: at(offset),
+                pattern: text.into(),
+            },
+            e => e,
+        }
+    }
+
+    /// Returns the syntax error kind, if this is a syntax error.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Error::Syntax { kind, .. } => Some(*kind),
+            Error::SizeLimit { .. } | Error::StateLimit { .. } => None,
+        }
+    }
+
+    /// Returns the char offset into the pattern where the error was found.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Syntax { offset, .. } => Some(*offset),
+            Error::SizeLimit { .. } | Error::StateLimit { .. } => None,
+        }
+    }
+
+    /// Returns the pattern a syntax error was found in, if known.
+    pub fn pattern(&self) -> Option<&str> {
+        match self {
+            Error::Syntax { pattern, .. } if !pattern.is_empty() => Some(pattern),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of syntax error found in a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A backslash at the end of the pattern, or at the end of a Perl class
+    /// left open, with nothing to escape.
+    InvalidEscape,
+    /// A Perl class escape such as `\d` inside a Perl class, which classes
+    /// cannot hold.
+    UnsupportedClassEscape,
+    /// In Perl syntax, an escaped letter or digit that is not a known escape.
+    UnsupportedEscape,
+    /// A `[` without a closing `]`.
+    UnclosedClass,
+    /// A class range such as `z-a` whose end comes before its start.
+    InvalidClassRange,
+    /// A `(` without a closing `)`.
+    UnclosedGroup,
+    /// A `)` without an opening `(`.
+    UnopenedGroup,
+    /// A `(?` group other than a named group or lookaround, or any `(?`
+    /// group when only POSIX syntax is accepted.
+    UnsupportedGroup,
+    /// A group name that is empty or not a word starting with a letter or `_`.
+    InvalidGroupName,
+    /// A group name used more than once.
+    DuplicateGroupName,
+    /// A `{` quantifier without a minimum count.
+    MissingRepetitionMin,
+    /// A `{` quantifier that is not closed by `}`.
+    InvalidRepetition,
+    /// A `{min,max}` quantifier with max below min.
+    RepetitionRange,
+    /// A repetition count that does not fit in `usize`.
+    RepetitionTooLarge,
+    /// A backreference to a group the pattern does not have.
+    InvalidBackreference { group: usize, groups: usize },
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let ErrorKind::InvalidBackreference { group, groups } = self {
+            return match groups {
+                0 => write!(f, "invalid backreference \\{}: no groups", group),
+                1 => write!(f, "invalid backreference \\{}: only 1 group", group),
+                _ => write!(
+                    f,
+                    "invalid backreference \\{}: only {} groups",
+                    group, groups
+                ),
+            };
+        }
+        f.write_str(match self {
+            ErrorKind::InvalidEscape => "trailing backslash",
+            ErrorKind::UnsupportedClassEscape => "unsupported escape in class",
+            ErrorKind::UnsupportedEscape => "unsupported escape",
+            ErrorKind::UnclosedClass => "unclosed class",
+            ErrorKind::InvalidClassRange => "invalid range end",
+            ErrorKind::UnclosedGroup => "unclosed group",
+            ErrorKind::UnopenedGroup => "unopened group",
+            ErrorKind::UnsupportedGroup => "unsupported group syntax",
+            ErrorKind::InvalidGroupName => "invalid group name",
+            ErrorKind::DuplicateGroupName => "duplicate group name",
+            ErrorKind::MissingRepetitionMin => "invalid repetition quantifier: missing min count",
+            ErrorKind::InvalidRepetition => "invalid repetition quantifier",
+            ErrorKind::RepetitionRange => "invalid repetition quantifier: max < min",
+            ErrorKind::RepetitionTooLarge => "repetition count too large",
+            ErrorKind::InvalidBackreference { .. } => unreachable!(),
+        })
+    }
+}
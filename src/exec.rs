@@ -0,0 +1,783 @@
+//! Backtracking execution of parsed nodes.
+//!
+//! What remains to be matched and the alternatives left to try are kept on
+//! explicit stacks rather than the call stack, so no haystack is too long
+//! to search; only lookarounds recurse, as deep as the pattern nests them.
+
+use alloc::collections::BTreeSet;
+use alloc::{format, string::String, vec::Vec};
+use core::mem::size_of;
+use core::ptr;
+
+use crate::ast::ClassPerl;
+use crate::compile::Node;
+use crate::regex::Config;
+
+/// Capture spans, indexed by slot.
+pub(crate) type Caps = Vec<Option<(usize, usize)>>;
+
+/// Decodes the char at pos; invalid UTF-8 decodes as U+FFFD one byte wide.
+pub(crate) fn decode(hay: &[u8], pos: usize) -> Option<(char, usize)> {
+    let b = *hay.get(pos)?;
+    let w = match b {
+        0x00..=0x7F => return Some((b as char, 1)),
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 0,
+    };
+    hay.get(pos..pos + w)
+        .and_then(|s| core::str::from_utf8(s).ok())
+        .and_then(|s| s.chars().next())
+        .map_or(Some((char::REPLACEMENT_CHARACTER, 1)), |c| Some((c, w)))
+}
+
+/// Buffers a matcher reuses from one search to the next.
+#[derive(Debug, Default)]
+pub(crate) struct Scratch {
+    /// The capture spans of the match being tried.
+    caps: Caps,
+    /// Spans overwritten since the search began, with the values they had,
+    /// for backtracking to restore.
+    undo: Vec<(usize, Option<(usize, usize)>)>,
+}
+
+/// Returns where the char ending at pos starts, stepping back over
+/// continuation bytes.
+fn start_before(hay: &[u8], pos: usize) -> Option<usize> {
+    (pos.saturating_sub(4)..pos)
+        .rev()
+        .find(|&i| hay[i] & 0xC0 != 0x80)
+        .or(pos.checked_sub(1))
+}
+
+/// Decodes the char ending at pos.
+fn decode_before(hay: &[u8], pos: usize) -> Option<char> {
+    let start = start_before(hay, pos)?;
+    match decode(hay, start) {
+        Some((c, w)) if start + w == pos => Some(c),
+        _ => Some(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+/// A step left to take once the nodes at hand have matched.
+#[derive(Clone, Copy, Debug)]
+enum Frame<'a> {
+    /// Matches the nodes.
+    Seq(&'a [Node]),
+    /// Records the group in the slot as spanning from the offset.
+    CapEnd(usize, usize),
+    /// Asserts the position is the offset; ends a lookbehind branch.
+    At(usize),
+    /// Repeats inner between min and, if bounded, max more times, as many
+    /// as possible if greedy; the iteration just matched, if it began at
+    /// `from`, must have moved past it.
+    Repeat {
+        inner: &'a Node,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+        from: Option<usize>,
+    },
+}
+
+/// A frame and the index of the one after it on the continuation stack.
+#[derive(Clone, Copy, Debug)]
+struct Cont<'a> {
+    frame: Frame<'a>,
+    next: Option<usize>,
+}
+
+/// Where matching stands: the position, the nodes at hand and the index
+/// of the frame to continue with after them.
+#[derive(Clone, Copy)]
+struct State<'a> {
+    pos: usize,
+    nodes: &'a [Node],
+    cont: Option<usize>,
+}
+
+/// An alternative to go back to once the one being tried fails.
+enum Choice<'a> {
+    /// Resumes from the state.
+    Try(State<'a>),
+    /// Matches the first of the branches from pos, leaving the rest for later.
+    Branches {
+        pos: usize,
+        brs: &'a [Vec<Node>],
+        cont: Option<usize>,
+    },
+    /// Gives back the last char of a greedy repetition of a single char
+    /// that began at start and repeated count times to end, while more than
+    /// min remain, then resumes with nodes and cont.
+    Back {
+        start: usize,
+        end: usize,
+        count: usize,
+        min: usize,
+        nodes: &'a [Node],
+        cont: Option<usize>,
+    },
+}
+
+/// A choice, with the lengths of the undo log and continuation stack when
+/// it was made.
+struct Saved<'a> {
+    choice: Choice<'a>,
+    undo: usize,
+    conts: usize,
+}
+
+/// What one step of the matcher came to.
+enum Step {
+    Next,
+    Fail,
+    Done,
+}
+
+/// Matcher state for one haystack: the input, options and step budget.
+pub(crate) struct Matcher<'a> {
+    hay: &'a [u8],
+    cfg: &'a Config,
+    scratch: &'a mut Scratch,
+    /// Alternatives left to try, the latest last.
+    stack: Vec<Saved<'a>>,
+    /// Frames the states on the stack continue with.
+    conts: Vec<Cont<'a>>,
+    steps: usize,
+    /// If set, the most bytes of backtracking state a search may keep.
+    pub(crate) state_limit: Option<usize>,
+    /// Set once the search needed more state than the state limit.
+    pub(crate) overflowed: bool,
+    /// The slot `\K` records its position in, past those of the groups.
+    keep: usize,
+    /// In shortest mode, the earliest accepting end seen so far.
+    pub(crate) shortest: Option<usize>,
+    /// If tracing, every node tried, with the position it was tried at.
+    pub(crate) trace: Option<Vec<(usize, String)>>,
+    /// The node a leading repetition with no upper bound repeats, if the
+    /// nodes searched for start with one and hold no backreference.
+    lead: Option<&'a Node>,
+    /// Where an iteration of the leading repetition was tried in matches
+    /// from earlier starts, up to the end of a run of it if it repeats a
+    /// single char and at each position in heads otherwise.
+    reach: Option<usize>,
+    heads: BTreeSet<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    pub(crate) fn new(
+        hay: &'a [u8],
+        cfg: &'a Config,
+        scratch: &'a mut Scratch,
+        keep: usize,
+    ) -> Self {
+        Matcher {
+            hay,
+            cfg,
+            scratch,
+            stack: Vec::new(),
+            conts: Vec::new(),
+            steps: 0,
+            state_limit: None,
+            overflowed: false,
+            keep,
+            shortest: None,
+            trace: None,
+            lead: None,
+            reach: None,
+            heads: BTreeSet::new(),
+        }
+    }
+
+    /// Notes the leading repetition of nodes, if they start with one with
+    /// no upper bound and hold no backreference, so that the starts a match
+    /// failing from an earlier one rules out are known; see
+    /// [`ruled_out`](Matcher::ruled_out).
+    pub(crate) fn skip_starts(&mut self, nodes: &'a [Node]) {
+        self.lead = match nodes.first() {
+            Some(Node::Lazy(inner, _, None)) => Some(&**inner),
+            Some(node) => bounds(node).and_then(|(inner, _, max)| max.is_none().then_some(inner)),
+            None => None,
+        }
+        .filter(|_| !has_ref(nodes));
+    }
+
+    /// Reports whether no match can start at pos, as an iteration of the
+    /// leading repetition was tried there in a match from an earlier start
+    /// that failed.
+    ///
+    /// A match from pos could only repeat it as one from that start could
+    /// go on to, and what follows cannot tell the two apart.
+    pub(crate) fn ruled_out(&self, pos: usize) -> bool {
+        self.reach.is_some_and(|end| pos <= end) || self.heads.contains(&pos)
+    }
+
+    /// Returns true once the backtrack limit or the state limit is spent;
+    /// every branch then fails.
+    fn exhausted(&mut self) -> bool {
+        self.steps += 1;
+        if let Some(limit) = self.state_limit {
+            let used = self.stack.len() * size_of::<Saved>()
+                + self.conts.len() * size_of::<Cont>()
+                + self.scratch.undo.len() * size_of::<(usize, Option<(usize, usize)>)>();
+            if used > limit {
+                self.overflowed = true;
+            }
+        }
+        self.overflowed || self.cfg.backtrack_limit.is_some_and(|l| self.steps > l)
+    }
+
+    fn is_digit(&self, c: char) -> bool {
+        if self.cfg.unicode {
+            c.is_numeric()
+        } else {
+            c.is_ascii_digit()
+        }
+    }
+
+    fn is_word(&self, c: char) -> bool {
+        if self.cfg.unicode {
+            c.is_alphanumeric() || c == '_'
+        } else {
+            c.is_ascii_alphanumeric() || c == '_'
+        }
+    }
+
+    fn is_space(&self, c: char) -> bool {
+        if self.cfg.unicode {
+            c.is_whitespace()
+        } else {
+            matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c')
+        }
+    }
+
+    /// Reports whether c is in the Perl class p.
+    fn perl(&self, p: ClassPerl, c: char) -> bool {
+        match p {
+            ClassPerl::Digit => self.is_digit(c),
+            ClassPerl::Word => self.is_word(c),
+            ClassPerl::Space => self.is_space(c),
+            ClassPerl::NotDigit => !self.is_digit(c),
+            ClassPerl::NotWord => !self.is_word(c),
+            ClassPerl::NotSpace => !self.is_space(c),
+        }
+    }
+
+    fn char_eq(&self, x: char, y: char) -> bool {
+        if x == y {
+            true
+        } else if !self.cfg.case_insensitive {
+            false
+        } else if self.cfg.unicode {
+            x.to_lowercase().eq(y.to_lowercase())
+        } else {
+            x.eq_ignore_ascii_case(&y)
+        }
+    }
+
+    /// Reports whether c, or with case folding a case variant of it, is
+    /// in set or one of ranges, comparing scalar values.
+    fn class_has(&self, set: &str, ranges: &[(char, char)], c: char) -> bool {
+        if set.chars().any(|x| self.char_eq(x, c)) {
+            return true;
+        }
+        let within = |c: char| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&c));
+        if within(c) {
+            true
+        } else if !self.cfg.case_insensitive {
+            false
+        } else if self.cfg.unicode {
+            c.to_lowercase().any(within) || c.to_uppercase().any(within)
+        } else {
+            within(c.to_ascii_lowercase()) || within(c.to_ascii_uppercase())
+        }
+    }
+
+    /// Advances past the next char if it satisfies the predicate.
+    fn step(&self, pos: usize, pred: impl Fn(char) -> bool) -> Option<usize> {
+        decode(self.hay, pos)
+            .filter(|&(c, _)| pred(c))
+            .map(|(_, w)| pos + w)
+    }
+
+    /// Matches the text of span against the haystack at pos, returning the end.
+    fn backref(&self, pos: usize, (s, e): (usize, usize)) -> Option<usize> {
+        let (mut p, mut q) = (pos, s);
+        while q < e {
+            let (x, wx) = decode(self.hay, q)?;
+            let (y, wy) = decode(self.hay, p)?;
+            if !self.char_eq(x, y) {
+                return None;
+            }
+            q += wx;
+            p += wy;
+        }
+        Some(p)
+    }
+
+    /// Backtracking matcher for a sequence of nodes from a position,
+    /// returning where the match ends and the spans of its groups.
+    pub(crate) fn match_from(&mut self, pos: usize, nodes: &'a [Node]) -> Option<(usize, Caps)> {
+        self.scratch.caps.clear();
+        self.scratch.undo.clear();
+        self.stack.clear();
+        self.conts.clear();
+        let end = self.run(pos, nodes, None)?;
+        Some((end, self.scratch.caps.clone()))
+    }
+
+    /// Matches nodes and then the frames from cont on, returning the end.
+    ///
+    /// Alternatives left untried are dropped once a match is found, and
+    /// the groups it set are kept; if none is found, they are restored.
+    fn run(&mut self, pos: usize, nodes: &'a [Node], cont: Option<usize>) -> Option<usize> {
+        let (base, undo, conts) = (self.stack.len(), self.scratch.undo.len(), self.conts.len());
+        let mut s = State { pos, nodes, cont };
+        loop {
+            let step = match self.exhausted() {
+                true => Step::Fail,
+                false => self.advance(&mut s),
+            };
+            match step {
+                Step::Next => continue,
+                Step::Done => {
+                    self.stack.truncate(base);
+                    self.conts.truncate(conts);
+                    return Some(s.pos);
+                }
+                Step::Fail => match self.resume(base) {
+                    Some(next) if !self.overflowed => s = next,
+                    _ => {
+                        self.stack.truncate(base);
+                        self.restore(undo);
+                        self.conts.truncate(conts);
+                        return None;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Takes one step from s: matches the node at hand, or if none is
+    /// left, the next frame.
+    fn advance(&mut self, s: &mut State<'a>) -> Step {
+        let Some((head, tail)) = s.nodes.split_first() else {
+            return self.pop(s);
+        };
+        if let Some(trace) = &mut self.trace {
+            trace.push((s.pos, format!("{:?}", head)));
+        }
+        s.nodes = tail;
+        if let Some((inner, min, max)) = bounds(head).filter(|b| is_single(b.0)) {
+            return self.greedy(s, inner, (min, max));
+        }
+        let pos = s.pos;
+        let ok = match head {
+            Node::Lit(_)
+            | Node::Perl(_)
+            | Node::Any
+            | Node::Pos(..)
+            | Node::Neg(..) => match self.single(head, pos) {
+                Some(p) => {
+                    s.pos = p;
+                    true
+                }
+                None => false,
+            },
+            Node::Ref(n) => {
+                let span = self.scratch.caps.get(n - 1).copied().flatten();
+                match span.and_then(|span| self.backref(pos, span)) {
+                    Some(p) => {
+                        s.pos = p;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Node::Start => pos == 0 || self.cfg.multi_line && self.hay[pos - 1] == b'\n',
+            Node::End => self.at_end(pos),
+            Node::NotWordBefore => !decode_before(self.hay, pos).is_some_and(|c| self.is_word(c)),
+            Node::NotWordAfter => !decode(self.hay, pos).is_some_and(|(c, _)| self.is_word(c)),
+            Node::Boundary(at) => {
+                let before = decode_before(self.hay, pos).is_some_and(|c| self.is_word(c));
+                let after = decode(self.hay, pos).is_some_and(|(c, _)| self.is_word(c));
+                (before != after) == *at
+            }
+            Node::Ahead(positive, brs) => {
+                let found = brs.iter().any(|b| self.run(pos, b, None).is_some());
+                found == *positive
+            }
+            Node::Behind(positive, brs, max) => self.behind(pos, brs, *max) == *positive,
+            Node::Keep => {
+                self.set(self.keep, Some((pos, pos)));
+                true
+            }
+            Node::Accept(end) => {
+                if *end && !self.at_end(pos) {
+                    return Step::Fail;
+                }
+                if let Some(best) = &mut self.shortest {
+                    *best = pos.min(*best);
+                    return Step::Fail;
+                }
+                return Step::Done;
+            }
+            Node::Opt(inner) => {
+                self.save(Choice::Try(*s));
+                self.descend(s, core::slice::from_ref(&**inner));
+                true
+            }
+            Node::Star(inner) => return self.repeat(s, inner, (0, None), true),
+            Node::Plus(inner) => return self.repeat(s, inner, (1, None), true),
+            Node::Rep(inner, k) => return self.repeat(s, inner, (*k, Some(*k)), true),
+            Node::MinRep(inner, k) => return self.repeat(s, inner, (*k, None), true),
+            Node::RangeRep(inner, lo, hi) => return self.repeat(s, inner, (*lo, Some(*hi)), true),
+            Node::Lazy(inner, min, max) => return self.repeat(s, inner, (*min, *max), false),
+            Node::Cap(id, brs) => {
+                s.cont = self.push_seq(s);
+                s.cont = self.push(Frame::CapEnd(id - 1, pos), s.cont);
+                return self.branch(s, brs);
+            }
+            Node::Alt(brs) => {
+                s.cont = self.push_seq(s);
+                return self.branch(s, brs);
+            }
+        };
+        match ok {
+            true => Step::Next,
+            false => Step::Fail,
+        }
+    }
+
+    /// Continues s with the next frame, once its nodes have matched.
+    fn pop(&mut self, s: &mut State<'a>) -> Step {
+        let Some(i) = s.cont else {
+            return Step::Done;
+        };
+        let Cont { frame, next } = self.conts[i];
+        s.cont = next;
+        match frame {
+            Frame::Seq(nodes) => s.nodes = nodes,
+            Frame::CapEnd(slot, start) => self.set(slot, Some((start, s.pos))),
+            Frame::At(end) if s.pos != end => return Step::Fail,
+            Frame::At(_) => {}
+            Frame::Repeat {
+                inner,
+                min,
+                max,
+                greedy,
+                from,
+            } => {
+                if from == Some(s.pos) {
+                    return Step::Fail;
+                }
+                return self.iterate(s, inner, (min, max), greedy);
+            }
+        }
+        Step::Next
+    }
+
+    /// Goes back to the latest alternative above base, restoring the
+    /// groups and frames as they were when it was made.
+    fn resume(&mut self, base: usize) -> Option<State<'a>> {
+        if self.stack.len() <= base {
+            return None;
+        }
+        let saved = self.stack.last_mut().unwrap();
+        let (undo, conts) = (saved.undo, saved.conts);
+        let (s, done) = match &mut saved.choice {
+            Choice::Try(s) => (*s, true),
+            Choice::Branches { pos, brs, cont } => {
+                let s = State {
+                    pos: *pos,
+                    nodes: &brs[0],
+                    cont: *cont,
+                };
+                *brs = &brs[1..];
+                (s, brs.is_empty())
+            }
+            Choice::Back {
+                start,
+                end,
+                count,
+                min,
+                nodes,
+                cont,
+            } => {
+                *end = back_one(self.hay, *start, *end);
+                *count -= 1;
+                let s = State {
+                    pos: *end,
+                    nodes,
+                    cont: *cont,
+                };
+                (s, count == min)
+            }
+        };
+        if done {
+            self.stack.pop();
+        }
+        self.restore(undo);
+        self.conts.truncate(conts);
+        Some(s)
+    }
+
+    /// Saves an alternative to come back to.
+    fn save(&mut self, choice: Choice<'a>) {
+        self.stack.push(Saved {
+            choice,
+            undo: self.scratch.undo.len(),
+            conts: self.conts.len(),
+        });
+    }
+
+    /// Pushes a frame continuing with next, returning its index.
+    fn push(&mut self, frame: Frame<'a>, next: Option<usize>) -> Option<usize> {
+        self.conts.push(Cont { frame, next });
+        Some(self.conts.len() - 1)
+    }
+
+    /// Pushes the nodes s has at hand as a frame, if any, returning the
+    /// frame to continue with after them.
+    fn push_seq(&mut self, s: &State<'a>) -> Option<usize> {
+        match s.nodes {
+            [] => s.cont,
+            nodes => self.push(Frame::Seq(nodes), s.cont),
+        }
+    }
+
+    /// Continues s with nodes, then with the nodes it had at hand.
+    fn descend(&mut self, s: &mut State<'a>, nodes: &'a [Node]) {
+        s.cont = self.push_seq(s);
+        s.nodes = nodes;
+    }
+
+    /// Continues s with the first of the branches, saving the rest.
+    fn branch(&mut self, s: &mut State<'a>, brs: &'a [Vec<Node>]) -> Step {
+        let Some((first, rest)) = brs.split_first() else {
+            return Step::Fail;
+        };
+        if !rest.is_empty() {
+            self.save(Choice::Branches {
+                pos: s.pos,
+                brs: rest,
+                cont: s.cont,
+            });
+        }
+        s.nodes = first;
+        Step::Next
+    }
+
+    /// Continues s with a repetition of inner, then with the nodes it had
+    /// at hand.
+    fn repeat(
+        &mut self,
+        s: &mut State<'a>,
+        inner: &'a Node,
+        bounds: (usize, Option<usize>),
+        greedy: bool,
+    ) -> Step {
+        s.cont = self.push_seq(s);
+        s.nodes = &[];
+        self.iterate(s, inner, bounds, greedy)
+    }
+
+    /// Continues s, which has no nodes at hand, with the iterations of
+    /// inner still to match: those required, then as many or as few more
+    /// as the tail allows. Each optional iteration must consume something,
+    /// so one matching nothing cannot loop.
+    fn iterate(
+        &mut self,
+        s: &mut State<'a>,
+        inner: &'a Node,
+        (min, max): (usize, Option<usize>),
+        greedy: bool,
+    ) -> Step {
+        if max.is_none() && self.lead.is_some_and(|lead| ptr::eq(lead, inner)) {
+            self.heads.insert(s.pos);
+        }
+        let less = max.map(|m| m.saturating_sub(1));
+        if min > 0 {
+            let rest = Frame::Repeat {
+                inner,
+                min: min - 1,
+                max: less,
+                greedy,
+                from: None,
+            };
+            s.cont = self.push(rest, s.cont);
+            s.nodes = core::slice::from_ref(inner);
+            return Step::Next;
+        }
+        if max == Some(0) {
+            return Step::Next;
+        }
+        let again = Frame::Repeat {
+            inner,
+            min: 0,
+            max: less,
+            greedy,
+            from: Some(s.pos),
+        };
+        if greedy {
+            self.save(Choice::Try(*s));
+            s.cont = self.push(again, s.cont);
+            s.nodes = core::slice::from_ref(inner);
+        } else {
+            let cont = self.push(again, s.cont);
+            self.save(Choice::Try(State {
+                pos: s.pos,
+                nodes: core::slice::from_ref(inner),
+                cont,
+            }));
+        }
+        Step::Next
+    }
+
+    /// Sets the span of a slot, logging the old one to restore.
+    fn set(&mut self, slot: usize, span: Option<(usize, usize)>) {
+        let caps = &mut self.scratch.caps;
+        if caps.len() <= slot {
+            caps.resize(slot + 1, None);
+        }
+        self.scratch.undo.push((slot, caps[slot]));
+        caps[slot] = span;
+    }
+
+    /// Restores the spans set since the undo log was len long.
+    fn restore(&mut self, len: usize) {
+        while self.scratch.undo.len() > len {
+            let (slot, span) = self.scratch.undo.pop().unwrap();
+            self.scratch.caps[slot] = span;
+        }
+    }
+
+    /// Reports whether `$` matches at pos: at the end of the haystack, or
+    /// of a line in multi-line mode, counting a `\r` before it in CRLF mode.
+    fn at_end(&self, pos: usize) -> bool {
+        let rest = &self.hay[pos..];
+        let rest = match rest.strip_prefix(b"\r") {
+            Some(after) if self.cfg.crlf => after,
+            _ => rest,
+        };
+        rest.is_empty() || self.cfg.multi_line && rest[0] == b'\n'
+    }
+
+    /// Reports whether one of the branches matches ending at pos, trying
+    /// the nearest starts first; the groups of the match found are kept.
+    fn behind(&mut self, pos: usize, brs: &'a [Vec<Node>], max: Option<usize>) -> bool {
+        let conts = self.conts.len();
+        let at = self.push(Frame::At(pos), None);
+        let mut start = pos;
+        let mut chars = 0;
+        let found = loop {
+            if brs.iter().any(|b| self.run(start, b, at).is_some()) {
+                break true;
+            }
+            if max.is_some_and(|m| chars >= m) {
+                break false;
+            }
+            match start_before(self.hay, start) {
+                Some(before) => start = before,
+                None => break false,
+            }
+            chars += 1;
+        };
+        self.conts.truncate(conts);
+        found
+    }
+
+    /// Matches a node that consumes exactly one char, returning the position after it.
+    fn single(&self, node: &Node, pos: usize) -> Option<usize> {
+        match node {
+            Node::Lit(ch) => self.step(pos, |c| self.char_eq(c, *ch)),
+            Node::Perl(p) => self.step(pos, |c| self.perl(*p, c)),
+            Node::Any => self.step(pos, |c| {
+                self.cfg.dot_matches_new_line || !(c == '\n' || self.cfg.crlf && c == '\r')
+            }),
+            Node::Pos(s, r) => self.step(pos, |c| self.class_has(s, r, c)),
+            Node::Neg(s, r) => self.step(pos, |c| !self.class_has(s, r, c)),
+            _ => None,
+        }
+    }
+
+    /// Repeats a single-char node as often as bounds allow, saving the
+    /// chance to back off one char at a time if what follows fails.
+    fn greedy(
+        &mut self,
+        s: &mut State<'a>,
+        inner: &Node,
+        (min, max): (usize, Option<usize>),
+    ) -> Step {
+        let start = s.pos;
+        let mut count = 0;
+        while max.map_or(true, |m| count < m) {
+            match self.single(inner, s.pos) {
+                Some(p) => s.pos = p,
+                None => break,
+            }
+            count += 1;
+        }
+        if max.is_none() && self.lead.is_some_and(|lead| ptr::eq(lead, inner)) {
+            self.reach = self.reach.max(Some(s.pos));
+        }
+        if count < min {
+            return Step::Fail;
+        }
+        if count > min {
+            self.save(Choice::Back {
+                start,
+                end: s.pos,
+                count,
+                min,
+                nodes: s.nodes,
+                cont: s.cont,
+            });
+        }
+        Step::Next
+    }
+}
+
+/// Returns where the char a forward scan from start stepped over to reach
+/// end began: the start of the UTF-8 sequence ending there if one does,
+/// else the byte before, as invalid bytes are stepped over one at a time.
+fn back_one(hay: &[u8], start: usize, end: usize) -> usize {
+    match start_before(hay, end) {
+        Some(p) if p >= start && decode(hay, p).is_some_and(|(_, w)| p + w == end) => p,
+        _ => end - 1,
+    }
+}
+
+/// Returns the repeated node and its min and max counts if node is a quantifier.
+fn bounds(node: &Node) -> Option<(&Node, usize, Option<usize>)> {
+    match node {
+        Node::Opt(n) => Some((n, 0, Some(1))),
+        Node::Star(n) => Some((n, 0, None)),
+        Node::Plus(n) => Some((n, 1, None)),
+        Node::Rep(n, k) => Some((n, *k, Some(*k))),
+        Node::MinRep(n, k) => Some((n, *k, None)),
+        Node::RangeRep(n, lo, hi) => Some((n, *lo, Some(*hi))),
+        _ => None,
+    }
+}
+
+/// Reports whether any of nodes is or holds a backreference.
+fn has_ref(nodes: &[Node]) -> bool {
+    nodes.iter().any(|node| match node {
+        Node::Ref(_) => true,
+        Node::Cap(_, brs) | Node::Alt(brs) | Node::Ahead(_, brs) | Node::Behind(_, brs, _) => {
+            brs.iter().any(|b| has_ref(b))
+        }
+        Node::Lazy(inner, ..) => has_ref(core::slice::from_ref(&**inner)),
+        node => bounds(node).is_some_and(|(inner, ..)| has_ref(core::slice::from_ref(inner))),
+    })
+}
+
+/// Whether node always consumes exactly one char and never backtracks.
+fn is_single(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::Lit(_) | Node::Perl(_) | Node::Any | Node::Pos(..) | Node::Neg(..)
+    )
+}
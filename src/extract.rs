@@ -0,0 +1,344 @@
+//! `--extract`: searching the text of documents rather than their bytes,
+//! through an [`Extractor`] chosen by extension or, failing that, by the
+//! MIME type the start of a file shows.
+//!
+//! Built in are Office Open XML and OpenDocument files, whose text is read
+//! from the XML inside them, and PDF files, read through `pdftotext`.
+//! `--extractor KEY=COMMAND` adds others, or replaces them.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::archive;
+use crate::pipe::Piped;
+
+/// Turns a file of some kind into the text searched in its place.
+pub trait Extractor {
+    /// Returns a reader of the text in the file at path; limit bounds what
+    /// an archive holding the text may unpack to.
+    fn extract(&self, path: &Path, limit: u64) -> io::Result<Box<dyn Read>>;
+}
+
+/// Reads a file as it is.
+struct Text;
+
+impl Extractor for Text {
+    fn extract(&self, path: &Path, _: u64) -> io::Result<Box<dyn Read>> {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// Reads the output of a command, run with the path in place of a `{}`
+/// argument, or else after its arguments and as its stdin.
+struct Tool(Vec<String>);
+
+impl Extractor for Tool {
+    fn extract(&self, path: &Path, _: u64) -> io::Result<Box<dyn Read>> {
+        if !self.0.iter().any(|arg| arg == "{}") {
+            return Ok(Box::new(Piped::spawn(&self.0, path)?));
+        }
+        let path = path.as_os_str();
+        let cmd: Vec<_> = self
+            .0
+            .iter()
+            .map(|arg| match arg.as_str() {
+                "{}" => path,
+                arg => arg.as_ref(),
+            })
+            .collect();
+        Ok(Box::new(Piped::run(&cmd)?))
+    }
+}
+
+/// Reads the text of the XML parts of a zip-based document, in order, a
+/// line for each element that ends a paragraph.
+struct Document {
+    /// Whether the part named is one holding text.
+    part: fn(&str) -> bool,
+    /// The elements ending paragraphs.
+    breaks: &'static [&'static str],
+}
+
+impl Extractor for Document {
+    fn extract(&self, path: &Path, limit: u64) -> io::Result<Box<dyn Read>> {
+        let mut parts = read_parts(path, limit, self.part)?;
+        // Slides and sheets are numbered, slide10 coming after slide9.
+        parts.sort_by_key(|(name, _)| numbered(name));
+        let mut text = String::new();
+        for (_, xml) in parts {
+            for token in tokens(&String::from_utf8_lossy(&xml)) {
+                match token {
+                    Token::Text(s) => text.push_str(&s),
+                    Token::Tag(name) if self.breaks.contains(&name) => text.push('\n'),
+                    Token::Tag("w:tab" | "text:tab") => text.push('\t'),
+                    Token::Tag("w:br" | "a:br" | "text:line-break") => text.push('\n'),
+                    Token::Tag(_) | Token::Open(..) => {}
+                }
+            }
+        }
+        Ok(Box::new(io::Cursor::new(text.into_bytes())))
+    }
+}
+
+/// Reads the sheets of an Office Open XML spreadsheet, a line for each row
+/// with its cells separated by tabs.
+struct Spreadsheet;
+
+impl Extractor for Spreadsheet {
+    fn extract(&self, path: &Path, limit: u64) -> io::Result<Box<dyn Read>> {
+        let mut parts = read_parts(path, limit, |name| {
+            name == "xl/sharedStrings.xml"
+                || (name.starts_with("xl/worksheets/sheet") && name.ends_with(".xml"))
+        })?;
+        parts.sort_by_key(|(name, _)| numbered(name));
+        let mut shared = Vec::new();
+        if let Some(i) = parts.iter().position(|(n, _)| n == "xl/sharedStrings.xml") {
+            let (_, xml) = parts.remove(i);
+            let mut string = String::new();
+            for token in tokens(&String::from_utf8_lossy(&xml)) {
+                match token {
+                    Token::Text(s) => string.push_str(&s),
+                    Token::Tag("/si") => shared.push(std::mem::take(&mut string)),
+                    _ => {}
+                }
+            }
+        }
+        let mut text = String::new();
+        for (_, xml) in parts {
+            // The type of the cell being read, `s` for a shared string, and
+            // whether its value is being read.
+            let (mut kind, mut value, mut cells) = (String::new(), false, 0);
+            for token in tokens(&String::from_utf8_lossy(&xml)) {
+                match token {
+                    Token::Open("c", attrs) => {
+                        kind = attr(attrs, "t").unwrap_or_default();
+                        if cells > 0 {
+                            text.push('\t');
+                        }
+                        cells += 1;
+                    }
+                    // An empty cell, which still takes up a column.
+                    Token::Tag("c") => {
+                        if cells > 0 {
+                            text.push('\t');
+                        }
+                        cells += 1;
+                    }
+                    Token::Open("v" | "t", _) => value = true,
+                    Token::Tag("/v" | "/t") => value = false,
+                    Token::Text(s) if value && kind == "s" => {
+                        let string = s.trim().parse::<usize>().ok().and_then(|i| shared.get(i));
+                        text.push_str(string.map_or("", String::as_str));
+                    }
+                    Token::Text(s) if value => text.push_str(&s),
+                    Token::Tag("/row") => {
+                        text.push('\n');
+                        cells = 0;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Box::new(io::Cursor::new(text.into_bytes())))
+    }
+}
+
+/// Returns the files in the zip archive at path that part accepts.
+fn read_parts(
+    path: &Path,
+    limit: u64,
+    part: fn(&str) -> bool,
+) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut parts = Vec::new();
+    let mut failed = None;
+    archive::zip(File::open(path)?, limit, |name, rdr| {
+        if part(name) {
+            let mut data = Vec::new();
+            match rdr.read_to_end(&mut data) {
+                Ok(_) => parts.push((name.to_string(), data)),
+                Err(e) => failed = Some(e),
+            }
+        }
+        failed.is_none()
+    })?;
+    match failed {
+        Some(e) => Err(e),
+        None => Ok(parts),
+    }
+}
+
+/// Returns a key ordering names by the number in them, then by name.
+fn numbered(name: &str) -> (u64, String) {
+    let digits: String = name.chars().filter(char::is_ascii_digit).collect();
+    (digits.parse().unwrap_or(0), name.to_string())
+}
+
+/// A piece of XML: text with its entities decoded, a start tag with its
+/// attributes, or any other tag by its name, such as `/w:p` or `w:br`.
+enum Token<'x> {
+    Text(String),
+    Open(&'x str, &'x str),
+    Tag(&'x str),
+}
+
+/// Splits xml into tokens, leaving out declarations and comments.
+fn tokens(xml: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = xml;
+    std::iter::from_fn(move || loop {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(tag) = rest.strip_prefix('<') else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = unescape(&rest[..end]);
+            rest = &rest[end..];
+            return Some(Token::Text(text));
+        };
+        let end = tag.find('>').unwrap_or(tag.len());
+        let inner = &tag[..end];
+        rest = tag.get(end + 1..).unwrap_or("");
+        if inner.starts_with(['?', '!']) {
+            continue;
+        }
+        let closed = inner.ends_with('/');
+        let inner = inner.trim_end_matches('/');
+        let (name, attrs) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+        return Some(match closed || name.starts_with('/') {
+            true => Token::Tag(name),
+            false => Token::Open(name, attrs),
+        });
+    })
+}
+
+/// Returns the value of the attribute named name in attrs.
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let at = attrs.find(&format!("{}=", name))?;
+    let rest = &attrs[at + name.len() + 1..];
+    let quote = rest.chars().next()?;
+    let value = rest[1..].split(quote).next()?;
+    Some(unescape(value))
+}
+
+/// Decodes the entities in XML text.
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|n| n.ok())
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The extractors known, by lowercased extension or MIME type.
+pub struct Extractors {
+    by_key: HashMap<String, Box<dyn Extractor>>,
+}
+
+impl Default for Extractors {
+    /// The built-in extractors.
+    fn default() -> Self {
+        let mut by_key: HashMap<String, Box<dyn Extractor>> = HashMap::new();
+        let word = || Document {
+            part: |name| name == "word/document.xml",
+            breaks: &["/w:p"],
+        };
+        let slides = || Document {
+            part: |name| name.starts_with("ppt/slides/slide") && name.ends_with(".xml"),
+            breaks: &["/a:p"],
+        };
+        let open = || Document {
+            part: |name| name == "content.xml",
+            breaks: &["/text:p", "/text:h"],
+        };
+        let pdf = || {
+            Tool(
+                ["pdftotext", "-q", "-layout", "{}", "-"]
+                    .map(String::from)
+                    .to_vec(),
+            )
+        };
+        by_key.insert("docx".to_string(), Box::new(word()));
+        by_key.insert("pptx".to_string(), Box::new(slides()));
+        by_key.insert("xlsx".to_string(), Box::new(Spreadsheet));
+        for ext in ["odt", "ods", "odp"] {
+            by_key.insert(ext.to_string(), Box::new(open()));
+        }
+        by_key.insert("pdf".to_string(), Box::new(pdf()));
+        by_key.insert("application/pdf".to_string(), Box::new(pdf()));
+        Extractors { by_key }
+    }
+}
+
+impl Extractors {
+    /// Adds an extractor from a `KEY=COMMAND` spec, the key an extension
+    /// or MIME type and the command `text` to read files as they are.
+    pub fn add(&mut self, spec: &str) -> Result<()> {
+        let (key, cmd) = spec
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty())
+            .ok_or_else(|| anyhow!("invalid extractor '{}': expected KEY=COMMAND", spec))?;
+        let extractor: Box<dyn Extractor> = match cmd.trim() {
+            "text" => Box::new(Text),
+            "" => return Err(anyhow!("invalid extractor '{}': COMMAND is empty", spec)),
+            cmd => Box::new(Tool(cmd.split_whitespace().map(str::to_string).collect())),
+        };
+        let key = key.trim_start_matches('.').to_ascii_lowercase();
+        self.by_key.insert(key, extractor);
+        Ok(())
+    }
+
+    /// Returns the extractor for the file at path, if there is one.
+    pub fn get(&self, path: &Path) -> Option<&dyn Extractor> {
+        let ext = path.extension().and_then(|e| e.to_str());
+        if let Some(extractor) = ext.and_then(|e| self.by_key.get(&e.to_ascii_lowercase())) {
+            return Some(extractor.as_ref());
+        }
+        self.by_key.get(mime(path)?).map(|e| e.as_ref())
+    }
+}
+
+/// Returns the MIME type of the file at path, if its first bytes show one
+/// an extractor may be given for.
+fn mime(path: &Path) -> Option<&'static str> {
+    let mut start = [0u8; 5];
+    File::open(path).ok()?.read_exact(&mut start).ok()?;
+    match &start {
+        b"%PDF-" => Some("application/pdf"),
+        [b'P', b'K', 3, 4, _] => Some("application/zip"),
+        [0x1f, 0x8b, ..] => Some("application/gzip"),
+        _ => None,
+    }
+}
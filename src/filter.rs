@@ -0,0 +1,439 @@
+//! Expressions over the capture groups of a match, deciding whether it
+//! counts, as `--filter` gives them.
+//!
+//! An expression reads the text of groups as `$1`, `${1}`, `$name` or
+//! `${name}`, the whole match being `$0`, and one that did not take part in
+//! the match as empty. It has numbers, strings in double or single quotes,
+//! `+ - * / %`, the comparisons `== != < <= > >=`, `&& || !`, parentheses
+//! and the functions `int`, `num` and `len`.
+//!
+//! A string compared with a number is read as one, so `$1 > 500` compares
+//! numerically; two strings compare byte by byte. A value that is not a
+//! number where one is needed, or a division by zero, makes the match not
+//! count rather than failing the search.
+//!
+//! ```
+//! use putao_cf::bytes::Regex;
+//! use putao_cf::filter::Filter;
+//!
+//! let re = Regex::new(r"took (\d+)ms").unwrap();
+//! let filter = Filter::new("int($1) > 500", &re).unwrap();
+//! let keeps = |hay: &[u8]| filter.keeps(&re.captures(hay).unwrap());
+//! assert!(keeps(b"took 750ms"));
+//! assert!(!keeps(b"took 20ms"));
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::bytes::{Captures, Regex};
+
+/// An expression deciding which matches count.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses expr, whose groups are those of re.
+    pub fn new(expr: &str, re: &Regex) -> Result<Filter, FilterError> {
+        let mut parser = Parser {
+            src: expr,
+            at: 0,
+            re,
+        };
+        let parsed = parser.or().and_then(|parsed| {
+            parser.skip_space();
+            match parser.at == expr.len() {
+                true => Ok(parsed),
+                false => Err(parser.expected("an operator")),
+            }
+        });
+        parsed
+            .map(|expr| Filter { expr })
+            .map_err(|reason| FilterError {
+                expr: expr.to_string(),
+                reason,
+            })
+    }
+
+    /// Reports whether the match whose groups are caps counts.
+    pub fn keeps(&self, caps: &Captures<'_>) -> bool {
+        eval(&self.expr, caps).is_some_and(|v| v.truth())
+    }
+}
+
+/// A filter [`Filter::new`] could not make sense of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilterError {
+    expr: String,
+    reason: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid filter '{}': {}", self.expr, self.reason)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Num(f64),
+    Str(Vec<u8>),
+    Group(usize),
+    Call(Func, Box<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Func {
+    /// The number a value reads as, its fraction dropped.
+    Int,
+    /// The number a value reads as.
+    Num,
+    /// The length of a string in bytes.
+    Len,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// What an expression evaluates to.
+enum Value<'a> {
+    Num(f64),
+    Str(&'a [u8]),
+    Bool(bool),
+}
+
+impl Value<'_> {
+    /// Returns the number the value reads as, if it is one.
+    fn num(&self) -> Option<f64> {
+        match self {
+            Value::Num(n) => Some(*n),
+            Value::Str(s) => {
+                let text = std::str::from_utf8(s).ok()?.trim();
+                // Only digits, so words such as "inf" are not numbers.
+                let numeric = |c: char| c.is_ascii_digit() || matches!(c, '+' | '-' | '.');
+                match text.chars().all(numeric) {
+                    true => text.parse().ok(),
+                    false => None,
+                }
+            }
+            Value::Bool(_) => None,
+        }
+    }
+
+    /// Reports whether the value counts as true: a true comparison, a
+    /// number other than zero or a string that is not empty.
+    fn truth(&self) -> bool {
+        match self {
+            Value::Num(n) => *n != 0.0,
+            Value::Str(s) => !s.is_empty(),
+            Value::Bool(b) => *b,
+        }
+    }
+}
+
+/// Evaluates expr for the match caps, or returns `None` if a value is not
+/// of the kind needed.
+fn eval<'a>(expr: &'a Expr, caps: &Captures<'a>) -> Option<Value<'a>> {
+    let value = match expr {
+        Expr::Num(n) => Value::Num(*n),
+        Expr::Str(s) => Value::Str(s),
+        Expr::Group(i) => Value::Str(caps.get(*i).map_or(&[][..], |m| m.as_bytes())),
+        Expr::Call(func, arg) => {
+            let arg = eval(arg, caps)?;
+            match func {
+                Func::Int => Value::Num(arg.num()?.trunc()),
+                Func::Num => Value::Num(arg.num()?),
+                Func::Len => match arg {
+                    Value::Str(s) => Value::Num(s.len() as f64),
+                    _ => return None,
+                },
+            }
+        }
+        Expr::Neg(arg) => Value::Num(-eval(arg, caps)?.num()?),
+        Expr::Not(arg) => Value::Bool(!eval(arg, caps)?.truth()),
+        Expr::Binary(Op::Or, lhs, rhs) => {
+            Value::Bool(eval(lhs, caps)?.truth() || eval(rhs, caps)?.truth())
+        }
+        Expr::Binary(Op::And, lhs, rhs) => {
+            Value::Bool(eval(lhs, caps)?.truth() && eval(rhs, caps)?.truth())
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let (lhs, rhs) = (eval(lhs, caps)?, eval(rhs, caps)?);
+            let ordering = || match (&lhs, &rhs) {
+                (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+                (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+                (Value::Bool(_), _) | (_, Value::Bool(_)) => None,
+                _ => lhs.num()?.partial_cmp(&rhs.num()?),
+            };
+            let numbers = || Some((lhs.num()?, rhs.num()?));
+            match op {
+                Op::Eq => Value::Bool(ordering()? == Ordering::Equal),
+                Op::Ne => Value::Bool(ordering()? != Ordering::Equal),
+                Op::Lt => Value::Bool(ordering()? == Ordering::Less),
+                Op::Le => Value::Bool(ordering()? != Ordering::Greater),
+                Op::Gt => Value::Bool(ordering()? == Ordering::Greater),
+                Op::Ge => Value::Bool(ordering()? != Ordering::Less),
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem => {
+                    let (a, b) = numbers()?;
+                    Value::Num(match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        _ if b == 0.0 => return None,
+                        Op::Div => a / b,
+                        _ => a % b,
+                    })
+                }
+                Op::Or | Op::And => unreachable!("evaluated above"),
+            }
+        }
+    };
+    Some(value)
+}
+
+/// Parses an expression by recursive descent, from the loosest binding
+/// operator to the tightest.
+struct Parser<'e, 'r> {
+    src: &'e str,
+    at: usize,
+    /// The regex whose groups the expression reads.
+    re: &'r Regex,
+}
+
+impl<'e> Parser<'e, '_> {
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Binary(Op::Or, Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Expr::Binary(Op::And, Box::new(lhs), Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                return Ok(Expr::Binary(op, Box::new(lhs), Box::new(self.sum()?)));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        loop {
+            let op = match () {
+                _ if self.eat("+") => Op::Add,
+                _ if self.eat("-") => Op::Sub,
+                _ => return Ok(lhs),
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match () {
+                _ if self.eat("*") => Op::Mul,
+                _ if self.eat("/") => Op::Div,
+                _ if self.eat("%") => Op::Rem,
+                _ => return Ok(lhs),
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        self.skip_space();
+        if !self.rest().starts_with("!=") && self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        self.skip_space();
+        let start = self.at;
+        match self.rest().chars().next() {
+            Some('(') => {
+                self.at += 1;
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(quote @ ('"' | '\'')) => self.string(quote),
+            Some('$') => {
+                self.at += 1;
+                self.group()
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let number = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                match number.parse() {
+                    Ok(n) => Ok(Expr::Num(n)),
+                    Err(_) => Err(format!("invalid number '{}'", number)),
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                let func = match name {
+                    "int" => Func::Int,
+                    "num" => Func::Num,
+                    "len" => Func::Len,
+                    _ => {
+                        self.at = start;
+                        return Err(format!("unknown function '{}'", name));
+                    }
+                };
+                self.expect("(")?;
+                let arg = self.or()?;
+                self.expect(")")?;
+                Ok(Expr::Call(func, Box::new(arg)))
+            }
+            _ => Err(self.expected("a value")),
+        }
+    }
+
+    /// Parses the group reference after a `$`.
+    fn group(&mut self) -> Result<Expr, String> {
+        let name = match self.rest().strip_prefix('{') {
+            Some(braced) => {
+                let Some(len) = braced.find('}') else {
+                    return Err("unclosed '${'".to_string());
+                };
+                self.at += len + 2;
+                &braced[..len]
+            }
+            None => self.take_while(|c| c.is_ascii_alphanumeric() || c == '_'),
+        };
+        if name.is_empty() {
+            return Err(self.expected("a group after '$'"));
+        }
+        let group = match name.parse::<usize>() {
+            Ok(i) if i < self.re.captures_len() => i,
+            Ok(i) => return Err(format!("there is no group {} in the patterns", i)),
+            Err(_) => match self.re.capture_index(name) {
+                Some(i) => i,
+                None => return Err(format!("there is no group named '{}'", name)),
+            },
+        };
+        Ok(Expr::Group(group))
+    }
+
+    /// Parses the string the quote starts, in which a backslash escapes
+    /// the character after it, as `\n` and `\t` do newlines and tabs.
+    fn string(&mut self, quote: char) -> Result<Expr, String> {
+        let column = self.column();
+        let mut text = String::new();
+        let mut chars = self.rest()[1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            let c = match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, c)) => c,
+                    None => break,
+                },
+                c if c == quote => {
+                    self.at += 1 + i + 1;
+                    return Ok(Expr::Str(text.into_bytes()));
+                }
+                c => c,
+            };
+            text.push(c);
+        }
+        Err(format!("unclosed string starting at column {}", column))
+    }
+
+    /// Takes the characters at the start of the rest that f allows.
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'e str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.at += len;
+        &self.src[self.at - len..self.at]
+    }
+
+    /// Skips space before token, and it if it comes next, reporting
+    /// whether it did.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.at += token.len();
+        }
+        found
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{}'", token))),
+        }
+    }
+
+    /// Describes what was found where what was expected.
+    fn expected(&self, what: &str) -> String {
+        match self.rest().chars().next() {
+            Some(c) => format!(
+                "expected {} at column {}, found '{}'",
+                what,
+                self.column(),
+                c
+            ),
+            None => format!("expected {} at the end", what),
+        }
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    fn rest(&self) -> &'e str {
+        &self.src[self.at..]
+    }
+
+    fn column(&self) -> usize {
+        self.src[..self.at].chars().count() + 1
+    }
+}
@@ -0,0 +1,678 @@
+//! The command line flags and the tokenizer that recognizes them.
+
+use std::mem;
+
+use anyhow::{bail, Result};
+
+/// A command line flag.
+#[derive(Debug)]
+pub struct Flag {
+    pub short: Option<char>,
+    /// The long name, which also identifies the flag.
+    pub long: &'static str,
+    /// Other long names accepted for the flag.
+    pub aliases: &'static [&'static str],
+    /// The name of the value the flag takes, if any.
+    pub value: Option<&'static str>,
+    /// Whether the value may be left out; it can then only be given after `=`.
+    pub optional: bool,
+    /// The values the flag accepts, if it takes only some; shell
+    /// completions offer them.
+    pub values: &'static [&'static str],
+    /// What the flag does, as listed by `--help`.
+    pub help: &'static str,
+}
+
+const fn switch(short: Option<char>, long: &'static str, help: &'static str) -> Flag {
+    Flag {
+        short,
+        long,
+        aliases: &[],
+        value: None,
+        optional: false,
+        values: &[],
+        help,
+    }
+}
+
+const fn valued(
+    short: Option<char>,
+    long: &'static str,
+    value: &'static str,
+    help: &'static str,
+) -> Flag {
+    Flag {
+        short,
+        long,
+        aliases: &[],
+        value: Some(value),
+        optional: false,
+        values: &[],
+        help,
+    }
+}
+
+/// Every flag, in the order `--help` lists them.
+pub const FLAGS: &[Flag] = &[
+    switch(
+        Some('G'),
+        "basic-regexp",
+        "PATTERNS are basic regular expressions",
+    ),
+    switch(
+        Some('E'),
+        "extended-regexp",
+        "PATTERNS are extended regular expressions",
+    ),
+    switch(
+        Some('P'),
+        "perl-regexp",
+        "PATTERNS are Perl regular expressions",
+    ),
+    switch(Some('F'), "fixed-strings", "PATTERNS are strings"),
+    valued(Some('e'), "regexp", "PATTERNS", "use PATTERNS for matching"),
+    valued(Some('f'), "file", "FILE", "take PATTERNS from FILE"),
+    valued(None, "preset", "NAME", "use the pattern named NAME, such as 'email' or 'ipv4'; with -w, not within longer words"),
+    switch(None, "preset-list", "list the patterns --preset names and exit"),
+    switch(
+        Some('i'),
+        "ignore-case",
+        "ignore case distinctions in patterns and data",
+    ),
+    switch(
+        Some('S'),
+        "smart-case",
+        "ignore case unless PATTERNS have an uppercase letter",
+    ),
+    switch(
+        None,
+        "no-ignore-case",
+        "match case, undoing an earlier -i or -S, as from a config file",
+    ),
+    switch(
+        None,
+        "unicode",
+        "let \\w, \\d, \\s, \\b, -w and -i go by Unicode rather than ASCII alone",
+    ),
+    switch(
+        None,
+        "no-unicode",
+        "let \\w, \\d, \\s, \\b, -w and -i go by ASCII alone, the default",
+    ),
+    switch(Some('w'), "word-regexp", "match only whole words"),
+    switch(Some('x'), "line-regexp", "match only whole lines"),
+    valued(
+        None,
+        "filter",
+        "EXPR",
+        "count only matches whose groups make EXPR true, such as 'int($1) > 500' or '$user != \"root\"'",
+    ),
+    switch(None, "all-of", "select only lines matching every one of PATTERNS, not any"),
+    valued(None, "none-of", "PATTERNS", "never select lines matching any of PATTERNS"),
+    switch(
+        Some('z'),
+        "null-data",
+        "a data line ends in 0 byte, not newline",
+    ),
+    switch(Some('U'), "multiline", "let matches span lines"),
+    switch(
+        None,
+        "multiline-dotall",
+        "with -U, let . match line terminators",
+    ),
+    switch(None, "search-zip", "search compressed files through their decompressor"),
+    switch(None, "search-archives", "search the files inside zip and tar archives"),
+    switch(
+        None,
+        "extract",
+        "search the text of documents such as PDF, docx and xlsx files rather than their bytes",
+    ),
+    valued(
+        None,
+        "extractor",
+        "KEY=COMMAND",
+        "with --extract, read files with extension or MIME type KEY through COMMAND, '{}' standing for the file; 'text' reads them as is",
+    ),
+    valued(
+        None,
+        "max-archive-size",
+        "NUM",
+        "with --search-archives or --extract, fail archives and documents unpacking to over NUM bytes (default 1G)",
+    ),
+    valued(None, "pre", "COMMAND", "search the output of COMMAND run over each file"),
+    valued(None, "pre-glob", "GLOB", "with --pre, preprocess only files that match GLOB"),
+    switch(
+        None,
+        "crlf",
+        "treat CRLF as a line terminator, so $ matches before \\r",
+    ),
+    Flag {
+        values: &["lf", "crlf", "cr", "any"],
+        ..valued(
+            None,
+            "line-endings",
+            "MODE",
+            "what ends lines: 'lf', 'crlf' (like --crlf), 'cr', or 'any' of them, even mixed",
+        )
+    },
+    Flag {
+        values: &["auto", "bom", "none", "utf-8", "utf-16le", "utf-16be", "latin1"],
+        ..valued(None, "encoding", "ENC", "read input as ENC, such as 'utf-16le' or 'latin1'; 'auto' guesses each file's from its byte order mark or its first bytes, 'bom' (the default) goes by byte order mark alone, 'none' reads bytes as is")
+    },
+    Flag {
+        values: &["nfc", "none"],
+        ..valued(None, "normalize", "FORM", "put patterns and input in Unicode normalization FORM before matching: 'nfc' or 'none'")
+    },
+    switch(Some('v'), "invert-match", "select non-matching lines"),
+    valued(
+        Some('m'),
+        "max-count",
+        "NUM",
+        "stop after NUM selected lines",
+    ),
+    valued(
+        Some('M'),
+        "max-columns",
+        "NUM",
+        "print lines longer than NUM bytes as a note of how many matches they hold",
+    ),
+    valued(
+        None,
+        "max-line-length",
+        "NUM",
+        "skip lines longer than NUM bytes unsearched, warning of how many were",
+    ),
+    valued(
+        None,
+        "regex-size-limit",
+        "NUM",
+        "fail if the compiled pattern takes more than NUM bytes; the default is 10M",
+    ),
+    valued(
+        None,
+        "state-size-limit",
+        "NUM",
+        "skip a file once matching a line needs more than NUM bytes of backtracking state; the default is 256M",
+    ),
+    switch(
+        Some('b'),
+        "byte-offset",
+        "print the byte offset with output lines",
+    ),
+    switch(
+        Some('n'),
+        "line-number",
+        "print line number with output lines",
+    ),
+    switch(
+        None,
+        "column",
+        "print the line number and 1-based column of the first match",
+    ),
+    switch(None, "passthru", "print every line, matching or not"),
+    switch(None, "line-buffered", "flush output on every line"),
+    switch(None, "heading", "print each file name once, above its lines; the default if output is a terminal"),
+    switch(None, "no-heading", "print the file name on every line"),
+    switch(
+        Some('H'),
+        "with-filename",
+        "print file name with output lines",
+    ),
+    switch(
+        Some('h'),
+        "no-filename",
+        "suppress the file name prefix on output",
+    ),
+    valued(
+        None,
+        "label",
+        "LABEL",
+        "use LABEL as the standard input file name prefix",
+    ),
+    switch(
+        Some('o'),
+        "only-matching",
+        "show only nonempty parts of lines that match",
+    ),
+    valued(
+        None,
+        "replace",
+        "REPLACEMENT",
+        "print REPLACEMENT for each match, with $1 or ${name} standing for a group",
+    ),
+    switch(None, "write", "with --replace, rewrite files with their matches replaced"),
+    valued(
+        None,
+        "backup-suffix",
+        "SUFFIX",
+        "with --write, keep the original of each file rewritten as FILE followed by SUFFIX",
+    ),
+    Flag {
+        optional: true,
+        values: &["grep", "regex"],
+        ..valued(
+            None,
+            "verify",
+            "ORACLE",
+            "instead of printing matches, print the lines ORACLE selects differently: 'grep' (the default), the system grep, or 'regex', the regex crate if built with it",
+        )
+    },
+    Flag {
+        aliases: &["silent"],
+        ..switch(Some('q'), "quiet", "suppress all normal output")
+    },
+    switch(Some('s'), "no-messages", "suppress error messages"),
+    switch(
+        Some('a'),
+        "text",
+        "search binary files as if they were text",
+    ),
+    switch(Some('I'), "ignore-binary", "skip binary files"),
+    Flag {
+        values: &["read", "recurse", "skip"],
+        ..valued(
+            Some('d'),
+            "directories",
+            "ACTION",
+            "how to handle directories; ACTION is 'read', 'recurse', or 'skip'",
+        )
+    },
+    Flag {
+        values: &["read", "skip"],
+        ..valued(
+            Some('D'),
+            "devices",
+            "ACTION",
+            "how to handle named pipes, sockets and devices given as FILE; ACTION is 'read' or 'skip'",
+        )
+    },
+    switch(Some('r'), "recursive", "like --directories=recurse"),
+    switch(
+        Some('R'),
+        "dereference-recursive",
+        "likewise, but follow all symlinks",
+    ),
+    switch(None, "follow", "with -r, follow symlinks too"),
+    valued(
+        None,
+        "max-depth",
+        "NUM",
+        "descend at most NUM directories below each FILE",
+    ),
+    valued(Some('g'), "glob", "GLOB", "search only paths that match GLOB, or with '!GLOB' skip them"),
+    switch(None, "no-ignore", "with -r, search files .gitignore and .ignore files leave out"),
+    switch(None, "git", "with -r, search only the files git tracks"),
+    valued(
+        None,
+        "files-from",
+        "FILE",
+        "search the files FILE names, a line each, as well as FILEs; '-' reads the names from standard input",
+    ),
+    valued(
+        None,
+        "files-from0",
+        "FILE",
+        "like --files-from, with each name ended by NUL, as 'find -print0' prints them",
+    ),
+    Flag {
+        optional: true,
+        ..valued(
+            None,
+            "changed",
+            "BASE",
+            "search only the lines added or changed since the git revision BASE (default HEAD), in the files changed unless FILEs are given",
+        )
+    },
+    Flag {
+        values: &["comments", "strings", "code"],
+        ..valued(
+            None,
+            "only",
+            "PART",
+            "search only the comments, the strings or the rest of source files, tokenized by their --type; files of other types are not searched",
+        )
+    },
+    switch(None, "hidden", "with -r, search hidden files and directories too"),
+    switch(None, "dedupe", "with -r, skip files with the same contents as one already searched"),
+    valued(
+        None,
+        "max-filesize",
+        "NUM",
+        "with -r, skip files larger than NUM bytes; a K, M or G suffix multiplies it",
+    ),
+    valued(
+        Some('t'),
+        "type",
+        "TYPE",
+        "with -r, search only files of TYPE, such as 'rust' or 'py'",
+    ),
+    valued(Some('T'), "type-not", "TYPE", "with -r, skip files of TYPE"),
+    valued(
+        None,
+        "type-add",
+        "NAME:GLOB",
+        "add GLOB to the files of type NAME, defining it if needed",
+    ),
+    switch(None, "type-list", "list the file types and their globs and exit"),
+    valued(None, "include", "GLOB", "search only files that match GLOB"),
+    valued(None, "exclude", "GLOB", "skip files that match GLOB"),
+    valued(
+        None,
+        "exclude-dir",
+        "GLOB",
+        "skip directories that match GLOB",
+    ),
+    valued(Some('j'), "threads", "NUM", "with -r, search NUM files at a time; the default is one per CPU"),
+    Flag {
+        values: &["path", "modified", "size", "none"],
+        ..valued(None, "sort", "KEY", "with -r, search files in order of KEY: 'path', 'modified', 'size' or 'none'; the default is 'path'")
+    },
+    switch(
+        Some('L'),
+        "files-without-match",
+        "print only names of FILEs with no selected lines",
+    ),
+    switch(
+        Some('l'),
+        "files-with-matches",
+        "print only names of FILEs with selected lines",
+    ),
+    switch(
+        None,
+        "files",
+        "print the files that would be searched, without searching",
+    ),
+    switch(
+        Some('c'),
+        "count",
+        "print only a count of selected lines per FILE",
+    ),
+    switch(
+        None,
+        "count-matches",
+        "print only a count of matches per FILE",
+    ),
+    switch(Some('Z'), "null", "print 0 byte after FILE name"),
+    valued(
+        None,
+        "path-separator",
+        "SEP",
+        "print SEP between the parts of file names, such as '/' on Windows",
+    ),
+    switch(None, "json", "print results as JSON Lines messages"),
+    Flag {
+        values: &["standard", "json", "sarif", "csv", "tsv"],
+        ..valued(
+            None,
+            "format",
+            "FORMAT",
+            "print results as 'standard' lines, 'json' like --json, a 'sarif' log for code scanning, or 'csv' or 'tsv' rows",
+        )
+    },
+    switch(
+        None,
+        "vimgrep",
+        "print every match as FILE:LINE:COLUMN:LINE",
+    ),
+    switch(
+        None,
+        "stats",
+        "print a summary of what was searched and found at the end",
+    ),
+    switch(
+        None,
+        "progress",
+        "show how far the search got on standard error while it runs, if that is a terminal",
+    ),
+    valued(
+        None,
+        "output",
+        "FILE",
+        "write results to FILE, replaced only once the search finishes, keeping --stats on stdout",
+    ),
+    switch(None, "append", "with --output, append results to FILE rather than replace it"),
+    Flag {
+        optional: true,
+        ..valued(
+            None,
+            "debug",
+            "TEXT",
+            "print to stderr how the patterns parse and compile, and with TEXT, each step of matching it",
+        )
+    },
+    switch(
+        None,
+        "repl",
+        "read patterns from stdin one per line, showing what each matches in the FILEs instead of searching",
+    ),
+    valued(
+        None,
+        "serve",
+        "SOCKET",
+        "answer JSON-RPC search requests on the Unix socket SOCKET, or on stdin and stdout if it is '-', instead of searching",
+    ),
+    switch(
+        None,
+        "watch",
+        "after searching, keep searching the lines appended to the FILEs, and files new or rewritten, until Ctrl-C",
+    ),
+    switch(
+        None,
+        "tail",
+        "instead of searching the FILEs, follow them from their end as 'tail -F' does, searching the lines appended, until Ctrl-C",
+    ),
+    switch(
+        None,
+        "tui",
+        "browse the matching lines in the terminal, opening the one selected in $EDITOR with Enter",
+    ),
+    valued(
+        Some('B'),
+        "before-context",
+        "NUM",
+        "print NUM lines of leading context",
+    ),
+    valued(
+        Some('A'),
+        "after-context",
+        "NUM",
+        "print NUM lines of trailing context",
+    ),
+    valued(
+        Some('C'),
+        "context",
+        "NUM",
+        "print NUM lines of output context",
+    ),
+    Flag {
+        aliases: &["group-separator"],
+        ..valued(
+            None,
+            "context-separator",
+            "SEP",
+            "print SEP between groups of context lines",
+        )
+    },
+    Flag {
+        aliases: &["no-group-separator"],
+        ..switch(
+            None,
+            "no-context-separator",
+            "print nothing between groups of context lines",
+        )
+    },
+    valued(
+        None,
+        "field-separator",
+        "SEP",
+        "print SEP after each part of a line's prefix, not ':' or '-'",
+    ),
+    valued(
+        None,
+        "field-match-separator",
+        "SEP",
+        "print SEP after each part of a matching line's prefix",
+    ),
+    valued(
+        None,
+        "field-context-separator",
+        "SEP",
+        "print SEP after each part of a context line's prefix",
+    ),
+    Flag {
+        aliases: &["colour"],
+        optional: true,
+        values: &["always", "never", "auto"],
+        ..valued(
+            None,
+            "color",
+            "WHEN",
+            "use markers to highlight the matching strings; WHEN is 'always', 'never', or 'auto'",
+        )
+    },
+    valued(
+        None,
+        "colors",
+        "SPEC",
+        "color a part of the output as SPEC says, such as 'match:fg:yellow' or 'path:style:bold'",
+    ),
+    switch(
+        None,
+        "no-config",
+        "don't read default flags from the file PUTAO_CONFIG names or a .putaorc",
+    ),
+    Flag {
+        values: &["bash", "zsh", "fish", "powershell"],
+        ..valued(
+            None,
+            "generate-completions",
+            "SHELL",
+            "print a completion script for SHELL and exit",
+        )
+    },
+    switch(Some('V'), "version", "display version information and exit"),
+    switch(None, "help", "display this help text and exit"),
+];
+
+/// The synopsis printed by `--help` and after a usage error.
+pub const USAGE: &str = "Usage: putao [OPTION]... PATTERNS [FILE]...";
+
+/// Returns the text printed by `--help`: the synopsis and every flag.
+pub fn help() -> String {
+    let mut out = format!(
+        "{}\n{}\nSearch for PATTERNS in each FILE, or with 'bench', time searching each PATH.\n\n",
+        USAGE,
+        crate::bench::USAGE.replacen("Usage:", "   or:", 1)
+    );
+    for flag in FLAGS {
+        let short = flag
+            .short
+            .map_or(String::from("    "), |c| format!("-{}, ", c));
+        let value = match (flag.value, flag.optional) {
+            (Some(v), true) => format!("[={}]", v),
+            (Some(v), false) => format!("={}", v),
+            (None, _) => String::new(),
+        };
+        let left = format!("{}--{}{}", short, flag.long, value);
+        out += &format!("  {:<30} {}\n", left, flag.help);
+    }
+    out
+}
+
+/// An argument recognized by a [`Parser`].
+#[derive(Debug)]
+pub enum Arg {
+    /// A flag, with its value if it takes one.
+    Flag(&'static Flag, Option<String>),
+    /// Anything that is not a flag.
+    Positional(String),
+}
+
+/// Splits command line arguments into flags and positionals.
+pub struct Parser<I> {
+    args: I,
+    /// The short flags left in a bundle such as `-rin`.
+    shorts: String,
+    /// Set after `--`, once every argument is positional.
+    ended: bool,
+}
+
+impl<I: Iterator<Item = String>> Parser<I> {
+    pub fn new(args: I) -> Self {
+        Parser {
+            args,
+            shorts: String::new(),
+            ended: false,
+        }
+    }
+
+    /// Returns the next argument, or None once they run out.
+    pub fn next_arg(&mut self) -> Result<Option<Arg>> {
+        if self.in_bundle() {
+            return self.short().map(Some);
+        }
+        let Some(arg) = self.args.next() else {
+            return Ok(None);
+        };
+        if self.ended {
+            return Ok(Some(Arg::Positional(arg)));
+        }
+        if arg == "--" {
+            self.ended = true;
+            return self.next_arg();
+        }
+        if let Some(long) = arg.strip_prefix("--").filter(|l| !l.is_empty()) {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, v)) => (name, Some(v.to_string())),
+                None => (long, None),
+            };
+            let Some(flag) = FLAGS
+                .iter()
+                .find(|f| f.long == name || f.aliases.contains(&name))
+            else {
+                bail!("unrecognized option '{}'", arg);
+            };
+            let value = match (flag.value, inline) {
+                (None, Some(_)) => bail!("option '--{}' doesn't allow an argument", name),
+                (Some(_), None) if !flag.optional => Some(self.value(|| format!("'--{}'", name))?),
+                (_, value) => value,
+            };
+            return Ok(Some(Arg::Flag(flag, value)));
+        }
+        match arg.strip_prefix('-') {
+            Some(shorts) if !shorts.is_empty() => {
+                self.shorts = shorts.to_string();
+                self.short().map(Some)
+            }
+            _ => Ok(Some(Arg::Positional(arg))),
+        }
+    }
+
+    /// Reports whether the last flag came from a bundle that has more in it.
+    fn in_bundle(&self) -> bool {
+        !self.shorts.is_empty()
+    }
+
+    /// Takes the next flag from a bundle; the rest of the bundle, as in
+    /// `-A3`, or else the next argument is the value of a flag taking one.
+    fn short(&mut self) -> Result<Arg> {
+        let c = self.shorts.remove(0);
+        let Some(flag) = FLAGS.iter().find(|f| f.short == Some(c)) else {
+            bail!("invalid option -- '{}'", c);
+        };
+        let value = match flag.value {
+            Some(_) if self.in_bundle() => Some(mem::take(&mut self.shorts)),
+            Some(_) if !flag.optional => Some(self.value(|| format!("-- '{}'", c))?),
+            _ => None,
+        };
+        Ok(Arg::Flag(flag, value))
+    }
+
+    /// Takes the argument following a flag as its value.
+    fn value(&mut self, flag: impl FnOnce() -> String) -> Result<String> {
+        match self.args.next() {
+            Some(value) => Ok(value),
+            None => bail!("option requires an argument {}", flag()),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Shell-style globs for selecting files by name.
+
+/// One element of a glob.
+#[derive(Clone, Debug)]
+enum Token {
+    Lit(char),
+    /// `?`, any one char.
+    Any,
+    /// `*`, any run of chars; in a path glob, not `/`.
+    Star,
+    /// `**/` in a path glob, any run of whole directories, or none.
+    Dirs,
+    /// `**` elsewhere in a path glob, any run of chars.
+    Globstar,
+    /// `[...]`, one char in (or, if negated, not in) the inclusive ranges.
+    Class(bool, Vec<(char, char)>),
+}
+
+/// A compiled glob supporting `*`, `?`, `[...]` classes and `\` escapes.
+///
+/// A `[` without a closing `]` matches itself.
+///
+/// ```
+/// use putao_cf::glob::Glob;
+///
+/// let glob = Glob::new("*.[ch]");
+/// assert!(glob.is_match("main.c"));
+/// assert!(!glob.is_match("main.rs"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Glob {
+    glob: String,
+    tokens: Vec<Token>,
+    /// Whether `*` and `?` stop at `/`, as in [`Glob::new_path`].
+    path: bool,
+}
+
+impl Glob {
+    /// Compiles a glob.
+    pub fn new(glob: &str) -> Glob {
+        Self::compile(glob, false)
+    }
+
+    /// Compiles a glob matched against paths, in which `*`, `?` and
+    /// classes do not match `/`, `**/` matches any run of directories and
+    /// another `**` anything at all.
+    ///
+    /// ```
+    /// use putao_cf::glob::Glob;
+    ///
+    /// let glob = Glob::new_path("src/**/*.rs");
+    /// assert!(glob.is_match("src/main.rs"));
+    /// assert!(glob.is_match("src/bin/cli.rs"));
+    /// assert!(!Glob::new_path("src/*.rs").is_match("src/bin/cli.rs"));
+    /// ```
+    pub fn new_path(glob: &str) -> Glob {
+        Self::compile(glob, true)
+    }
+
+    fn compile(glob: &str, path: bool) -> Glob {
+        let chars: Vec<char> = glob.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let tok = match chars[i] {
+                '*' if path && chars.get(i + 1) == Some(&'*') => {
+                    i += 1;
+                    if chars.get(i + 1) == Some(&'/') {
+                        i += 1;
+                        Token::Dirs
+                    } else {
+                        Token::Globstar
+                    }
+                }
+                '*' => Token::Star,
+                '?' => Token::Any,
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+                    Token::Lit(chars[i])
+                }
+                '[' => match class(&chars[i + 1..]) {
+                    Some((tok, used)) => {
+                        i += used;
+                        tok
+                    }
+                    None => Token::Lit('['),
+                },
+                c => Token::Lit(c),
+            };
+            tokens.push(tok);
+            i += 1;
+        }
+        Glob {
+            glob: glob.to_string(),
+            tokens,
+            path,
+        }
+    }
+
+    /// Returns the glob this was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.glob
+    }
+
+    /// Reports whether the glob matches all of text.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        if self.path {
+            return match_path(&self.tokens, &text);
+        }
+        let (mut t, mut p) = (0, 0);
+        // Where to resume after the last `*` when what follows it fails.
+        let mut star = None;
+        while t < text.len() {
+            match self.tokens.get(p) {
+                Some(Token::Star) => {
+                    star = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                Some(tok) if matches_one(tok, text[t]) => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            match star {
+                Some((sp, st)) => {
+                    star = Some((sp, st + 1));
+                    p = sp + 1;
+                    t = st + 1;
+                }
+                None => return false,
+            }
+        }
+        self.tokens[p..].iter().all(|t| matches!(t, Token::Star))
+    }
+}
+
+/// Parses a class body following `[`, returning it and how many chars it
+/// used, including the closing `]`.
+fn class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = negated as usize;
+    let mut ranges = Vec::new();
+    // A `]` first in the class is a member rather than its end.
+    let mut first = true;
+    loop {
+        let c = *chars.get(i)?;
+        if c == ']' && !first {
+            return Some((Token::Class(negated, ranges), i + 1));
+        }
+        first = false;
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(&hi)) if hi != ']' => {
+                ranges.push((c, hi));
+                i += 3;
+            }
+            _ => {
+                ranges.push((c, c));
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Matches a path glob against all of text, trying every length for each
+/// run it holds.
+fn match_path(tokens: &[Token], text: &[char]) -> bool {
+    let Some((tok, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+    match tok {
+        Token::Star => {
+            let run = text.iter().take_while(|&&c| c != '/').count();
+            (0..=run).any(|n| match_path(rest, &text[n..]))
+        }
+        Token::Globstar => (0..=text.len()).any(|n| match_path(rest, &text[n..])),
+        Token::Dirs => {
+            match_path(rest, text)
+                || (0..text.len()).any(|n| text[n] == '/' && match_path(rest, &text[n + 1..]))
+        }
+        tok => match text.split_first() {
+            Some((&c, text)) => {
+                let lit = matches!(tok, Token::Lit(_));
+                (lit || c != '/') && matches_one(tok, c) && match_path(rest, text)
+            }
+            None => false,
+        },
+    }
+}
+
+fn matches_one(tok: &Token, c: char) -> bool {
+    match tok {
+        Token::Lit(l) => *l == c,
+        Token::Any => true,
+        Token::Star | Token::Dirs | Token::Globstar => false,
+        Token::Class(negated, ranges) => {
+            ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+        }
+    }
+}
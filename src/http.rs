@@ -0,0 +1,206 @@
+//! Fetching URLs given as inputs, so a remote file is searched as it
+//! streams in rather than downloaded first.
+//!
+//! Plain `http://` URLs are fetched by a small HTTP/1.1 client here;
+//! `https://` ones are read through `curl`, as TLS is more than is worth
+//! carrying for this.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::pipe::Piped;
+
+/// How many redirects are followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// How long connecting, or waiting for more of a response, may take.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Reports whether input names a URL to fetch rather than a path.
+pub fn is_url(input: &str) -> bool {
+    scheme(input).is_some()
+}
+
+/// Returns the scheme of url, lowercased, if it is one fetched.
+fn scheme(url: &str) -> Option<&'static str> {
+    let (scheme, _) = url.split_once("://")?;
+    ["http", "https"]
+        .into_iter()
+        .find(|s| s.eq_ignore_ascii_case(scheme))
+}
+
+/// Starts fetching url, returning a reader of the body of its response;
+/// one saying the request failed is an error.
+pub fn get(url: &str) -> io::Result<Box<dyn Read>> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        if scheme(&url) == Some("https") {
+            let cmd = [
+                "curl",
+                "--silent",
+                "--show-error",
+                "--fail",
+                "--location",
+                &url,
+            ];
+            return Ok(Box::new(Piped::run(&cmd)?));
+        }
+        match request(&url)? {
+            Response::Body(body) => return Ok(body),
+            Response::Redirect(to) => url = resolve(&url, &to),
+        }
+    }
+    Err(io::Error::other(format!(
+        "more than {} redirects",
+        MAX_REDIRECTS
+    )))
+}
+
+enum Response {
+    Body(Box<dyn Read>),
+    Redirect(String),
+}
+
+/// Sends a GET request for the `http://` url.
+fn request(url: &str) -> io::Result<Response> {
+    let rest = &url["http://".len()..];
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let path = path.split('#').next().unwrap_or("");
+    let path = match path.starts_with('/') {
+        true => path.to_string(),
+        false => format!("/{}", path),
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let addr = match host.rsplit_once(':') {
+        Some((_, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host.to_string(),
+        _ => format!("{}:80", host),
+    };
+    let stream = connect(&addr)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    write!(
+        &stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: putao/{}\r\nAccept-Encoding: identity\r\nConnection: close\r\n\r\n",
+        path,
+        host,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    let mut rdr = BufReader::new(stream);
+    let mut line = String::new();
+    rdr.read_line(&mut line)?;
+    let mut status = line.split_whitespace().skip(1);
+    let code: u16 = status
+        .next()
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response"))?;
+    let reason = status.collect::<Vec<_>>().join(" ");
+    let (mut length, mut chunked, mut location) = (None, false, None);
+    loop {
+        line.clear();
+        if rdr.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => length = value.parse::<u64>().ok(),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            "location" => location = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    match code {
+        200..=299 => {}
+        301 | 302 | 303 | 307 | 308 if location.is_some() => {
+            return Ok(Response::Redirect(location.expect("location given")))
+        }
+        _ => return Err(io::Error::other(format!("HTTP {} {}", code, reason))),
+    }
+    Ok(Response::Body(match (chunked, length) {
+        (true, _) => Box::new(Chunked {
+            rdr,
+            left: 0,
+            done: false,
+        }),
+        (false, Some(n)) => Box::new(rdr.take(n)),
+        (false, None) => Box::new(rdr),
+    }))
+}
+
+/// Connects to the first address host resolves to that answers.
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let mut last = None;
+    for addr in std::net::ToSocketAddrs::to_socket_addrs(addr)? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| io::Error::other(format!("{}: no address found", addr))))
+}
+
+/// Returns the URL a redirect from base to location goes to.
+fn resolve(base: &str, location: &str) -> String {
+    if is_url(location) {
+        return location.to_string();
+    }
+    let (scheme, rest) = base.split_once("://").expect("base is a URL");
+    let host = rest.split('/').next().unwrap_or(rest);
+    match location.strip_prefix("//") {
+        Some(rest) => format!("{}://{}", scheme, rest),
+        None if location.starts_with('/') => format!("{}://{}{}", scheme, host, location),
+        None => {
+            let dir = rest.rsplit_once('/').map_or(rest, |(dir, _)| dir);
+            format!("{}://{}/{}", scheme, dir, location)
+        }
+    }
+}
+
+/// The body of a response sent in chunks, each after its length in hex.
+struct Chunked<R> {
+    rdr: R,
+    /// What is left of the chunk being read.
+    left: u64,
+    /// Whether the last chunk, of length 0, was read.
+    done: bool,
+}
+
+impl<R: BufRead> Read for Chunked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done {
+            return Ok(0);
+        }
+        if self.left == 0 {
+            let mut line = String::new();
+            self.rdr.read_line(&mut line)?;
+            // The end of the chunk before this one.
+            if line.trim().is_empty() {
+                line.clear();
+                self.rdr.read_line(&mut line)?;
+            }
+            let size = line.split(';').next().unwrap_or("").trim();
+            self.left = u64::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk"))?;
+            if self.left == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let max = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        let n = self.rdr.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
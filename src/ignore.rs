@@ -0,0 +1,97 @@
+//! Gitignore-style rules for leaving files out of a [`Walker`](crate::walk::Walker).
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::glob::Glob;
+
+/// One line of an ignore file.
+#[derive(Clone, Debug)]
+struct Rule {
+    glob: Glob,
+    /// Whether the line started with `!`, keeping what it matches.
+    negated: bool,
+    /// Whether the line ended with `/`, matching directories only.
+    dir_only: bool,
+}
+
+/// The rules of a `.gitignore` file, matched against paths relative to
+/// the directory holding it.
+///
+/// A rule without a `/` but at its end matches a name at any depth; one
+/// with a `/` matches from the directory on. As in git, the last rule
+/// matching a path decides, and `!` keeps what earlier rules left out.
+///
+/// ```
+/// use putao_cf::ignore::Gitignore;
+///
+/// let ignore = Gitignore::new("target/\n*.log\n!keep.log\n");
+/// assert_eq!(ignore.matched("target", true), Some(true));
+/// assert_eq!(ignore.matched("src/debug.log", false), Some(true));
+/// assert_eq!(ignore.matched("keep.log", false), Some(false));
+/// assert_eq!(ignore.matched("src/main.rs", false), None);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Gitignore {
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    /// Parses the contents of an ignore file; blank lines and `#`
+    /// comments are skipped.
+    pub fn new(text: &str) -> Gitignore {
+        let rules = text.lines().filter_map(rule).collect();
+        Gitignore { rules }
+    }
+
+    /// Reads the ignore file at path.
+    pub fn from_path(path: &Path) -> io::Result<Gitignore> {
+        let bytes = fs::read(path)?;
+        Ok(Self::new(&String::from_utf8_lossy(&bytes)))
+    }
+
+    /// Reports whether the file has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns whether the last rule matching path, given with `/` between
+    /// its components, ignores it; None if no rule matches.
+    pub fn matched(&self, path: &str, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| (is_dir || !r.dir_only) && r.glob.is_match(path))
+            .map(|r| !r.negated)
+    }
+}
+
+/// Parses one line of an ignore file.
+fn rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    if line.is_empty() {
+        return None;
+    }
+    let glob = match line.strip_prefix('/') {
+        Some(anchored) => Glob::new_path(anchored),
+        None if line.contains('/') => Glob::new_path(line),
+        None => Glob::new_path(&format!("**/{}", line)),
+    };
+    Some(Rule {
+        glob,
+        negated,
+        dir_only,
+    })
+}
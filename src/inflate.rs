@@ -0,0 +1,247 @@
+//! Inflating raw deflate data (RFC 1951), the way zip archives hold files.
+
+use std::io;
+
+/// The base length of each length code from 257, and its extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distance of each distance code, and its extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order code length code lengths are given in.
+const CODE_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Returns the data deflated in input, failing if it is malformed or
+/// would come to more than limit bytes.
+pub fn inflate(input: &[u8], limit: u64) -> io::Result<Vec<u8>> {
+    let mut bits = Bits {
+        input,
+        pos: 0,
+        buf: 0,
+        count: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.take(1)? == 1;
+        match bits.take(2)? {
+            0 => bits.stored(&mut out)?,
+            1 => {
+                let (lengths, distances) = fixed();
+                codes(&mut bits, &mut out, &lengths, &distances, limit)?
+            }
+            2 => {
+                let (lengths, distances) = dynamic(&mut bits)?;
+                codes(&mut bits, &mut out, &lengths, &distances, limit)?
+            }
+            _ => return Err(invalid()),
+        }
+        if out.len() as u64 > limit {
+            return Err(too_large(limit));
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid deflate data")
+}
+
+fn too_large(limit: u64) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unpacks to more than {} bytes", limit),
+    )
+}
+
+/// Reads input a bit at a time, least significant bit first.
+struct Bits<'a> {
+    input: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl Bits<'_> {
+    /// Takes the next n bits, n being at most 16.
+    fn take(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self.input.get(self.pos).ok_or_else(invalid)?;
+            self.pos += 1;
+            self.buf |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Copies a stored block to out, leaving the bits left in this byte.
+    fn stored(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        self.buf = 0;
+        self.count = 0;
+        let header = self.input.get(self.pos..self.pos + 4).ok_or_else(invalid)?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        let nlen = u16::from_le_bytes([header[2], header[3]]);
+        if len != !nlen {
+            return Err(invalid());
+        }
+        self.pos += 4;
+        let end = self.pos + usize::from(len);
+        out.extend_from_slice(self.input.get(self.pos..end).ok_or_else(invalid)?);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+/// A canonical Huffman code: how many codes there are of each length, and
+/// the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code giving symbol i a code lengths[i] bits long, or none
+    /// if that is 0; fails if there are more codes than lengths allow.
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(invalid());
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// Reads the next symbol from bits.
+    fn decode(&self, bits: &mut Bits<'_>) -> io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.take(1)? as i32;
+            let count = i32::from(count);
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid())
+    }
+}
+
+/// Returns the codes fixed blocks use for literals and lengths, and for
+/// distances.
+fn fixed() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    let lengths = Huffman::new(&lengths).expect("fixed code is complete");
+    let distances = Huffman::new(&[5; 30]).expect("fixed code is complete");
+    (lengths, distances)
+}
+
+/// Reads the codes a dynamic block starts with.
+fn dynamic(bits: &mut Bits<'_>) -> io::Result<(Huffman, Huffman)> {
+    let nlen = bits.take(5)? as usize + 257;
+    let ndist = bits.take(5)? as usize + 1;
+    let ncode = bits.take(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_ORDER[..ncode] {
+        code_lengths[i] = bits.take(3)? as u8;
+    }
+    let code = Huffman::new(&code_lengths)?;
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code.decode(bits)?;
+        let (len, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths[..i].last().ok_or_else(invalid)?;
+                (prev, 3 + bits.take(2)?)
+            }
+            17 => (0, 3 + bits.take(3)?),
+            _ => (0, 11 + bits.take(7)?),
+        };
+        let end = i + repeat as usize;
+        lengths.get_mut(i..end).ok_or_else(invalid)?.fill(len);
+        i = end;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid());
+    }
+    let literals = Huffman::new(&lengths[..nlen])?;
+    let distances = Huffman::new(&lengths[nlen..])?;
+    Ok((literals, distances))
+}
+
+/// Decodes a compressed block onto out.
+fn codes(
+    bits: &mut Bits<'_>,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+    limit: u64,
+) -> io::Result<()> {
+    loop {
+        let symbol = lengths.decode(bits)?;
+        match symbol {
+            0..=255 => {
+                if out.len() as u64 >= limit {
+                    return Err(too_large(limit));
+                }
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let i = usize::from(symbol - 257);
+                let extra = *LENGTH_EXTRA.get(i).ok_or_else(invalid)?;
+                let len = usize::from(LENGTH_BASE[i]) + bits.take(u32::from(extra))? as usize;
+                let i = usize::from(distances.decode(bits)?);
+                let extra = *DIST_EXTRA.get(i).ok_or_else(invalid)?;
+                let dist = usize::from(DIST_BASE[i]) + bits.take(u32::from(extra))? as usize;
+                let start = out.len().checked_sub(dist).ok_or_else(invalid)?;
+                if (out.len() + len) as u64 > limit {
+                    return Err(too_large(limit));
+                }
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Winding a search down cleanly on Ctrl-C.
+//!
+//! The first SIGINT only sets a flag: the walk stops handing out files,
+//! reads fail so searches end before printing a partial line, and what was
+//! printed is flushed before exiting with status 130. A second SIGINT exits
+//! at once, for a read that blocks, such as from a terminal.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The status a process killed by SIGINT exits with.
+pub const STATUS: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+
+    pub const SIGINT: c_int = 2;
+    pub const SIG_IGN: usize = 1;
+
+    extern "C" {
+        pub fn signal(signum: c_int, handler: usize) -> usize;
+        pub fn _exit(status: c_int) -> !;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sigint(_: std::os::raw::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: _exit is async-signal-safe.
+        unsafe { sys::_exit(STATUS) }
+    }
+}
+
+/// Sets up the SIGINT handler, unless SIGINT is ignored as it is for
+/// background jobs; elsewhere Ctrl-C ends the process as usual.
+pub fn install() {
+    // SAFETY: the handler only touches an atomic and calls _exit.
+    #[cfg(unix)]
+    unsafe {
+        let handler: extern "C" fn(std::os::raw::c_int) = on_sigint;
+        if sys::signal(sys::SIGINT, handler as usize) == sys::SIG_IGN {
+            sys::signal(sys::SIGINT, sys::SIG_IGN);
+        }
+    }
+}
+
+/// Reports whether Ctrl-C was pressed.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// A reader failing once Ctrl-C is pressed.
+pub struct Interruptible<R>(pub R);
+
+impl<R: Read> Read for Interruptible<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if interrupted() {
+            return Err(io::Error::other("interrupted"));
+        }
+        self.0.read(buf)
+    }
+}
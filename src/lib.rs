@@ -0,0 +1,44 @@
+//! A small backtracking regex engine, and the grep built on top of it.
+//!
+//! Without the default `std` feature the engine builds as `no_std` with
+//! `alloc`, matching in-memory `&str` and `&[u8]` haystacks only.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod ast;
+pub mod bytes;
+#[cfg(feature = "std")]
+mod cache;
+mod compile;
+mod error;
+mod exec;
+pub mod normalize;
+mod parse;
+mod pool;
+mod regex;
+
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod glob;
+#[cfg(feature = "std")]
+pub mod ignore;
+#[cfg(feature = "std")]
+pub mod printer;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod searcher;
+#[cfg(feature = "std")]
+pub mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use crate::error::{Error, ErrorKind};
+pub use crate::regex::{
+    CaptureMatches, CaptureNames, Captures, Match, Matches, Regex, RegexBuilder,
+};
@@ -1,565 +1,3011 @@
-use anyhow::{bail, Result};
+mod archive;
+mod bench;
+mod changes;
+mod complete;
+mod dedupe;
+mod extract;
+mod flags;
+#[cfg(feature = "http")]
+mod http;
+mod inflate;
+mod interrupt;
+mod pipe;
+mod presets;
+mod progress;
+mod repl;
+mod scope;
+mod serve;
+mod tail;
+mod tui;
+mod types;
+mod verify;
+mod watch;
+
+use anyhow::{anyhow, bail, Result};
+use changes::Changes;
+use flags::{Arg, Flag, Parser, USAGE};
+use interrupt::{interrupted, Interruptible};
+use pipe::Piped;
+use putao_cf::bytes::Regex;
+use putao_cf::encoding::Encoding;
+use putao_cf::filter::Filter;
+use putao_cf::glob::Glob;
+use putao_cf::ignore::Gitignore;
+use putao_cf::normalize::nfc;
+use putao_cf::printer::{Colors, Csv, Json, Printer, Sarif, Standard, Stats};
+use putao_cf::searcher::{Searcher, Sink, SinkContext, SinkFinish, SinkMatch};
+use putao_cf::walk::{self, Entry, Sort, Walker};
+use putao_cf::RegexBuilder;
+use repl::Repl;
 use std::{
-    env, fs,
-    io::{self, Read},
-    path::Path,
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fmt,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, LineWriter, Read, Seek, SeekFrom, Write},
+    mem,
+    path::{Path, PathBuf},
     process,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Instant,
 };
+use tail::{Event, Followed};
+use types::Types;
+use watch::{Change, Watch};
+
+/// What is printed for each input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Mode {
+    /// Every matching line.
+    #[default]
+    Lines,
+    /// The number of matching lines.
+    Count,
+    /// The number of matches, counting each match on a line.
+    CountMatches,
+    /// The name of each input with a match.
+    FilesWithMatches,
+    /// The name of each input without a match.
+    FilesWithoutMatch,
+    /// Nothing; the search ends at the first match anywhere.
+    Quiet,
+    /// The name of each input, which is not searched at all.
+    Files,
+}
+
+/// The dialect patterns are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Syntax {
+    /// POSIX basic regular expressions, as plain `grep` reads them.
+    #[default]
+    Basic,
+    /// POSIX extended regular expressions.
+    Extended,
+    /// Extended regular expressions with the Perl extensions.
+    Perl,
+}
+
+/// What is done with a directory given as an input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Directories {
+    /// It is read like a file, which fails.
+    #[default]
+    Read,
+    /// It is skipped.
+    Skip,
+    /// Every file below it is searched.
+    Recurse,
+}
 
-#[derive(Clone)]
-enum Node {
-    Lit(char),
-    Digit,
-    Word,
+/// What ends the lines of inputs, beyond `\n`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum LineEndings {
+    /// `\n`, with `\r\n` as well if `--crlf` is given.
+    #[default]
+    Lf,
+    /// A lone `\r`.
+    Cr,
+    /// `\n`, `\r\n` or a lone `\r`.
     Any,
-    Pos(String),
-    Neg(String),
-    Opt(Box<Node>),
-    Plus(Box<Node>),
-    Star(Box<Node>),
-    Rep(Box<Node>, usize),
-    MinRep(Box<Node>, usize),
-    RangeRep(Box<Node>, usize, usize),
-    Cap(usize, Vec<Vec<Node>>),
-    CapEnd(usize, usize),
-    Ref(usize),
-}
-
-/// Parses a pattern into AST nodes and anchor flags.
-fn parse(pattern: &str) -> Result<(Vec<Node>, bool, bool)> {
-    let (mut start, mut end) = (false, false);
-    let mut pat = pattern;
-    if pat.starts_with('^') {
-        start = true;
-        pat = &pat[1..];
-    }
-    if pat.ends_with('$') && !pat.ends_with("\\$") {
-        end = true;
-        pat = &pat[..pat.len() - 1];
-    }
-    let cs: Vec<char> = pat.chars().collect();
-    let mut i = 0usize;
-    let mut gid = 0usize;
-    Ok((elems(&cs, &mut i, &mut gid)?, start, end))
-}
-
-/// Parses a single base atom (lit, escape, class, group, any) and advances i.
-fn parse_atom(cs: &[char], i: &mut usize, gid: &mut usize) -> Result<Option<Node>> {
-    if *i >= cs.len() {
-        return Ok(None);
+}
+
+/// The format results are printed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Format {
+    /// Lines prefixed as grep does.
+    #[default]
+    Standard,
+    /// JSON Lines messages.
+    Json,
+    /// A SARIF log.
+    Sarif,
+    /// Comma-separated values.
+    Csv,
+    /// Tab-separated values.
+    Tsv,
+}
+
+/// The printer lines are written with, in the format asked for.
+enum Output {
+    Standard(Standard<Box<dyn Write>>),
+    Json(Json<Box<dyn Write>>),
+    Sarif(Sarif<Box<dyn Write>>),
+    Csv(Csv<Box<dyn Write>>),
+    Tui(tui::Results),
+}
+
+impl Output {
+    fn get_mut(&mut self) -> &mut Box<dyn Write> {
+        match self {
+            Output::Standard(p) => p.get_mut(),
+            Output::Json(p) => p.get_mut(),
+            Output::Sarif(p) => p.get_mut(),
+            Output::Csv(p) => p.get_mut(),
+            Output::Tui(p) => p.get_mut(),
+        }
     }
-    let c = cs[*i];
-    let base = if c == '\\' {
-        *i += 1;
-        if *i >= cs.len() {
-            bail!("invalid escape");
-        }
-        let e = cs[*i];
-        *i += 1;
-        match e {
-            'd' => Some(Node::Digit),
-            'w' => Some(Node::Word),
-            '1'..='9' => Some(Node::Ref((e as u8 - b'0') as usize)),
-            _ => Some(Node::Lit(e)),
-        }
-    } else if c == '[' {
-        *i += 1;
-        let neg = *i < cs.len() && cs[*i] == '^';
-        if neg {
-            *i += 1;
-        }
-        let mut s = String::new();
-        while *i < cs.len() && cs[*i] != ']' {
-            s.push(cs[*i]);
-            *i += 1;
-        }
-        if *i >= cs.len() || cs[*i] != ']' {
-            bail!("unclosed class");
-        }
-        *i += 1;
-        Some(if neg { Node::Neg(s) } else { Node::Pos(s) })
-    } else if c == '(' {
-        *i += 1;
-        *gid += 1;
-        let id = *gid;
-        let mut buf = String::new();
-        let mut d = 0;
-        while *i < cs.len() {
-            let ch = cs[*i];
-            *i += 1;
-            if ch == '(' {
-                d += 1;
-            }
-            if ch == ')' {
-                if d == 0 {
-                    break;
-                }
-                d -= 1;
-            }
-            buf.push(ch);
+}
+
+impl Printer for Output {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        match self {
+            Output::Standard(p) => p.matched(path, m),
+            Output::Json(p) => p.matched(path, m),
+            Output::Sarif(p) => p.matched(path, m),
+            Output::Csv(p) => p.matched(path, m),
+            Output::Tui(p) => p.matched(path, m),
         }
-        Some(Node::Cap(id, branches(&buf, gid)?))
-    } else if c == '.' {
-        *i += 1;
-        Some(Node::Any)
-    } else if c == ')' {
-        None
-    } else {
-        *i += 1;
-        Some(Node::Lit(c))
-    };
-    Ok(base)
-}
-
-/// Applies a quantifier to the base node if present, advances i.
-fn parse_quantifier(cs: &[char], i: &mut usize, base: Node) -> Result<Node> {
-    if *i >= cs.len() {
-        return Ok(base);
-    }
-    let mut n = base.clone();
-    if cs[*i] == '+' {
-        *i += 1;
-        n = Node::Plus(Box::new(n));
-    } else if cs[*i] == '?' {
-        *i += 1;
-        n = Node::Opt(Box::new(n));
-    } else if cs[*i] == '*' {
-        *i += 1;
-        n = Node::Star(Box::new(n));
-    } else if cs[*i] == '{' {
-        *i += 1;
-        let mut min_str = String::new();
-        while *i < cs.len() && cs[*i].is_ascii_digit() {
-            min_str.push(cs[*i]);
-            *i += 1;
-        }
-        if min_str.is_empty() {
-            bail!("invalid repetition quantifier: missing min count");
-        }
-        let min: usize = min_str.parse()?;
-        if *i < cs.len() && cs[*i] == ',' {
-            *i += 1;
-            let mut max_str = String::new();
-            while *i < cs.len() && cs[*i].is_ascii_digit() {
-                max_str.push(cs[*i]);
-                *i += 1;
-            }
-            if *i >= cs.len() || cs[*i] != '}' {
-                bail!("invalid repetition quantifier");
-            }
-            *i += 1;
-            if max_str.is_empty() {
-                n = Node::MinRep(Box::new(base), min);
-            } else {
-                let max: usize = max_str.parse()?;
-                if max < min {
-                    bail!("invalid repetition quantifier: max < min");
-                }
-                n = Node::RangeRep(Box::new(base), min, max);
-            }
-        } else {
-            if *i >= cs.len() || cs[*i] != '}' {
-                bail!("invalid repetition quantifier");
-            }
-            *i += 1;
-            n = Node::Rep(Box::new(base), min);
+    }
+
+    fn context(&mut self, path: Option<&str>, ctx: &SinkContext<'_>) -> io::Result<()> {
+        match self {
+            Output::Standard(p) => p.context(path, ctx),
+            Output::Json(p) => p.context(path, ctx),
+            Output::Sarif(p) => p.context(path, ctx),
+            Output::Csv(p) => p.context(path, ctx),
+            Output::Tui(p) => p.context(path, ctx),
+        }
+    }
+
+    fn context_break(&mut self) -> io::Result<()> {
+        match self {
+            Output::Standard(p) => p.context_break(),
+            Output::Json(p) => p.context_break(),
+            Output::Sarif(p) => p.context_break(),
+            Output::Csv(p) => p.context_break(),
+            Output::Tui(p) => p.context_break(),
+        }
+    }
+
+    fn begin(&mut self, path: Option<&str>) -> io::Result<()> {
+        match self {
+            Output::Standard(p) => p.begin(path),
+            Output::Json(p) => p.begin(path),
+            Output::Sarif(p) => p.begin(path),
+            Output::Csv(p) => p.begin(path),
+            Output::Tui(p) => p.begin(path),
+        }
+    }
+
+    fn finish(&mut self, path: Option<&str>, finish: &SinkFinish) -> io::Result<()> {
+        match self {
+            Output::Standard(p) => p.finish(path, finish),
+            Output::Json(p) => p.finish(path, finish),
+            Output::Sarif(p) => p.finish(path, finish),
+            Output::Csv(p) => p.finish(path, finish),
+            Output::Tui(p) => p.finish(path, finish),
         }
     }
-    Ok(n)
 }
 
-/// Parses a sequence of nodes until end or ')'.
-fn elems(cs: &[char], i: &mut usize, gid: &mut usize) -> Result<Vec<Node>> {
-    let mut out = Vec::new();
-    loop {
-        match parse_atom(cs, i, gid)? {
-            Some(base) => {
-                let quantified = parse_quantifier(cs, i, base)?;
-                out.push(quantified);
-            }
-            None => break, // End of sequence (e.g., ')')
-        }
-    }
-    Ok(out)
-}
-
-/// Parses top-level alternation branches within a group.
-fn branches(s: &str, gid: &mut usize) -> Result<Vec<Vec<Node>>> {
-    let mut out = Vec::new();
-    let mut cur = String::new();
-    let cs: Vec<char> = s.chars().collect();
-    let mut i = 0usize;
-    let mut d = 0i32;
-    while i < cs.len() {
-        let c = cs[i];
-        if d == 0 && c == '|' {
-            let v: Vec<char> = cur.chars().collect();
-            let mut j = 0usize;
-            out.push(elems(&v, &mut j, gid)?);
-            cur.clear();
-        } else {
-            if c == '(' {
-                d += 1;
-            }
-            if c == ')' {
-                d -= 1;
-            }
-            cur.push(c);
+/// The compiled pattern, search options and output shared by every input.
+struct Grep {
+    re: Regex,
+    searcher: Searcher,
+    walker: Walker,
+    printer: Output,
+    /// The file results go to, if `--output` names one.
+    output: Option<OutputFile>,
+    mode: Mode,
+    invert_match: bool,
+    /// Whether lines are prefixed with the name of their input.
+    with_filename: bool,
+    /// The name standard input is labelled with.
+    stdin_label: String,
+    /// Whether file names end with NUL rather than `:` or a newline.
+    null: bool,
+    /// What separates the components of labels, if not the platform's.
+    path_separator: Option<char>,
+    /// Whether binary inputs are skipped as if they did not match.
+    skip_binary: bool,
+    /// Whether messages about inputs that cannot be read are left out.
+    no_messages: bool,
+    /// Whether compressed files are searched through their decompressor.
+    search_zip: bool,
+    /// Whether the files in zip and tar archives are searched.
+    archives: bool,
+    /// The template matches in files are rewritten with, if `--write` asks
+    /// for that.
+    write: Option<String>,
+    /// What the originals of files rewritten are kept as, after their name.
+    backup_suffix: Option<String>,
+    /// The engine selected lines are checked against, if `--verify` asks.
+    verify: Option<verify::Oracle>,
+    /// How documents are read, if their text is searched.
+    extractors: Option<extract::Extractors>,
+    /// The most an archive or document may unpack to.
+    unpack_limit: u64,
+    /// Whether recursion searches only the files git tracks.
+    git: bool,
+    /// The lines files are searched on, if `--changed` limits them.
+    changes: Option<Arc<Changes>>,
+    /// The part of source files searched, if `--only` limits it.
+    only: Option<scope::Only>,
+    /// The globs of the files each language is tokenized for.
+    languages: Vec<(Glob, scope::Language)>,
+    /// The command files are searched through, if any.
+    pre: Option<String>,
+    /// Globs a file name must match one of to go through `pre`, if any.
+    pre_globs: Vec<Glob>,
+    /// What was searched and found so far, if `--stats` asks for it.
+    stats: Option<Stats>,
+    /// How many files recursion searches at once.
+    threads: usize,
+    /// Set once an input could not be searched.
+    errored: bool,
+    /// How many lines of the input last searched were too long to search.
+    long_lines: u64,
+    /// The canonical paths of the files searched so far, when recursing
+    /// through roots that may overlap.
+    seen: Option<HashSet<PathBuf>>,
+    /// The contents searched so far, if `--dedupe` leaves out copies.
+    dedupe: Option<dedupe::Dedupe>,
+    /// Set once nothing more needs to be searched.
+    done: bool,
+}
+
+impl Grep {
+    /// Reports whether the file at path is yet to be searched, recording
+    /// that it now is.
+    fn first_visit(&mut self, path: &Path) -> bool {
+        match &mut self.seen {
+            Some(seen) => fs::canonicalize(path).map_or(true, |path| seen.insert(path)),
+            None => true,
+        }
+    }
+
+    /// Reports whether the file at path is a copy of one searched before,
+    /// which `--dedupe` leaves out; one that cannot be read is not.
+    fn duplicate(&mut self, path: &Path) -> bool {
+        let dedupe = self.dedupe.as_mut();
+        dedupe.is_some_and(|d| d.duplicate(path).unwrap_or(false))
+    }
+
+    /// Reports that searching the input labelled path failed, unless
+    /// messages are suppressed.
+    fn warn(&mut self, path: &str, err: &anyhow::Error) {
+        exit_if_broken_pipe(err);
+        self.errored = true;
+        // What Ctrl-C cut short is not worth a message.
+        if !self.no_messages && !interrupted() {
+            progress::clear();
+            eprintln!("putao: {}: {}", path, describe(err));
         }
-        i += 1;
     }
-    let v: Vec<char> = cur.chars().collect();
-    let mut j = 0usize;
-    out.push(elems(&v, &mut j, gid)?);
-    Ok(out)
 }
 
-/// Attempts to match the pattern against the input string.
-fn is_match(input: &str, pat: &str) -> Result<bool> {
-    let (nodes, start, end) = parse(pat)?;
-    let cs: Vec<char> = input.chars().collect();
-    let n = cs.len();
-    let starts: Vec<usize> = if start { vec![0] } else { (0..=n).collect() };
-    Ok(starts.iter().any(|&st| {
-        match_from(st, &nodes, &cs, Vec::new())
-            .map(|(e, _)| if end { e == n } else { true })
-            .unwrap_or(false)
-    }))
+/// Counts matching lines, or every match within them, without printing them.
+struct Count {
+    /// Whether each match is counted rather than each line.
+    each: bool,
+    n: u64,
+}
+
+impl Sink for Count {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        self.n += if self.each {
+            m.matches().count() as u64
+        } else {
+            1
+        };
+        Ok(true)
+    }
 }
 
-/// Prints a segment with optional filename prefix, preserving existing newline.
-fn print_with_prefix(prefix: Option<&str>, seg: &str) {
-    match prefix {
-        Some(pfx) if seg.ends_with('\n') => print!("{}:{}", pfx, seg),
-        Some(pfx) => print!("{}:{}\n", pfx, seg),
-        None if seg.ends_with('\n') => print!("{}", seg),
-        None => println!("{}", seg),
+/// Stops the search at the first matching line.
+#[derive(Default)]
+struct First(bool);
+
+impl Sink for First {
+    fn matched(&mut self, _: &SinkMatch<'_>) -> io::Result<bool> {
+        self.0 = true;
+        Ok(false)
     }
 }
 
-/// Prints matching lines from content with optional prefix; returns true if any matched.
-fn grep_content(content: &str, pattern: &str, prefix: Option<&str>) -> Result<bool> {
-    let mut any = false;
-    let mut consumed = 0usize;
-    for seg in content.split_inclusive('\n') {
-        let ln = seg.trim_end_matches(|c| c == '\n' || c == '\r');
-        if is_match(ln, pattern)? {
-            any = true;
-            print_with_prefix(prefix, seg);
+/// Passes lines on to a sink while the input looks like text; once it
+/// looks binary, the search ends at the next match instead.
+struct Text<S> {
+    sink: S,
+    binary: bool,
+    /// Whether a match was found after the input turned out to be binary.
+    binary_match: bool,
+}
+
+impl<S: Sink> Sink for Text<S> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        if self.binary {
+            self.binary_match = true;
+            return Ok(false);
         }
-        consumed += seg.len();
+        self.sink.matched(m)
     }
-    if consumed < content.len() {
-        let seg = &content[consumed..];
-        let ln = seg.trim_end_matches('\r');
-        if is_match(ln, pattern)? {
-            any = true;
-            print_with_prefix(prefix, seg);
+
+    fn context(&mut self, ctx: &SinkContext<'_>) -> io::Result<bool> {
+        if self.binary {
+            return Ok(true);
         }
+        self.sink.context(ctx)
+    }
+
+    fn context_break(&mut self) -> io::Result<bool> {
+        if self.binary {
+            return Ok(true);
+        }
+        self.sink.context_break()
+    }
+
+    fn binary_data(&mut self, _offset: u64) -> io::Result<bool> {
+        self.binary = true;
+        Ok(true)
+    }
+
+    fn begin(&mut self) -> io::Result<bool> {
+        self.sink.begin()
+    }
+
+    fn finish(&mut self, finish: &SinkFinish) -> io::Result<()> {
+        self.sink.finish(finish)
     }
-    Ok(any)
 }
 
-fn grep_file_with_label(path: &Path, pattern: &str, label: &str) -> Result<bool> {
-    let content = fs::read_to_string(path)?;
-    grep_content(&content, pattern, Some(label))
-}
-
-/// Backtracking matcher for a sequence of nodes from a position.
-fn match_from(
-    pos: usize,
-    nodes: &[Node],
-    cs: &[char],
-    caps: Vec<Option<String>>,
-) -> Option<(usize, Vec<Option<String>>)> {
-    if nodes.is_empty() {
-        return Some((pos, caps));
-    }
-    let head = &nodes[0];
-    let tail = &nodes[1..];
-
-    fn more(
-        pos: usize,
-        inner: &Node,
-        rest: &[Node],
-        cs: &[char],
-        caps: Vec<Option<String>>,
-    ) -> Option<(usize, Vec<Option<String>>)> {
-        if let Some((p1, c1)) = match_from(pos, &[inner.clone()], cs, caps) {
-            if let Some((e, c2)) = more(p1, inner, rest, cs, c1.clone()) {
-                return Some((e, c2));
-            }
-            match_from(p1, rest, cs, c1)
-        } else {
-            None
-        }
-    }
-
-    fn bounded_more(
-        pos: usize,
-        inner: &Node,
-        rest: &[Node],
-        cs: &[char],
-        caps: Vec<Option<String>>,
-        remaining: usize,
-    ) -> Option<(usize, Vec<Option<String>>)> {
-        if remaining > 0 {
-            if let Some((p1, c1)) = match_from(pos, &[inner.clone()], cs, caps.clone()) {
-                if let Some((e, c2)) = bounded_more(p1, inner, rest, cs, c1, remaining - 1) {
-                    return Some((e, c2));
-                }
-            }
+/// Ends the search as soon as the input is found to be binary.
+struct SkipBinary<S> {
+    sink: S,
+    skipped: bool,
+}
+
+impl<S: Sink> Sink for SkipBinary<S> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        self.sink.matched(m)
+    }
+
+    fn context(&mut self, ctx: &SinkContext<'_>) -> io::Result<bool> {
+        self.sink.context(ctx)
+    }
+
+    fn context_break(&mut self) -> io::Result<bool> {
+        self.sink.context_break()
+    }
+
+    fn binary_data(&mut self, _offset: u64) -> io::Result<bool> {
+        self.skipped = true;
+        Ok(false)
+    }
+
+    fn begin(&mut self) -> io::Result<bool> {
+        self.sink.begin()
+    }
+
+    fn finish(&mut self, finish: &SinkFinish) -> io::Result<()> {
+        self.sink.finish(finish)
+    }
+}
+
+/// Passes lines on to a sink, adding what the search found to stats if
+/// they are kept.
+struct Tally<'s, S> {
+    sink: S,
+    stats: Option<&'s mut Stats>,
+    /// The counts for this search alone.
+    search: Stats,
+    started: Instant,
+    /// Where to put how many lines were too long to search.
+    long_lines: &'s mut u64,
+}
+
+impl<S: Sink> Sink for Tally<'_, S> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        progress::matched();
+        if self.stats.is_some() {
+            self.search.matched_lines += 1;
+            self.search.matches += m.matches().count() as u64;
         }
-        match_from(pos, rest, cs, caps)
+        self.sink.matched(m)
     }
 
-    match head {
-        Node::Plus(inner) => more(pos, &*inner, tail, cs, caps),
-        Node::Star(inner) => {
-            if let Some((e, c)) = more(pos, &*inner, tail, cs, caps.clone()) {
-                Some((e, c))
-            } else {
-                match_from(pos, tail, cs, caps)
-            }
+    fn context(&mut self, ctx: &SinkContext<'_>) -> io::Result<bool> {
+        self.sink.context(ctx)
+    }
+
+    fn context_break(&mut self) -> io::Result<bool> {
+        self.sink.context_break()
+    }
+
+    fn binary_data(&mut self, offset: u64) -> io::Result<bool> {
+        self.sink.binary_data(offset)
+    }
+
+    fn begin(&mut self) -> io::Result<bool> {
+        self.sink.begin()
+    }
+
+    fn finish(&mut self, finish: &SinkFinish) -> io::Result<()> {
+        *self.long_lines = finish.long_lines();
+        if let Some(stats) = &mut self.stats {
+            self.search.elapsed = self.started.elapsed();
+            self.search.searches = 1;
+            self.search.searches_with_match = (self.search.matched_lines > 0) as u64;
+            self.search.bytes_searched = finish.byte_count();
+            stats.add(&self.search);
         }
-        Node::Rep(inner, count) => {
-            let mut p = pos;
-            let mut c = caps;
-            for _ in 0..*count {
-                if let Some((np, nc)) = match_from(p, &[*(*inner).clone()], cs, c) {
-                    p = np;
-                    c = nc;
-                } else {
-                    return None;
-                }
+        self.sink.finish(finish)
+    }
+}
+
+/// Searches rdr into sink, returning false if it was skipped for being binary.
+fn search<R: Read, S: Sink>(
+    searcher: &Searcher,
+    re: &Regex,
+    skip_binary: bool,
+    stats: Option<&mut Stats>,
+    long_lines: &mut u64,
+    rdr: R,
+    sink: S,
+) -> io::Result<bool> {
+    let sink = Tally {
+        sink,
+        stats,
+        search: Stats::default(),
+        started: Instant::now(),
+        long_lines,
+    };
+    if !skip_binary {
+        searcher.search_reader(re, rdr, sink)?;
+        return Ok(true);
+    }
+    let mut skip = SkipBinary {
+        sink,
+        skipped: false,
+    };
+    searcher.search_reader(re, rdr, &mut skip)?;
+    Ok(!skip.skipped)
+}
+
+/// Prints what the mode asks for about rdr, prefixing lines with label if
+/// filenames are shown; returns true if anything was selected. Warns of
+/// lines left unsearched for their length.
+fn grep_reader<R: Read>(rdr: R, grep: &mut Grep, label: &str) -> Result<bool> {
+    grep.long_lines = 0;
+    progress::searching(label);
+    let found = select(Interruptible(progress::Counted(rdr)), grep, label);
+    if grep.long_lines > 0 && !grep.no_messages {
+        eprintln!(
+            "putao: warning: {}: skipped {} line{} over the maximum line length",
+            label,
+            grep.long_lines,
+            if grep.long_lines == 1 { "" } else { "s" }
+        );
+    }
+    found
+}
+
+/// The search behind [`grep_reader`], which is left to warn.
+fn select<R: Read>(rdr: R, grep: &mut Grep, label: &str) -> Result<bool> {
+    let prefix = grep.with_filename.then_some(label);
+    match grep.mode {
+        Mode::Lines => {
+            let mut text = Text {
+                sink: grep.printer.sink(prefix),
+                binary: false,
+                binary_match: false,
+            };
+            let searched = search(
+                &grep.searcher,
+                &grep.re,
+                grep.skip_binary,
+                grep.stats.as_mut(),
+                &mut grep.long_lines,
+                rdr,
+                &mut text,
+            )?;
+            let (printed, binary_match) = (text.sink.has_match(), text.binary_match);
+            if binary_match && matches!(grep.printer, Output::Standard(_)) {
+                writeln!(grep.printer.get_mut(), "Binary file {} matches", label)?;
             }
-            match_from(p, tail, cs, c)
+            Ok(searched && (printed || binary_match))
         }
-        Node::Opt(inner) => {
-            if let Some((p1, c1)) = match_from(pos, &[(*inner.clone())], cs, caps.clone()) {
-                if let Some((e, c2)) = match_from(p1, tail, cs, c1) {
-                    return Some((e, c2));
-                }
+        Mode::Count | Mode::CountMatches => {
+            // Inverted lines hold no matches, so those are counted by line.
+            let each = grep.mode == Mode::CountMatches && !grep.invert_match;
+            let mut count = Count { each, n: 0 };
+            if !search(
+                &grep.searcher,
+                &grep.re,
+                grep.skip_binary,
+                grep.stats.as_mut(),
+                &mut grep.long_lines,
+                rdr,
+                &mut count,
+            )? {
+                count.n = 0;
             }
-            match_from(pos, tail, cs, caps)
-        }
-        Node::Lit(ch) => {
-            if pos < cs.len() && cs[pos] == *ch {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
+            let out = grep.printer.get_mut();
+            if let Some(pfx) = prefix {
+                write!(out, "{}{}", pfx, if grep.null { '\0' } else { ':' })?;
             }
+            writeln!(out, "{}", count.n)?;
+            Ok(count.n > 0)
         }
-        Node::Digit => {
-            if pos < cs.len() && cs[pos].is_ascii_digit() {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
+        Mode::FilesWithMatches | Mode::FilesWithoutMatch => {
+            let mut first = First::default();
+            first.0 &= search(
+                &grep.searcher,
+                &grep.re,
+                grep.skip_binary,
+                grep.stats.as_mut(),
+                &mut grep.long_lines,
+                rdr,
+                &mut first,
+            )?;
+            let listed = first.0 == (grep.mode == Mode::FilesWithMatches);
+            if listed {
+                let end = if grep.null { '\0' } else { '\n' };
+                write!(grep.printer.get_mut(), "{}{}", label, end)?;
             }
+            Ok(listed)
         }
-        Node::Word => {
-            if pos < cs.len() && (cs[pos].is_ascii_alphanumeric() || cs[pos] == '_') {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
-            }
+        Mode::Files => {
+            let end = if grep.null { '\0' } else { '\n' };
+            write!(grep.printer.get_mut(), "{}{}", label, end)?;
+            Ok(true)
         }
-        Node::Any => {
-            if pos < cs.len() {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
-            }
+        Mode::Quiet => {
+            let mut first = First::default();
+            first.0 &= search(
+                &grep.searcher,
+                &grep.re,
+                grep.skip_binary,
+                grep.stats.as_mut(),
+                &mut grep.long_lines,
+                rdr,
+                &mut first,
+            )?;
+            grep.done = first.0;
+            Ok(first.0)
         }
-        Node::Pos(s) => {
-            if pos < cs.len() && s.contains(cs[pos]) {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
-            }
+    }
+}
+
+fn grep_file_with_label(path: &Path, grep: &mut Grep, label: &str) -> Result<bool> {
+    if grep.output.as_ref().is_some_and(|output| output.is(path)) {
+        // Searching what results are written to would find them again.
+        return Ok(false);
+    }
+    if let Some(changes) = &grep.changes {
+        let Some(lines) = changes.lines(path) else {
+            return Ok(false);
+        };
+        grep.searcher.only_lines(Some(lines.to_vec()));
+    }
+    if let Some(only) = grep.only {
+        let Some(language) = language(path, grep) else {
+            return Ok(false);
+        };
+        // Tokenized as it is, the file is searched and rewritten so too.
+        let data = fs::read(path)?;
+        grep.searcher
+            .only_bytes(Some(scope::ranges(only, language, &data)));
+        if grep.write.is_some() {
+            return rewrite(path, grep, label);
         }
-        Node::Neg(s) => {
-            if pos < cs.len() && !s.contains(cs[pos]) {
-                match_from(pos + 1, tail, cs, caps)
-            } else {
-                None
-            }
+        return grep_reader(data.as_slice(), grep, label);
+    }
+    // What is read through a command is not the file, so is left as is.
+    if grep.write.is_some() && !piped(path, grep) {
+        return rewrite(path, grep, label);
+    }
+    if grep.verify.is_some() && !piped(path, grep) {
+        return verify_input(&fs::read(path)?, grep, label);
+    }
+    if let Some(extractor) = extractor(path, grep) {
+        let rdr = extractor.extract(path, grep.unpack_limit)?;
+        return grep_reader(rdr, grep, label);
+    }
+    if let Some(kind) = archive_kind(path, grep) {
+        return grep_archive(path, kind, grep, label);
+    }
+    let rdr = open(path, grep)?;
+    grep_reader(rdr, grep, label)
+}
+
+/// Searches the file at path as [`grep_file_with_label`] does, then
+/// rewrites it with every match in it replaced; binary files are left as
+/// they are.
+fn rewrite(path: &Path, grep: &mut Grep, label: &str) -> Result<bool> {
+    let data = fs::read(path)?;
+    let found = grep_reader(data.as_slice(), grep, label)?;
+    // Every file is rewritten, not only the first to match.
+    grep.done &= grep.mode != Mode::Quiet;
+    let template = grep.write.as_deref().expect("files are rewritten");
+    let mut edits = Edits {
+        data: &data,
+        template,
+        out: Vec::new(),
+        last: 0,
+    };
+    let mut long_lines = 0;
+    let text = search(
+        &grep.searcher,
+        &grep.re,
+        true,
+        None,
+        &mut long_lines,
+        data.as_slice(),
+        &mut edits,
+    )?;
+    if !text || edits.last == 0 {
+        return Ok(found);
+    }
+    let Edits { mut out, last, .. } = edits;
+    out.extend_from_slice(&data[last..]);
+    if out != data {
+        replace_file(path, &out, grep.backup_suffix.as_deref())?;
+    }
+    Ok(found)
+}
+
+/// Collects what an input becomes with every match on its matching lines
+/// replaced by a template.
+struct Edits<'d> {
+    data: &'d [u8],
+    template: &'d str,
+    /// The input up to the end of the last line replaced, as it becomes.
+    out: Vec<u8>,
+    /// Where in the input that line ends.
+    last: usize,
+}
+
+impl Sink for Edits<'_> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        let Some(re) = m.regex() else {
+            return Ok(true);
+        };
+        let start = usize::try_from(m.absolute_byte_offset()).unwrap_or(usize::MAX);
+        let end = start.saturating_add(m.bytes().len());
+        if self.data.get(start..end) != Some(m.bytes()) {
+            return Err(io::Error::other(
+                "its text was decoded to be searched, so it is not rewritten",
+            ));
         }
-        Node::Cap(id, brs) => {
-            let slot = id - 1;
-            for b in brs {
-                let mut seq = b.clone();
-                seq.push(Node::CapEnd(slot, pos));
-                seq.extend_from_slice(tail);
-                if let Some((e, c)) = match_from(pos, &seq, cs, caps.clone()) {
-                    return Some((e, c));
-                }
+        self.out.extend_from_slice(&self.data[self.last..start]);
+        let line = m.line();
+        let mut at = 0;
+        for caps in re.captures_iter(line) {
+            if !m.counts(&caps) {
+                continue;
             }
-            None
+            let r = caps.get(0).unwrap().range();
+            self.out.extend_from_slice(&line[at..r.start]);
+            re.expand(&caps, self.template, &mut self.out);
+            at = r.end;
         }
-        Node::CapEnd(slot, start) => {
-            let mut nc = caps.clone();
-            if nc.len() <= *slot {
-                nc.resize(*slot + 1, None);
+        self.out.extend_from_slice(&m.bytes()[at..]);
+        self.last = end;
+        Ok(true)
+    }
+}
+
+/// Searches data and prints the lines the `--verify` oracle selects
+/// differently, returning whether there were any.
+fn verify_input(data: &[u8], grep: &mut Grep, label: &str) -> Result<bool> {
+    let mut ours = Selected(Vec::new());
+    let mut long_lines = 0;
+    search(
+        &grep.searcher,
+        &grep.re,
+        false,
+        None,
+        &mut long_lines,
+        data,
+        &mut ours,
+    )?;
+    let oracle = grep.verify.as_ref().expect("files are verified");
+    let theirs = oracle.select(data)?;
+    let lines = oracle.lines(data);
+    let name = oracle.name();
+    let (mut ours, mut theirs) = (ours.0.into_iter().peekable(), theirs.into_iter().peekable());
+    let mut diverged = false;
+    let out = grep.printer.get_mut();
+    loop {
+        let (n, by, not) = match (ours.peek(), theirs.peek()) {
+            (Some(a), Some(b)) if a == b => {
+                ours.next();
+                theirs.next();
+                continue;
             }
-            let s: String = cs[*start..pos].iter().collect();
-            nc[*slot] = Some(s);
-            match_from(pos, tail, cs, nc)
+            (Some(&a), b) if b.map_or(true, |&b| a < b) => (ours.next().unwrap(), "putao", name),
+            (_, Some(_)) => (theirs.next().unwrap(), name, "putao"),
+            (_, None) => break,
+        };
+        let line = lines.get((n - 1) as usize).copied().unwrap_or_default();
+        writeln!(
+            out,
+            "{}:{}: selected by {}, not {}: {}",
+            label,
+            n,
+            by,
+            not,
+            String::from_utf8_lossy(line)
+        )?;
+        diverged = true;
+    }
+    Ok(diverged)
+}
+
+/// Collects the numbers of the lines a search selects.
+struct Selected(Vec<u64>);
+
+impl Sink for Selected {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        self.0.push(m.line_number());
+        Ok(true)
+    }
+}
+
+/// Replaces the file at path, or the one it links to, with data, through
+/// a temporary file renamed over it so it is never left half written; the
+/// original is kept as its name followed by backup_suffix if given.
+fn replace_file(path: &Path, data: &[u8], backup_suffix: Option<&str>) -> io::Result<()> {
+    let target = fs::canonicalize(path)?;
+    let tmp = temp_path(&target);
+    let written = (|| {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)?;
+        file.write_all(data)?;
+        file.set_permissions(fs::metadata(&target)?.permissions())?;
+        file.sync_all()?;
+        if let Some(suffix) = backup_suffix {
+            let mut backup = target.clone().into_os_string();
+            backup.push(suffix);
+            fs::copy(&target, backup)?;
         }
-        Node::Ref(n) => {
-            if let Some(Some(s)) = caps.get(n - 1) {
-                let rs: Vec<char> = s.chars().collect();
-                let len = rs.len();
-                if pos + len <= cs.len() && cs[pos..pos + len] == rs[..] {
-                    match_from(pos + len, tail, cs, caps)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
+        fs::rename(&tmp, &target)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Returns where a file written to replace the one at target goes until
+/// it is renamed over it: beside it, hidden, and named for this process.
+fn temp_path(target: &Path) -> PathBuf {
+    let name = target.file_name().expect("a file has a name");
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".putao-{}", process::id()));
+    target.with_file_name(tmp_name)
+}
+
+/// The file `--output` writes results to: through a temporary file renamed
+/// over it once they are all written, so it never holds only some of them,
+/// unless they are appended to it.
+struct OutputFile {
+    /// Where the file is, made absolute.
+    path: PathBuf,
+    /// The temporary file written in its place, until it is renamed.
+    tmp: Option<PathBuf>,
+    /// What is written to, for whatever prints apart from the printer.
+    file: File,
+}
+
+impl OutputFile {
+    /// Opens the file at path to be written, or appended to if append.
+    fn create(path: &Path, append: bool) -> io::Result<(OutputFile, File)> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::other("not a file name"))?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => fs::canonicalize(dir)?,
+            _ => env::current_dir()?,
+        };
+        let path = dir.join(name);
+        if append {
+            let file = fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)?;
+            let output = OutputFile {
+                path,
+                tmp: None,
+                file: file.try_clone()?,
+            };
+            return Ok((output, file));
         }
-        Node::MinRep(inner, min) => {
-            let mut p = pos;
-            let mut c = caps;
-            for _ in 0..*min {
-                if let Some((np, nc)) = match_from(p, &[*inner.clone()], cs, c) {
-                    p = np;
-                    c = nc;
-                } else {
-                    return None;
-                }
-            }
-            if let Some((e, cc)) = more(p, &*inner, tail, cs, c.clone()) {
-                Some((e, cc))
-            } else {
-                match_from(p, tail, cs, c)
-            }
+        let tmp = temp_path(&path);
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)?;
+        if let Ok(meta) = fs::metadata(&path) {
+            file.set_permissions(meta.permissions())?;
         }
-        Node::RangeRep(inner, min, max) => {
-            let mut p = pos;
-            let mut c = caps;
-            for _ in 0..*min {
-                if let Some((np, nc)) = match_from(p, &[*inner.clone()], cs, c) {
-                    p = np;
-                    c = nc;
-                } else {
-                    return None;
-                }
-            }
-            let extra = if *max > *min { *max - *min } else { 0 };
-            bounded_more(p, &*inner, tail, cs, c, extra)
-        }
-    }
-}
-
-/// Recursively searches a directory or file, labeling outputs relateive to procided root arguement
-fn grep_dir(root: &str, pattern: &str) -> Result<bool> {
-    let base = Path::new(root);
-    let label_base = root.trim_end_matches(std::path::MAIN_SEPARATOR);
-    fn walk(
-        base: &Path,
-        label_base: &str,
-        dir: &Path,
-        pattern: &str,
-        any: &mut bool,
-    ) -> Result<()> {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let ft = entry.file_type()?;
-            if ft.is_dir() {
-                walk(base, label_base, &path, pattern, any)?;
-            } else if ft.is_file() {
-                let rel = path.strip_prefix(base).unwrap_or(&path);
-                let label = if rel.as_os_str().is_empty() {
-                    label_base.to_string()
-                } else {
-                    format!("{}/{}", label_base, rel.display())
-                };
-                if grep_file_with_label(&path, pattern, &label)? {
-                    *any = true;
-                }
-            }
+        let output = OutputFile {
+            path,
+            tmp: Some(tmp),
+            file: file.try_clone()?,
+        };
+        Ok((output, file))
+    }
+
+    /// Reports whether the file at path is this one, or its temporary file,
+    /// which are not searched.
+    fn is(&self, path: &Path) -> bool {
+        let name = path.file_name();
+        let named = |p: &Path| p.file_name() == name;
+        if !named(&self.path) && !self.tmp.as_deref().is_some_and(named) {
+            return false;
         }
-        Ok(())
+        fs::canonicalize(path).is_ok_and(|p| p == self.path || Some(&p) == self.tmp.as_ref())
+    }
+
+    /// Puts the file written in place, once everything is written to it.
+    fn commit(mut self) -> io::Result<()> {
+        match self.tmp.take() {
+            Some(tmp) => fs::rename(tmp, &self.path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    /// Removes the temporary file of a search that did not finish.
+    fn drop(&mut self) {
+        if let Some(tmp) = &self.tmp {
+            let _ = fs::remove_file(tmp);
+        }
+    }
+}
+
+/// Returns the kind of archive the file at path is, if `--search-archives`
+/// asks for its files to be searched and no `--pre` command applies.
+fn archive_kind(path: &Path, grep: &Grep) -> Option<archive::Kind> {
+    if !grep.archives {
+        return None;
     }
+    pre(path, grep).map_or_else(|| archive::kind(path), |_| None)
+}
+
+/// Returns the extractor the file at path is read through, if `--extract`
+/// asks for the text of documents and no `--pre` command applies.
+fn extractor<'g>(path: &Path, grep: &'g Grep) -> Option<&'g dyn extract::Extractor> {
+    let extractors = grep.extractors.as_ref()?;
+    pre(path, grep).map_or_else(|| extractors.get(path), |_| None)
+}
+
+/// Searches each file in the archive at path, labelled as
+/// `ARCHIVE!NAME`; one that cannot be read is warned about and passed over.
+fn grep_archive(path: &Path, kind: archive::Kind, grep: &mut Grep, label: &str) -> Result<bool> {
+    let limit = grep.unpack_limit;
     let mut any = false;
-    if base.is_dir() {
-        walk(base, label_base, base, pattern, &mut any)?;
-    } else if base.is_file() {
-        let label = label_base.to_string();
-        if grep_file_with_label(base, pattern, &label)? {
-            any = true;
+    let each = |name: &str, rdr: &mut dyn Read| {
+        let label = format!("{}!{}", label, name);
+        match grep_reader(rdr, grep, &label) {
+            Ok(found) => any |= found,
+            Err(e) => grep.warn(&label, &e),
+        }
+        !grep.done && !interrupted()
+    };
+    match kind {
+        archive::Kind::Zip => archive::zip(File::open(path)?, limit, each)?,
+        archive::Kind::Tar => archive::tar(File::open(path)?, limit, each)?,
+        archive::Kind::CompressedTar => {
+            let cmd = pipe::decompressor(path).expect("compressed tar has a decompressor");
+            archive::tar(Piped::spawn(cmd, path)?, limit, each)?
         }
     }
     Ok(any)
 }
 
-/// CLI entrypoint compatible with the runner contract.
-fn main() {
-    match cli() {
-        Ok(code) => process::exit(code),
-        Err(e) => {
-            eprintln!("{}", e);
-            process::exit(1);
+/// Opens the file at path, reading it through the `--pre` command, or else
+/// its decompressor if `--search-zip` asks for that and one is known.
+fn open(path: &Path, grep: &Grep) -> io::Result<Box<dyn Read>> {
+    if let Some(pre) = pre(path, grep) {
+        return Ok(Box::new(Piped::spawn_with_path(&[pre], path)?));
+    }
+    match pipe::decompressor(path).filter(|_| grep.search_zip) {
+        Some(cmd) => Ok(Box::new(Piped::spawn(cmd, path)?)),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+/// Returns the `--pre` command the file at path is read through, if any.
+fn pre<'g>(path: &Path, grep: &'g Grep) -> Option<&'g String> {
+    let globs = &grep.pre_globs;
+    grep.pre
+        .as_ref()
+        .filter(|_| globs.is_empty() || globs.iter().any(|g| matches(g, path)))
+}
+
+/// Returns the language the file at path is tokenized as by `--only`, if
+/// its type is one of theirs.
+fn language(path: &Path, grep: &Grep) -> Option<scope::Language> {
+    let mut languages = grep.languages.iter();
+    languages
+        .find(|(glob, _)| matches(glob, path))
+        .map(|&(_, language)| language)
+}
+
+/// Reports whether [`open`] reads the file at path through a command
+/// rather than as is.
+fn piped(path: &Path, grep: &Grep) -> bool {
+    archive_kind(path, grep).is_some()
+        || extractor(path, grep).is_some()
+        || pre(path, grep).is_some()
+        || (grep.search_zip && pipe::decompressor(path).is_some())
+}
+
+/// Returns the label of a file found below root, relative to it, with the
+/// platform's separator between components unless sep is given; an empty
+/// root is the working directory, whose files are labelled without a prefix.
+fn dir_label(root: &str, base: &Path, path: &Path, sep: Option<char>) -> String {
+    let sep = sep.unwrap_or(std::path::MAIN_SEPARATOR);
+    let root = with_separator(root, Some(sep));
+    let label_base = root.trim_end_matches(sep);
+    let rel = path.strip_prefix(base).unwrap_or(path);
+    let rel: Vec<_> = rel
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    let rel = rel.join(sep.encode_utf8(&mut [0; 4]));
+    if rel.is_empty() {
+        label_base.to_string()
+    } else if root.is_empty() {
+        rel
+    } else if label_base.is_empty() {
+        // The root is the file system root.
+        format!("{}{}", sep, rel)
+    } else {
+        format!("{}{}{}", label_base, sep, rel)
+    }
+}
+
+/// Returns path with each of the platform's separators in it replaced by
+/// sep, if given.
+fn with_separator(path: &str, sep: Option<char>) -> Cow<'_, str> {
+    match sep {
+        Some(sep) if path.contains(|c| std::path::is_separator(c) && c != sep) => path
+            .chars()
+            .map(|c| if std::path::is_separator(c) { sep } else { c })
+            .collect(),
+        _ => Cow::Borrowed(path),
+    }
+}
+
+/// Recursively searches a directory or file, labeling outputs relateive to procided root arguement;
+/// an empty root is the working directory, whose files are labelled without a prefix.
+fn grep_dir(root: &str, grep: &mut Grep, args: &Args) -> Result<bool> {
+    // JSON totals, the one SARIF log and lines to browse are kept by the one
+    // printer, so that output stays on one thread.
+    if grep.threads > 1 && matches!(grep.printer, Output::Standard(_)) {
+        return grep_dir_parallel(root, grep, args);
+    }
+    let base = Path::new(if root.is_empty() { "." } else { root });
+    let tracked = tracked(base, grep)?;
+    let mut any = false;
+    let walker = mem::take(&mut grep.walker);
+    let res = walker.run_with_errors(base, |entry| {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                walk_error(root, base, grep, e);
+                return Ok(true);
+            }
+        };
+        let path = entry.path();
+        if !keeps(tracked.as_ref(), base, path) || !grep.first_visit(path) || grep.duplicate(path) {
+            return Ok(true);
+        }
+        let label = dir_label(root, base, path, grep.path_separator);
+        match grep_file_with_label(path, grep, &label) {
+            Ok(found) => any |= found,
+            Err(e) => grep.warn(&label, &e),
         }
+        Ok(!grep.done && !interrupted())
+    });
+    grep.walker = walker;
+    res?;
+    Ok(any)
+}
+
+/// Returns the files git tracks below the directory base, relative to it,
+/// if `--git` leaves out the rest.
+fn tracked(base: &Path, grep: &Grep) -> Result<Option<HashSet<PathBuf>>> {
+    if !grep.git || !base.is_dir() {
+        return Ok(None);
     }
+    let out = process::Command::new("git")
+        .arg("-C")
+        .arg(base)
+        .args(["ls-files", "-z", "--cached", "--recurse-submodules"])
+        .output()
+        .map_err(|e| anyhow!("cannot run git: {}", describe(&e)))?;
+    if !out.status.success() {
+        let err = String::from_utf8_lossy(&out.stderr);
+        let err = err.lines().next().unwrap_or("git ls-files failed");
+        bail!("{}", err.strip_prefix("fatal: ").unwrap_or(err));
+    }
+    let paths = out.stdout.split(|&b| b == 0).filter(|p| !p.is_empty());
+    Ok(Some(paths.map(path_from_bytes).collect()))
 }
 
-/// Reads a file and prints matches with optional filename prefixes.
-fn grep_file(file: &str, pattern: &str, prefix: bool) -> Result<bool> {
-    let content = fs::read_to_string(file)?;
-    grep_content(&content, pattern, if prefix { Some(file) } else { None })
+/// Reports whether path, found below base, is searched given the files
+/// `--git` keeps, if any.
+fn keeps(tracked: Option<&HashSet<PathBuf>>, base: &Path, path: &Path) -> bool {
+    tracked.map_or(true, |t| {
+        path.strip_prefix(base).is_ok_and(|p| t.contains(p))
+    })
 }
 
-/// Parses args, matches against stdin or files, prints matches with optional
-/// prefixes, return 0 on any match
-fn cli() -> Result<i32> {
-    let mut args = env::args();
-    args.next();
-    let mut recursive = false;
-    let mut head = args.next().unwrap_or_default();
-    if head == "-r" {
-        recursive = true;
-        head = args.next().unwrap_or_default();
+/// Returns the path the bytes a command printed name.
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+/// Returns the path the bytes a command printed name.
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Reports a path below root recursion could not read; a symlink looping
+/// back to a directory above is only warned about.
+fn walk_error(root: &str, base: &Path, grep: &mut Grep, e: walk::Error) {
+    let label = dir_label(root, base, e.path(), grep.path_separator);
+    if e.loop_ancestor().is_some() {
+        if !grep.no_messages {
+            eprintln!("putao: warning: {}: recursive directory loop", label);
+        }
+        return;
     }
-    if head != "-E" {
-        bail!("Expected '-E' after flags");
+    grep.warn(&label, &e.into_io_error().into());
+}
+
+/// What a worker of [`grep_dir_parallel`] found over the files it searched.
+struct Found {
+    any: bool,
+    errored: bool,
+    stats: Option<Stats>,
+}
+
+/// The outputs of files searched by [`grep_dir_parallel`] waiting for those
+/// walked before them to be printed.
+struct Ordered {
+    /// Where they are printed.
+    out: Box<dyn Write + Send>,
+    /// The index in walk order of the next file to print.
+    next: usize,
+    /// What was found in later files, by index: their output, and whether
+    /// it starts with the line setting apart headings.
+    ready: BTreeMap<usize, (Vec<u8>, bool)>,
+    /// Whether any output was printed yet.
+    printed: bool,
+}
+
+impl Ordered {
+    /// Takes the output of the file at index, printing it and any ready to
+    /// come after it if every file before it was.
+    fn finish(
+        &mut self,
+        index: usize,
+        out: Vec<u8>,
+        separated: bool,
+        headings: bool,
+    ) -> io::Result<()> {
+        self.ready.insert(index, (out, separated));
+        while let Some((out, separated)) = self.ready.remove(&self.next) {
+            self.next += 1;
+            if out.is_empty() {
+                continue;
+            }
+            if headings && !separated && self.printed {
+                self.out.write_all(b"\n")?;
+            }
+            self.printed = true;
+            self.out.write_all(&out)?;
+        }
+        Ok(())
     }
-    let pattern = args.next().unwrap_or_default();
-    let rest: Vec<String> = args.collect();
+}
 
-    if recursive {
-        if rest.is_empty() {
-            return Ok(1);
+/// Like [`grep_dir`], walking on this thread while `grep.threads` workers
+/// search the files found, printing each file's output whole in walk order.
+fn grep_dir_parallel(root: &str, grep: &mut Grep, args: &Args) -> Result<bool> {
+    grep.printer.get_mut().flush()?;
+    let base = Path::new(if root.is_empty() { "." } else { root });
+    let tracked = tracked(base, grep)?;
+    let (tx, rx) = mpsc::sync_channel::<(usize, PathBuf, String)>(grep.threads * 4);
+    let rx = Mutex::new(rx);
+    let done = AtomicBool::new(false);
+    let walker = mem::take(&mut grep.walker);
+    let out: Box<dyn Write + Send> = match &grep.output {
+        Some(output) => Box::new(output.file.try_clone()?),
+        None if args.progress && io::stdout().is_terminal() => {
+            Box::new(progress::Clearing(io::stdout()))
         }
-        let mut any = false;
-        for root in &rest {
-            if grep_dir(root, &pattern)? {
-                any = true;
+        None => Box::new(io::stdout()),
+    };
+    let ordered = Mutex::new(Ordered {
+        out,
+        next: 0,
+        ready: BTreeMap::new(),
+        printed: false,
+    });
+    let mut index = 0;
+    let (res, found) = thread::scope(|s| {
+        let workers: Vec<_> = (0..grep.threads)
+            .map(|_| s.spawn(|| search_worker(args, &rx, &done, &ordered)))
+            .collect();
+        let res = walker.run_with_errors(base, |entry| {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    walk_error(root, base, grep, e);
+                    return Ok(true);
+                }
+            };
+            let output = grep.output.as_ref().is_some_and(|o| o.is(entry.path()));
+            if !keeps(tracked.as_ref(), base, entry.path())
+                || !grep.first_visit(entry.path())
+                || output
+                || grep.duplicate(entry.path())
+            {
+                return Ok(true);
             }
+            index += 1;
+            let work = (
+                index - 1,
+                entry.path().to_path_buf(),
+                dir_label(root, base, entry.path(), grep.path_separator),
+            );
+            Ok(tx.send(work).is_ok() && !done.load(Ordering::Relaxed) && !interrupted())
+        });
+        drop(tx);
+        let found: Vec<_> = workers
+            .into_iter()
+            .map(|w| w.join().expect("search worker panicked"))
+            .collect();
+        (res, found)
+    });
+    grep.walker = walker;
+    grep.done |= done.load(Ordering::Relaxed);
+    let mut any = false;
+    for found in found {
+        let found = found?;
+        any |= found.any;
+        grep.errored |= found.errored;
+        if let (Some(total), Some(stats)) = (&mut grep.stats, &found.stats) {
+            total.add(stats);
         }
-        return Ok(if any { 0 } else { 1 });
     }
+    res?;
+    Ok(any)
+}
 
-    if rest.is_empty() {
-        // stdin
-        let mut buf = String::new();
-        io::stdin().read_to_string(&mut buf)?;
-        Ok(if grep_content(&buf, &pattern, None)? {
-            0
-        } else {
-            1
-        })
-    } else {
-        let prefix = rest.len() > 1;
-        let mut any = false;
-        for file in &rest {
-            if grep_file(file, &pattern, prefix)? {
-                any = true;
+/// Searches the files received on rx with a [`Grep`] of its own, handing
+/// each file's output to ordered to print; sets done once nothing more
+/// needs to be searched.
+fn search_worker(
+    args: &Args,
+    rx: &Mutex<mpsc::Receiver<(usize, PathBuf, String)>>,
+    done: &AtomicBool,
+    ordered: &Mutex<Ordered>,
+) -> Result<Found> {
+    let buf = Buffer::default();
+    let mut grep = args.grep_to(Box::new(buf.clone()))?;
+    // The printer sets apart the headings after its first, but not from
+    // what other workers printed before that.
+    let mut wrote = false;
+    let mut any = false;
+    let mut failed = None;
+    loop {
+        let Ok((index, path, label)) = rx.lock().expect("work queue poisoned").recv() else {
+            break;
+        };
+        // Files skipped still take their turn, so those after them print.
+        if !done.load(Ordering::Relaxed) && !interrupted() {
+            match grep_file_with_label(&path, &mut grep, &label) {
+                Ok(found) => any |= found,
+                Err(e) => grep.warn(&label, &e),
             }
         }
-        Ok(if any { 0 } else { 1 })
+        let out = buf.0.take();
+        let separated = wrote && !out.is_empty();
+        wrote |= !out.is_empty();
+        let res = ordered.lock().expect("output queue poisoned").finish(
+            index,
+            out,
+            separated,
+            args.headings(),
+        );
+        if let (Err(e), None) = (res, &failed) {
+            let e = e.into();
+            exit_if_broken_pipe(&e);
+            // Keep taking work so the walk sending it is not blocked.
+            failed = Some(e);
+            grep.done = true;
+        }
+        if grep.done {
+            done.store(true, Ordering::Relaxed);
+        }
     }
+    if let Some(e) = failed {
+        return Err(e);
+    }
+    Ok(Found {
+        any,
+        errored: grep.errored,
+        stats: grep.stats,
+    })
+}
+
+/// A writer collecting what a worker prints about one file.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// CLI entrypoint compatible with the runner contract.
+fn main() {
+    interrupt::install();
+    match cli() {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            exit_if_broken_pipe(&e);
+            eprintln!("putao: {}", describe(&e));
+            process::exit(2);
+        }
+    }
+}
+
+/// Describes err the way grep does, leaving out the "(os error N)" std
+/// puts after a system error's message.
+fn describe(err: &dyn fmt::Display) -> String {
+    let text = err.to_string();
+    match text.rfind(" (os error ") {
+        Some(at) if text.ends_with(')') => text[..at].to_string(),
+        _ => text,
+    }
+}
+
+/// Exits quietly, as if killed by SIGPIPE, if err is a write to an output
+/// closed early, as when piped into `head`.
+fn exit_if_broken_pipe(err: &anyhow::Error) {
+    let broken = err.chain().any(|e| {
+        e.downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    });
+    if broken {
+        process::exit(141);
+    }
+}
+
+/// Searches standard input, as `-` or when no inputs are named.
+fn grep_stdin(grep: &mut Grep) -> Result<bool> {
+    let label = grep.stdin_label.clone();
+    if grep.only.is_some() {
+        // Standard input has no type, so no language to tokenize it as.
+        return Ok(false);
+    }
+    if grep.verify.is_some() {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        return verify_input(&data, grep, &label);
+    }
+    grep_reader(io::stdin(), grep, &label)
+}
+
+/// Reads a file and prints matches, labelled with its name.
+fn grep_file(file: &str, grep: &mut Grep) -> Result<bool> {
+    // Read as a file, a directory fails the same way wherever it is, and
+    // before any `--pre` command or decompressor is run on it.
+    if Path::new(file).is_dir() {
+        bail!("Is a directory");
+    }
+    let label = with_separator(file, grep.path_separator).into_owned();
+    grep_file_with_label(Path::new(file), grep, &label)
+}
+
+/// Fetches a URL and prints the matches in its body, labelled with it.
+#[cfg(feature = "http")]
+fn grep_url(url: &str, grep: &mut Grep) -> Result<bool> {
+    let body = http::get(url)?;
+    grep_reader(body, grep, url)
+}
+
+/// Options collected from the command line.
+#[derive(Debug, Default)]
+struct Args {
+    directories: Directories,
+    /// Whether named pipes, sockets and devices given as inputs are skipped.
+    skip_devices: bool,
+    /// Whether recursion follows symlinks.
+    dereference: bool,
+    /// How many directories deep recursion goes below each root.
+    max_depth: Option<usize>,
+    /// Globs a file found by recursion must match one of, if any.
+    include: Vec<String>,
+    /// Globs a file found by recursion must not match.
+    exclude: Vec<String>,
+    /// Globs a directory found by recursion must not match to be descended into.
+    exclude_dir: Vec<String>,
+    /// The size in bytes above which recursion skips files, if given.
+    max_filesize: Option<u64>,
+    /// Whether the files in zip and tar archives are searched.
+    search_archives: bool,
+    /// Whether the text of documents is searched rather than their bytes.
+    extract: bool,
+    /// The `KEY=COMMAND` extractors given, on top of the built-in ones.
+    extractors: Vec<String>,
+    /// The most an archive or document may unpack to, if given.
+    max_archive_size: Option<u64>,
+    /// The order recursion searches files in, if given.
+    sort: Option<Sort>,
+    /// How many threads recursion searches on, if given.
+    threads: Option<usize>,
+    /// Whether recursion enters hidden files and directories.
+    hidden: bool,
+    /// Whether files found by recursion with the contents of one searched
+    /// before are left out.
+    dedupe: bool,
+    /// Whether ignore files are disregarded during recursion.
+    no_ignore: bool,
+    /// Globs given with `-g`, matched against paths below each root.
+    globs: Vec<String>,
+    /// The known file types, with those given with `--type-add`.
+    file_types: Types,
+    /// The types a file found by recursion must be one of, if any.
+    types: Vec<String>,
+    /// The types a file found by recursion must not be.
+    types_not: Vec<String>,
+    /// Whether `--type-list` was given, which skips the search.
+    type_list: bool,
+    /// The names of the presets whose patterns are used.
+    presets: Vec<String>,
+    /// Whether `--preset-list` was given, which skips the search.
+    preset_list: bool,
+    syntax: Syntax,
+    fixed_strings: bool,
+    ignore_case: bool,
+    /// Whether case is ignored unless a pattern has an uppercase letter.
+    smart_case: bool,
+    /// Whether classes, word boundaries and case folding go by Unicode
+    /// rather than ASCII alone.
+    unicode: bool,
+    word: bool,
+    line_regexp: bool,
+    /// The expression over capture groups matches must make true, if any.
+    filter: Option<String>,
+    /// Whether lines must match every pattern rather than any.
+    all_of: bool,
+    /// The patterns lines matching are never selected.
+    none_of: Vec<String>,
+    invert_match: bool,
+    line_number: bool,
+    byte_offset: bool,
+    only_matching: bool,
+    after_context: Option<usize>,
+    before_context: Option<usize>,
+    /// The context for either side not given its own with `-A` or `-B`.
+    context: usize,
+    max_count: Option<u64>,
+    /// The length in bytes above which lines are left out of the output, if given.
+    max_columns: Option<usize>,
+    /// The length in bytes above which lines are not searched, if given.
+    max_line_length: Option<usize>,
+    /// The size in bytes the compiled pattern may reach, if given.
+    regex_size_limit: Option<usize>,
+    /// The backtracking state in bytes matching a line may use, if given.
+    state_size_limit: Option<usize>,
+    /// Whether lines are prefixed with their file name, if given with `-H` or `-h`.
+    with_filename: Option<bool>,
+    label: Option<String>,
+    null: bool,
+    path_separator: Option<char>,
+    null_data: bool,
+    /// The encoding input is transcoded from, if given.
+    encoding: Option<Encoding>,
+    /// Whether input is read as is even with a byte order mark.
+    no_bom: bool,
+    /// Whether the encoding of input without a mark is guessed.
+    detect_encoding: bool,
+    /// Whether patterns and input are put in normalization form C.
+    nfc: bool,
+    /// Whether compressed files are decompressed before they are searched.
+    search_zip: bool,
+    /// Whether recursion searches only the files git tracks.
+    git: bool,
+    /// The git revision only lines changed since are searched, if given.
+    changed: Option<String>,
+    /// What changed since then, found once the command line is read.
+    changes: Option<Arc<Changes>>,
+    /// The part of source files to search, if not all.
+    only: Option<scope::Only>,
+    /// The files `--files-from` names, until they are added to the paths.
+    files_from: Option<Vec<String>>,
+    /// The command files are preprocessed with, if given.
+    pre: Option<String>,
+    /// Globs limiting which files are preprocessed.
+    pre_glob: Vec<String>,
+    /// Whether `\r\n` ends a line for the regex.
+    crlf: bool,
+    line_endings: LineEndings,
+    /// Whether a match may span lines.
+    multiline: bool,
+    /// Whether `.` matches line terminators in multi-line mode.
+    multiline_dotall: bool,
+    /// Whether binary inputs are searched as if they were text.
+    text: bool,
+    skip_binary: bool,
+    no_messages: bool,
+    line_buffered: bool,
+    color: bool,
+    /// The `--colors` specs, applied in order over `GREP_COLORS`.
+    colors: Vec<String>,
+    /// The template every match is printed as, if given.
+    replace: Option<String>,
+    /// Whether files are rewritten with their matches replaced.
+    write: bool,
+    /// What the originals of files rewritten are kept as, after their name.
+    backup_suffix: Option<String>,
+    /// The oracle selected lines are checked against, if given.
+    verify: Option<String>,
+    /// The format results are printed in.
+    format: Format,
+    /// Whether every match is printed as `file:line:column:text`.
+    vimgrep: bool,
+    /// Whether matching lines are prefixed with the column of their first match.
+    column: bool,
+    /// Whether a summary of the search is printed at the end.
+    stats: bool,
+    /// Whether a line on standard error shows how far the search got.
+    progress: bool,
+    /// The file results are written to, if not stdout.
+    output: Option<String>,
+    /// Whether results are appended to that file rather than replace it.
+    append: bool,
+    /// Whether each file's lines follow its name on a line of its own; by
+    /// default if stdout is a terminal.
+    heading: bool,
+    /// The line between groups of context, if changed, or None to leave it out.
+    context_separator: Option<Option<Vec<u8>>>,
+    /// What follows each part of the prefix of a matching line, if changed.
+    field_match_separator: Option<Vec<u8>>,
+    /// What follows each part of the prefix of a context line, if changed.
+    field_context_separator: Option<Vec<u8>>,
+    /// Whether lines that do not match are printed too.
+    passthru: bool,
+    mode: Mode,
+    /// Whether `--help` was given, which skips the search.
+    help: bool,
+    /// Whether `--version` was given, which skips the search.
+    version: bool,
+    /// The shell to print a completion script for, which skips the search.
+    completions: Option<String>,
+    /// Whether `--debug` was given, and the text it traces matching, if any.
+    debug: Option<Option<String>>,
+    /// Whether `--repl` was given, which reads patterns instead of searching.
+    repl: bool,
+    /// Whether the lines found are browsed rather than printed.
+    tui: bool,
+    /// Whether `--watch` was given, which searches what changes after the
+    /// first search until Ctrl-C.
+    watch: bool,
+    /// Whether `--tail` was given, which follows the FILEs from their end
+    /// instead of searching them.
+    tail: bool,
+    /// The socket `--serve` answers searches on, which replaces searching.
+    serve: Option<String>,
+    /// Whether output goes to a terminal, to be colored when `--color=auto`.
+    terminal: bool,
+    patterns: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl Args {
+    /// Parses flags wherever they appear; of the other arguments, the first
+    /// is the pattern, unless `-e` or `-f` gave them, and the rest are the
+    /// paths to search.
+    fn parse(it: impl Iterator<Item = String>) -> Result<Args> {
+        Args::parse_for(it, terminal_stdout())
+    }
+
+    /// Like [`Args::parse`], for output going to a terminal if terminal
+    /// says so.
+    fn parse_for(it: impl Iterator<Item = String>, terminal: bool) -> Result<Args> {
+        // Recursion lists each directory by name unless told otherwise, so
+        // its output is the same from run to run; on a terminal, lines go
+        // under the name of their file.
+        let mut args = Args {
+            sort: Some(Sort::Path),
+            heading: terminal,
+            terminal,
+            ..Args::default()
+        };
+        let mut given = false;
+        let mut positionals = Vec::new();
+        let mut parser = Parser::new(it);
+        let try_help = |e| anyhow!("{}\n{}\nTry 'putao --help' for more information.", e, USAGE);
+        while let Some(arg) = parser.next_arg().map_err(try_help)? {
+            match arg {
+                Arg::Flag(flag, value) => {
+                    given |= matches!(flag.long, "regexp" | "file" | "preset");
+                    args.apply(flag, value)?;
+                }
+                Arg::Positional(arg) => positionals.push(arg),
+            }
+        }
+        if args.help
+            || args.version
+            || args.type_list
+            || args.preset_list
+            || args.completions.is_some()
+        {
+            return Ok(args);
+        }
+        if !args.presets.is_empty() && args.fixed_strings {
+            bail!("--preset patterns are regexes, so cannot be used with -F");
+        }
+        if args.write && args.replace.is_none() {
+            bail!("--write needs --replace to say what matches become");
+        }
+        if args.tail && args.watch {
+            bail!("--tail and --watch both follow FILEs; give one of them");
+        }
+        if args.output.is_some() && args.tui {
+            bail!("--tui browses results on the terminal, so cannot be used with --output");
+        }
+        if args.append && args.output.is_none() {
+            bail!("--append needs --output to say what file to append to");
+        }
+        if args.only.is_some() && args.verify.is_some() {
+            bail!("--verify checks whole lines, so cannot be used with --only");
+        }
+        if args.filter.is_some() && args.verify.is_some() {
+            bail!("--verify checks whole lines, so cannot be used with --filter");
+        }
+        if args.all_of || !args.none_of.is_empty() {
+            if args.multiline {
+                bail!("--all-of and --none-of match lines, so cannot be used with -U");
+            }
+            if args.verify.is_some() {
+                bail!("--verify gives the oracle PATTERNS alone, so cannot be used with --all-of or --none-of");
+            }
+        }
+        for name in &args.presets {
+            let pattern = presets::pattern(name, args.syntax == Syntax::Basic)?;
+            args.patterns.push(pattern);
+        }
+        let mut positionals = positionals.into_iter();
+        if !given && args.mode != Mode::Files && !args.repl && args.serve.is_none() {
+            let Some(pattern) = positionals.next() else {
+                bail!("{}\nTry 'putao --help' for more information.", USAGE);
+            };
+            args.patterns.push(pattern);
+        }
+        args.paths = positionals.collect();
+        if let Some(names) = &mut args.files_from {
+            args.paths.append(names);
+        }
+        if let Some(base) = &args.changed {
+            let changes = Changes::since(base)?;
+            if args.paths.is_empty() {
+                args.paths = changes.paths().to_vec();
+            }
+            args.changes = Some(Arc::new(changes));
+        }
+        Ok(args)
+    }
+
+    /// Records a flag and the value given with it.
+    fn apply(&mut self, flag: &Flag, value: Option<String>) -> Result<()> {
+        let arg = value.as_deref().unwrap_or_default();
+        match flag.long {
+            "basic-regexp" => self.syntax = Syntax::Basic,
+            "extended-regexp" => self.syntax = Syntax::Extended,
+            "perl-regexp" => self.syntax = Syntax::Perl,
+            "fixed-strings" => self.fixed_strings = true,
+            "recursive" => self.directories = Directories::Recurse,
+            "follow" => self.dereference = true,
+            "dereference-recursive" => {
+                self.directories = Directories::Recurse;
+                self.dereference = true;
+            }
+            "directories" => {
+                self.directories = match arg {
+                    "read" => Directories::Read,
+                    "skip" => Directories::Skip,
+                    "recurse" => Directories::Recurse,
+                    _ => bail!("invalid argument '{}' for '--directories'", arg),
+                }
+            }
+            "line-endings" => {
+                (self.line_endings, self.crlf) = match arg {
+                    "lf" => (LineEndings::Lf, false),
+                    "crlf" => (LineEndings::Lf, true),
+                    "cr" => (LineEndings::Cr, false),
+                    "any" => (LineEndings::Any, true),
+                    _ => bail!("invalid argument '{}' for '--line-endings'", arg),
+                }
+            }
+            "devices" => {
+                self.skip_devices = match arg {
+                    "read" => false,
+                    "skip" => true,
+                    _ => bail!("invalid argument '{}' for '--devices'", arg),
+                }
+            }
+            "max-depth" => {
+                let n = arg
+                    .parse()
+                    .map_err(|_| anyhow!("invalid max depth: {}", arg))?;
+                self.max_depth = Some(n);
+            }
+            "max-filesize" => self.max_filesize = Some(filesize(arg)?),
+            "max-archive-size" => self.max_archive_size = Some(filesize(arg)?),
+            "include" => self.include.push(arg.to_string()),
+            "exclude" => self.exclude.push(arg.to_string()),
+            "exclude-dir" => self.exclude_dir.push(arg.to_string()),
+            "glob" => self.globs.push(arg.to_string()),
+            "type" => self.types.push(arg.to_string()),
+            "type-not" => self.types_not.push(arg.to_string()),
+            "type-add" => self.file_types.add(arg)?,
+            "type-list" => self.type_list = true,
+            "no-ignore" => self.no_ignore = true,
+            "git" => self.git = true,
+            "files-from" | "files-from0" => {
+                let end = if flag.long == "files-from0" {
+                    b'\0'
+                } else {
+                    b'\n'
+                };
+                let names = self.files_from.get_or_insert_with(Vec::new);
+                names.extend(read_names(arg, end)?);
+            }
+            "changed" => self.changed = Some(value.unwrap_or_else(|| "HEAD".to_string())),
+            "hidden" => self.hidden = true,
+            "dedupe" => self.dedupe = true,
+            "sort" => {
+                self.sort = match arg {
+                    "path" => Some(Sort::Path),
+                    "modified" => Some(Sort::Modified),
+                    "size" => Some(Sort::Size),
+                    "none" => None,
+                    _ => bail!("invalid argument '{}' for '--sort'", arg),
+                }
+            }
+            "threads" => {
+                let n = arg
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| anyhow!("invalid number of threads: {}", arg))?;
+                self.threads = Some(n);
+            }
+            "regexp" => self.patterns.push(arg.to_string()),
+            "file" => self.patterns.extend(read_patterns(arg)?),
+            "preset" => {
+                presets::pattern(arg, false)?;
+                self.presets.push(arg.to_string());
+            }
+            "preset-list" => self.preset_list = true,
+            "ignore-case" => (self.ignore_case, self.smart_case) = (true, false),
+            "smart-case" => (self.ignore_case, self.smart_case) = (false, true),
+            "no-ignore-case" => (self.ignore_case, self.smart_case) = (false, false),
+            "unicode" => self.unicode = true,
+            "no-unicode" => self.unicode = false,
+            "word-regexp" => self.word = true,
+            "line-regexp" => self.line_regexp = true,
+            "filter" => self.filter = value,
+            "all-of" => self.all_of = true,
+            "none-of" => self.none_of.push(arg.to_string()),
+            "invert-match" => self.invert_match = true,
+            "line-number" => self.line_number = true,
+            "byte-offset" => self.byte_offset = true,
+            "only-matching" => self.only_matching = true,
+            "after-context" => self.after_context = Some(context(arg)?),
+            "before-context" => self.before_context = Some(context(arg)?),
+            "context" => self.context = context(arg)?,
+            "max-columns" => {
+                let n = arg
+                    .parse()
+                    .map_err(|_| anyhow!("invalid max columns: {}", arg))?;
+                self.max_columns = Some(n).filter(|&n| n > 0);
+            }
+            "max-line-length" => {
+                let n = arg
+                    .parse()
+                    .map_err(|_| anyhow!("invalid max line length: {}", arg))?;
+                self.max_line_length = Some(n);
+            }
+            "regex-size-limit" => self.regex_size_limit = Some(size_limit(arg)?),
+            "state-size-limit" => self.state_size_limit = Some(size_limit(arg)?),
+            "max-count" => {
+                let n = arg
+                    .parse()
+                    .map_err(|_| anyhow!("invalid max count: {}", arg))?;
+                self.max_count = Some(n);
+            }
+            "label" => self.label = value,
+            "null" => self.null = true,
+            "path-separator" => {
+                let mut chars = arg.chars();
+                self.path_separator = match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(c),
+                    _ => bail!("invalid argument '{}' for '--path-separator'", arg),
+                };
+            }
+            "null-data" => self.null_data = true,
+            "normalize" => {
+                self.nfc = match arg {
+                    "nfc" => true,
+                    "none" => false,
+                    _ => bail!("invalid argument '{}' for '--normalize'", arg),
+                }
+            }
+            "encoding" => {
+                (self.encoding, self.no_bom, self.detect_encoding) = match arg {
+                    "auto" => (None, false, true),
+                    "bom" => (None, false, false),
+                    "none" => (None, true, false),
+                    _ => match Encoding::from_label(arg) {
+                        Some(enc) => (Some(enc), false, false),
+                        None => bail!("unknown encoding: {}", arg),
+                    },
+                }
+            }
+            "crlf" => self.crlf = true,
+            "search-zip" => self.search_zip = true,
+            "search-archives" => self.search_archives = true,
+            "extract" => self.extract = true,
+            "extractor" => {
+                extract::Extractors::default().add(arg)?;
+                self.extractors.push(arg.to_string());
+            }
+            "pre" => self.pre = value,
+            "pre-glob" => self.pre_glob.push(arg.to_string()),
+            "multiline" => self.multiline = true,
+            "multiline-dotall" => self.multiline_dotall = true,
+            "text" => self.text = true,
+            "ignore-binary" => self.skip_binary = true,
+            "no-messages" => self.no_messages = true,
+            "line-buffered" => self.line_buffered = true,
+            "color" => self.color = color(value.as_deref(), self.terminal)?,
+            "colors" => {
+                Colors::default().apply_spec(arg)?;
+                self.colors.push(arg.to_string());
+            }
+            "replace" => self.replace = value,
+            "write" => self.write = true,
+            "backup-suffix" => self.backup_suffix = value,
+            "verify" => self.verify = Some(value.unwrap_or_else(|| "grep".to_string())),
+            "only" => {
+                self.only = Some(match arg {
+                    "comments" => scope::Only::Comments,
+                    "strings" => scope::Only::Strings,
+                    "code" => scope::Only::Code,
+                    _ => bail!("invalid argument '{}' for '--only'", arg),
+                })
+            }
+            "json" => self.format = Format::Json,
+            "format" => {
+                self.format = match arg {
+                    "standard" => Format::Standard,
+                    "json" => Format::Json,
+                    "sarif" => Format::Sarif,
+                    "csv" => Format::Csv,
+                    "tsv" => Format::Tsv,
+                    _ => bail!("invalid argument '{}' for '--format'", arg),
+                }
+            }
+            "vimgrep" => self.vimgrep = true,
+            "column" => self.column = true,
+            "stats" => self.stats = true,
+            "progress" => self.progress = true,
+            "output" => {
+                self.output = value;
+                // Results go to the file; the terminal is left what sums them.
+                if self.terminal {
+                    self.terminal = false;
+                    self.heading = false;
+                }
+            }
+            "append" => self.append = true,
+            "debug" => self.debug = Some(value),
+            "repl" => self.repl = true,
+            "tui" => self.tui = true,
+            "watch" => self.watch = true,
+            "tail" => self.tail = true,
+            "serve" => self.serve = value,
+            "heading" => self.heading = true,
+            "context-separator" => self.context_separator = Some(Some(unescape(arg))),
+            "no-context-separator" => self.context_separator = Some(None),
+            "field-separator" => {
+                self.field_match_separator = Some(unescape(arg));
+                self.field_context_separator = Some(unescape(arg));
+            }
+            "field-match-separator" => self.field_match_separator = Some(unescape(arg)),
+            "field-context-separator" => self.field_context_separator = Some(unescape(arg)),
+            "no-heading" => self.heading = false,
+            "passthru" => self.passthru = true,
+            "with-filename" => self.with_filename = Some(true),
+            "no-filename" => self.with_filename = Some(false),
+            "count" => self.mode = Mode::Count,
+            "count-matches" => self.mode = Mode::CountMatches,
+            "files-with-matches" => self.mode = Mode::FilesWithMatches,
+            "files-without-match" => self.mode = Mode::FilesWithoutMatch,
+            "quiet" => self.mode = Mode::Quiet,
+            "files" => {
+                self.mode = Mode::Files;
+                self.directories = Directories::Recurse;
+            }
+            "no-config" => {}
+            "help" => self.help = true,
+            "version" => self.version = true,
+            "generate-completions" => {
+                complete::generate(arg)?;
+                self.completions = value;
+            }
+            long => unreachable!("flag --{} is not handled", long),
+        }
+        Ok(())
+    }
+
+    /// Returns the byte input lines end with.
+    fn line_terminator(&self) -> u8 {
+        match self.line_endings {
+            _ if self.null_data => b'\0',
+            LineEndings::Cr => b'\r',
+            _ => b'\n',
+        }
+    }
+
+    /// Reports whether lines are printed below a heading naming their file.
+    fn headings(&self) -> bool {
+        self.heading && !self.vimgrep && self.mode == Mode::Lines
+    }
+
+    /// Decides whether lines are prefixed with the name of their input: as
+    /// `-H` or `-h` says, or else if more than one input may be searched,
+    /// that is with several paths, when recursing into a directory or when
+    /// searching the files inside an archive.
+    fn with_filename(&self) -> bool {
+        // Structured output, vimgrep lines and the lines browsed always say
+        // which input they are about.
+        if self.format != Format::Standard || self.vimgrep || self.tui {
+            return true;
+        }
+        self.with_filename
+            .unwrap_or_else(|| match self.paths.as_slice() {
+                // However many files changed or were named.
+                _ if self.changed.is_some() || self.files_from.is_some() => true,
+                [] => self.directories == Directories::Recurse,
+                [path] => {
+                    let path = Path::new(path);
+                    (self.directories == Directories::Recurse && path.is_dir())
+                        || (self.search_archives && archive::kind(path).is_some())
+                }
+                _ => true,
+            })
+    }
+
+    /// Returns the built-in extractors with those given applied over them.
+    fn extractors(&self) -> extract::Extractors {
+        let mut extractors = extract::Extractors::default();
+        for spec in &self.extractors {
+            extractors
+                .add(spec)
+                .expect("extractors are checked when given");
+        }
+        extractors
+    }
+
+    /// Returns the globs of the files of each language `--only` knows, as
+    /// their types give them.
+    fn languages(&self) -> Result<Vec<(Glob, scope::Language)>> {
+        let mut languages = Vec::new();
+        if self.only.is_some() {
+            for &(name, language) in scope::LANGUAGES {
+                let types = globs(self.file_types.globs(name)?);
+                languages.extend(types.into_iter().map(|glob| (glob, language)));
+            }
+        }
+        Ok(languages)
+    }
+
+    /// Returns the globs of every type named in names.
+    fn type_globs(&self, names: &[String]) -> Result<Vec<Glob>> {
+        let mut all = Vec::new();
+        for name in names {
+            all.extend(globs(self.file_types.globs(name)?));
+        }
+        Ok(all)
+    }
+
+    /// Compiles the pattern and configures the searcher and printer from the
+    /// options given, printing to stdout.
+    fn grep(&self) -> Result<Grep> {
+        let mut output = None;
+        let out: Box<dyn Write> = match &self.output {
+            Some(name) => {
+                let (file, out) = OutputFile::create(Path::new(name), self.append)
+                    .map_err(|e| anyhow!("{}: {}", name, e))?;
+                output = Some(file);
+                Box::new(out)
+            }
+            None if self.progress && io::stdout().is_terminal() => {
+                Box::new(progress::Clearing(io::stdout()))
+            }
+            None => Box::new(io::stdout()),
+        };
+        let wtr: Box<dyn Write> = if self.line_buffered {
+            Box::new(LineWriter::new(out))
+        } else {
+            Box::new(BufWriter::new(out))
+        };
+        let mut grep = self.grep_to(wtr)?;
+        grep.output = output;
+        Ok(grep)
+    }
+
+    /// Returns a builder for the regex of the patterns and options given.
+    fn regex(&self) -> RegexBuilder {
+        self.regex_of(&self.patterns)
+    }
+
+    /// Returns a builder for the regex of patterns with the options given.
+    fn regex_of(&self, patterns: &[String]) -> RegexBuilder {
+        let mut builder = match self.nfc {
+            true => RegexBuilder::new_many(patterns.iter().map(|p| nfc(p))),
+            false => RegexBuilder::new_many(patterns),
+        };
+        builder
+            .basic(self.syntax == Syntax::Basic)
+            .posix(self.syntax != Syntax::Perl)
+            .literal(self.fixed_strings)
+            .case_insensitive(self.ignore_case)
+            .smart_case(self.smart_case)
+            .unicode(self.unicode)
+            .word(self.word)
+            .whole_line(self.line_regexp)
+            .multi_line(self.multiline)
+            .crlf(self.crlf)
+            .dot_matches_new_line(self.multiline && self.multiline_dotall);
+        if let Some(limit) = self.regex_size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.state_size_limit {
+            builder.state_limit(limit);
+        }
+        builder
+    }
+
+    /// Returns the regexes of each pattern, which `--all-of` requires lines
+    /// to match every one of; none for a single pattern.
+    fn required(&self) -> Result<Vec<Regex>> {
+        if !self.all_of || self.patterns.len() < 2 {
+            return Ok(Vec::new());
+        }
+        let each = self.patterns.iter().map(|p| {
+            let re = self.regex_of(std::slice::from_ref(p)).build()?;
+            Ok(Regex::from(re))
+        });
+        each.collect()
+    }
+
+    /// Returns the regex of the `--none-of` patterns, if any are given.
+    fn excluded(&self) -> Result<Option<Regex>> {
+        if self.none_of.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Regex::from(self.regex_of(&self.none_of).build()?)))
+    }
+
+    /// Returns the colors output is marked in, if it is colored.
+    fn colors(&self) -> Result<Option<Colors>> {
+        if !self.color {
+            return Ok(None);
+        }
+        let spec = env::var("GREP_COLORS").unwrap_or_default();
+        let mut colors = Colors::from_grep_colors(&spec);
+        colors.apply_specs(self.colors.iter().map(String::as_str))?;
+        Ok(Some(colors))
+    }
+
+    /// Runs `--repl`, matching each pattern read from stdin against the
+    /// lines of the FILEs given.
+    fn repl(&self) -> Result<()> {
+        let mut sample = Vec::new();
+        for path in &self.paths {
+            if path == "-" {
+                bail!(
+                    "--repl reads patterns from standard input; name a FILE to match them against"
+                );
+            }
+            let text = read_lossy(path).map_err(|e| anyhow!("{}: {}", path, describe(&e)))?;
+            sample.extend(text.lines().map(str::to_string));
+        }
+        let stdin = io::stdin();
+        let mut repl = Repl::new(sample, |p: &str| self.regex_of(&[p.to_string()]).build());
+        repl.colors(self.colors()?).prompt(stdin.is_terminal());
+        repl.run(stdin.lock(), io::stdout().lock())?;
+        Ok(())
+    }
+
+    /// Prints to stderr what `--debug` shows: the patterns as parsed, the
+    /// program compiled from them and, given text, each step matching it.
+    fn debug(&self, text: Option<&str>) -> Result<()> {
+        let builder = self.regex();
+        let mut err = io::stderr().lock();
+        for (pattern, ast) in self.patterns.iter().zip(builder.asts()?) {
+            writeln!(
+                err,
+                "putao: debug: pattern {:?} parses as {:?}",
+                pattern, ast
+            )?;
+        }
+        let re = builder.build()?;
+        for line in re.explain().lines() {
+            writeln!(err, "putao: debug: {}", line)?;
+        }
+        let Some(text) = text else {
+            return Ok(());
+        };
+        let (steps, found) = re.trace(text);
+        writeln!(err, "putao: debug: matching {:?}:", text)?;
+        for (at, node) in steps {
+            writeln!(err, "putao: debug:     at {}: {}", at, node)?;
+        }
+        match found {
+            Some(m) => writeln!(
+                err,
+                "putao: debug: matched {:?} at {}..{}",
+                m.as_str(),
+                m.start(),
+                m.end()
+            )?,
+            None => writeln!(err, "putao: debug: no match")?,
+        }
+        Ok(())
+    }
+
+    /// Like [`Args::grep`], printing to wtr.
+    fn grep_to(&self, wtr: Box<dyn Write>) -> Result<Grep> {
+        self.grep_with(self.regex().build()?, wtr)
+    }
+
+    /// Like [`Args::grep_to`], searching with re, compiled from
+    /// [`Args::regex`].
+    fn grep_with(&self, re: putao_cf::Regex, wtr: Box<dyn Write>) -> Result<Grep> {
+        let terminator = if self.null_data { b'\0' } else { b'\n' };
+        let re = Regex::from(re);
+        let filter = (self.filter.as_deref())
+            .map(|expr| Filter::new(expr, &re))
+            .transpose()?;
+        let mut searcher = Searcher::new();
+        searcher
+            .line_terminator(self.line_terminator())
+            .any_newline(self.line_endings == LineEndings::Any && !self.null_data)
+            .binary_detection(!self.text)
+            .passthru(self.passthru)
+            .multi_line(self.multiline)
+            .encoding(self.encoding)
+            .bom_sniffing(!self.no_bom)
+            .detect_encoding(self.detect_encoding)
+            .nfc(self.nfc)
+            .max_line_length(self.max_line_length)
+            .invert_match(self.invert_match)
+            .after_context(self.after_context.unwrap_or(self.context))
+            .before_context(self.before_context.unwrap_or(self.context))
+            .max_count(self.max_count)
+            .filter(filter)
+            .require(self.required()?)
+            .exclude(self.excluded()?);
+        let mut walker = Walker::new();
+        walker
+            .follow_links(self.dereference)
+            .max_depth(self.max_depth)
+            .ignore_files(!self.no_ignore)
+            .skip_hidden(!self.hidden)
+            .max_filesize(self.max_filesize)
+            .sort(self.sort);
+        if let Some(path) = global_excludes().filter(|_| !self.no_ignore) {
+            if let Ok(ignore) = Gitignore::from_path(&path) {
+                walker.add_ignore(ignore);
+            }
+        }
+        if !self.include.is_empty() {
+            let include = globs(&self.include);
+            walker.filter(move |e| {
+                !e.file_type().is_file() || include.iter().any(|g| matches(g, e.path()))
+            });
+        }
+        if !self.exclude.is_empty() {
+            let exclude = globs(&self.exclude);
+            walker.filter(move |e| {
+                !e.file_type().is_file() || !exclude.iter().any(|g| matches(g, e.path()))
+            });
+        }
+        if !self.exclude_dir.is_empty() {
+            let exclude = globs(&self.exclude_dir);
+            walker.filter(move |e| {
+                !e.file_type().is_dir() || !exclude.iter().any(|g| matches(g, e.path()))
+            });
+        }
+        if !self.globs.is_empty() {
+            let globs = PathGlobs::new(&self.globs);
+            walker.filter(move |e| globs.keep(e));
+        }
+        if !self.types.is_empty() {
+            let select = self.type_globs(&self.types)?;
+            walker.filter(move |e| {
+                !e.file_type().is_file() || select.iter().any(|g| matches(g, e.path()))
+            });
+        }
+        if !self.types_not.is_empty() {
+            let negate = self.type_globs(&self.types_not)?;
+            walker.filter(move |e| {
+                !e.file_type().is_file() || !negate.iter().any(|g| matches(g, e.path()))
+            });
+        }
+        let printer = if self.tui {
+            Output::Tui(tui::Results::new(wtr))
+        } else if self.format == Format::Json {
+            Output::Json(Json::new(wtr))
+        } else if self.format == Format::Sarif {
+            let rules = self.patterns.iter().map(|p| {
+                let re = self.regex_of(std::slice::from_ref(p)).build()?;
+                Ok((p.clone(), Regex::from(re)))
+            });
+            Output::Sarif(Sarif::new(wtr, rules.collect::<Result<_>>()?))
+        } else if matches!(self.format, Format::Csv | Format::Tsv) {
+            let mut printer = Csv::new(wtr);
+            if self.format == Format::Tsv {
+                printer.delimiter(b'\t');
+            }
+            Output::Csv(printer)
+        } else {
+            let mut printer = Standard::new(wtr);
+            printer
+                .line_number(self.line_number || self.column || self.vimgrep)
+                .column(self.column || self.vimgrep)
+                .per_match(self.vimgrep)
+                .heading(self.headings())
+                .max_columns(self.max_columns)
+                .byte_offset(self.byte_offset)
+                .only_matching(self.only_matching)
+                .replacement(self.replace.clone())
+                .path_terminator(self.null.then_some(0))
+                .line_terminator(terminator);
+            if let Some(sep) = &self.context_separator {
+                printer.context_separator(sep.as_deref());
+            }
+            printer.field_separators(
+                self.field_match_separator.as_deref().unwrap_or(b":"),
+                self.field_context_separator.as_deref().unwrap_or(b"-"),
+            );
+            printer.colors(self.colors()?);
+            Output::Standard(printer)
+        };
+        Ok(Grep {
+            re,
+            searcher,
+            walker,
+            printer,
+            output: None,
+            mode: self.mode,
+            invert_match: self.invert_match,
+            with_filename: self.with_filename(),
+            stdin_label: self
+                .label
+                .clone()
+                .unwrap_or_else(|| "(standard input)".to_string()),
+            null: self.null,
+            path_separator: self.path_separator,
+            skip_binary: self.skip_binary && !self.text,
+            no_messages: self.no_messages,
+            search_zip: self.search_zip,
+            archives: self.search_archives,
+            write: self.replace.clone().filter(|_| self.write),
+            backup_suffix: self.backup_suffix.clone(),
+            verify: (self.verify.as_deref())
+                .map(|name| verify::Oracle::new(name, self))
+                .transpose()?,
+            extractors: self.extract.then(|| self.extractors()),
+            unpack_limit: self.max_archive_size.unwrap_or(1 << 30),
+            git: self.git,
+            changes: self.changes.clone(),
+            only: self.only,
+            languages: self.languages()?,
+            pre: self.pre.clone(),
+            pre_globs: globs(&self.pre_glob),
+            stats: self.stats.then(Stats::default),
+            threads: self
+                .threads
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+            errored: false,
+            long_lines: 0,
+            seen: None,
+            dedupe: self.dedupe.then(dedupe::Dedupe::default),
+            done: false,
+        })
+    }
+}
+
+/// Reads the patterns in the file given to `-f`, one per line; `-` reads stdin.
+fn read_patterns(path: &str) -> Result<Vec<String>> {
+    let bytes = read_arg(path)?;
+    // Invalid UTF-8 becomes U+FFFD, which matches invalid UTF-8 in the input.
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(text.lines().map(str::to_string).collect())
+}
+
+/// Reads the file names in the file at path, or standard input for `-`,
+/// each ended by end; empty names are left out.
+fn read_names(path: &str, end: u8) -> Result<Vec<String>> {
+    let bytes = read_arg(path)?;
+    let names = bytes.split(|&b| b == end).map(|name| match end {
+        b'\n' => name.strip_suffix(b"\r").unwrap_or(name),
+        _ => name,
+    });
+    Ok(names
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+/// Reads the file a flag names, standard input for `-`.
+fn read_arg(path: &str) -> Result<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    fs::read(path).map_err(|e| anyhow!("{}: {}", path, describe(&e)))
+}
+
+/// Reads the text file at path, turning invalid UTF-8 into U+FFFD.
+fn read_lossy(path: impl AsRef<Path>) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn globs(globs: &[String]) -> Vec<Glob> {
+    globs.iter().map(|g| Glob::new(g)).collect()
+}
+
+/// The `-g` globs, each selecting the paths it matches or, starting with
+/// `!`, leaving them out.
+struct PathGlobs {
+    globs: Vec<(Glob, bool)>,
+    /// Whether a file has to match a selecting glob.
+    whitelist: bool,
+}
+
+impl PathGlobs {
+    fn new(globs: &[String]) -> PathGlobs {
+        let globs: Vec<_> = globs
+            .iter()
+            .map(|g| match g.strip_prefix('!') {
+                Some(g) => (Glob::new(g.trim_start_matches('/')), false),
+                None => (Glob::new(g.trim_start_matches('/')), true),
+            })
+            .collect();
+        let whitelist = globs.iter().any(|&(_, select)| select);
+        PathGlobs { globs, whitelist }
+    }
+
+    /// Decides whether entry is kept: the last glob matching it says, else
+    /// a file is kept unless there are selecting globs. A glob without `/`
+    /// is matched against the name alone, and only `!` globs apply to
+    /// directories, matching one when they would match a path inside it.
+    fn keep(&self, entry: &Entry) -> bool {
+        let rel = entry.relative_path();
+        let name = rel.rsplit('/').next().unwrap_or_default();
+        let is_dir = entry.file_type().is_dir();
+        let dir = format!("{}/", rel);
+        let last = self.globs.iter().rev().find(|(glob, select)| {
+            if is_dir && *select {
+                return false;
+            }
+            if !glob.as_str().contains('/') {
+                return glob.is_match(name);
+            }
+            glob.is_match(&rel) || (is_dir && glob.is_match(&dir))
+        });
+        match last {
+            Some(&(_, select)) => select,
+            None => is_dir || !self.whitelist,
+        }
+    }
+}
+
+/// Returns the path of git's global excludes file: `core.excludesFile` in
+/// the user's git config, or else `git/ignore` in the config directory.
+fn global_excludes() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".config")));
+    let configs = [
+        home.as_ref().map(|h| h.join(".gitconfig")),
+        config_home.as_ref().map(|c| c.join("git").join("config")),
+    ];
+    for config in configs.into_iter().flatten() {
+        let Ok(text) = read_lossy(config) else {
+            continue;
+        };
+        if let Some(path) = excludes_file(&text) {
+            return match (path.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => Some(home.join(rest)),
+                _ => Some(PathBuf::from(path)),
+            };
+        }
+    }
+    config_home.map(|c| c.join("git").join("ignore"))
+}
+
+/// Finds `excludesFile` in the `[core]` section of a git config.
+fn excludes_file(config: &str) -> Option<&str> {
+    let mut core = false;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            core = line.eq_ignore_ascii_case("[core]");
+        } else if let Some((key, value)) = line.split_once('=') {
+            if core && key.trim().eq_ignore_ascii_case("excludesfile") {
+                return Some(value.trim().trim_matches('"'));
+            }
+        }
+    }
+    None
+}
+
+/// Reports whether glob matches the file name of path.
+fn matches(glob: &Glob, path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default();
+    glob.is_match(&name.to_string_lossy())
+}
+
+/// Reports whether stdout is a terminal, which decides whether output is
+/// colored and put under headings unless told otherwise.
+fn terminal_stdout() -> bool {
+    io::stdout().is_terminal()
+}
+
+/// Decides whether to color output from the value given to `--color`;
+/// without one it is colored if it goes to a terminal.
+fn color(when: Option<&str>, terminal: bool) -> Result<bool> {
+    match when.unwrap_or("auto") {
+        "always" | "yes" | "force" => Ok(true),
+        "never" | "no" | "none" => Ok(false),
+        "auto" | "tty" | "if-tty" => {
+            Ok(terminal && env::var_os("TERM").is_some_and(|t| t != "dumb"))
+        }
+        when => bail!("invalid argument '{}' for '--color'", when),
+    }
+}
+
+/// Turns the escapes `\t`, `\n`, `\r`, `\0`, `\\` and `\xHH` in a
+/// separator into the bytes they stand for; other text is kept as is.
+fn unescape(arg: &str) -> Vec<u8> {
+    let bytes = arg.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let (b, used) = match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b't')) => (b'\t', 2),
+            (b'\\', Some(b'n')) => (b'\n', 2),
+            (b'\\', Some(b'r')) => (b'\r', 2),
+            (b'\\', Some(b'0')) => (0, 2),
+            (b'\\', Some(b'\\')) => (b'\\', 2),
+            (b'\\', Some(b'x')) => match arg.get(i + 2..i + 4).map(|h| u8::from_str_radix(h, 16)) {
+                Some(Ok(b)) => (b, 4),
+                _ => (b'\\', 1),
+            },
+            (b, _) => (b, 1),
+        };
+        out.push(b);
+        i += used;
+    }
+    out
+}
+
+/// Parses the size given to `--max-filesize`: a number of bytes, or of
+/// kibibytes, mebibytes or gibibytes with a `K`, `M` or `G` suffix.
+fn filesize(arg: &str) -> Result<u64> {
+    let invalid = || anyhow!("invalid file size: {}", arg);
+    let (num, shift) = match arg.as_bytes().last().map(u8::to_ascii_uppercase) {
+        Some(b'K') => (&arg[..arg.len() - 1], 10),
+        Some(b'M') => (&arg[..arg.len() - 1], 20),
+        Some(b'G') => (&arg[..arg.len() - 1], 30),
+        _ => (arg, 0),
+    };
+    let n: u64 = num.parse().map_err(|_| invalid())?;
+    n.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
+/// Parses the size given to `--regex-size-limit` or `--state-size-limit`.
+fn size_limit(arg: &str) -> Result<usize> {
+    filesize(arg)
+        .ok()
+        .and_then(|n| n.try_into().ok())
+        .ok_or_else(|| anyhow!("invalid size limit: {}", arg))
+}
+
+/// Parses the line count given to a context flag.
+fn context(arg: &str) -> Result<usize> {
+    arg.parse()
+        .map_err(|_| anyhow!("{}: invalid context length argument", arg))
+}
+
+/// The flags a project's `.putaorc` may not give: those running commands,
+/// handing a command what they are given or writing files, which a
+/// repository cloned should not be able to make a search in it do.
+const PROJECT_UNSAFE: &[&str] = &[
+    "pre",
+    "extractor",
+    "write",
+    "backup-suffix",
+    "output",
+    "append",
+    "serve",
+    "changed",
+];
+
+/// Returns the first flag in args that [`PROJECT_UNSAFE`] names, if any.
+fn unsafe_flag(args: &[String]) -> Option<&'static str> {
+    let mut parser = Parser::new(args.iter().cloned());
+    while let Ok(Some(arg)) = parser.next_arg() {
+        match arg {
+            Arg::Flag(flag, _) if PROJECT_UNSAFE.contains(&flag.long) => return Some(flag.long),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Reads the flags in the file `PUTAO_CONFIG` names, to go before those on
+/// the command line.
+fn config_args() -> Vec<String> {
+    match env::var_os("PUTAO_CONFIG").filter(|p| !p.is_empty()) {
+        Some(path) => read_config(Path::new(&path)),
+        None => Vec::new(),
+    }
+}
+
+/// Reads the flags in the `.putaorc` nearest above where the search given
+/// args starts, to go between those of `PUTAO_CONFIG` and the command line;
+/// flags that [`PROJECT_UNSAFE`] names are reported and left out.
+fn project_args(args: &[String]) -> Vec<String> {
+    let root = search_root(args);
+    let Some(path) = root
+        .ancestors()
+        .map(|dir| dir.join(".putaorc"))
+        .find(|rc| rc.is_file())
+    else {
+        return Vec::new();
+    };
+    let mut kept = Vec::new();
+    let mut parser = Parser::new(read_config(&path).into_iter());
+    loop {
+        let left_out = match parser.next_arg() {
+            Ok(None) => break,
+            Ok(Some(Arg::Flag(flag, _))) if PROJECT_UNSAFE.contains(&flag.long) => format!(
+                "--{} is not read from a project's .putaorc, as it runs commands or writes files",
+                flag.long
+            ),
+            Ok(Some(Arg::Flag(flag, value))) => {
+                kept.push(match value {
+                    Some(value) => format!("--{}={}", flag.long, value),
+                    None => format!("--{}", flag.long),
+                });
+                continue;
+            }
+            Ok(Some(Arg::Positional(arg))) => format!("'{}' is not a flag, so is left out", arg),
+            Err(e) => {
+                eprintln!("putao: {}: {}", path.display(), e);
+                break;
+            }
+        };
+        eprintln!("putao: {}: {}", path.display(), left_out);
+    }
+    kept
+}
+
+/// Returns the directory the search args give starts in: that of the first
+/// path to search, or else the working directory.
+fn search_root(args: &[String]) -> PathBuf {
+    let mut parser = Parser::new(args.iter().cloned());
+    let (mut given, mut positionals) = (false, Vec::new());
+    while let Ok(Some(arg)) = parser.next_arg() {
+        match arg {
+            Arg::Flag(flag, _) => given |= matches!(flag.long, "regexp" | "file" | "preset"),
+            Arg::Positional(arg) => positionals.push(arg),
+        }
+    }
+    let cwd = env::current_dir().unwrap_or_default();
+    let path = match positionals.into_iter().nth(usize::from(!given)) {
+        Some(path) => cwd.join(path),
+        None => return cwd,
+    };
+    let dir = match path.is_dir() {
+        true => path,
+        false => path.parent().map_or(cwd, Path::to_path_buf),
+    };
+    fs::canonicalize(&dir).unwrap_or(dir)
+}
+
+/// Reads the flags in the file at path, one per line; blank lines and `#`
+/// comments are skipped. A file that cannot be read is reported and left
+/// out.
+fn read_config(path: &Path) -> Vec<String> {
+    match read_lossy(path) {
+        Ok(text) => text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        Err(e) => {
+            eprintln!("putao: {}: {}", path.display(), describe(&e));
+            Vec::new()
+        }
+    }
+}
+
+/// Parses args, matches against stdin or files, prints matches with optional
+/// prefixes; returns 0 on any match, 1 on none and 2 if an input failed,
+/// unless `-q` found a match anyway.
+fn cli() -> Result<i32> {
+    let mut argv: Vec<String> = env::args().skip(1).collect();
+    // `putao -- bench FILE` searches for "bench".
+    let bench = argv.first().is_some_and(|a| a == "bench");
+    if bench {
+        argv.remove(0);
+    }
+    let mut config = Vec::new();
+    if !argv.iter().any(|a| a == "--no-config") {
+        config = config_args();
+        let project = project_args(&[config.as_slice(), argv.as_slice()].concat());
+        config.extend(project);
+    }
+    if bench {
+        return bench::run(config.into_iter().chain(argv));
+    }
+    let args = Args::parse(config.into_iter().chain(argv))?;
+    if args.help {
+        print!("{}", flags::help());
+        return Ok(0);
+    }
+    if args.version {
+        println!("putao {}", env!("CARGO_PKG_VERSION"));
+        return Ok(0);
+    }
+    if let Some(shell) = &args.completions {
+        print!("{}", complete::generate(shell)?);
+        return Ok(0);
+    }
+    if args.type_list {
+        print!("{}", args.file_types.list());
+        return Ok(0);
+    }
+    if args.preset_list {
+        print!("{}", presets::list());
+        return Ok(0);
+    }
+    if let Some(text) = &args.debug {
+        args.debug(text.as_deref())?;
+    }
+    if args.repl {
+        args.repl()?;
+        return Ok(0);
+    }
+    if let Some(socket) = &args.serve {
+        return serve(socket);
+    }
+    let recursive = args.directories == Directories::Recurse;
+    if args.watch && !recursive && args.paths.iter().all(|p| p == "-") {
+        bail!("--watch needs a FILE or directory to watch");
+    }
+    if args.tail && !recursive && args.paths.iter().all(|p| p == "-") {
+        bail!("--tail needs a FILE or directory to follow");
+    }
+    if (args.changes.is_some() || args.files_from.is_some()) && args.paths.is_empty() {
+        // Nothing changed, or no files were named, so nothing was found.
+        return Ok(1);
+    }
+    let mut grep = args.grep()?;
+    let started = Instant::now();
+    if args.progress {
+        progress::start();
+    }
+    let searched = match args.tail {
+        true => tail_inputs(&args, &mut grep),
+        false => grep_inputs(&args, &mut grep),
+    };
+    progress::stop();
+    let mut any = searched?;
+    if args.verify.is_some() {
+        // Agreeing with the oracle is what a verification looks for.
+        any = !any;
+    }
+    if args.watch {
+        any |= watch_inputs(&args, &mut grep)?;
+    }
+    if interrupted() {
+        grep.printer.get_mut().flush()?;
+        // A watch ends with Ctrl-C, so what it found is kept; a search cut
+        // short leaves the file it would have replaced as it was.
+        if let Some(output) = grep.output.take().filter(|_| args.watch || args.tail) {
+            output.commit()?;
+        }
+        return Ok(interrupt::STATUS);
+    }
+    finish(&mut grep, any, started)
+}
+
+/// Prints what goes after the search begun at started, whether it selected
+/// anything as any says, and returns the status to exit with.
+fn finish(grep: &mut Grep, any: bool, started: Instant) -> Result<i32> {
+    match &mut grep.printer {
+        Output::Json(json) => json.summary()?,
+        Output::Sarif(sarif) => sarif.close()?,
+        Output::Csv(csv) => csv.close()?,
+        Output::Tui(results) => results.browse()?,
+        Output::Standard(_) => {}
+    }
+    grep.printer.get_mut().flush()?;
+    let to_file = grep.output.is_some();
+    if let Some(output) = grep.output.take() {
+        output.commit()?;
+    }
+    if let Some(stats) = &grep.stats {
+        // A summary is for whoever ran the search, so stays on the terminal
+        // when results go to a file.
+        let mut stdout = io::stdout();
+        let out: &mut dyn Write = match to_file {
+            true => &mut stdout,
+            false => grep.printer.get_mut(),
+        };
+        writeln!(out)?;
+        writeln!(out, "{} matches", stats.matches)?;
+        writeln!(out, "{} matched lines", stats.matched_lines)?;
+        writeln!(out, "{} files contained matches", stats.searches_with_match)?;
+        writeln!(out, "{} files searched", stats.searches)?;
+        writeln!(out, "{} bytes searched", stats.bytes_searched)?;
+        let searching = stats.elapsed.as_secs_f64();
+        writeln!(out, "{:.6} seconds spent searching", searching)?;
+        writeln!(out, "{:.6} seconds", started.elapsed().as_secs_f64())?;
+    }
+    grep.printer.get_mut().flush()?;
+    Ok(match (any, grep.errored) {
+        (true, _) if grep.mode == Mode::Quiet => 0,
+        (_, true) => 2,
+        (true, false) => 0,
+        (false, false) => 1,
+    })
+}
+
+/// Runs `--serve`, reusing the regex compiled for each set of patterns and
+/// options requests give again.
+fn serve(socket: &str) -> Result<i32> {
+    let mut compiled = HashMap::new();
+    serve::serve(socket, |argv| {
+        serve_search(argv, &mut compiled).map_err(|e| describe(&e))
+    })?;
+    Ok(if interrupted() { interrupt::STATUS } else { 0 })
+}
+
+/// Runs the search a `--serve` request gives the command line of,
+/// returning its status and output.
+fn serve_search(
+    argv: &[String],
+    compiled: &mut HashMap<String, putao_cf::Regex>,
+) -> Result<(i32, Vec<u8>)> {
+    if let Some(flag) = unsafe_flag(argv) {
+        bail!("a request to --serve cannot give --{}, as it runs commands or writes files", flag);
+    }
+    let args = Args::parse_for(argv.iter().cloned(), false)?;
+    let other = args.help
+        || args.version
+        || args.type_list
+        || args.preset_list
+        || args.completions.is_some()
+        || args.debug.is_some()
+        || args.repl
+        || args.watch
+        || args.tail
+        || args.serve.is_some();
+    if other {
+        bail!("a request to --serve can only search");
+    }
+    let recursive = args.directories == Directories::Recurse;
+    if (args.paths.is_empty() && !recursive) || args.paths.iter().any(|p| p == "-") {
+        bail!("a request to --serve needs a FILE or directory to search");
+    }
+    let builder = args.regex();
+    // The builder shows every option the regex is compiled with.
+    let key = format!("{:?}", builder);
+    if !compiled.contains_key(&key) {
+        if compiled.len() >= 64 {
+            compiled.clear();
+        }
+        compiled.insert(key.clone(), builder.build()?);
+    }
+    let buf = Buffer::default();
+    let mut grep = args.grep_with(compiled[&key].clone(), Box::new(buf.clone()))?;
+    let started = Instant::now();
+    let any = grep_inputs(&args, &mut grep)?;
+    let status = finish(&mut grep, any, started)?;
+    drop(grep);
+    let output = mem::take(&mut *buf.0.borrow_mut());
+    Ok((status, output))
+}
+
+/// Reports whether the input named path is a named pipe, socket or
+/// device rather than a regular file or directory; `-` never is.
+fn is_device(path: &str) -> bool {
+    path != "-" && fs::metadata(path).is_ok_and(|md| !md.file_type().is_file() && !md.is_dir())
+}
+
+/// Reports whether searching the input labelled path found anything,
+/// warning of the error it failed with if it did, so the inputs after it
+/// are still searched.
+fn settle(grep: &mut Grep, path: &str, found: Result<bool>) -> bool {
+    found.unwrap_or_else(|e| {
+        grep.warn(path, &e);
+        false
+    })
+}
+
+/// Searches what changes in the inputs named in args until Ctrl-C: the
+/// lines appended to a file, or all of one new or rewritten; returns true
+/// if anything was selected.
+fn watch_inputs(args: &Args, grep: &mut Grep) -> Result<bool> {
+    let mut watch = Watch::new(args.line_terminator());
+    for (path, _) in watched(args, grep) {
+        // What cannot be looked at now is new once it can be.
+        let _ = watch.record(&path);
+    }
+    let mut any = false;
+    while !interrupted() {
+        grep.printer.get_mut().flush()?;
+        thread::sleep(watch::INTERVAL);
+        for (path, label) in watched(args, grep) {
+            // A file that cannot be looked at may be being replaced; it is
+            // tried again next time.
+            let Ok(Some(change)) = watch.change(&path) else {
+                continue;
+            };
+            let found = grep_change(&path, grep, &label, change);
+            any |= settle(grep, &label, found);
+            if interrupted() {
+                break;
+            }
+        }
+    }
+    Ok(any)
+}
+
+/// Follows the files named in args from their end until Ctrl-C, searching
+/// the lines appended to them; returns true if anything was selected. A
+/// file that cannot be read is reported and no longer followed.
+fn tail_inputs(args: &Args, grep: &mut Grep) -> Result<bool> {
+    let terminator = args.line_terminator();
+    let mut files = Vec::new();
+    for (path, label) in watched(args, grep) {
+        match Followed::new(&path, terminator) {
+            Ok(followed) => files.push((followed, label)),
+            Err(e) => grep.warn(&label, &e.into()),
+        }
+    }
+    let mut any = false;
+    while !interrupted() {
+        grep.printer.get_mut().flush()?;
+        thread::sleep(watch::INTERVAL);
+        files.retain_mut(|(followed, label)| {
+            let (event, lines) = match followed.poll() {
+                Ok(polled) => polled,
+                Err(e) => {
+                    grep.warn(label, &e.into());
+                    return false;
+                }
+            };
+            let notice = match event {
+                Some(Event::Appeared) => "has appeared; following the new file",
+                Some(Event::Truncated) => "file truncated",
+                Some(Event::Replaced) => "has been replaced; following the new file",
+                None => "",
+            };
+            if !notice.is_empty() && !grep.no_messages {
+                progress::clear();
+                eprintln!("putao: {}: {}", label, notice);
+            }
+            if let Some(lines) = lines {
+                grep.searcher.start_at(lines.line, lines.offset);
+                let found = grep_reader(lines.data.as_slice(), grep, label);
+                grep.searcher.start_at(1, 0);
+                any |= settle(grep, label, found);
+            }
+            true
+        });
+    }
+    Ok(any)
+}
+
+/// Returns the files among the inputs named in args that `--watch` looks
+/// at, each with its label.
+fn watched(args: &Args, grep: &mut Grep) -> Vec<(PathBuf, String)> {
+    let mut files = Vec::new();
+    if args.directories != Directories::Recurse {
+        for file in args.paths.iter().filter(|f| *f != "-") {
+            if Path::new(file).is_dir() || (args.skip_devices && is_device(file)) {
+                continue;
+            }
+            let label = with_separator(file, grep.path_separator).into_owned();
+            files.push((PathBuf::from(file), label));
+        }
+        return files;
+    }
+    let roots = match args.paths.is_empty() {
+        true => vec![""],
+        false => args.paths.iter().map(String::as_str).collect(),
+    };
+    if let Some(seen) = &mut grep.seen {
+        seen.clear();
+    }
+    let walker = mem::take(&mut grep.walker);
+    for root in roots.into_iter().filter(|r| *r != "-") {
+        let base = Path::new(if root.is_empty() { "." } else { root });
+        // These errors were reported by the first search.
+        let Ok(tracked) = tracked(base, grep) else {
+            continue;
+        };
+        let _ = walker.run_with_errors(base, |entry| {
+            if let Ok(entry) = entry {
+                if keeps(tracked.as_ref(), base, entry.path()) && grep.first_visit(entry.path()) {
+                    let label = dir_label(root, base, entry.path(), grep.path_separator);
+                    files.push((entry.path().to_path_buf(), label));
+                }
+            }
+            Ok(!interrupted())
+        });
+    }
+    grep.walker = walker;
+    files
+}
+
+/// Searches what change says is new in the file at path, labelled label.
+fn grep_change(path: &Path, grep: &mut Grep, label: &str, change: Change) -> Result<bool> {
+    match change {
+        // A file read through a command is only known whole.
+        Change::Appended { from, to, line } if !piped(path, grep) => {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(from))?;
+            grep.searcher.start_at(line, from);
+            let found = grep_reader(file.take(to - from), grep, label);
+            grep.searcher.start_at(1, 0);
+            found
+        }
+        _ => grep_file_with_label(path, grep, label),
+    }
+}
+
+/// Searches every input named in args, or stdin if there are none;
+/// returns true if anything was selected.
+fn grep_inputs(args: &Args, grep: &mut Grep) -> Result<bool> {
+    let rest = &args.paths;
+
+    if args.directories == Directories::Recurse {
+        if rest.is_empty() {
+            let found = grep_dir("", grep, args);
+            return Ok(settle(grep, ".", found));
+        }
+        // Roots named twice, or one below another, would have their files
+        // searched again.
+        if rest.len() > 1 {
+            grep.seen = Some(HashSet::new());
+        }
+        let mut roots = HashSet::new();
+        let mut any = false;
+        for root in rest {
+            if args.skip_devices && is_device(root) {
+                continue;
+            }
+            if root != "-" && fs::canonicalize(root).is_ok_and(|root| !roots.insert(root)) {
+                continue;
+            }
+            any |= match root.as_str() {
+                "-" => {
+                    let found = grep_stdin(grep);
+                    let label = grep.stdin_label.clone();
+                    settle(grep, &label, found)
+                }
+                #[cfg(feature = "http")]
+                url if http::is_url(url) => {
+                    let found = grep_url(url, grep);
+                    settle(grep, url, found)
+                }
+                root => {
+                    let found = grep_dir(root, grep, args);
+                    settle(grep, root, found)
+                }
+            };
+            if grep.done || interrupted() {
+                break;
+            }
+        }
+        return Ok(any);
+    }
+
+    if rest.is_empty() {
+        // Waiting on a terminal for input is more likely a FILE forgotten.
+        if io::stdin().is_terminal() {
+            bail!(
+                "no FILE given, and standard input is a terminal; name '-' to read it\n{}\nTry 'putao --help' for more information.",
+                USAGE
+            );
+        }
+        let found = grep_stdin(grep);
+        let label = grep.stdin_label.clone();
+        return Ok(settle(grep, &label, found));
+    }
+    let mut any = false;
+    for file in rest {
+        if args.directories == Directories::Skip && Path::new(file).is_dir() {
+            continue;
+        }
+        if args.skip_devices && is_device(file) {
+            continue;
+        }
+        let found = match file.as_str() {
+            "-" => grep_stdin(grep),
+            #[cfg(feature = "http")]
+            url if http::is_url(url) => grep_url(url, grep),
+            file => grep_file(file, grep),
+        };
+        any |= settle(grep, file, found);
+        if grep.done || interrupted() {
+            break;
+        }
+    }
+    Ok(any)
 }
@@ -0,0 +1,165 @@
+//! Unicode normalization form C, so text typed with combining sequences
+//! and text using precomposed characters compare equal.
+//!
+//! ```
+//! use putao_cf::normalize::nfc;
+//!
+//! assert_eq!(nfc("cafe\u{301}"), "caf\u{e9}");
+//! ```
+
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+mod tables;
+
+use tables::{COMBINING_CLASSES, COMPOSITIONS, DECOMPOSITIONS};
+
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+/// Returns text in normalization form C, borrowing it if it already is.
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut chars = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        decompose(c, &mut chars);
+    }
+    reorder(&mut chars);
+    let composed: String = compose(chars).into_iter().collect();
+    if composed == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(composed)
+    }
+}
+
+/// Like [`nfc`] for bytes, normalizing each run of valid UTF-8 and passing
+/// invalid bytes through unchanged.
+pub fn nfc_bytes(bytes: &[u8]) -> Cow<'_, [u8]> {
+    if bytes.is_ascii() {
+        return Cow::Borrowed(bytes);
+    }
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut changed = false;
+    for chunk in bytes.utf8_chunks() {
+        let text = nfc(chunk.valid());
+        changed |= matches!(text, Cow::Owned(_));
+        out.extend_from_slice(text.as_bytes());
+        out.extend_from_slice(chunk.invalid());
+    }
+    if changed {
+        Cow::Owned(out)
+    } else {
+        Cow::Borrowed(bytes)
+    }
+}
+
+/// Appends the full canonical decomposition of c to out.
+fn decompose(c: char, out: &mut Vec<char>) {
+    let cp = c as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&cp) {
+        let s = cp - S_BASE;
+        out.extend(char::from_u32(L_BASE + s / N_COUNT));
+        out.extend(char::from_u32(V_BASE + s % N_COUNT / T_COUNT));
+        if s % T_COUNT != 0 {
+            out.extend(char::from_u32(T_BASE + s % T_COUNT));
+        }
+        return;
+    }
+    match DECOMPOSITIONS.binary_search_by_key(&c, |&(c, _, _)| c) {
+        Ok(i) => {
+            let (_, first, second) = DECOMPOSITIONS[i];
+            decompose(first, out);
+            if second != '\0' {
+                decompose(second, out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+/// Returns the canonical combining class of c.
+fn class(c: char) -> u8 {
+    let found = COMBINING_CLASSES.binary_search_by(|&(lo, hi, _)| {
+        if hi < c {
+            core::cmp::Ordering::Less
+        } else if lo > c {
+            core::cmp::Ordering::Greater
+        } else {
+            core::cmp::Ordering::Equal
+        }
+    });
+    found.map_or(0, |i| COMBINING_CLASSES[i].2)
+}
+
+/// Sorts each run of combining characters by combining class, keeping
+/// those of the same class in order.
+fn reorder(chars: &mut [char]) {
+    let mut start = 0;
+    while start < chars.len() {
+        if class(chars[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let len = chars[start..]
+            .iter()
+            .take_while(|&&c| class(c) != 0)
+            .count();
+        chars[start..start + len].sort_by_key(|&c| class(c));
+        start += len;
+    }
+}
+
+/// Returns the primary composite of a and b, if they have one.
+fn pair(a: char, b: char) -> Option<char> {
+    let (a32, b32) = (a as u32, b as u32);
+    if (L_BASE..L_BASE + L_COUNT).contains(&a32) && (V_BASE..V_BASE + V_COUNT).contains(&b32) {
+        let s = S_BASE + ((a32 - L_BASE) * V_COUNT + (b32 - V_BASE)) * T_COUNT;
+        return char::from_u32(s);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&a32)
+        && (a32 - S_BASE) % T_COUNT == 0
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&b32)
+    {
+        return char::from_u32(a32 + (b32 - T_BASE));
+    }
+    COMPOSITIONS
+        .binary_search_by_key(&(a, b), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+/// Composes decomposed, canonically ordered chars, each combining
+/// character into the starter before it unless one in between blocks it.
+fn compose(chars: Vec<char>) -> Vec<char> {
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+    let mut starter = None;
+    // The combining class of the last char kept after the starter.
+    let mut last = 0;
+    for c in chars {
+        let cls = class(c);
+        if let Some(s) = starter {
+            let adjacent = s + 1 == out.len();
+            if adjacent || (last != 0 && last < cls) {
+                if let Some(composite) = pair(out[s], c) {
+                    out[s] = composite;
+                    continue;
+                }
+            }
+        }
+        if cls == 0 {
+            starter = Some(out.len());
+        }
+        last = cls;
+        out.push(c);
+    }
+    out
+}
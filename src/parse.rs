@@ -0,0 +1,443 @@
+//! Pattern parser producing the public [`Ast`].
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::mem;
+
+use crate::ast::{
+    Assertion, Ast, Class, ClassPerl, Group, Lookaround, LookaroundKind, Repetition, RepetitionOp,
+};
+use crate::error::{Error, ErrorKind};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Reports whether c has a special meaning outside a class and needs a backslash.
+pub(crate) fn is_meta(c: char) -> bool {
+    "\\^$.|?*+()[]{}".contains(c)
+}
+
+/// Rewrites a POSIX basic pattern, where `?+|{}()` are literal unless
+/// escaped, into the extended syntax [`parse`] reads, along with the char
+/// offset in pattern each char of the rewrite comes from.
+pub(crate) fn basic(pattern: &str) -> (String, Vec<usize>) {
+    let mut out = String::with_capacity(pattern.len());
+    let mut from = Vec::with_capacity(pattern.len());
+    let mut push = |c: char, at: usize| {
+        out.push(c);
+        from.push(at);
+    };
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut cs = chars.iter().copied().enumerate();
+    // Whether a `*` here has nothing to repeat, which makes it literal, and
+    // a `^` starts the pattern or a group or branch, which makes it an anchor.
+    let mut leading = true;
+    while let Some((at, c)) = cs.next() {
+        let was_leading = mem::replace(&mut leading, false);
+        match c {
+            '\\' => match cs.next() {
+                Some((_, e @ ('?' | '+' | '|' | '{' | '}' | '(' | ')'))) => {
+                    push(e, at);
+                    leading = matches!(e, '(' | '|');
+                }
+                Some((_, e)) => {
+                    push('\\', at);
+                    push(e, at + 1);
+                }
+                None => push('\\', at),
+            },
+            '?' | '+' | '|' | '{' | '}' | '(' | ')' => {
+                push('\\', at);
+                push(c, at);
+            }
+            '*' if was_leading => {
+                push('\\', at);
+                push(c, at);
+            }
+            '^' if was_leading => {
+                push(c, at);
+                leading = true;
+            }
+            // Only a `$` that ends the pattern or a group or branch anchors.
+            '$' if !matches!(chars[at + 1..], [] | ['\\', ')' | '|', ..]) => {
+                push('\\', at);
+                push(c, at);
+            }
+            '^' => {
+                push('\\', at);
+                push(c, at);
+            }
+            '[' => {
+                // Classes read the same in both syntaxes; a `]` first in
+                // one is a member rather than its end.
+                let first = at + 1 + usize::from(chars.get(at + 1) == Some(&'^'));
+                push(c, at);
+                for (at, c) in cs.by_ref() {
+                    push(c, at);
+                    if c == ']' && at > first {
+                        break;
+                    }
+                }
+            }
+            _ => push(c, at),
+        }
+    }
+    (out, from)
+}
+
+/// Parses a pattern into its AST; with posix, the Perl extensions such as
+/// lookaround and lazy quantifiers are not recognized.
+pub(crate) fn parse(pattern: &str, posix: bool) -> Result<Ast> {
+    let cs: Vec<char> = pattern.chars().collect();
+    let mut i = 0usize;
+    let mut names = Vec::new();
+    let mut refs = Vec::new();
+    let mut brs = vec![concat(elems(&cs, &mut i, &mut names, &mut refs, posix)?)];
+    while i < cs.len() && cs[i] == '|' {
+        i += 1;
+        brs.push(concat(elems(&cs, &mut i, &mut names, &mut refs, posix)?));
+    }
+    if i < cs.len() {
+        return Err(Error::syntax(ErrorKind::UnopenedGroup, i));
+    }
+    let groups = names.len();
+    if let Some(&(group, offset)) = refs.iter().find(|(group, _)| *group > groups) {
+        let kind = ErrorKind::InvalidBackreference { group, groups };
+        return Err(Error::syntax(kind, offset));
+    }
+    Ok(if brs.len() == 1 {
+        brs.pop().unwrap()
+    } else {
+        Ast::Alternation(brs)
+    })
+}
+
+/// Collapses a sequence into the simplest equivalent node.
+fn concat(mut items: Vec<Ast>) -> Ast {
+    match items.len() {
+        0 => Ast::Empty,
+        1 => items.pop().unwrap(),
+        _ => Ast::Concat(items),
+    }
+}
+
+/// Parses a single base atom (lit, escape, class, group, any, anchor) and advances i;
+/// backreferences are noted in refs with their offsets, to be checked once
+/// every group is known.
+fn parse_atom(
+    cs: &[char],
+    i: &mut usize,
+    names: &mut Vec<Option<String>>,
+    refs: &mut Vec<(usize, usize)>,
+    posix: bool,
+) -> Result<Option<Ast>> {
+    if *i >= cs.len() {
+        return Ok(None);
+    }
+    let c = cs[*i];
+    let base = if c == '\\' {
+        *i += 1;
+        if *i >= cs.len() {
+            return Err(Error::syntax(ErrorKind::InvalidEscape, *i - 1));
+        }
+        let e = cs[*i];
+        *i += 1;
+        match e {
+            'd' => Some(Ast::Perl(ClassPerl::Digit)),
+            'w' => Some(Ast::Perl(ClassPerl::Word)),
+            's' => Some(Ast::Perl(ClassPerl::Space)),
+            'D' => Some(Ast::Perl(ClassPerl::NotDigit)),
+            'W' => Some(Ast::Perl(ClassPerl::NotWord)),
+            'S' => Some(Ast::Perl(ClassPerl::NotSpace)),
+            'b' => Some(Ast::Assertion(Assertion::WordBoundary)),
+            'B' => Some(Ast::Assertion(Assertion::NotWordBoundary)),
+            '1'..='9' => {
+                let group = (e as u8 - b'0') as usize;
+                refs.push((group, *i - 2));
+                Some(Ast::Backref(group))
+            }
+            'K' if !posix => Some(Ast::Keep),
+            'n' | 't' | 'r' | 'f' => Some(Ast::Literal(control(e))),
+            // POSIX grep reads other escaped chars as themselves; Perl
+            // syntax reserves escaped letters and digits for escapes.
+            _ if !posix && e.is_ascii_alphanumeric() => {
+                return Err(Error::syntax(ErrorKind::UnsupportedEscape, *i - 2));
+            }
+            _ => Some(Ast::Literal(e)),
+        }
+    } else if c == '[' {
+        let open = *i;
+        *i += 1;
+        let neg = *i < cs.len() && cs[*i] == '^';
+        if neg {
+            *i += 1;
+        }
+        let mut chars = String::new();
+        let mut ranges = Vec::new();
+        // A `]` first in the class is a member rather than its end.
+        let first = *i;
+        while *i < cs.len() && (cs[*i] != ']' || *i == first) {
+            let start = *i;
+            let lo = class_item(cs, i, posix)?;
+            // A `-` first or last in the class is just another char.
+            if *i + 1 < cs.len() && cs[*i] == '-' && cs[*i + 1] != ']' {
+                *i += 1;
+                let hi = class_item(cs, i, posix)?;
+                if hi < lo {
+                    return Err(Error::syntax(ErrorKind::InvalidClassRange, start));
+                }
+                ranges.push((lo, hi));
+            } else {
+                chars.push(lo);
+            }
+        }
+        if *i >= cs.len() {
+            return Err(Error::syntax(ErrorKind::UnclosedClass, open));
+        }
+        *i += 1;
+        Some(Ast::Class(Class {
+            negated: neg,
+            chars,
+            ranges,
+        }))
+    } else if c == '(' {
+        let open = *i;
+        *i += 1;
+        let ext = *i < cs.len() && cs[*i] == '?';
+        if ext && posix {
+            return Err(Error::syntax(ErrorKind::UnsupportedGroup, open));
+        }
+        if let Some(kind) = lookaround(cs, i) {
+            let ast = branches(cs, i, names, refs, posix, open)?;
+            return Ok(Some(Ast::Lookaround(Lookaround {
+                kind,
+                ast: Box::new(ast),
+            })));
+        }
+        let name = if ext {
+            Some(group_name(cs, i, names, open)?)
+        } else {
+            None
+        };
+        names.push(name.clone());
+        let id = names.len();
+        let ast = branches(cs, i, names, refs, posix, open)?;
+        Some(Ast::Group(Group {
+            index: id,
+            name,
+            ast: Box::new(ast),
+        }))
+    } else if c == '.' {
+        *i += 1;
+        Some(Ast::Dot)
+    } else if c == ')' || c == '|' {
+        None
+    } else if c == '^' || c == '$' {
+        *i += 1;
+        return Ok(Some(Ast::Assertion(if c == '^' {
+            Assertion::Start
+        } else {
+            Assertion::End
+        })));
+    } else {
+        *i += 1;
+        Some(Ast::Literal(c))
+    };
+    Ok(base)
+}
+
+/// Parses the char at cs[i] inside a class, or the escape there in a Perl
+/// class, and advances i past it.
+fn class_item(cs: &[char], i: &mut usize, posix: bool) -> Result<char> {
+    // In POSIX classes a backslash is just another char.
+    let c = if cs[*i] == '\\' && !posix {
+        class_escape(cs, i)?
+    } else {
+        cs[*i]
+    };
+    *i += 1;
+    Ok(c)
+}
+
+/// Parses the escape at cs[i] inside a Perl class, leaving i at its last char.
+fn class_escape(cs: &[char], i: &mut usize) -> Result<char> {
+    let at = *i;
+    *i += 1;
+    match cs.get(*i) {
+        None => Err(Error::syntax(ErrorKind::InvalidEscape, at)),
+        Some(&c @ ('n' | 't' | 'r' | 'f')) => Ok(control(c)),
+        Some('d' | 'w' | 's' | 'D' | 'W' | 'S') => {
+            Err(Error::syntax(ErrorKind::UnsupportedClassEscape, at))
+        }
+        Some(c) if c.is_ascii_alphanumeric() => Err(Error::syntax(ErrorKind::UnsupportedEscape, at)),
+        Some(&c) => Ok(c),
+    }
+}
+
+/// Returns the control char the escape `\n`, `\t`, `\r` or `\f` stands for.
+fn control(e: char) -> char {
+    match e {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        _ => '\x0c',
+    }
+}
+
+/// Parses the `|`-separated branches of the group opened at open, and its `)`.
+fn branches(
+    cs: &[char],
+    i: &mut usize,
+    names: &mut Vec<Option<String>>,
+    refs: &mut Vec<(usize, usize)>,
+    posix: bool,
+    open: usize,
+) -> Result<Ast> {
+    let mut brs = vec![concat(elems(cs, i, names, refs, posix)?)];
+    while *i < cs.len() && cs[*i] == '|' {
+        *i += 1;
+        brs.push(concat(elems(cs, i, names, refs, posix)?));
+    }
+    if *i >= cs.len() {
+        return Err(Error::syntax(ErrorKind::UnclosedGroup, open));
+    }
+    *i += 1;
+    Ok(if brs.len() == 1 {
+        brs.pop().unwrap()
+    } else {
+        Ast::Alternation(brs)
+    })
+}
+
+/// Parses the `?=`, `?!`, `?<=` or `?<!` after a group's `(`, if it opens a
+/// lookaround, and advances i past it.
+fn lookaround(cs: &[char], i: &mut usize) -> Option<LookaroundKind> {
+    let (kind, len) = match &cs[*i..] {
+        ['?', '=', ..] => (LookaroundKind::Ahead, 2),
+        ['?', '!', ..] => (LookaroundKind::NotAhead, 2),
+        ['?', '<', '=', ..] => (LookaroundKind::Behind, 3),
+        ['?', '<', '!', ..] => (LookaroundKind::NotBehind, 3),
+        _ => return None,
+    };
+    *i += len;
+    Some(kind)
+}
+
+/// Parses the `?<name>` or `?P<name>` after a group's `(` and advances i past `>`.
+fn group_name(cs: &[char], i: &mut usize, names: &[Option<String>], open: usize) -> Result<String> {
+    *i += 1;
+    if *i < cs.len() && cs[*i] == 'P' {
+        *i += 1;
+    }
+    if *i >= cs.len() || cs[*i] != '<' {
+        return Err(Error::syntax(ErrorKind::UnsupportedGroup, open));
+    }
+    *i += 1;
+    let begin = *i;
+    while *i < cs.len() && cs[*i] != '>' {
+        *i += 1;
+    }
+    if *i >= cs.len() {
+        return Err(Error::syntax(ErrorKind::UnclosedGroup, open));
+    }
+    let name: String = cs[begin..*i].iter().collect();
+    *i += 1;
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(Error::syntax(ErrorKind::InvalidGroupName, begin));
+    }
+    if names.iter().flatten().any(|n| *n == name) {
+        return Err(Error::syntax(ErrorKind::DuplicateGroupName, begin));
+    }
+    Ok(name)
+}
+
+/// Applies a quantifier to the base node if present, advances i; unless
+/// posix, a `?` after it makes it lazy.
+fn parse_quantifier(cs: &[char], i: &mut usize, base: Ast, posix: bool) -> Result<Ast> {
+    if *i >= cs.len() {
+        return Ok(base);
+    }
+    let op = if cs[*i] == '+' {
+        *i += 1;
+        RepetitionOp::OneOrMore
+    } else if cs[*i] == '?' {
+        *i += 1;
+        RepetitionOp::ZeroOrOne
+    } else if cs[*i] == '*' {
+        *i += 1;
+        RepetitionOp::ZeroOrMore
+    } else if cs[*i] == '{' {
+        let open = *i;
+        *i += 1;
+        let mut min_str = String::new();
+        while *i < cs.len() && cs[*i].is_ascii_digit() {
+            min_str.push(cs[*i]);
+            *i += 1;
+        }
+        if min_str.is_empty() {
+            return Err(Error::syntax(ErrorKind::MissingRepetitionMin, open));
+        }
+        let min = count(&min_str, open)?;
+        if *i < cs.len() && cs[*i] == ',' {
+            *i += 1;
+            let mut max_str = String::new();
+            while *i < cs.len() && cs[*i].is_ascii_digit() {
+                max_str.push(cs[*i]);
+                *i += 1;
+            }
+            if *i >= cs.len() || cs[*i] != '}' {
+                return Err(Error::syntax(ErrorKind::InvalidRepetition, open));
+            }
+            *i += 1;
+            if max_str.is_empty() {
+                RepetitionOp::AtLeast(min)
+            } else {
+                let max = count(&max_str, open)?;
+                if max < min {
+                    return Err(Error::syntax(ErrorKind::RepetitionRange, open));
+                }
+                RepetitionOp::Bounded(min, max)
+            }
+        } else {
+            if *i >= cs.len() || cs[*i] != '}' {
+                return Err(Error::syntax(ErrorKind::InvalidRepetition, open));
+            }
+            *i += 1;
+            RepetitionOp::Exactly(min)
+        }
+    } else {
+        return Ok(base);
+    };
+    let greedy = posix || cs.get(*i) != Some(&'?');
+    if !greedy {
+        *i += 1;
+    }
+    Ok(Ast::Repetition(Repetition {
+        op,
+        greedy,
+        ast: Box::new(base),
+    }))
+}
+
+/// Parses a repetition count written at offset.
+fn count(digits: &str, offset: usize) -> Result<usize> {
+    digits
+        .parse()
+        .map_err(|_| Error::syntax(ErrorKind::RepetitionTooLarge, offset))
+}
+
+/// Parses a sequence of nodes until end, ')' or '|'.
+fn elems(
+    cs: &[char],
+    i: &mut usize,
+    names: &mut Vec<Option<String>>,
+    refs: &mut Vec<(usize, usize)>,
+    posix: bool,
+) -> Result<Vec<Ast>> {
+    let mut out = Vec::new();
+    while let Some(base) = parse_atom(cs, i, names, refs, posix)? {
+        let quantified = parse_quantifier(cs, i, base, posix)?;
+        out.push(quantified);
+    }
+    Ok(out)
+}
@@ -0,0 +1,135 @@
+//! Inputs read through an external command, such as a decompressor or a
+//! `--pre` preprocessor.
+
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+/// The commands that decompress files by extension, writing to stdout.
+const DECOMPRESSORS: &[(&[&str], &[&str])] = &[
+    (&["gz", "tgz"], &["gzip", "-d", "-c"]),
+    (&["bz2", "tbz2"], &["bzip2", "-d", "-c"]),
+    (&["xz", "txz"], &["xz", "-d", "-c"]),
+    (&["lzma"], &["xz", "--format=lzma", "-d", "-c"]),
+    (&["lz4"], &["lz4", "-d", "-c"]),
+    (&["zst", "zstd"], &["zstd", "-q", "-d", "-c"]),
+    (&["br"], &["brotli", "-d", "-c"]),
+    (&["Z"], &["uncompress", "-c"]),
+];
+
+/// Returns the command decompressing the file at path, if its extension
+/// names a known format.
+pub fn decompressor(path: &Path) -> Option<&'static [&'static str]> {
+    let ext = path.extension().and_then(OsStr::to_str)?;
+    DECOMPRESSORS
+        .iter()
+        .find(|(exts, _)| exts.contains(&ext))
+        .map(|&(_, cmd)| cmd)
+}
+
+/// The output of a command run over an input given as its stdin, and as
+/// its last argument too for a `--pre` command.
+///
+/// Reading fails once the output ends if the command exited unsuccessfully,
+/// with what it wrote to stderr. That is read on a thread of its own as it
+/// is written, so a command writing more to it than a pipe holds is not
+/// left waiting for it to be read.
+pub struct Piped {
+    name: String,
+    child: Child,
+    stdout: ChildStdout,
+    /// The start of what the command writes to stderr, once it closes it.
+    stderr: Option<JoinHandle<Vec<u8>>>,
+}
+
+/// How much of what a command writes to stderr is kept for its error.
+const STDERR_LIMIT: u64 = 64 << 10;
+
+impl Piped {
+    /// Runs cmd, its program first, over the file at path as its stdin.
+    pub fn spawn<S: AsRef<OsStr>>(cmd: &[S], path: &Path) -> io::Result<Piped> {
+        let stdin = File::open(path)?;
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]).stdin(stdin);
+        Piped::start(cmd, command)
+    }
+
+    /// Runs cmd as [`spawn`](Piped::spawn) does, also giving it path as its
+    /// last argument, as `--pre` commands expect; a relative path is given
+    /// from `./`, so one starting with `-` is not read as an option.
+    pub fn spawn_with_path<S: AsRef<OsStr>>(cmd: &[S], path: &Path) -> io::Result<Piped> {
+        let stdin = File::open(path)?;
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]).stdin(stdin);
+        match path.is_absolute() || path.starts_with(".") {
+            true => command.arg(path),
+            false => command.arg(Path::new(".").join(path)),
+        };
+        Piped::start(cmd, command)
+    }
+
+    /// Runs cmd, its program first, over no input.
+    pub fn run<S: AsRef<OsStr>>(cmd: &[S]) -> io::Result<Piped> {
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]).stdin(Stdio::null());
+        Piped::start(cmd, command)
+    }
+
+    fn start<S: AsRef<OsStr>>(cmd: &[S], mut command: Command) -> io::Result<Piped> {
+        let name = Path::new(&cmd[0]).display().to_string();
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", name, e)))?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let stderr = Some(thread::spawn(move || drain(stderr)));
+        Ok(Piped {
+            name,
+            child,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl Read for Piped {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let msg = match self.stderr.take() {
+                Some(stderr) => stderr.join().unwrap_or_default(),
+                None => Vec::new(),
+            };
+            let status = self.child.wait()?;
+            if !status.success() {
+                let msg = match String::from_utf8_lossy(&msg).trim() {
+                    "" => format!("{} exited with {}", self.name, status),
+                    msg => msg.to_string(),
+                };
+                return Err(io::Error::other(msg));
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Reads stderr to its end, returning the first [`STDERR_LIMIT`] bytes.
+fn drain(mut stderr: ChildStderr) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let _ = (&mut stderr).take(STDERR_LIMIT).read_to_end(&mut kept);
+    let _ = io::copy(&mut stderr, &mut io::sink());
+    kept
+}
+
+/// Stops a command whose output was not read to the end.
+impl Drop for Piped {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,51 @@
+//! A thread-safe pool of reusable values.
+
+use core::fmt;
+
+/// Lends out values so concurrent searches reuse each other's buffers.
+///
+/// Without `std` there is no lock to guard the pool, so every borrow gets a
+/// fresh value instead.
+#[derive(Default)]
+pub(crate) struct Pool<T> {
+    #[cfg(feature = "std")]
+    stack: std::sync::Mutex<alloc::vec::Vec<T>>,
+    #[cfg(not(feature = "std"))]
+    _marker: core::marker::PhantomData<T>,
+}
+
+impl<T: Default> Pool<T> {
+    /// Runs f with a value from the pool, returning it to the pool afterwards.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        #[cfg(feature = "std")]
+        {
+            let popped = self.stack.lock().ok().and_then(|mut s| s.pop());
+            let mut value = popped.unwrap_or_default();
+            let r = f(&mut value);
+            if let Ok(mut s) = self.stack.lock() {
+                s.push(value);
+            }
+            r
+        }
+        #[cfg(not(feature = "std"))]
+        f(&mut T::default())
+    }
+}
+
+/// Clones start with an empty pool; pooled values are only scratch space.
+impl<T> Clone for Pool<T> {
+    fn clone(&self) -> Self {
+        Pool {
+            #[cfg(feature = "std")]
+            stack: Default::default(),
+            #[cfg(not(feature = "std"))]
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Pool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Pool { .. }")
+    }
+}
@@ -0,0 +1,84 @@
+//! Named patterns for things often searched for, selected with `--preset`
+//! and listed with `--preset-list`.
+
+use anyhow::{anyhow, Result};
+
+/// The presets, each a name, what it matches and its pattern in extended
+/// syntax. Those made of digits or hex digits are bounded by `\b`, so none
+/// matches part of a longer run of them.
+const PRESETS: &[(&str, &str, &str)] = &[
+    (
+        "email",
+        "email addresses",
+        r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+    ),
+    (
+        "ipv4",
+        "IPv4 addresses, each part from 0 to 255",
+        r"\b((25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\.){3}(25[0-5]|2[0-4][0-9]|1[0-9][0-9]|[1-9]?[0-9])\b",
+    ),
+    (
+        "iso-date",
+        "ISO 8601 dates, such as 2024-02-29",
+        r"\b[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|[12][0-9]|3[01])\b",
+    ),
+    (
+        "uuid",
+        "UUIDs, such as 123e4567-e89b-12d3-a456-426614174000",
+        r"\b[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}\b",
+    ),
+];
+
+/// Returns the pattern of the preset named name, written in basic syntax
+/// if basic says so and in extended syntax otherwise.
+pub fn pattern(name: &str, basic: bool) -> Result<String> {
+    let (_, _, pattern) = PRESETS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .ok_or_else(|| anyhow!("unknown preset '{}'; --preset-list lists them", name))?;
+    Ok(match basic {
+        true => to_basic(pattern),
+        false => pattern.to_string(),
+    })
+}
+
+/// Returns the presets, a line each, as `--preset-list` prints them.
+pub fn list() -> String {
+    PRESETS
+        .iter()
+        .map(|(name, about, pattern)| format!("{}: {}\n    {}\n", name, about, pattern))
+        .collect()
+}
+
+/// Rewrites an extended pattern in basic syntax, where `?+|{}()` need a
+/// backslash to be special.
+fn to_basic(pattern: &str) -> String {
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            '[' => {
+                // Classes read the same in both syntaxes; a `]` first in
+                // one is a member rather than its end.
+                out.push(c);
+                let open = out.len();
+                for c in chars.by_ref() {
+                    out.push(c);
+                    if c == ']' && !matches!(&out[open..], "]" | "^]") {
+                        break;
+                    }
+                }
+            }
+            '?' | '+' | '|' | '{' | '}' | '(' | ')' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
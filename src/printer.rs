@@ -0,0 +1,1237 @@
+//! Output formats for lines reported by a [`Searcher`](crate::searcher::Searcher).
+//!
+//! A [`Printer`] turns matches and context into output; [`Printer::sink`]
+//! adapts one to the [`Sink`] a search reports to.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::bytes::Regex;
+use crate::searcher::{Sink, SinkContext, SinkFinish, SinkMatch};
+
+/// Formats the lines found in one or more inputs.
+pub trait Printer {
+    /// Writes a matching line from the input labelled path.
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()>;
+
+    /// Writes a context line from the input labelled path.
+    fn context(&mut self, _path: Option<&str>, _ctx: &SinkContext<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Writes the separator between non-contiguous groups of lines.
+    fn context_break(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called before the input labelled path is searched.
+    fn begin(&mut self, _path: Option<&str>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the input labelled path has been searched.
+    fn finish(&mut self, _path: Option<&str>, _finish: &SinkFinish) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns a sink printing the lines of the input labelled path.
+    fn sink<'p>(&'p mut self, path: Option<&'p str>) -> PrinterSink<'p, Self>
+    where
+        Self: Sized,
+    {
+        PrinterSink {
+            printer: self,
+            path,
+            matched: false,
+        }
+    }
+}
+
+/// A [`Sink`] forwarding to a [`Printer`].
+pub struct PrinterSink<'p, P> {
+    printer: &'p mut P,
+    path: Option<&'p str>,
+    matched: bool,
+}
+
+impl<P> PrinterSink<'_, P> {
+    /// Reports whether any line matched.
+    pub fn has_match(&self) -> bool {
+        self.matched
+    }
+}
+
+impl<P: Printer> Sink for PrinterSink<'_, P> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        self.matched = true;
+        self.printer.matched(self.path, m)?;
+        Ok(true)
+    }
+
+    fn context(&mut self, ctx: &SinkContext<'_>) -> io::Result<bool> {
+        self.printer.context(self.path, ctx)?;
+        Ok(true)
+    }
+
+    fn context_break(&mut self) -> io::Result<bool> {
+        self.printer.context_break()?;
+        Ok(true)
+    }
+
+    fn begin(&mut self) -> io::Result<bool> {
+        self.printer.begin(self.path)?;
+        Ok(true)
+    }
+
+    fn finish(&mut self, finish: &SinkFinish) -> io::Result<()> {
+        self.printer.finish(self.path, finish)
+    }
+}
+
+/// The SGR parameters used to color each part of a line, e.g. `01;31`;
+/// an empty one leaves that part uncolored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Colors {
+    /// Matched text in a matching line (`ms` in `GREP_COLORS`).
+    pub matched: String,
+    /// File names (`fn`).
+    pub path: String,
+    /// Line numbers (`ln`).
+    pub line_number: String,
+    /// Columns, colored like line numbers in `GREP_COLORS`.
+    pub column: String,
+    /// Byte offsets (`bn`).
+    pub byte_offset: String,
+    /// Separators between the parts of a line, and between groups of lines (`se`).
+    pub separator: String,
+}
+
+/// The colors GNU grep uses by default.
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            matched: "01;31".to_string(),
+            path: "35".to_string(),
+            line_number: "32".to_string(),
+            column: "32".to_string(),
+            byte_offset: "32".to_string(),
+            separator: "36".to_string(),
+        }
+    }
+}
+
+impl Colors {
+    /// Parses a `GREP_COLORS` value such as `ms=01;32:fn=34`, keeping the
+    /// default for every part it does not name; unknown names are ignored.
+    pub fn from_grep_colors(spec: &str) -> Colors {
+        let mut colors = Colors::default();
+        for (name, value) in spec.split(':').filter_map(|kv| kv.split_once('=')) {
+            if name == "ln" {
+                colors.column = value.to_string();
+            }
+            let part = match name {
+                "ms" | "mt" => &mut colors.matched,
+                "fn" => &mut colors.path,
+                "ln" => &mut colors.line_number,
+                "bn" => &mut colors.byte_offset,
+                "se" => &mut colors.separator,
+                _ => continue,
+            };
+            *part = value.to_string();
+        }
+        colors
+    }
+
+    /// Applies a ripgrep-style spec `PART:ATTR:VALUE` on top of the colors,
+    /// such as `match:fg:yellow` or `path:style:bold`; `PART:none` leaves
+    /// that part uncolored.
+    ///
+    /// The parts are `path`, `line`, `column`, `match` and `separator`. A
+    /// `fg` or `bg` value is a color name, a number up to 255 or an `R,G,B`
+    /// triple; a `style` is `bold`, `underline` or `italic`, or those
+    /// prefixed with `no`.
+    pub fn apply_spec(&mut self, spec: &str) -> Result<(), ColorSpecError> {
+        let err = |reason| ColorSpecError {
+            spec: spec.to_string(),
+            reason,
+        };
+        let mut fields = spec.splitn(3, ':');
+        let part = self
+            .part(fields.next().unwrap_or_default())
+            .ok_or_else(|| err("unknown part"))?;
+        let sgr = match (fields.next(), fields.next()) {
+            (Some("none"), None) => {
+                part.clear();
+                return Ok(());
+            }
+            (Some("fg"), Some(color)) => {
+                sgr_color(color, 30).ok_or_else(|| err("unknown color"))?
+            }
+            (Some("bg"), Some(color)) => {
+                sgr_color(color, 40).ok_or_else(|| err("unknown color"))?
+            }
+            (Some("style"), Some(style)) => match style {
+                "bold" => "1",
+                "nobold" => "22",
+                "italic" => "3",
+                "noitalic" => "23",
+                "underline" => "4",
+                "nounderline" => "24",
+                _ => return Err(err("unknown style")),
+            }
+            .to_string(),
+            _ => {
+                return Err(err(
+                    "expected PART:fg:COLOR, PART:bg:COLOR, PART:style:STYLE or PART:none",
+                ))
+            }
+        };
+        // A later parameter overrides an earlier one of its kind.
+        if !part.is_empty() {
+            part.push(';');
+        }
+        part.push_str(&sgr);
+        Ok(())
+    }
+
+    /// Applies specs in order as [`apply_spec`](Colors::apply_spec) does,
+    /// except that the first one naming a part replaces its colors rather
+    /// than adding to them, as in ripgrep: `match:fg:yellow` alone colors
+    /// matches yellow, not bold yellow.
+    pub fn apply_specs<'a>(
+        &mut self,
+        specs: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ColorSpecError> {
+        let mut named = Vec::new();
+        for spec in specs {
+            let name = spec.split(':').next().unwrap_or_default();
+            if !named.contains(&name) {
+                named.push(name);
+                if let Some(part) = self.part(name) {
+                    part.clear();
+                }
+            }
+            self.apply_spec(spec)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the colors of the part a spec names.
+    fn part(&mut self, name: &str) -> Option<&mut String> {
+        match name {
+            "path" => Some(&mut self.path),
+            "line" => Some(&mut self.line_number),
+            "column" => Some(&mut self.column),
+            "match" => Some(&mut self.matched),
+            "separator" => Some(&mut self.separator),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the SGR parameters of color for a foreground, with base 30, or
+/// a background, with base 40.
+fn sgr_color(color: &str, base: u8) -> Option<String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    if let Some(i) = NAMES.iter().position(|&n| n == color) {
+        return Some((base + i as u8).to_string());
+    }
+    let channel = |s: &str| match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => s.parse::<u8>().ok(),
+    };
+    match color.split(',').collect::<Vec<_>>()[..] {
+        [n] => Some(format!("{};5;{}", base + 8, channel(n)?)),
+        [r, g, b] => Some(format!(
+            "{};2;{};{};{}",
+            base + 8,
+            channel(r)?,
+            channel(g)?,
+            channel(b)?
+        )),
+        _ => None,
+    }
+}
+
+/// A color spec [`Colors::apply_spec`] could not make sense of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorSpecError {
+    spec: String,
+    reason: &'static str,
+}
+
+impl fmt::Display for ColorSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid color spec '{}': {}", self.spec, self.reason)
+    }
+}
+
+impl std::error::Error for ColorSpecError {}
+
+/// Writes text wrapped in the escapes for the SGR parameters spec, if any.
+fn paint<W: Write>(wtr: &mut W, spec: Option<&str>, text: &[u8]) -> io::Result<()> {
+    match spec.filter(|s| !s.is_empty()) {
+        Some(spec) => {
+            write!(wtr, "\x1b[{}m\x1b[K", spec)?;
+            wtr.write_all(text)?;
+            wtr.write_all(b"\x1b[m\x1b[K")
+        }
+        None => wtr.write_all(text),
+    }
+}
+
+/// Whether a line printed by a [`Standard`] printer matched.
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Match,
+    Context,
+}
+
+/// Classic grep output: `path:line`, with `-` after the path for context lines.
+#[derive(Debug)]
+pub struct Standard<W> {
+    wtr: W,
+    line_number: bool,
+    byte_offset: bool,
+    only_matching: bool,
+    path_terminator: Option<u8>,
+    line_terminator: u8,
+    colors: Option<Colors>,
+    replacement: Option<String>,
+    column: bool,
+    per_match: bool,
+    heading: bool,
+    match_separator: Vec<u8>,
+    context_field_separator: Vec<u8>,
+    context_separator: Option<Vec<u8>>,
+    max_columns: Option<usize>,
+    /// Whether the current input has had its heading.
+    headed: bool,
+    /// Whether any input has had a heading, so the next one is set apart.
+    grouped: bool,
+}
+
+impl<W: Write> Standard<W> {
+    /// Creates a printer writing to wtr.
+    pub fn new(wtr: W) -> Self {
+        Standard {
+            wtr,
+            line_number: false,
+            byte_offset: false,
+            only_matching: false,
+            path_terminator: None,
+            line_terminator: b'\n',
+            colors: None,
+            replacement: None,
+            column: false,
+            per_match: false,
+            heading: false,
+            match_separator: b":".to_vec(),
+            context_field_separator: b"-".to_vec(),
+            context_separator: Some(b"--".to_vec()),
+            max_columns: None,
+            headed: false,
+            grouped: false,
+        }
+    }
+
+    /// Prefixes each line with its 1-based line number.
+    pub fn line_number(&mut self, yes: bool) -> &mut Self {
+        self.line_number = yes;
+        self
+    }
+
+    /// Prefixes each line with the byte offset of its start in the input,
+    /// or of the match itself when only matches are printed.
+    pub fn byte_offset(&mut self, yes: bool) -> &mut Self {
+        self.byte_offset = yes;
+        self
+    }
+
+    /// Prints each non-empty match on its own line instead of whole lines,
+    /// leaving out context lines.
+    pub fn only_matching(&mut self, yes: bool) -> &mut Self {
+        self.only_matching = yes;
+        self
+    }
+
+    /// Ends the path with byte instead of the usual separator, e.g. NUL so
+    /// paths holding `:` or newlines can be split off reliably.
+    pub fn path_terminator(&mut self, byte: Option<u8>) -> &mut Self {
+        self.path_terminator = byte;
+        self
+    }
+
+    /// Sets the byte ending each printed line, added to lines lacking it.
+    pub fn line_terminator(&mut self, byte: u8) -> &mut Self {
+        self.line_terminator = byte;
+        self
+    }
+
+    /// Colors output with ANSI escapes, or leaves it plain if None.
+    pub fn colors(&mut self, colors: Option<Colors>) -> &mut Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Prints every match as the template, expanded as by
+    /// [`Regex::expand`](crate::bytes::Regex::expand), or as it is if None.
+    pub fn replacement(&mut self, template: Option<String>) -> &mut Self {
+        self.replacement = template;
+        self
+    }
+
+    /// Prefixes each matching line with the 1-based byte column of its
+    /// first match, after the line number.
+    pub fn column(&mut self, yes: bool) -> &mut Self {
+        self.column = yes;
+        self
+    }
+
+    /// Prints a matching line once for every match in it, each time with
+    /// that match colored and its column in the prefix.
+    pub fn per_match(&mut self, yes: bool) -> &mut Self {
+        self.per_match = yes;
+        self
+    }
+
+    /// Prints the path once on a line of its own before the lines of each
+    /// input instead of in front of every line, with an empty line between
+    /// inputs.
+    pub fn heading(&mut self, yes: bool) -> &mut Self {
+        self.heading = yes;
+        self
+    }
+
+    /// Sets what follows each part of the prefix of a matching line and of
+    /// a context line, `:` and `-` by default.
+    pub fn field_separators(&mut self, matched: &[u8], context: &[u8]) -> &mut Self {
+        self.match_separator = matched.to_vec();
+        self.context_field_separator = context.to_vec();
+        self
+    }
+
+    /// Sets the line between non-contiguous groups of lines, `--` by
+    /// default, or leaves it out if None.
+    pub fn context_separator(&mut self, sep: Option<&[u8]>) -> &mut Self {
+        self.context_separator = sep.map(<[u8]>::to_vec);
+        self
+    }
+
+    /// Prints lines longer than columns bytes as a note saying how many
+    /// matches they hold instead.
+    pub fn max_columns(&mut self, columns: Option<usize>) -> &mut Self {
+        self.max_columns = columns;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
+    /// Writes the heading of the input labelled path, unless it has one.
+    fn write_heading(&mut self, path: &str) -> io::Result<()> {
+        if self.headed {
+            return Ok(());
+        }
+        if self.grouped {
+            self.wtr.write_all(&[self.line_terminator])?;
+        }
+        (self.headed, self.grouped) = (true, true);
+        let color = self.colors.as_ref().map(|c| c.path.as_str());
+        paint(&mut self.wtr, color, path.as_bytes())?;
+        self.wtr.write_all(&[self.path_terminator.unwrap_or(b'\n')])
+    }
+
+    /// Writes a matching line with every match replaced by the template,
+    /// or with only matching, each replaced match on its own line.
+    fn replaced(
+        &mut self,
+        path: Option<&str>,
+        m: &SinkMatch<'_>,
+        re: &Regex,
+        template: &str,
+    ) -> io::Result<()> {
+        let (number, offset) = (m.line_number(), m.absolute_byte_offset());
+        let line = m.line();
+        let mut out = Vec::with_capacity(line.len());
+        let mut spans = Vec::new();
+        let mut column = None;
+        let mut last = 0;
+        for caps in re.captures_iter(line) {
+            if !m.counts(&caps) {
+                continue;
+            }
+            let r = caps.get(0).unwrap().range();
+            if self.only_matching {
+                if !r.is_empty() {
+                    let mut text = Vec::new();
+                    re.expand(&caps, template, &mut text);
+                    let whole = 0..text.len();
+                    let at = (number, Some(r.start as u64 + 1), offset + r.start as u64);
+                    self.line(path, Kind::Match, at, &text, &[whole])?;
+                }
+                continue;
+            }
+            if spans.is_empty() {
+                column = Some(r.start as u64 + 1);
+            }
+            out.extend_from_slice(&line[last..r.start]);
+            let start = out.len();
+            re.expand(&caps, template, &mut out);
+            spans.push(start..out.len());
+            last = r.end;
+        }
+        if self.only_matching {
+            return Ok(());
+        }
+        out.extend_from_slice(&m.bytes()[last..]);
+        self.line(path, Kind::Match, (number, column, offset), &out, &spans)
+    }
+
+    /// Writes a matching line, or each line of a multi-line match after a
+    /// prefix of its own, coloring the parts of spans on each.
+    fn lines(
+        &mut self,
+        path: Option<&str>,
+        (number, column, offset): (u64, Option<u64>, u64),
+        bytes: &[u8],
+        spans: &[Range<usize>],
+    ) -> io::Result<()> {
+        let term = self.line_terminator;
+        if !bytes[..bytes.len().saturating_sub(1)].contains(&term) {
+            return self.line(path, Kind::Match, (number, column, offset), bytes, spans);
+        }
+        let mut start = 0;
+        for (i, piece) in bytes.split_inclusive(|&b| b == term).enumerate() {
+            let end = start + piece.len();
+            // Spans stop short of the terminator, so colors end with the line.
+            let text = end - piece.ends_with(&[term]) as usize;
+            let clipped: Vec<_> = spans
+                .iter()
+                .filter(|r| r.start < text && r.end > start)
+                .map(|r| r.start.max(start) - start..r.end.min(text) - start)
+                .collect();
+            let at = (
+                number + i as u64,
+                column.filter(|_| i == 0),
+                offset + start as u64,
+            );
+            self.line(path, Kind::Match, at, piece, &clipped)?;
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Writes a line after its prefix, coloring the matches at spans; the
+    /// column is left out of the prefix if None.
+    fn line(
+        &mut self,
+        path: Option<&str>,
+        kind: Kind,
+        (number, column, offset): (u64, Option<u64>, u64),
+        line: &[u8],
+        spans: &[Range<usize>],
+    ) -> io::Result<()> {
+        let path = match path {
+            Some(path) if self.heading => {
+                self.write_heading(path)?;
+                None
+            }
+            path => path,
+        };
+        let colors = self.colors.as_ref();
+        let wtr = &mut self.wtr;
+        let sep = match kind {
+            Kind::Match => &self.match_separator,
+            Kind::Context => &self.context_field_separator,
+        };
+        let color_sep = colors.map(|c| c.separator.as_str());
+        if let Some(path) = path {
+            paint(wtr, colors.map(|c| c.path.as_str()), path.as_bytes())?;
+            match self.path_terminator {
+                Some(byte) => wtr.write_all(&[byte])?,
+                None => paint(wtr, color_sep, sep)?,
+            }
+        }
+        if self.line_number {
+            let number = number.to_string();
+            paint(
+                wtr,
+                colors.map(|c| c.line_number.as_str()),
+                number.as_bytes(),
+            )?;
+            paint(wtr, color_sep, sep)?;
+        }
+        if let Some(column) = column.filter(|_| self.column) {
+            let column = column.to_string();
+            paint(wtr, colors.map(|c| c.column.as_str()), column.as_bytes())?;
+            paint(wtr, color_sep, sep)?;
+        }
+        if self.byte_offset {
+            let offset = offset.to_string();
+            paint(
+                wtr,
+                colors.map(|c| c.byte_offset.as_str()),
+                offset.as_bytes(),
+            )?;
+            paint(wtr, color_sep, sep)?;
+        }
+        let text = line.len() - (line.last() == Some(&self.line_terminator)) as usize;
+        if self.max_columns.is_some_and(|max| text > max) {
+            let note = match kind {
+                Kind::Match => format!("[Omitted long line with {} matches]", spans.len()),
+                Kind::Context => "[Omitted long context line]".to_string(),
+            };
+            wtr.write_all(note.as_bytes())?;
+            return wtr.write_all(&[self.line_terminator]);
+        }
+        let mut last = 0;
+        for span in spans.iter().filter(|r| !r.is_empty()) {
+            wtr.write_all(&line[last..span.start])?;
+            paint(wtr, colors.map(|c| c.matched.as_str()), &line[span.clone()])?;
+            last = span.end;
+        }
+        wtr.write_all(&line[last..])?;
+        if line.last() != Some(&self.line_terminator) {
+            wtr.write_all(&[self.line_terminator])?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Printer for Standard<W> {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        if let (Some(re), Some(template)) = (m.regex(), self.replacement.take()) {
+            let written = self.replaced(path, m, re, &template);
+            self.replacement = Some(template);
+            return written;
+        }
+        let (number, offset) = (m.line_number(), m.absolute_byte_offset());
+        if self.only_matching {
+            for r in m.matches().filter(|r| !r.is_empty()) {
+                let at = (number, Some(r.start as u64 + 1), offset + r.start as u64);
+                let whole = 0..r.len();
+                self.line(path, Kind::Match, at, &m.line()[r], &[whole])?;
+            }
+            return Ok(());
+        }
+        if self.per_match && m.regex().is_some() {
+            for r in m.matches() {
+                let at = (number, Some(r.start as u64 + 1), offset);
+                self.line(path, Kind::Match, at, m.bytes(), &[r])?;
+            }
+            return Ok(());
+        }
+        // Spans are also counted when a long line is left out.
+        let spans: Vec<_> = match (&self.colors, self.column, self.max_columns) {
+            (None, false, None) => Vec::new(),
+            _ => m.matches().collect(),
+        };
+        let column = spans.first().map(|r| r.start as u64 + 1);
+        self.lines(path, (number, column, offset), m.bytes(), &spans)
+    }
+
+    fn context(&mut self, path: Option<&str>, ctx: &SinkContext<'_>) -> io::Result<()> {
+        if self.only_matching {
+            return Ok(());
+        }
+        let at = (ctx.line_number(), None, ctx.absolute_byte_offset());
+        self.line(path, Kind::Context, at, ctx.bytes(), &[])
+    }
+
+    fn context_break(&mut self) -> io::Result<()> {
+        let Some(sep) = &self.context_separator else {
+            return Ok(());
+        };
+        let color = self.colors.as_ref().map(|c| c.separator.as_str());
+        paint(&mut self.wtr, color, sep)?;
+        self.wtr.write_all(b"\n")
+    }
+
+    fn begin(&mut self, _path: Option<&str>) -> io::Result<()> {
+        self.headed = false;
+        Ok(())
+    }
+}
+
+/// Counts kept over one search, or over every search, such as by a [`Json`] printer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The time spent searching.
+    pub elapsed: Duration,
+    /// The number of inputs searched.
+    pub searches: u64,
+    /// The number of inputs with a matching line.
+    pub searches_with_match: u64,
+    /// The number of bytes read from the inputs.
+    pub bytes_searched: u64,
+    /// The number of bytes written.
+    pub bytes_printed: u64,
+    /// The number of matching lines.
+    pub matched_lines: u64,
+    /// The number of matches, counting each match within a line.
+    pub matches: u64,
+}
+
+impl Stats {
+    /// Adds the counts of other to these.
+    pub fn add(&mut self, other: &Stats) {
+        self.elapsed += other.elapsed;
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.bytes_searched += other.bytes_searched;
+        self.bytes_printed += other.bytes_printed;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+}
+
+/// JSON Lines output in the message format of ripgrep's `--json`.
+///
+/// An input with lines to report gets a `begin` message, a `match` or
+/// `context` message per line, holding `path`, `lines`, `line_number`,
+/// `absolute_offset` and the byte spans of its `submatches`, and an `end`
+/// message with the [`Stats`] of its search. [`Json::summary`] writes the
+/// totals. Text is written as `{"text":...}` if it is valid UTF-8 and as
+/// `{"bytes":...}`, in base64, if not.
+#[derive(Debug)]
+pub struct Json<W> {
+    wtr: W,
+    /// The message being written, held back to count the bytes printed.
+    buf: Vec<u8>,
+    /// When the printer was created, for the elapsed time of the summary.
+    created: Instant,
+    /// When the current input started being searched.
+    started: Instant,
+    /// Whether the current input has had its `begin` message.
+    begun: bool,
+    search: Stats,
+    total: Stats,
+}
+
+impl<W: Write> Json<W> {
+    /// Creates a printer writing to wtr.
+    pub fn new(wtr: W) -> Self {
+        Json {
+            wtr,
+            buf: Vec::new(),
+            created: Instant::now(),
+            started: Instant::now(),
+            begun: false,
+            search: Stats::default(),
+            total: Stats::default(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
+    /// Returns the totals over every finished search.
+    pub fn stats(&self) -> &Stats {
+        &self.total
+    }
+
+    /// Writes the `summary` message closing the output.
+    pub fn summary(&mut self) -> io::Result<()> {
+        let buf = &mut self.buf;
+        buf.clear();
+        buf.extend_from_slice(br#"{"type":"summary","data":{"elapsed_total":"#);
+        write_duration(buf, self.created.elapsed())?;
+        buf.extend_from_slice(br#","stats":"#);
+        write_stats(buf, &self.total)?;
+        buf.extend_from_slice(b"}}\n");
+        self.wtr.write_all(&self.buf)
+    }
+
+    /// Writes the message in buf, counting it as printed by the current search.
+    fn emit(&mut self) -> io::Result<()> {
+        self.search.bytes_printed += self.buf.len() as u64;
+        self.wtr.write_all(&self.buf)
+    }
+
+    fn line(&mut self, kind: &str, line: &Reported<'_>) -> io::Result<()> {
+        let path = line.path;
+        if !self.begun {
+            self.begun = true;
+            self.buf.clear();
+            self.buf
+                .extend_from_slice(br#"{"type":"begin","data":{"path":"#);
+            write_path(&mut self.buf, path)?;
+            self.buf.extend_from_slice(b"}}\n");
+            self.emit()?;
+        }
+        let buf = &mut self.buf;
+        buf.clear();
+        write!(buf, r#"{{"type":"{}","data":{{"path":"#, kind)?;
+        write_path(buf, path)?;
+        buf.extend_from_slice(br#","lines":"#);
+        write_data(buf, line.bytes)?;
+        write!(
+            buf,
+            r#","line_number":{},"absolute_offset":{},"submatches":["#,
+            line.number, line.offset
+        )?;
+        for (i, r) in line.spans.iter().enumerate() {
+            if i > 0 {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(br#"{"match":"#);
+            write_data(buf, &line.bytes[r.clone()])?;
+            write!(buf, r#","start":{},"end":{}}}"#, r.start, r.end)?;
+        }
+        buf.extend_from_slice(b"]}}\n");
+        self.emit()
+    }
+}
+
+impl<W: Write> Printer for Json<W> {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        let line = Reported::matched(path, m);
+        self.search.matched_lines += 1;
+        self.search.matches += line.spans.len() as u64;
+        self.line("match", &line)
+    }
+
+    fn context(&mut self, path: Option<&str>, ctx: &SinkContext<'_>) -> io::Result<()> {
+        self.line("context", &Reported::context(path, ctx))
+    }
+
+    fn begin(&mut self, _path: Option<&str>) -> io::Result<()> {
+        self.started = Instant::now();
+        self.begun = false;
+        self.search = Stats::default();
+        Ok(())
+    }
+
+    fn finish(&mut self, path: Option<&str>, finish: &SinkFinish) -> io::Result<()> {
+        self.search.elapsed = self.started.elapsed();
+        self.search.searches = 1;
+        self.search.searches_with_match = (self.search.matched_lines > 0) as u64;
+        self.search.bytes_searched = finish.byte_count();
+        if self.begun {
+            let buf = &mut self.buf;
+            buf.clear();
+            buf.extend_from_slice(br#"{"type":"end","data":{"path":"#);
+            write_path(buf, path)?;
+            match finish.binary_byte_offset() {
+                Some(at) => write!(buf, r#","binary_offset":{}"#, at)?,
+                None => buf.extend_from_slice(br#","binary_offset":null"#),
+            }
+            buf.extend_from_slice(br#","stats":"#);
+            write_stats(buf, &self.search)?;
+            buf.extend_from_slice(b"}}\n");
+            self.emit()?;
+        }
+        self.total.add(&self.search);
+        Ok(())
+    }
+}
+
+/// A line a search reported, as the structured printers describe it.
+struct Reported<'a> {
+    /// The label of the input it is in, if any.
+    path: Option<&'a str>,
+    /// Its 1-based line number.
+    number: u64,
+    /// Where it starts in its input.
+    offset: u64,
+    /// The line, with its terminator.
+    bytes: &'a [u8],
+    /// The byte spans of the matches in it.
+    spans: Vec<Range<usize>>,
+}
+
+impl<'a> Reported<'a> {
+    fn matched(path: Option<&'a str>, m: &SinkMatch<'a>) -> Self {
+        Reported {
+            path,
+            number: m.line_number(),
+            offset: m.absolute_byte_offset(),
+            bytes: m.bytes(),
+            spans: m.matches().collect(),
+        }
+    }
+
+    fn context(path: Option<&'a str>, ctx: &SinkContext<'a>) -> Self {
+        Reported {
+            path,
+            number: ctx.line_number(),
+            offset: ctx.absolute_byte_offset(),
+            bytes: ctx.bytes(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Returns the line without its terminator.
+    fn text(&self) -> &'a [u8] {
+        let text = self.bytes.strip_suffix(b"\n").unwrap_or(self.bytes);
+        text.strip_suffix(b"\r").unwrap_or(text)
+    }
+}
+
+/// Rows of comma-separated values, `path,line,column,match`, after a row
+/// naming them.
+///
+/// Each match is a row, with its 1-based byte column; an inverted match is
+/// a row holding its whole line, with no column. Fields holding the
+/// delimiter, a quote or a line break are quoted, as RFC 4180 has it, and
+/// text that is not valid UTF-8 is written lossily. Context lines are left
+/// out.
+#[derive(Debug)]
+pub struct Csv<W> {
+    wtr: W,
+    delimiter: u8,
+    /// Whether the row naming the fields was written.
+    started: bool,
+}
+
+impl<W: Write> Csv<W> {
+    /// Creates a printer writing to wtr.
+    pub fn new(wtr: W) -> Self {
+        Csv {
+            wtr,
+            delimiter: b',',
+            started: false,
+        }
+    }
+
+    /// Separates fields with byte rather than a comma, such as a tab for
+    /// tab-separated values.
+    pub fn delimiter(&mut self, byte: u8) -> &mut Self {
+        self.delimiter = byte;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
+    /// Ends the output, which is only the row naming the fields if there
+    /// are no others.
+    pub fn close(&mut self) -> io::Result<()> {
+        self.start()
+    }
+
+    /// Writes the row naming the fields, if that is not done yet.
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.row(["path", "line", "column", "match"])?;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, fields: [&str; 4]) -> io::Result<()> {
+        let mut buf = Vec::new();
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                buf.push(self.delimiter);
+            }
+            let delimiter = self.delimiter;
+            if field
+                .bytes()
+                .any(|b| matches!(b, b'"' | b'\n' | b'\r') || b == delimiter)
+            {
+                buf.push(b'"');
+                buf.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+                buf.push(b'"');
+            } else {
+                buf.extend_from_slice(field.as_bytes());
+            }
+        }
+        buf.push(b'\n');
+        self.wtr.write_all(&buf)
+    }
+}
+
+impl<W: Write> Printer for Csv<W> {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        self.start()?;
+        let line = Reported::matched(path, m);
+        let path = line.path.unwrap_or("");
+        let number = line.number.to_string();
+        if line.spans.is_empty() {
+            let text = String::from_utf8_lossy(line.text());
+            return self.row([path, &number, "", &text]);
+        }
+        for r in &line.spans {
+            let column = (r.start + 1).to_string();
+            let text = String::from_utf8_lossy(&line.bytes[r.clone()]);
+            self.row([path, &number, &column, &text])?;
+        }
+        Ok(())
+    }
+}
+
+/// A SARIF 2.1.0 log, the format code scanning dashboards take results in.
+///
+/// Each match is a result of the rule for the first pattern matching where
+/// it starts, located by the path of its input and the lines and columns it
+/// spans, counted in Unicode code points; an inverted match is located by
+/// its line alone. The log is one JSON document, ended by [`Sarif::close`].
+/// Context lines are left out.
+#[derive(Debug)]
+pub struct Sarif<W> {
+    wtr: W,
+    /// The id of each rule, and the regex of its pattern.
+    rules: Vec<(String, Regex)>,
+    /// Whether the log up to its results was written.
+    started: bool,
+    /// Whether a result was written, so the next follows a comma.
+    results: bool,
+}
+
+impl<W: Write> Sarif<W> {
+    /// Creates a printer writing to wtr results of rules, each an id and
+    /// the regex of its pattern.
+    pub fn new(wtr: W, rules: Vec<(String, Regex)>) -> Self {
+        Sarif {
+            wtr,
+            rules,
+            started: false,
+            results: false,
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
+    /// Writes what is left of the log after its results.
+    pub fn close(&mut self) -> io::Result<()> {
+        self.start()?;
+        self.wtr.write_all(b"]}]}\n")
+    }
+
+    /// Writes the log up to its results, if that is not done yet.
+    fn start(&mut self) -> io::Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        let mut buf = Vec::new();
+        buf.extend_from_slice(
+            br#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{"tool":{"driver":{"name":"putao","#,
+        );
+        write!(
+            buf,
+            r#""version":"{}","rules":["#,
+            env!("CARGO_PKG_VERSION")
+        )?;
+        for (i, (id, _)) in self.rules.iter().enumerate() {
+            if i > 0 {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(br#"{"id":"#);
+            write_json_str(&mut buf, id)?;
+            buf.extend_from_slice(br#","shortDescription":{"text":"#);
+            write_json_str(&mut buf, &format!("matches {}", id))?;
+            buf.extend_from_slice(b"}}");
+        }
+        buf.extend_from_slice(br#"]}},"columnKind":"unicodeCodePoints","results":["#);
+        self.wtr.write_all(&buf)
+    }
+
+    /// Returns the index of the rule whose pattern matches line at start.
+    fn rule(&self, line: &[u8], start: usize) -> usize {
+        self.rules
+            .iter()
+            .position(|(_, re)| re.find_iter(line).any(|m| m.start() == start))
+            .unwrap_or(0)
+    }
+}
+
+impl<W: Write> Printer for Sarif<W> {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        self.start()?;
+        let reported = Reported::matched(path, m);
+        let (line, text) = (reported.bytes, reported.text());
+        let mut spans: Vec<_> = reported.spans.into_iter().map(Some).collect();
+        if spans.is_empty() {
+            spans.push(None);
+        }
+        let mut buf = Vec::new();
+        for span in spans {
+            buf.clear();
+            if self.results {
+                buf.push(b',');
+            }
+            self.results = true;
+            let rule = span.as_ref().map_or(0, |r| self.rule(line, r.start));
+            let message = match &span {
+                Some(r) => format!("matched {}", String::from_utf8_lossy(&line[r.clone()])),
+                None => "does not match".to_string(),
+            };
+            buf.extend_from_slice(br#"{"ruleId":"#);
+            write_json_str(&mut buf, self.rules.get(rule).map_or("", |r| &r.0))?;
+            write!(
+                buf,
+                r#","ruleIndex":{},"level":"warning","message":{{"text":"#,
+                rule
+            )?;
+            write_json_str(&mut buf, &message)?;
+            buf.extend_from_slice(br#"},"locations":[{"physicalLocation":{"#);
+            if let Some(path) = path {
+                buf.extend_from_slice(br#""artifactLocation":{"uri":"#);
+                write_json_str(&mut buf, &uri(path))?;
+                buf.extend_from_slice(b"},");
+            }
+            let number = reported.number;
+            match &span {
+                Some(r) => {
+                    let (start_line, start_column) = position(line, r.start);
+                    let (end_line, end_column) = position(line, r.end);
+                    write!(
+                        buf,
+                        r#""region":{{"startLine":{},"startColumn":{},"endLine":{},"endColumn":{},"snippet":{{"text":"#,
+                        number + start_line,
+                        start_column,
+                        number + end_line,
+                        end_column
+                    )?;
+                }
+                None => write!(
+                    buf,
+                    r#""region":{{"startLine":{},"snippet":{{"text":"#,
+                    number
+                )?,
+            }
+            write_json_str(&mut buf, &String::from_utf8_lossy(text))?;
+            buf.extend_from_slice(b"}}}}]}");
+            self.wtr.write_all(&buf)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns how many lines into text at is, and its column on that line,
+/// counted in code points from 1.
+fn position(text: &[u8], at: usize) -> (u64, usize) {
+    let before = &text[..at];
+    let (lines, start) = match before.iter().rposition(|&b| b == b'\n') {
+        Some(i) => (before.iter().filter(|&&b| b == b'\n').count() as u64, i + 1),
+        None => (0, 0),
+    };
+    (
+        lines,
+        String::from_utf8_lossy(&before[start..]).chars().count() + 1,
+    )
+}
+
+/// Returns the relative URI reference of path: with `/` separating its
+/// components, and the bytes a URI cannot hold percent-encoded.
+fn uri(path: &str) -> String {
+    let mut out = String::new();
+    for b in path.replace('\\', "/").bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Writes path as a `{"text":...}` object, or `null` if there is none.
+fn write_path(buf: &mut Vec<u8>, path: Option<&str>) -> io::Result<()> {
+    match path {
+        Some(path) => {
+            buf.extend_from_slice(br#"{"text":"#);
+            write_json_str(buf, path)?;
+            buf.extend_from_slice(b"}");
+        }
+        None => buf.extend_from_slice(b"null"),
+    }
+    Ok(())
+}
+
+/// Writes data as a `{"text":...}` object if it is valid UTF-8, and as a
+/// `{"bytes":...}` object holding it in base64 if not.
+fn write_data(buf: &mut Vec<u8>, data: &[u8]) -> io::Result<()> {
+    match std::str::from_utf8(data) {
+        Ok(text) => {
+            buf.extend_from_slice(br#"{"text":"#);
+            write_json_str(buf, text)?;
+        }
+        Err(_) => {
+            buf.extend_from_slice(br#"{"bytes":""#);
+            write_base64(buf, data);
+            buf.push(b'"');
+        }
+    }
+    buf.push(b'}');
+    Ok(())
+}
+
+/// Appends data in standard, padded base64.
+fn write_base64(buf: &mut Vec<u8>, data: &[u8]) {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            buf.push(match i <= chunk.len() {
+                true => DIGITS[(n >> (18 - 6 * i) & 63) as usize],
+                false => b'=',
+            });
+        }
+    }
+}
+
+fn write_duration(buf: &mut Vec<u8>, d: Duration) -> io::Result<()> {
+    write!(
+        buf,
+        r#"{{"secs":{},"nanos":{},"human":"{:.6}s"}}"#,
+        d.as_secs(),
+        d.subsec_nanos(),
+        d.as_secs_f64()
+    )
+}
+
+fn write_stats(buf: &mut Vec<u8>, stats: &Stats) -> io::Result<()> {
+    buf.extend_from_slice(br#"{"elapsed":"#);
+    write_duration(buf, stats.elapsed)?;
+    write!(
+        buf,
+        concat!(
+            r#","searches":{},"searches_with_match":{},"bytes_searched":{},"#,
+            r#""bytes_printed":{},"matched_lines":{},"matches":{}}}"#
+        ),
+        stats.searches,
+        stats.searches_with_match,
+        stats.bytes_searched,
+        stats.bytes_printed,
+        stats.matched_lines,
+        stats.matches
+    )
+}
+
+/// Writes s as a quoted JSON string.
+pub(crate) fn write_json_str<W: Write + ?Sized>(wtr: &mut W, s: &str) -> io::Result<()> {
+    wtr.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => wtr.write_all(b"\\\"")?,
+            '\\' => wtr.write_all(b"\\\\")?,
+            '\n' => wtr.write_all(b"\\n")?,
+            '\r' => wtr.write_all(b"\\r")?,
+            '\t' => wtr.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(wtr, "\\u{:04x}", c as u32)?,
+            c => write!(wtr, "{}", c)?,
+        }
+    }
+    wtr.write_all(b"\"")
+}
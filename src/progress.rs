@@ -0,0 +1,186 @@
+//! `--progress`: a line on standard error saying how far a search got,
+//! redrawn a few times a second while it runs.
+//!
+//! What was searched is counted in globals, as every thread searching adds
+//! to the same totals, and the line is drawn by a thread of its own. Output
+//! to the same terminal goes through [`Clearing`], which takes the line
+//! away first and keeps it away until output ends a line.
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How long to wait between redrawing the line.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STOPPED: AtomicBool = AtomicBool::new(false);
+static FILES: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static MATCHES: AtomicU64 = AtomicU64::new(0);
+/// The label of the input searched last.
+static CURRENT: Mutex<String> = Mutex::new(String::new());
+static DRAWER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+static TERMINAL: Mutex<Terminal> = Mutex::new(Terminal {
+    shown: false,
+    line_start: true,
+});
+
+/// What the terminal shows.
+struct Terminal {
+    /// Whether the progress line is on it.
+    shown: bool,
+    /// Whether output last ended a line, so the progress line may be drawn.
+    line_start: bool,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Starts drawing the progress line, if standard error is a terminal.
+pub fn start() {
+    if !io::stderr().is_terminal() {
+        return;
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+    let width = columns();
+    let started = Instant::now();
+    *lock(&DRAWER) = Some(thread::spawn(move || {
+        while !STOPPED.load(Ordering::Relaxed) {
+            thread::sleep(INTERVAL);
+            draw(started, width);
+        }
+    }));
+}
+
+/// Stops drawing the progress line and takes it away.
+pub fn stop() {
+    STOPPED.store(true, Ordering::Relaxed);
+    if let Some(drawer) = lock(&DRAWER).take() {
+        let _ = drawer.join();
+    }
+    clear();
+}
+
+/// Takes the progress line away until it is next drawn, as before a
+/// message is printed.
+pub fn clear() {
+    let mut terminal = lock(&TERMINAL);
+    if terminal.shown {
+        eprint!("\r\x1b[K");
+        terminal.shown = false;
+    }
+}
+
+/// Counts the input labelled label as searched.
+pub fn searching(label: &str) {
+    if ENABLED.load(Ordering::Relaxed) {
+        FILES.fetch_add(1, Ordering::Relaxed);
+        let mut current = lock(&CURRENT);
+        current.clear();
+        current.push_str(label);
+    }
+}
+
+/// Counts a matching line.
+pub fn matched() {
+    if ENABLED.load(Ordering::Relaxed) {
+        MATCHES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Draws the progress line, unless output is partway through a line.
+fn draw(started: Instant, width: usize) {
+    let mut terminal = lock(&TERMINAL);
+    if !terminal.line_start || STOPPED.load(Ordering::Relaxed) {
+        return;
+    }
+    let files = FILES.load(Ordering::Relaxed);
+    let matches = MATCHES.load(Ordering::Relaxed);
+    let rate = BYTES.load(Ordering::Relaxed) as f64 / started.elapsed().as_secs_f64();
+    let line = format!(
+        "{} file{}, {} matching line{}, {}/s: {}",
+        files,
+        if files == 1 { "" } else { "s" },
+        matches,
+        if matches == 1 { "" } else { "s" },
+        bytes(rate),
+        lock(&CURRENT),
+    );
+    // A line as wide as the terminal would wrap onto the next.
+    let line: String = line.chars().take(width.saturating_sub(1)).collect();
+    eprint!("\r{}\x1b[K", line);
+    terminal.shown = true;
+}
+
+/// Returns n bytes in the largest unit that keeps it at least 1.
+pub fn bytes(n: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut n = n;
+    let mut unit = 0;
+    while n >= 1024.0 && unit + 1 < units.len() {
+        n /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", n, units[unit])
+}
+
+/// Returns how many columns the terminal has.
+fn columns() -> usize {
+    let size = File::open("/dev/tty").and_then(|tty| {
+        Command::new("stty")
+            .arg("size")
+            .stdin(tty)
+            .stderr(Stdio::null())
+            .output()
+    });
+    let size = size.map_or_else(
+        |_| String::new(),
+        |out| String::from_utf8_lossy(&out.stdout).into_owned(),
+    );
+    match size.split_whitespace().nth(1).and_then(|c| c.parse().ok()) {
+        Some(cols) if cols > 0 => cols,
+        _ => 80,
+    }
+}
+
+/// A reader counting the bytes read from it as searched.
+pub struct Counted<R>(pub R);
+
+impl<R: Read> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        if ENABLED.load(Ordering::Relaxed) {
+            BYTES.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        Ok(n)
+    }
+}
+
+/// A writer to the terminal the progress line is on, taking the line away
+/// before anything is written.
+pub struct Clearing<W>(pub W);
+
+impl<W: Write> Write for Clearing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut terminal = lock(&TERMINAL);
+        if terminal.shown {
+            eprint!("\r\x1b[K");
+            terminal.shown = false;
+        }
+        let n = self.0.write(buf)?;
+        if n > 0 {
+            terminal.line_start = buf[n - 1] == b'\n';
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
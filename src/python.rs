@@ -0,0 +1,141 @@
+//! Python bindings via pyo3, enabled by the `python` feature; the crate in
+//! `python/` builds them into an extension module with maturin.
+//!
+//! Offsets reported to Python are in code points, matching `str` indexing.
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::Regex;
+
+/// A compiled pattern, exported to Python as `Regex`.
+#[pyclass(name = "Regex", module = "putao_cf", frozen)]
+pub struct PyRegex(Regex);
+
+/// A match, exported to Python as `Match`.
+#[pyclass(name = "Match", module = "putao_cf", frozen)]
+#[derive(Clone)]
+pub struct PyMatch {
+    #[pyo3(get)]
+    start: usize,
+    #[pyo3(get)]
+    end: usize,
+    #[pyo3(get)]
+    text: String,
+}
+
+/// The groups of a match, exported to Python as `Captures`.
+#[pyclass(name = "Captures", module = "putao_cf", frozen)]
+pub struct PyCaptures {
+    groups: Vec<Option<PyMatch>>,
+    names: Vec<Option<String>>,
+}
+
+/// A group index or name accepted by `Captures[...]`.
+#[derive(FromPyObject)]
+enum Group {
+    Index(usize),
+    Name(String),
+}
+
+impl From<crate::Match<'_>> for PyMatch {
+    fn from(m: crate::Match<'_>) -> Self {
+        PyMatch {
+            start: m.char_start(),
+            end: m.char_end(),
+            text: m.as_str().to_string(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyRegex {
+    /// Compiles a pattern, raising `ValueError` if it is invalid.
+    #[new]
+    fn new(pattern: &str) -> PyResult<Self> {
+        Regex::new(pattern)
+            .map(PyRegex)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// The pattern this regex was compiled from.
+    #[getter]
+    fn pattern(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Reports whether the pattern matches anywhere in the haystack.
+    fn is_match(&self, hay: &str) -> bool {
+        self.0.is_match(hay)
+    }
+
+    /// Returns the leftmost match, or `None`.
+    fn find(&self, hay: &str) -> Option<PyMatch> {
+        self.0.find(hay).map(PyMatch::from)
+    }
+
+    /// Returns every non-overlapping match as a list.
+    fn find_all(&self, hay: &str) -> Vec<PyMatch> {
+        self.0.find_iter(hay).map(PyMatch::from).collect()
+    }
+
+    /// Returns the groups of the leftmost match, or `None`.
+    fn captures(&self, hay: &str) -> Option<PyCaptures> {
+        let caps = self.0.captures(hay)?;
+        Some(PyCaptures {
+            groups: (0..caps.len())
+                .map(|i| caps.get(i).map(PyMatch::from))
+                .collect(),
+            names: self
+                .0
+                .capture_names()
+                .map(|n| n.map(str::to_string))
+                .collect(),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Regex({:?})", self.0.as_str())
+    }
+}
+
+#[pymethods]
+impl PyMatch {
+    fn __repr__(&self) -> String {
+        format!("Match({}, {}, {:?})", self.start, self.end, self.text)
+    }
+}
+
+#[pymethods]
+impl PyCaptures {
+    /// Returns the group with the given index or name, or `None` if it did not match.
+    fn get(&self, group: Group) -> PyResult<Option<PyMatch>> {
+        let i = match group {
+            Group::Index(i) if i < self.groups.len() => i,
+            Group::Index(i) => return Err(PyIndexError::new_err(format!("no group {}", i))),
+            Group::Name(name) => self
+                .names
+                .iter()
+                .position(|n| n.as_deref() == Some(name.as_str()))
+                .ok_or_else(|| PyIndexError::new_err(format!("no group named {}", name)))?,
+        };
+        Ok(self.groups[i].clone())
+    }
+
+    fn __getitem__(&self, group: Group) -> PyResult<Option<PyMatch>> {
+        self.get(group)
+    }
+
+    fn __len__(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+/// The `putao_cf` Python module.
+#[pymodule]
+fn putao_cf(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRegex>()?;
+    m.add_class::<PyMatch>()?;
+    m.add_class::<PyCaptures>()?;
+    Ok(())
+}
@@ -0,0 +1,1018 @@
+//! Compiled patterns and the builder used to configure them.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::mem;
+use core::ops::{Index, Range};
+use core::str::FromStr;
+
+use crate::ast::{Ast, Visitor};
+use crate::compile::{self, Node};
+use crate::error::Error;
+use crate::exec::{self, Caps, Matcher, Scratch};
+use crate::parse;
+use crate::pool::Pool;
+
+/// Options applied when compiling and executing a pattern.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Config {
+    pub(crate) case_insensitive: bool,
+    pub(crate) multi_line: bool,
+    pub(crate) dot_matches_new_line: bool,
+    pub(crate) crlf: bool,
+    pub(crate) unicode: bool,
+    pub(crate) size_limit: usize,
+    pub(crate) backtrack_limit: Option<usize>,
+    pub(crate) state_limit: usize,
+    pub(crate) word: bool,
+    pub(crate) whole_line: bool,
+    pub(crate) literal: bool,
+    pub(crate) basic: bool,
+    pub(crate) posix: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            case_insensitive: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            crlf: false,
+            unicode: false,
+            size_limit: 10 * (1 << 20),
+            backtrack_limit: None,
+            state_limit: 256 << 20,
+            word: false,
+            whole_line: false,
+            literal: false,
+            basic: false,
+            posix: false,
+        }
+    }
+}
+
+/// Configures and compiles a [`Regex`].
+#[derive(Clone, Debug)]
+pub struct RegexBuilder {
+    patterns: Vec<String>,
+    config: Config,
+    smart_case: bool,
+}
+
+impl RegexBuilder {
+    /// Starts a builder for the given pattern with default options.
+    pub fn new(pattern: &str) -> Self {
+        RegexBuilder::new_many([pattern])
+    }
+
+    /// Starts a builder for a regex matching wherever any of the patterns
+    /// does; a match reports the groups of the pattern that matched.
+    pub fn new_many<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        RegexBuilder {
+            patterns: patterns
+                .into_iter()
+                .map(|p| p.as_ref().to_string())
+                .collect(),
+            config: Config::default(),
+            smart_case: false,
+        }
+    }
+
+    /// Matches letters regardless of case.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.config.case_insensitive = yes;
+        self
+    }
+
+    /// Matches letters regardless of case unless a pattern has an uppercase
+    /// letter in it; letters in escapes such as `\W` do not count.
+    pub fn smart_case(&mut self, yes: bool) -> &mut Self {
+        self.smart_case = yes;
+        self
+    }
+
+    /// Lets `^` and `$` match at the start and end of every line.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.config.multi_line = yes;
+        self
+    }
+
+    /// Lets `.` match `\n`.
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut Self {
+        self.config.dot_matches_new_line = yes;
+        self
+    }
+
+    /// Treats `\r\n` as a line terminator: `$` matches before it and `.`
+    /// does not match `\r`.
+    pub fn crlf(&mut self, yes: bool) -> &mut Self {
+        self.config.crlf = yes;
+        self
+    }
+
+    /// Makes `\d`, `\w` and case folding Unicode-aware instead of ASCII-only.
+    pub fn unicode(&mut self, yes: bool) -> &mut Self {
+        self.config.unicode = yes;
+        self
+    }
+
+    /// Sets the approximate size limit, in bytes, of the compiled pattern.
+    pub fn size_limit(&mut self, bytes: usize) -> &mut Self {
+        self.config.size_limit = bytes;
+        self
+    }
+
+    /// Caps the matcher steps spent per search; a search that runs out reports no match.
+    pub fn backtrack_limit(&mut self, steps: usize) -> &mut Self {
+        self.config.backtrack_limit = Some(steps);
+        self
+    }
+
+    /// Sets the approximate limit, in bytes, of the backtracking state a
+    /// search by one of the `try_` methods may keep; one that needs more
+    /// fails with [`Error::StateLimit`]. Other searches are not limited,
+    /// and always run to completion.
+    pub fn state_limit(&mut self, bytes: usize) -> &mut Self {
+        self.config.state_limit = bytes;
+        self
+    }
+
+    /// Only matches that are neither preceded nor followed by a word char.
+    pub fn word(&mut self, yes: bool) -> &mut Self {
+        self.config.word = yes;
+        self
+    }
+
+    /// Only matches spanning the whole haystack, as if the pattern were
+    /// anchored with `^` and `$`.
+    pub fn whole_line(&mut self, yes: bool) -> &mut Self {
+        self.config.whole_line = yes;
+        self
+    }
+
+    /// Matches the pattern as literal text instead of parsing it, with each
+    /// of its lines an alternative.
+    pub fn literal(&mut self, yes: bool) -> &mut Self {
+        self.config.literal = yes;
+        self
+    }
+
+    /// Parses the pattern as a POSIX basic regular expression, where `?`,
+    /// `+`, `|`, `{}` and `()` are literal unless escaped with a backslash.
+    pub fn basic(&mut self, yes: bool) -> &mut Self {
+        self.config.basic = yes;
+        self
+    }
+
+    /// Reads the pattern as POSIX syntax alone, without the Perl extensions
+    /// such as lookaround, lazy quantifiers, `\K` and named groups.
+    pub fn posix(&mut self, yes: bool) -> &mut Self {
+        self.config.posix = yes;
+        self
+    }
+
+    /// Compiles the patterns with the configured options.
+    pub fn build(&self) -> Result<Regex, Error> {
+        if self.smart_case && !self.config.case_insensitive && !self.has_uppercase() {
+            let mut folded = self.clone();
+            folded.config.case_insensitive = true;
+            return folded.build();
+        }
+        let mut groups = Groups(vec![None]);
+        let mut literals = Vec::new();
+        let mut required = Vec::new();
+        let mut progs = Vec::with_capacity(self.patterns.len());
+        for pattern in &self.patterns {
+            progs.push(self.compile(pattern, &mut groups, &mut literals, &mut required)?);
+        }
+        // A pattern that can start with anything leaves nothing to skip to,
+        // and one needing no text leaves no haystack out.
+        if literals.iter().any(Vec::is_empty) {
+            literals.clear();
+        }
+        if required.iter().any(|texts: &Vec<_>| texts.is_empty()) {
+            required.clear();
+        }
+        let (nodes, start) = if progs.len() == 1 {
+            progs.pop().unwrap()
+        } else {
+            // Anchoring the whole search only works if every pattern is
+            // anchored; otherwise the anchored ones check for themselves.
+            let start = !progs.is_empty() && progs.iter().all(|p| p.1);
+            let brs = progs.into_iter().map(|(mut nodes, anchored)| {
+                if anchored && !start {
+                    nodes.insert(0, Node::Start);
+                }
+                nodes
+            });
+            (vec![Node::Alt(brs.collect())], start)
+        };
+        let size = size(&nodes).saturating_mul(mem::size_of::<Node>());
+        if size > self.config.size_limit {
+            return Err(Error::SizeLimit {
+                limit: self.config.size_limit,
+            });
+        }
+        Ok(Regex {
+            pattern: self.patterns.join("\n"),
+            nodes,
+            start,
+            literals,
+            required,
+            names: groups.0,
+            config: self.config.clone(),
+            scratch: Pool::default(),
+        })
+    }
+
+    /// Parses every pattern in the configured syntax, as [`build`] does, for
+    /// inspecting them; a literal pattern reads as a concatenation of
+    /// literals, or an alternation of them if it spans lines.
+    ///
+    /// [`build`]: RegexBuilder::build
+    pub fn asts(&self) -> Result<Vec<Ast>, Error> {
+        if !self.config.literal {
+            return self.patterns.iter().map(|p| self.parse(p)).collect();
+        }
+        let line = |l: &str| Ast::Concat(l.chars().map(Ast::Literal).collect());
+        let asts = self.patterns.iter().map(|p| match p.split_once('\n') {
+            None => line(p),
+            Some(_) => Ast::Alternation(p.split('\n').map(line).collect()),
+        });
+        Ok(asts.collect())
+    }
+
+    /// Parses pattern in the configured syntax; syntax errors carry the
+    /// pattern, with offsets into it as given.
+    fn parse(&self, pattern: &str) -> Result<Ast, Error> {
+        if !self.config.basic {
+            return parse::parse(pattern, self.config.posix)
+                .map_err(|e| e.in_pattern(pattern, |at| at));
+        }
+        let (extended, from) = parse::basic(pattern);
+        parse::parse(&extended, self.config.posix).map_err(|e| {
+            e.in_pattern(pattern, |at| {
+                from.get(at)
+                    .copied()
+                    .unwrap_or_else(|| pattern.chars().count())
+            })
+        })
+    }
+
+    /// Reports whether any pattern matches an uppercase letter literally; a
+    /// pattern that does not parse counts, leaving its error to compiling.
+    fn has_uppercase(&self) -> bool {
+        self.patterns.iter().any(|pattern| {
+            if self.config.literal {
+                return pattern.chars().any(char::is_uppercase);
+            }
+            let Ok(ast) = self.parse(pattern) else {
+                return true;
+            };
+            let mut upper = Uppercase(false);
+            ast.visit(&mut upper);
+            upper.0
+        })
+    }
+
+    /// Reports whether the regex built ignores case, as asked or because
+    /// smart case finds no uppercase letter in the patterns.
+    pub fn ignores_case(&self) -> bool {
+        self.config.case_insensitive || (self.smart_case && !self.has_uppercase())
+    }
+
+    /// Compiles one pattern into nodes and its start anchor flag, recording
+    /// its groups and, if case-sensitive, the text its matches start with and
+    /// the texts they all hold.
+    fn compile(
+        &self,
+        pattern: &str,
+        groups: &mut Groups,
+        literals: &mut Vec<Vec<u8>>,
+        required: &mut Vec<Vec<Vec<u8>>>,
+    ) -> Result<(Vec<Node>, bool), Error> {
+        let (mut nodes, mut start) = if self.config.literal {
+            if !self.config.case_insensitive {
+                literals.extend(pattern.split('\n').map(|l| known(l).as_bytes().to_vec()));
+            }
+            (compile::literal(pattern), false)
+        } else {
+            let ast = self.parse(pattern)?;
+            ast.visit(groups);
+            let (nodes, start) = compile::compile(&ast);
+            if !self.config.case_insensitive {
+                literals.push(prefix(&nodes));
+                required.push(inner(&nodes));
+            }
+            (nodes, start)
+        };
+        if self.config.whole_line {
+            start = true;
+            *nodes.last_mut().unwrap() = Node::Accept(true);
+        }
+        if self.config.word {
+            nodes.insert(0, Node::NotWordBefore);
+            nodes.insert(nodes.len() - 1, Node::NotWordAfter);
+        }
+        Ok((nodes, start))
+    }
+}
+
+/// Collects the name of every capture group, indexed by group.
+struct Groups(Vec<Option<String>>);
+
+impl Visitor for Groups {
+    fn visit_pre(&mut self, ast: &Ast) {
+        if let Ast::Group(g) = ast {
+            if self.0.len() <= g.index {
+                self.0.resize(g.index + 1, None);
+            }
+            // With several patterns, the first name given to an index wins.
+            if self.0[g.index].is_none() {
+                self.0[g.index] = g.name.clone();
+            }
+        }
+    }
+}
+
+/// Notes whether a literal or a class holds an uppercase letter.
+struct Uppercase(bool);
+
+impl Visitor for Uppercase {
+    fn visit_pre(&mut self, ast: &Ast) {
+        self.0 |= match ast {
+            Ast::Literal(c) => c.is_uppercase(),
+            Ast::Class(c) => {
+                let ends = c.ranges.iter().flat_map(|&(lo, hi)| [lo, hi]);
+                c.chars.chars().chain(ends).any(char::is_uppercase)
+            }
+            _ => false,
+        };
+    }
+}
+
+/// A compiled pattern.
+///
+/// With the `serde` feature the compiled program can be serialized and
+/// loaded again without reparsing. Loading checks the data is in the
+/// format this version writes and that its groups are consistent, so no
+/// data can make a search panic; it is not compared with the pattern.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "Compiled", try_from = "Compiled")
+)]
+pub struct Regex {
+    pattern: String,
+    nodes: Vec<Node>,
+    start: bool,
+    /// For patterns matched case-sensitively, the texts a match must start
+    /// with one of, if each has one; candidate starts are found by
+    /// substring search.
+    literals: Vec<Vec<u8>>,
+    /// For patterns matched case-sensitively, the texts a haystack must
+    /// hold every one of to match a pattern, if each pattern needs some.
+    required: Vec<Vec<Vec<u8>>>,
+    names: Vec<Option<String>>,
+    config: Config,
+    scratch: Pool<Scratch>,
+}
+
+/// The version of the format [`Compiled`] is written in, raised whenever
+/// the layout of it or of the nodes changes so that older data is refused
+/// rather than misread.
+#[cfg(feature = "serde")]
+const FORMAT: u32 = 1;
+
+/// A [`Regex`] as serialized: its compiled program, with the version of
+/// the format it is in.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Compiled {
+    format: u32,
+    pattern: String,
+    nodes: Vec<Node>,
+    start: bool,
+    literals: Vec<Vec<u8>>,
+    required: Vec<Vec<Vec<u8>>>,
+    names: Vec<Option<String>>,
+    config: Config,
+}
+
+#[cfg(feature = "serde")]
+impl From<Regex> for Compiled {
+    fn from(re: Regex) -> Self {
+        Compiled {
+            format: FORMAT,
+            pattern: re.pattern,
+            nodes: re.nodes,
+            start: re.start,
+            literals: re.literals,
+            required: re.required,
+            names: re.names,
+            config: re.config,
+        }
+    }
+}
+
+/// Loads a compiled program, refusing one in another format, one whose
+/// groups are not those it names, or one larger than its size limit.
+#[cfg(feature = "serde")]
+impl TryFrom<Compiled> for Regex {
+    type Error = &'static str;
+
+    fn try_from(c: Compiled) -> Result<Regex, &'static str> {
+        if c.format != FORMAT {
+            return Err("compiled regex is in an unsupported format");
+        }
+        if c.names.first() != Some(&None) {
+            return Err("compiled regex does not name group 0");
+        }
+        if !groups_within(&c.nodes, c.names.len()) {
+            return Err("compiled regex refers to a group it does not have");
+        }
+        if size(&c.nodes).saturating_mul(mem::size_of::<Node>()) > c.config.size_limit {
+            return Err("compiled regex exceeds its size limit");
+        }
+        Ok(Regex {
+            pattern: c.pattern,
+            nodes: c.nodes,
+            start: c.start,
+            literals: c.literals,
+            required: c.required,
+            names: c.names,
+            config: c.config,
+            scratch: Pool::default(),
+        })
+    }
+}
+
+/// Searches borrow their scratch from a pool, so a regex can be shared between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Regex>();
+};
+
+impl Regex {
+    /// Compiles a pattern with default options.
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    /// Escapes every metacharacter in text so it matches literally.
+    pub fn escape(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        for c in text.chars() {
+            if parse::is_meta(c) {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// Returns the pattern this regex was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the number of capture groups, including the implicit group 0.
+    pub fn captures_len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Iterates over the name of every group in index order, starting with group 0.
+    pub fn capture_names(&self) -> CaptureNames<'_> {
+        CaptureNames(self.names.iter())
+    }
+
+    /// Returns the index of the group with the given name.
+    pub fn capture_index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n.as_deref() == Some(name))
+    }
+
+    /// Reports whether the pattern matches anywhere in the haystack.
+    pub fn is_match(&self, hay: &str) -> bool {
+        self.search(hay.as_bytes(), 0).is_some()
+    }
+
+    /// Like [`is_match`](Regex::is_match), but fails instead of reporting
+    /// no match if the search runs past the state limit.
+    pub fn try_is_match(&self, hay: &str) -> Result<bool, Error> {
+        Ok(self.try_search(hay.as_bytes(), 0)?.is_some())
+    }
+
+    /// Returns the leftmost match in the haystack.
+    pub fn find<'h>(&self, hay: &'h str) -> Option<Match<'h>> {
+        self.search(hay.as_bytes(), 0)
+            .map(|slots| Match::new(hay, slots[0].unwrap()))
+    }
+
+    /// Iterates over successive non-overlapping matches.
+    pub fn find_iter<'r, 'h>(&'r self, hay: &'h str) -> Matches<'r, 'h> {
+        Matches(hay, Searches::new(self, hay.as_bytes()))
+    }
+
+    /// Returns the capture groups of the leftmost match.
+    pub fn captures<'h>(&self, hay: &'h str) -> Option<Captures<'h>> {
+        self.search(hay.as_bytes(), 0)
+            .map(|slots| Captures { hay, slots })
+    }
+
+    /// Iterates over the capture groups of successive non-overlapping matches.
+    pub fn captures_iter<'r, 'h>(&'r self, hay: &'h str) -> CaptureMatches<'r, 'h> {
+        CaptureMatches(hay, Searches::new(self, hay.as_bytes()))
+    }
+
+    /// Replaces every non-overlapping match with what f computes from its captures.
+    ///
+    /// ```
+    /// use putao_cf::Regex;
+    ///
+    /// let re = Regex::new(r"(\d+)").unwrap();
+    /// let doubled = re.replace_all_with("3 apples, 12 pears", |caps| {
+    ///     (caps[1].parse::<u32>().unwrap() * 2).to_string()
+    /// });
+    /// assert_eq!(doubled, "6 apples, 24 pears");
+    /// ```
+    pub fn replace_all_with<F, T>(&self, hay: &str, mut f: F) -> String
+    where
+        F: FnMut(&Captures<'_>) -> T,
+        T: AsRef<str>,
+    {
+        let mut out = String::with_capacity(hay.len());
+        let mut last = 0;
+        for caps in self.captures_iter(hay) {
+            let m = caps.get(0).unwrap();
+            out.push_str(&hay[last..m.start()]);
+            out.push_str(f(&caps).as_ref());
+            last = m.end();
+        }
+        out.push_str(&hay[last..]);
+        out
+    }
+
+    /// Reports whether the pattern matches at or after byte offset `start`.
+    ///
+    /// Unlike slicing the haystack, `^` still only matches at offset 0.
+    pub fn is_match_at(&self, hay: &str, start: usize) -> bool {
+        self.search(hay.as_bytes(), start).is_some()
+    }
+
+    /// Returns the leftmost match at or after byte offset `start`.
+    pub fn find_at<'h>(&self, hay: &'h str, start: usize) -> Option<Match<'h>> {
+        self.search(hay.as_bytes(), start)
+            .map(|slots| Match::new(hay, slots[0].unwrap()))
+    }
+
+    /// Returns the earliest position at which some match ends.
+    ///
+    /// This may differ from the end of [`Regex::find`]'s match, e.g. `a+`
+    /// on `aaa` gives 1.
+    pub fn shortest_match(&self, hay: &str) -> Option<usize> {
+        self.shortest_match_at(hay, 0)
+    }
+
+    /// Like [`Regex::shortest_match`], starting at byte offset `start`.
+    pub fn shortest_match_at(&self, hay: &str, start: usize) -> Option<usize> {
+        self.shortest(hay.as_bytes(), start)
+    }
+
+    /// Describes how the regex runs, for debugging: the engine, the literals
+    /// candidate starts are found by and the compiled program, a node a line.
+    pub fn explain(&self) -> String {
+        let mut engine = String::from("backtracking");
+        if self.start {
+            engine += ", anchored at the start";
+        }
+        if !self.literals.is_empty() {
+            engine += ", with a literal prefilter";
+        }
+        if !self.required.is_empty() {
+            engine += ", skipping haystacks without a required literal";
+        }
+        let literals: Vec<_> = self
+            .literals
+            .iter()
+            .map(|l| String::from_utf8_lossy(l))
+            .collect();
+        let mut out = format!("engine: {}\nliterals: {:?}\nprogram:\n", engine, literals);
+        for node in &self.nodes {
+            out += &format!("    {:?}\n", node);
+        }
+        out
+    }
+
+    /// Searches hay for the leftmost match as [`Regex::find`] does,
+    /// returning every node tried with the byte offset it was tried at.
+    pub fn trace<'h>(&self, hay: &'h str) -> (Vec<(usize, String)>, Option<Match<'h>>) {
+        let bytes = hay.as_bytes();
+        let mut scratch = Scratch::default();
+        let mut m = Matcher::new(bytes, &self.config, &mut scratch, self.names.len() - 1);
+        m.trace = Some(Vec::new());
+        let found = self.find_start(&mut m, bytes, 0, |m, st| {
+            let (e, mut caps) = m.match_from(st, &self.nodes)?;
+            caps.resize(self.names.len(), None);
+            let kept = caps.pop().flatten().map_or(st, |(k, _)| k);
+            Some(Match::new(hay, (kept, e)))
+        });
+        (m.trace.take().unwrap_or_default(), found)
+    }
+
+    /// Yields candidate start positions at or after `from`.
+    fn starts<'a>(&'a self, hay: &'a [u8], from: usize) -> impl Iterator<Item = usize> + 'a {
+        let multi = self.config.multi_line;
+        // Checked for a whole haystack only, so iterating over its matches
+        // does not search it again each time.
+        let hopeless = from == 0
+            && !self.required.is_empty()
+            && !self
+                .required
+                .iter()
+                .any(|texts| texts.iter().all(|t| contains(hay, t)));
+        let mut next = Some(from).filter(|_| !hopeless);
+        core::iter::from_fn(move || loop {
+            let mut st = next?;
+            if !self.literals.is_empty() {
+                st = find_literal(hay, st, &self.literals)?;
+            }
+            next = match exec::decode(hay, st) {
+                Some((_, w)) if multi || !self.start => Some(st + w),
+                _ => None,
+            };
+            if !self.start || st == 0 || (multi && hay[st - 1] == b'\n') {
+                return Some(st);
+            }
+        })
+    }
+
+    /// Calls f with each candidate start at or after `from` until it gives
+    /// a result, leaving out those a failure at an earlier start rules out.
+    fn find_start<'m, T>(
+        &'m self,
+        m: &mut Matcher<'m>,
+        hay: &[u8],
+        from: usize,
+        mut f: impl FnMut(&mut Matcher<'m>, usize) -> Option<T>,
+    ) -> Option<T> {
+        m.skip_starts(&self.nodes);
+        for st in self.starts(hay, from) {
+            if m.ruled_out(st) {
+                continue;
+            }
+            if let Some(found) = f(m, st) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    /// Finds the leftmost match starting at or after `from`, however much
+    /// backtracking state that takes.
+    ///
+    /// Slot 0 holds the overall match and slot i the span of group i.
+    pub(crate) fn search(&self, hay: &[u8], from: usize) -> Option<Caps> {
+        self.leftmost(hay, from, None).0
+    }
+
+    /// Like [`search`](Regex::search), failing if the state limit is hit.
+    pub(crate) fn try_search(&self, hay: &[u8], from: usize) -> Result<Option<Caps>, Error> {
+        let limit = self.config.state_limit;
+        match self.leftmost(hay, from, Some(limit)) {
+            (_, true) => Err(Error::StateLimit { limit }),
+            (found, false) => Ok(found),
+        }
+    }
+
+    /// Finds the leftmost match starting at or after `from` keeping at
+    /// most limit bytes of backtracking state, and whether it ran out.
+    fn leftmost(&self, hay: &[u8], from: usize, limit: Option<usize>) -> (Option<Caps>, bool) {
+        self.scratch.with(|scratch| {
+            let mut m = Matcher::new(hay, &self.config, scratch, self.names.len() - 1);
+            m.state_limit = limit;
+            let found = self.find_start(&mut m, hay, from, |m, st| {
+                let (e, mut caps) = m.match_from(st, &self.nodes)?;
+                caps.resize(self.names.len(), None);
+                let kept = caps.pop().flatten().map_or(st, |(k, _)| k);
+                caps.insert(0, Some((kept, e)));
+                Some(caps)
+            });
+            (found, m.overflowed)
+        })
+    }
+
+    /// Finds the earliest end of any match starting at or after `from`.
+    pub(crate) fn shortest(&self, hay: &[u8], from: usize) -> Option<usize> {
+        self.scratch.with(|scratch| {
+            let mut m = Matcher::new(hay, &self.config, scratch, self.names.len() - 1);
+            m.shortest = Some(usize::MAX);
+            m.skip_starts(&self.nodes);
+            for st in self.starts(hay, from) {
+                if m.shortest.is_some_and(|best| best <= st) {
+                    break;
+                }
+                if !m.ruled_out(st) {
+                    m.match_from(st, &self.nodes);
+                }
+            }
+            m.shortest.filter(|&best| best != usize::MAX)
+        })
+    }
+}
+
+/// Compiles a pattern with default options.
+impl FromStr for Regex {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Regex, Error> {
+        Regex::new(s)
+    }
+}
+
+impl TryFrom<&str> for Regex {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Regex, Error> {
+        Regex::new(s)
+    }
+}
+
+impl TryFrom<String> for Regex {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Regex, Error> {
+        Regex::new(&s)
+    }
+}
+
+/// Writes the pattern the regex was compiled from.
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+/// A single match of a regex in a haystack.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match<'h> {
+    hay: &'h str,
+    start: usize,
+    end: usize,
+}
+
+impl<'h> Match<'h> {
+    fn new(hay: &'h str, (start, end): (usize, usize)) -> Self {
+        Match { hay, start, end }
+    }
+
+    /// Returns the byte offset where the match starts.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the byte offset just past the end of the match.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the byte range of the match.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the char offset where the match starts, for column reporting.
+    pub fn char_start(&self) -> usize {
+        self.hay[..self.start].chars().count()
+    }
+
+    /// Returns the char offset just past the end of the match.
+    pub fn char_end(&self) -> usize {
+        self.char_start() + self.as_str().chars().count()
+    }
+
+    /// Returns the char range of the match.
+    pub fn char_range(&self) -> Range<usize> {
+        let start = self.char_start();
+        start..start + self.as_str().chars().count()
+    }
+
+    /// Returns the matched text.
+    pub fn as_str(&self) -> &'h str {
+        &self.hay[self.range()]
+    }
+}
+
+/// The capture groups of a single match; group 0 is the whole match.
+#[derive(Clone, Debug)]
+pub struct Captures<'h> {
+    hay: &'h str,
+    slots: Caps,
+}
+
+impl<'h> Captures<'h> {
+    /// Returns group i, or `None` if it did not participate in the match.
+    pub fn get(&self, i: usize) -> Option<Match<'h>> {
+        self.slots
+            .get(i)
+            .copied()
+            .flatten()
+            .map(|s| Match::new(self.hay, s))
+    }
+
+    /// Returns the number of groups, including group 0.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Always false: group 0 is always present.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Returns the text of group i; panics if the group did not match.
+impl Index<usize> for Captures<'_> {
+    type Output = str;
+
+    fn index(&self, i: usize) -> &str {
+        self.get(i)
+            .map(|m| m.as_str())
+            .unwrap_or_else(|| panic!("no group at index {}", i))
+    }
+}
+
+/// Iterator returned by [`Regex::capture_names`].
+#[derive(Clone, Debug)]
+pub struct CaptureNames<'r>(core::slice::Iter<'r, Option<String>>);
+
+impl<'r> Iterator for CaptureNames<'r> {
+    type Item = Option<&'r str>;
+
+    fn next(&mut self) -> Option<Option<&'r str>> {
+        self.0.next().map(|n| n.as_deref())
+    }
+}
+
+/// Shared state for iterating over non-overlapping matches.
+#[derive(Debug)]
+pub(crate) struct Searches<'r, 'h> {
+    re: &'r Regex,
+    hay: &'h [u8],
+    pos: usize,
+    last_end: Option<usize>,
+}
+
+impl<'r, 'h> Searches<'r, 'h> {
+    pub(crate) fn new(re: &'r Regex, hay: &'h [u8]) -> Self {
+        Searches {
+            re,
+            hay,
+            pos: 0,
+            last_end: None,
+        }
+    }
+
+    /// Returns the next match, skipping empty matches that abut the previous one.
+    pub(crate) fn next_slots(&mut self) -> Option<Caps> {
+        let hay = self.hay;
+        loop {
+            if self.pos > hay.len() {
+                return None;
+            }
+            let slots = self.re.search(hay, self.pos)?;
+            let (s, e) = slots[0].unwrap();
+            let step = exec::decode(hay, e).map_or(1, |(_, w)| w);
+            if s == e && self.last_end == Some(e) {
+                self.pos = e + step;
+                continue;
+            }
+            self.pos = if s == e { e + step } else { e };
+            self.last_end = Some(e);
+            return Some(slots);
+        }
+    }
+}
+
+/// Iterator returned by [`Regex::find_iter`].
+#[derive(Debug)]
+pub struct Matches<'r, 'h>(&'h str, Searches<'r, 'h>);
+
+impl<'h> Iterator for Matches<'_, 'h> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        let hay = self.0;
+        self.1.next_slots().map(|s| Match::new(hay, s[0].unwrap()))
+    }
+}
+
+/// Iterator returned by [`Regex::captures_iter`].
+#[derive(Debug)]
+pub struct CaptureMatches<'r, 'h>(&'h str, Searches<'r, 'h>);
+
+impl<'h> Iterator for CaptureMatches<'_, 'h> {
+    type Item = Captures<'h>;
+
+    fn next(&mut self) -> Option<Captures<'h>> {
+        let hay = self.0;
+        self.1.next_slots().map(|slots| Captures { hay, slots })
+    }
+}
+
+/// Returns the text every match of nodes starts with, from the literals
+/// they begin with, short of any U+FFFD as for [`known`].
+fn prefix(nodes: &[Node]) -> Vec<u8> {
+    let mut text = String::new();
+    let skip = matches!(nodes.first(), Some(Node::NotWordBefore));
+    for node in &nodes[skip as usize..] {
+        match node {
+            Node::Lit(c) if *c != char::REPLACEMENT_CHARACTER => text.push(*c),
+            _ => break,
+        }
+    }
+    text.into_bytes()
+}
+
+/// Returns the start of text up to any U+FFFD, which also matches invalid
+/// UTF-8 and so cannot be searched for as is.
+fn known(text: &str) -> &str {
+    text.split(char::REPLACEMENT_CHARACTER)
+        .next()
+        .unwrap_or_default()
+}
+
+/// Returns the runs of literals every match of nodes holds, from those
+/// outside any repetition, alternation or group, split at any U+FFFD.
+fn inner(nodes: &[Node]) -> Vec<Vec<u8>> {
+    let mut runs = vec![Vec::new()];
+    for node in nodes {
+        match (node, runs.last_mut()) {
+            (Node::Lit(c), Some(run)) if *c != char::REPLACEMENT_CHARACTER => {
+                run.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            _ => runs.push(Vec::new()),
+        }
+    }
+    runs.retain(|run| !run.is_empty());
+    runs
+}
+
+/// Reports whether needle occurs in hay.
+fn contains(hay: &[u8], needle: &[u8]) -> bool {
+    hay.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Returns the first position at or after from where any of lits starts.
+fn find_literal(hay: &[u8], from: usize, lits: &[Vec<u8>]) -> Option<usize> {
+    (from..=hay.len()).find(|&i| lits.iter().any(|l| hay[i..].starts_with(l)))
+}
+
+/// Reports whether every group nodes capture or refer back to is one of
+/// the count there are, past group 0.
+#[cfg(feature = "serde")]
+fn groups_within(nodes: &[Node], count: usize) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Ref(i) => (1..count).contains(i),
+        Node::Cap(i, brs) => (1..count).contains(i) && brs.iter().all(|b| groups_within(b, count)),
+        Node::Alt(brs) | Node::Ahead(_, brs) | Node::Behind(_, brs, _) => {
+            brs.iter().all(|b| groups_within(b, count))
+        }
+        Node::Opt(n)
+        | Node::Plus(n)
+        | Node::Star(n)
+        | Node::Rep(n, _)
+        | Node::MinRep(n, _)
+        | Node::RangeRep(n, ..)
+        | Node::Lazy(n, ..) => groups_within(core::slice::from_ref(&**n), count),
+        _ => true,
+    })
+}
+
+/// Counts nodes, multiplying counted repetitions out.
+fn size(nodes: &[Node]) -> usize {
+    nodes.iter().map(node_size).fold(0, usize::saturating_add)
+}
+
+fn node_size(node: &Node) -> usize {
+    match node {
+        Node::Opt(n) | Node::Plus(n) | Node::Star(n) => 1 + node_size(n),
+        Node::Rep(n, k) | Node::MinRep(n, k) | Node::RangeRep(n, _, k) => {
+            node_size(n).saturating_mul((*k).max(1)).saturating_add(1)
+        }
+        Node::Lazy(n, min, max) => node_size(n)
+            .saturating_mul(max.unwrap_or(*min).max(1))
+            .saturating_add(1),
+        Node::Cap(_, brs) | Node::Alt(brs) | Node::Ahead(_, brs) | Node::Behind(_, brs, _) => {
+            brs.iter().map(|b| size(b)).fold(1, usize::saturating_add)
+        }
+        _ => 1,
+    }
+}
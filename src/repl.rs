@@ -0,0 +1,142 @@
+//! `--repl`: trying patterns out against sample text, one per line read,
+//! before putting them in a script.
+//!
+//! Each line read is compiled with the flags given and run over every line
+//! of the sample; those matching are printed with their matches marked,
+//! followed by each match's span and the value of every group. A few lines
+//! starting with `:` are commands instead: `:add TEXT` appends a line to the
+//! sample, `:clear` empties it, `:sample` prints it and `:quit` ends.
+
+use std::io::{self, BufRead, Write};
+
+use putao_cf::printer::Colors;
+use putao_cf::{Error, Regex};
+
+use crate::interrupt::interrupted;
+
+/// What is printed before reading each line, if input is a terminal.
+const PROMPT: &str = "> ";
+
+/// A session matching patterns against sample lines.
+pub struct Repl<F> {
+    sample: Vec<String>,
+    compile: F,
+    colors: Option<Colors>,
+    prompt: bool,
+}
+
+impl<F: FnMut(&str) -> Result<Regex, Error>> Repl<F> {
+    /// Starts a session matching against sample, compiling each pattern
+    /// with compile.
+    pub fn new(sample: Vec<String>, compile: F) -> Self {
+        Repl {
+            sample,
+            compile,
+            colors: None,
+            prompt: false,
+        }
+    }
+
+    /// Marks matches in the colors given, if any, rather than with brackets.
+    pub fn colors(&mut self, colors: Option<Colors>) -> &mut Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Prints a prompt before reading each line.
+    pub fn prompt(&mut self, yes: bool) -> &mut Self {
+        self.prompt = yes;
+        self
+    }
+
+    /// Reads lines from input until it ends or `:quit`, writing what each
+    /// shows to out.
+    pub fn run<R: BufRead, W: Write>(&mut self, mut input: R, mut out: W) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            if self.prompt {
+                write!(out, "{}", PROMPT)?;
+                out.flush()?;
+            }
+            line.clear();
+            if input.read_line(&mut line)? == 0 || interrupted() {
+                break;
+            }
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            match line {
+                "" => {}
+                ":quit" => break,
+                ":clear" => self.sample.clear(),
+                ":sample" => {
+                    for (n, text) in self.sample.iter().enumerate() {
+                        writeln!(out, "{}:{}", n + 1, text)?;
+                    }
+                }
+                _ => match line.strip_prefix(":add ") {
+                    Some(text) => self.sample.push(text.to_string()),
+                    None => self.try_pattern(line, &mut out)?,
+                },
+            }
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Compiles pattern and shows what it matches in the sample, or why it
+    /// does not compile.
+    fn try_pattern<W: Write>(&mut self, pattern: &str, out: &mut W) -> io::Result<()> {
+        let re = match (self.compile)(pattern) {
+            Ok(re) => re,
+            Err(e) => return writeln!(out, "{}", e),
+        };
+        let names: Vec<_> = re.capture_names().collect();
+        let (mut matches, mut lines) = (0, 0);
+        for (n, text) in self.sample.iter().enumerate() {
+            let found: Vec<_> = re.captures_iter(text).collect();
+            if found.is_empty() {
+                continue;
+            }
+            lines += 1;
+            matches += found.len();
+            write!(out, "{}:", n + 1)?;
+            let mut last = 0;
+            for caps in &found {
+                let m = caps.get(0).unwrap();
+                write!(out, "{}", &text[last..m.start()])?;
+                self.mark(m.as_str(), out)?;
+                last = m.end();
+            }
+            writeln!(out, "{}", &text[last..])?;
+            for caps in &found {
+                let m = caps.get(0).unwrap();
+                write!(out, "  {}..{} {:?}", m.start(), m.end(), m.as_str())?;
+                for (i, name) in names.iter().enumerate().skip(1) {
+                    match name {
+                        Some(name) => write!(out, "  {}=", name)?,
+                        None => write!(out, "  {}=", i)?,
+                    }
+                    match caps.get(i) {
+                        Some(group) => write!(out, "{:?}", group.as_str())?,
+                        None => write!(out, "-")?,
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+        match (matches, lines) {
+            (0, _) => writeln!(out, "no match"),
+            (1, _) => writeln!(out, "1 match"),
+            (_, 1) => writeln!(out, "{} matches on 1 line", matches),
+            _ => writeln!(out, "{} matches on {} lines", matches, lines),
+        }
+    }
+
+    /// Writes the text of a match set apart from the rest of its line.
+    fn mark<W: Write>(&self, text: &str, out: &mut W) -> io::Result<()> {
+        match &self.colors {
+            Some(colors) => write!(out, "\x1b[{}m\x1b[K{}\x1b[m\x1b[K", colors.matched, text),
+            None => write!(out, "[{}]", text),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! `--only`: searching only the comments, the string literals or the rest
+//! of source files, as a tokenizer for their language tells them apart.
+//!
+//! The tokenizers are lightweight: they know how each language writes
+//! comments and quotes strings, and nothing else, so code that only a full
+//! parser would read right, such as a shell here-document, may be split
+//! wrongly. The language of a file is that of its `--type`.
+
+use std::ops::Range;
+
+/// The part of source files to search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Only {
+    Comments,
+    Strings,
+    /// What is neither a comment nor a string.
+    Code,
+}
+
+/// The languages a tokenizer is known for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    /// Languages writing comments and strings as C does.
+    C,
+    Python,
+    /// Languages commenting with `#`, and quoting as shells do.
+    Shell,
+}
+
+/// The file types whose files are tokenized, with their languages.
+pub const LANGUAGES: &[(&str, Language)] = &[
+    ("rust", Language::Rust),
+    ("c", Language::C),
+    ("cpp", Language::C),
+    ("cs", Language::C),
+    ("css", Language::C),
+    ("go", Language::C),
+    ("java", Language::C),
+    ("js", Language::C),
+    ("php", Language::C),
+    ("swift", Language::C),
+    ("ts", Language::C),
+    ("py", Language::Python),
+    ("sh", Language::Shell),
+    ("cmake", Language::Shell),
+    ("docker", Language::Shell),
+    ("make", Language::Shell),
+    ("toml", Language::Shell),
+    ("yaml", Language::Shell),
+];
+
+/// A piece of source that is not code.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Comment,
+    String,
+}
+
+/// Returns the byte ranges of data, source in language, that only selects.
+pub fn ranges(only: Only, language: Language, data: &[u8]) -> Vec<Range<u64>> {
+    let tokens = tokenize(language, data);
+    let kind = match only {
+        Only::Comments => Token::Comment,
+        Only::Strings => Token::String,
+        Only::Code => {
+            let mut code = Vec::new();
+            let mut at = 0;
+            for (_, r) in tokens {
+                if at < r.start {
+                    code.push(at as u64..r.start as u64);
+                }
+                at = r.end;
+            }
+            if at < data.len() {
+                code.push(at as u64..data.len() as u64);
+            }
+            return code;
+        }
+    };
+    tokens
+        .into_iter()
+        .filter(|(token, _)| *token == kind)
+        .map(|(_, r)| r.start as u64..r.end as u64)
+        .collect()
+}
+
+/// Splits the comments and strings out of data, in order, their markers
+/// and quotes included.
+fn tokenize(language: Language, data: &[u8]) -> Vec<(Token, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let rest = &data[i..];
+        let after_word = i > 0 && !word_start(data[i - 1]);
+        let found = match (language, rest[0]) {
+            (Language::Rust | Language::C, b'/') if rest.starts_with(b"//") => {
+                Some((Token::Comment, line_end(rest)))
+            }
+            (Language::Rust | Language::C, b'/') if rest.starts_with(b"/*") => {
+                let nested = language == Language::Rust;
+                Some((Token::Comment, block_comment(rest, nested)))
+            }
+            (Language::Rust, b'r') if i == 0 || !ident(data[i - 1]) || prefixed(data, i) => {
+                raw_string(rest).map(|len| (Token::String, len))
+            }
+            (Language::Rust, b'"') => Some((Token::String, quoted(rest, b'"', true, false))),
+            (Language::Rust, b'\'') => char_literal(rest).map(|len| (Token::String, len)),
+            (Language::C, b'"' | b'\'') => Some((Token::String, quoted(rest, rest[0], true, true))),
+            (Language::C, b'`') => Some((Token::String, quoted(rest, b'`', true, false))),
+            (Language::Python, b'#') => Some((Token::Comment, line_end(rest))),
+            (Language::Python, b'"' | b'\'') => Some((Token::String, python_string(rest))),
+            (Language::Shell, b'#') if !after_word => Some((Token::Comment, line_end(rest))),
+            (Language::Shell, b'\'') => Some((Token::String, quoted(rest, b'\'', false, false))),
+            (Language::Shell, b'"') => Some((Token::String, quoted(rest, b'"', true, false))),
+            _ => None,
+        };
+        match found {
+            Some((token, len)) => {
+                tokens.push((token, i..i + len));
+                i += len;
+            }
+            // An escaped character in a shell script starts nothing.
+            None if language == Language::Shell && rest[0] == b'\\' => i += 2,
+            None => i += 1,
+        }
+    }
+    tokens
+}
+
+/// Returns the length of the line rest starts, without its terminator.
+fn line_end(rest: &[u8]) -> usize {
+    rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len())
+}
+
+/// Returns the length of the block comment rest starts; Rust's nest.
+fn block_comment(rest: &[u8], nested: bool) -> usize {
+    let (mut depth, mut i) = (1, 2);
+    while i < rest.len() {
+        if rest[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else if nested && rest[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    rest.len()
+}
+
+/// Returns the length of the string rest starts, up to the next close
+/// quote not escaped with a backslash, if escapes are read, or the end of
+/// its line if one_line.
+fn quoted(rest: &[u8], close: u8, escapes: bool, one_line: bool) -> usize {
+    let mut i = 1;
+    while i < rest.len() {
+        match rest[i] {
+            b'\\' if escapes => i += 1,
+            b'\n' if one_line => return i,
+            b if b == close => return i + 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    rest.len()
+}
+
+/// Returns the length of the Rust raw string rest starts, as `r"..."` or
+/// `r#"..."#`, if it starts one.
+fn raw_string(rest: &[u8]) -> Option<usize> {
+    let hashes = rest[1..].iter().take_while(|&&b| b == b'#').count();
+    if rest.get(1 + hashes) != Some(&b'"') {
+        return None;
+    }
+    let body = 2 + hashes;
+    let mut close = vec![b'"'];
+    close.extend(std::iter::repeat(b'#').take(hashes));
+    let end = rest[body..]
+        .windows(close.len())
+        .position(|w| w == close.as_slice())
+        .map_or(rest.len(), |at| body + at + close.len());
+    Some(end)
+}
+
+/// Returns the length of the Rust character literal rest starts, unless
+/// its quote starts a lifetime or label instead.
+fn char_literal(rest: &[u8]) -> Option<usize> {
+    if rest.get(1) == Some(&b'\\') {
+        return Some(quoted(rest, b'\'', true, true));
+    }
+    // One character, of however many bytes, then the close quote.
+    let len = match rest.get(1) {
+        Some(&b) if b < 0x80 => 1,
+        Some(&b) if b >= 0xf0 => 4,
+        Some(&b) if b >= 0xe0 => 3,
+        Some(_) => 2,
+        None => return None,
+    };
+    (rest.get(1 + len) == Some(&b'\'')).then_some(2 + len)
+}
+
+/// Returns the length of the Python string rest starts, tripled quotes
+/// running across lines and single ones to the end of theirs.
+fn python_string(rest: &[u8]) -> usize {
+    let quote = rest[0];
+    let triple = [quote; 3];
+    if !rest.starts_with(&triple) {
+        return quoted(rest, quote, true, true);
+    }
+    let mut i = 3;
+    while i < rest.len() {
+        if rest[i] == b'\\' {
+            i += 2;
+        } else if rest[i..].starts_with(&triple) {
+            return i + 3;
+        } else {
+            i += 1;
+        }
+    }
+    rest.len()
+}
+
+/// Reports whether b may be part of an identifier.
+fn ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Reports whether the `r` at i in data follows a `b` prefix, as in
+/// `br"..."`, rather than ending an identifier.
+fn prefixed(data: &[u8], i: usize) -> bool {
+    data[i - 1] == b'b' && (i < 2 || !ident(data[i - 2]))
+}
+
+/// Reports whether a word starts after b, as a shell comment must.
+fn word_start(b: u8) -> bool {
+    b.is_ascii_whitespace() || matches!(b, b';' | b'|' | b'&' | b'(' | b')')
+}
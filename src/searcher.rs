@@ -0,0 +1,940 @@
+//! Line-oriented search over readers, reporting to a [`Sink`].
+//!
+//! The [`Searcher`] frames input into lines, runs the regex over each one
+//! and tracks the before/after context around matches, so consumers only
+//! decide what to do with each reported line.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::bytes::{Captures, Regex};
+use crate::encoding::{Decoder, Encoding};
+use crate::filter::Filter;
+use crate::normalize::nfc_bytes;
+use crate::walk::Walker;
+
+/// Receives the lines reported by a [`Searcher`].
+///
+/// Every method returns whether the search should continue; returning
+/// `Ok(false)` stops reading the input early.
+pub trait Sink {
+    /// Called for every matching line.
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool>;
+
+    /// Called for every context line around a match.
+    fn context(&mut self, _ctx: &SinkContext<'_>) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    /// Called between two non-contiguous groups of reported lines.
+    fn context_break(&mut self) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    /// Called once, before any line holding it is reported, when binary
+    /// detection finds a NUL byte at the given offset.
+    fn binary_data(&mut self, _offset: u64) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    /// Called once before anything is read.
+    fn begin(&mut self) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    /// Called once the search ends, early or not, unless reading failed.
+    fn finish(&mut self, _finish: &SinkFinish) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        (**self).matched(m)
+    }
+
+    fn context(&mut self, ctx: &SinkContext<'_>) -> io::Result<bool> {
+        (**self).context(ctx)
+    }
+
+    fn context_break(&mut self) -> io::Result<bool> {
+        (**self).context_break()
+    }
+
+    fn binary_data(&mut self, offset: u64) -> io::Result<bool> {
+        (**self).binary_data(offset)
+    }
+
+    fn begin(&mut self) -> io::Result<bool> {
+        (**self).begin()
+    }
+
+    fn finish(&mut self, finish: &SinkFinish) -> io::Result<()> {
+        (**self).finish(finish)
+    }
+}
+
+/// What is known about an input once its search ends.
+#[derive(Clone, Debug, Default)]
+pub struct SinkFinish {
+    byte_count: u64,
+    binary_byte_offset: Option<u64>,
+    long_lines: u64,
+}
+
+impl SinkFinish {
+    /// The number of bytes read from the input.
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// The offset of the NUL byte binary detection found, if any.
+    pub fn binary_byte_offset(&self) -> Option<u64> {
+        self.binary_byte_offset
+    }
+
+    /// The number of lines left unsearched for being over the length limit.
+    pub fn long_lines(&self) -> u64 {
+        self.long_lines
+    }
+}
+
+/// A matching line, or in multi-line mode the lines a match spans.
+#[derive(Clone, Debug)]
+pub struct SinkMatch<'b> {
+    bytes: &'b [u8],
+    /// The length of the line without its terminator.
+    len: usize,
+    /// The regex that matched, unless the search is inverted.
+    re: Option<&'b Regex>,
+    /// Where in the input matches count, if limited.
+    only_bytes: Option<&'b [Range<u64>]>,
+    /// What the groups of matches that count make true, if limited.
+    filter: Option<&'b Filter>,
+    line_number: u64,
+    absolute_byte_offset: u64,
+}
+
+impl<'b> SinkMatch<'b> {
+    /// The line, including its terminator if it had one.
+    pub fn bytes(&self) -> &'b [u8] {
+        self.bytes
+    }
+
+    /// The line without its terminator, as the regex saw it.
+    pub fn line(&self) -> &'b [u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// The byte ranges of every match within the line; none for lines
+    /// reported by an inverted search.
+    pub fn matches(&self) -> impl Iterator<Item = Range<usize>> + 'b {
+        let line = self.line();
+        let this = self.clone();
+        self.re
+            .into_iter()
+            .flat_map(move |re| re.captures_iter(line))
+            .filter(move |caps| this.counts(caps))
+            .map(|caps| caps.get(0).unwrap().range())
+    }
+
+    /// Whether the match whose groups within the line are caps counts,
+    /// lying where [`Searcher::only_bytes`] allows and kept by
+    /// [`Searcher::filter`].
+    pub fn counts(&self, caps: &Captures<'_>) -> bool {
+        counts(
+            self.only_bytes,
+            self.filter,
+            self.absolute_byte_offset,
+            caps,
+        )
+    }
+
+    /// The regex that matched, unless the search is inverted.
+    pub fn regex(&self) -> Option<&'b Regex> {
+        self.re
+    }
+
+    /// The 1-based line number.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// The byte offset of the start of the line in the input.
+    pub fn absolute_byte_offset(&self) -> u64 {
+        self.absolute_byte_offset
+    }
+}
+
+/// Whether a context line precedes or follows a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SinkContextKind {
+    /// A line before a match.
+    Before,
+    /// A line after a match.
+    After,
+    /// Any other line not matching, reported in passthru mode.
+    Other,
+}
+
+/// A context line near a match.
+#[derive(Clone, Debug)]
+pub struct SinkContext<'b> {
+    bytes: &'b [u8],
+    kind: SinkContextKind,
+    line_number: u64,
+    absolute_byte_offset: u64,
+}
+
+impl<'b> SinkContext<'b> {
+    /// The line, including its terminator if it had one.
+    pub fn bytes(&self) -> &'b [u8] {
+        self.bytes
+    }
+
+    /// Whether the line comes before or after a match.
+    pub fn kind(&self) -> SinkContextKind {
+        self.kind
+    }
+
+    /// The 1-based line number.
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// The byte offset of the start of the line in the input.
+    pub fn absolute_byte_offset(&self) -> u64 {
+        self.absolute_byte_offset
+    }
+}
+
+/// Searches inputs line by line.
+#[derive(Clone, Debug)]
+pub struct Searcher {
+    line_terminator: u8,
+    any_newline: bool,
+    before_context: usize,
+    after_context: usize,
+    invert_match: bool,
+    max_count: Option<u64>,
+    binary_detection: bool,
+    passthru: bool,
+    multi_line: bool,
+    encoding: Option<Encoding>,
+    bom_sniffing: bool,
+    detect_encoding: bool,
+    max_line_length: Option<usize>,
+    nfc: bool,
+    /// The only lines that may be selected, by number, if limited.
+    only_lines: Option<Vec<Range<u64>>>,
+    /// The only bytes matches may lie in, by offset, if limited.
+    only_bytes: Option<Vec<Range<u64>>>,
+    /// What the groups of matches that count make true, if limited.
+    filter: Option<Filter>,
+    /// The regexes a line must match too to be treated as matching.
+    required: Vec<Regex>,
+    /// The regex lines treated as not matching match, if any.
+    excluded: Option<Regex>,
+    /// The line number and byte offset the input starts at.
+    start: (u64, u64),
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Searcher {
+            line_terminator: b'\n',
+            any_newline: false,
+            before_context: 0,
+            after_context: 0,
+            invert_match: false,
+            max_count: None,
+            binary_detection: false,
+            passthru: false,
+            multi_line: false,
+            encoding: None,
+            bom_sniffing: false,
+            detect_encoding: false,
+            max_line_length: None,
+            nfc: false,
+            only_lines: None,
+            only_bytes: None,
+            filter: None,
+            required: Vec::new(),
+            excluded: None,
+            start: (1, 0),
+        }
+    }
+}
+
+/// A buffered line remembered for before-context.
+struct Line {
+    bytes: Vec<u8>,
+    number: u64,
+    offset: u64,
+}
+
+impl Searcher {
+    /// Creates a searcher for `\n`-terminated lines without context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the byte that ends each line.
+    pub fn line_terminator(&mut self, byte: u8) -> &mut Self {
+        self.line_terminator = byte;
+        self
+    }
+
+    /// Ends lines at `\n`, `\r\n` or a lone `\r`, so files with classic Mac
+    /// or mixed line endings split into lines; the line terminator byte is
+    /// then not used.
+    pub fn any_newline(&mut self, yes: bool) -> &mut Self {
+        self.any_newline = yes;
+        self
+    }
+
+    /// Reports up to n lines before each match as context.
+    pub fn before_context(&mut self, n: usize) -> &mut Self {
+        self.before_context = n;
+        self
+    }
+
+    /// Reports up to n lines after each match as context.
+    pub fn after_context(&mut self, n: usize) -> &mut Self {
+        self.after_context = n;
+        self
+    }
+
+    /// Reports the lines that do not match instead of those that do.
+    pub fn invert_match(&mut self, yes: bool) -> &mut Self {
+        self.invert_match = yes;
+        self
+    }
+
+    /// Stops after n matching lines, once their trailing context is reported.
+    pub fn max_count(&mut self, n: Option<u64>) -> &mut Self {
+        self.max_count = n;
+        self
+    }
+
+    /// Reports a NUL byte in the input to [`Sink::binary_data`], looking at
+    /// the first buffer read and then every line.
+    pub fn binary_detection(&mut self, yes: bool) -> &mut Self {
+        self.binary_detection = yes;
+        self
+    }
+
+    /// Reports every line that does not match as context, so the sink sees
+    /// the whole input.
+    pub fn passthru(&mut self, yes: bool) -> &mut Self {
+        self.passthru = yes;
+        self
+    }
+
+    /// Runs the regex over the whole input instead of each line, so a match
+    /// may span lines; the lines it touches are reported as one match.
+    pub fn multi_line(&mut self, yes: bool) -> &mut Self {
+        self.multi_line = yes;
+        self
+    }
+
+    /// Leaves lines longer than len bytes, not counting the terminator,
+    /// unsearched, so they are never selected; they may still be shown as
+    /// context. [`SinkFinish::long_lines`] counts them.
+    pub fn max_line_length(&mut self, len: Option<usize>) -> &mut Self {
+        self.max_line_length = len;
+        self
+    }
+
+    /// Puts input in Unicode normalization form C before searching it, so
+    /// what is reported is the normalized text; byte offsets count the
+    /// input as read, except in multi-line mode.
+    pub fn nfc(&mut self, yes: bool) -> &mut Self {
+        self.nfc = yes;
+        self
+    }
+
+    /// Selects only lines numbered within one of ranges, if given, the
+    /// rest being treated as neither matching nor, inverted, not matching;
+    /// they may still be shown as context.
+    pub fn only_lines(&mut self, ranges: Option<Vec<Range<u64>>>) -> &mut Self {
+        self.only_lines = ranges;
+        self
+    }
+
+    /// Counts only matches lying wholly within one of ranges, if given, by
+    /// byte offset; the ranges are sorted and apart, and a line with no
+    /// match counted is treated as not matching.
+    pub fn only_bytes(&mut self, ranges: Option<Vec<Range<u64>>>) -> &mut Self {
+        self.only_bytes = ranges;
+        self
+    }
+
+    /// Counts only matches whose capture groups filter keeps, if given; a
+    /// line with no match counted is treated as not matching.
+    pub fn filter(&mut self, filter: Option<Filter>) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Treats a line as matching only if each of res matches it as well
+    /// as the regex searched for, as when every one of several patterns
+    /// must; multi-line searches do not consult them.
+    pub fn require(&mut self, res: Vec<Regex>) -> &mut Self {
+        self.required = res;
+        self
+    }
+
+    /// Treats a line re matches, if given, as not matching whatever else
+    /// does, so it is selected only by an inverted search; multi-line
+    /// searches do not consult it.
+    pub fn exclude(&mut self, re: Option<Regex>) -> &mut Self {
+        self.excluded = re;
+        self
+    }
+
+    /// Numbers lines and byte offsets as though the input began at line
+    /// `line`, byte `offset` of a larger one, as when searching only what
+    /// was appended to a file.
+    pub fn start_at(&mut self, line: u64, offset: u64) -> &mut Self {
+        self.start = (line.max(1), offset);
+        self
+    }
+
+    /// Transcodes input from the given encoding to UTF-8 before searching it.
+    pub fn encoding(&mut self, encoding: Option<Encoding>) -> &mut Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Transcodes input starting with a UTF-16 byte order mark, and strips
+    /// a UTF-8 one, unless an encoding is set.
+    pub fn bom_sniffing(&mut self, yes: bool) -> &mut Self {
+        self.bom_sniffing = yes;
+        self
+    }
+
+    /// Guesses the encoding of each input with no byte order mark, as
+    /// [`Encoding::detect`] does, unless an encoding is set.
+    pub fn detect_encoding(&mut self, yes: bool) -> &mut Self {
+        self.detect_encoding = yes;
+        self
+    }
+
+    /// Searches the file at path.
+    pub fn search_path<S: Sink>(&self, re: &Regex, path: &Path, sink: S) -> io::Result<()> {
+        self.search_reader(re, File::open(path)?, sink)
+    }
+
+    /// Searches an in-memory buffer.
+    pub fn search_slice<S: Sink>(&self, re: &Regex, slice: &[u8], sink: S) -> io::Result<()> {
+        self.search_reader(re, slice, sink)
+    }
+
+    /// Searches everything read from rdr.
+    pub fn search_reader<R: Read, S: Sink>(
+        &self,
+        re: &Regex,
+        rdr: R,
+        mut sink: S,
+    ) -> io::Result<()> {
+        if !sink.begin()? {
+            return Ok(());
+        }
+        let mut finish = SinkFinish::default();
+        if self.encoding.is_some() || self.bom_sniffing || self.detect_encoding {
+            let mut rdr = Decoder::new(rdr, self.encoding);
+            rdr.detect(self.detect_encoding);
+            self.search_lines(re, rdr, &mut sink, &mut finish)?;
+        } else {
+            self.search_lines(re, rdr, &mut sink, &mut finish)?;
+        }
+        sink.finish(&finish)
+    }
+
+    /// Reports the lines of rdr to sink, counting what it reads in finish.
+    fn search_lines<R: Read, S: Sink>(
+        &self,
+        re: &Regex,
+        rdr: R,
+        mut sink: S,
+        finish: &mut SinkFinish,
+    ) -> io::Result<()> {
+        let mut rdr = if self.multi_line {
+            let mut hay = Vec::new();
+            BufReader::new(rdr).read_to_end(&mut hay)?;
+            if self.nfc {
+                if let Cow::Owned(text) = nfc_bytes(&hay) {
+                    hay = text;
+                }
+            }
+            let regions = self.regions(re, &hay);
+            Frames::Regions {
+                hay,
+                pos: 0,
+                regions,
+            }
+        } else {
+            Frames::Lines(BufReader::new(rdr))
+        };
+        let mut before: VecDeque<Line> = VecDeque::with_capacity(self.before_context);
+        let mut after_left = 0usize;
+        // Line number of the last line handed to the sink, for context breaks.
+        let mut last: Option<u64> = None;
+        let mut buf = Vec::new();
+        let (mut number, base) = (self.start.0 - 1, self.start.1);
+        let mut matched = 0u64;
+        // With NUL-terminated lines, a NUL says nothing about binary data.
+        let mut detect = self.binary_detection && (self.any_newline || self.line_terminator != 0);
+        if detect {
+            if let Some(at) = rdr.peek()?.iter().position(|&b| b == 0) {
+                detect = false;
+                finish.binary_byte_offset = Some(base + at as u64);
+                if !sink.binary_data(base + at as u64)? {
+                    return Ok(());
+                }
+            }
+        }
+        loop {
+            let done = self.max_count.is_some_and(|max| matched >= max);
+            if done && after_left == 0 {
+                return Ok(());
+            }
+            buf.clear();
+            let Some(unit) = rdr.next(self.term(), &mut buf)? else {
+                return Ok(());
+            };
+            number += 1;
+            let first = number;
+            let line_offset = base + finish.byte_count;
+            finish.byte_count += buf.len() as u64;
+            if self.nfc && matches!(unit, Unit::Line) {
+                if let Cow::Owned(text) = nfc_bytes(&buf) {
+                    buf = text;
+                }
+            }
+            let len = self.strip_terminator(&buf).len();
+            if detect {
+                if let Some(at) = buf.iter().position(|&b| b == 0) {
+                    detect = false;
+                    finish.binary_byte_offset = Some(line_offset + at as u64);
+                    if !sink.binary_data(line_offset + at as u64)? {
+                        return Ok(());
+                    }
+                }
+            }
+            let is_match = match unit {
+                Unit::Line if self.max_line_length.is_some_and(|max| len > max) => {
+                    finish.long_lines += 1;
+                    self.invert_match
+                }
+                Unit::Line => self.line_matches(re, &buf[..len], line_offset)?,
+                Unit::Region => {
+                    number += self.term().count(&buf[..len]);
+                    true
+                }
+                Unit::Between => false,
+            };
+            if is_match != self.invert_match && self.may_select(first..number + 1) {
+                if done {
+                    return Ok(());
+                }
+                matched += 1;
+                for line in before.drain(..) {
+                    if !self.gap(&mut sink, &mut last, line.number)? {
+                        return Ok(());
+                    }
+                    let ctx = SinkContext {
+                        bytes: &line.bytes,
+                        kind: SinkContextKind::Before,
+                        line_number: line.number,
+                        absolute_byte_offset: line.offset,
+                    };
+                    if !sink.context(&ctx)? {
+                        return Ok(());
+                    }
+                }
+                if !self.gap(&mut sink, &mut last, first)? {
+                    return Ok(());
+                }
+                last = Some(number);
+                let m = SinkMatch {
+                    bytes: &buf,
+                    len,
+                    re: (!self.invert_match).then_some(re),
+                    only_bytes: self.only_bytes.as_deref(),
+                    filter: self.filter.as_ref(),
+                    line_number: first,
+                    absolute_byte_offset: line_offset,
+                };
+                if !sink.matched(&m)? {
+                    return Ok(());
+                }
+                after_left = self.after_context;
+            } else if self.passthru {
+                last = Some(number);
+                let ctx = SinkContext {
+                    bytes: &buf,
+                    kind: SinkContextKind::Other,
+                    line_number: number,
+                    absolute_byte_offset: line_offset,
+                };
+                if !sink.context(&ctx)? {
+                    return Ok(());
+                }
+            } else if after_left > 0 {
+                after_left -= 1;
+                last = Some(number);
+                let ctx = SinkContext {
+                    bytes: &buf,
+                    kind: SinkContextKind::After,
+                    line_number: number,
+                    absolute_byte_offset: line_offset,
+                };
+                if !sink.context(&ctx)? {
+                    return Ok(());
+                }
+            } else if self.before_context > 0 {
+                if before.len() == self.before_context {
+                    before.pop_front();
+                }
+                before.push_back(Line {
+                    bytes: buf.clone(),
+                    number,
+                    offset: line_offset,
+                });
+            }
+        }
+    }
+
+    /// Reports whether line, at byte offset base, is treated as matching:
+    /// re matching it where a match counts, each of [`Searcher::require`]
+    /// matching it too and [`Searcher::exclude`] not.
+    fn line_matches(&self, re: &Regex, line: &[u8], base: u64) -> io::Result<bool> {
+        let is_match = |re: &Regex| re.try_is_match(line).map_err(io::Error::other);
+        let found = match self.only_bytes.is_some() || self.filter.is_some() {
+            true => {
+                let (only_bytes, filter) = (self.only_bytes.as_deref(), self.filter.as_ref());
+                re.captures_iter(line)
+                    .any(|caps| counts(only_bytes, filter, base, &caps))
+            }
+            false => is_match(re)?,
+        };
+        if !found {
+            return Ok(false);
+        }
+        for re in &self.required {
+            if !is_match(re)? {
+                return Ok(false);
+            }
+        }
+        match &self.excluded {
+            Some(re) => Ok(!is_match(re)?),
+            None => Ok(true),
+        }
+    }
+
+    /// Reports whether any of lines is one [`Searcher::only_lines`] allows.
+    fn may_select(&self, lines: Range<u64>) -> bool {
+        self.only_lines.as_ref().map_or(true, |ranges| {
+            ranges
+                .iter()
+                .any(|r| r.start < lines.end && lines.start < r.end)
+        })
+    }
+
+    /// Returns what ends lines.
+    fn term(&self) -> Term {
+        if self.any_newline {
+            Term::Any
+        } else {
+            Term::Byte(self.line_terminator)
+        }
+    }
+
+    /// Returns the ranges of the whole lines each match in hay touches,
+    /// merging those that share a line.
+    fn regions(&self, re: &Regex, hay: &[u8]) -> VecDeque<Range<usize>> {
+        let term = self.term();
+        let mut regions: VecDeque<Range<usize>> = VecDeque::new();
+        let base = self.start.1;
+        let (only_bytes, filter) = (self.only_bytes.as_deref(), self.filter.as_ref());
+        let counted = re
+            .captures_iter(hay)
+            .filter(|caps| counts(only_bytes, filter, base, caps));
+        for m in counted.map(|caps| caps.get(0).unwrap()) {
+            let start = term.line_start(hay, m.start());
+            // A match ending with a terminator does not touch the next line.
+            let last = if m.end() > m.start() {
+                m.end() - 1
+            } else {
+                m.start()
+            };
+            let end = term.line_end(hay, last);
+            match regions.back_mut() {
+                Some(prev) if prev.end > start => prev.end = prev.end.max(end),
+                _ => regions.push_back(start..end),
+            }
+        }
+        regions
+    }
+
+    /// Strips the terminator (and a `\r` before `\n`) from a line, leaving
+    /// the text the regex is matched against.
+    pub fn strip_terminator<'b>(&self, line: &'b [u8]) -> &'b [u8] {
+        if self.any_newline {
+            let line = line.strip_suffix(b"\n").unwrap_or(line);
+            return line.strip_suffix(b"\r").unwrap_or(line);
+        }
+        let line = line.strip_suffix(&[self.line_terminator]).unwrap_or(line);
+        if self.line_terminator == b'\n' {
+            line.strip_suffix(b"\r").unwrap_or(line)
+        } else {
+            line
+        }
+    }
+
+    /// Emits a context break if line does not directly follow the last reported line.
+    fn gap<S: Sink>(&self, sink: &mut S, last: &mut Option<u64>, line: u64) -> io::Result<bool> {
+        let contiguous = last.map_or(true, |l| l + 1 == line);
+        *last = Some(line);
+        let has_context = self.before_context > 0 || self.after_context > 0;
+        if !contiguous && has_context {
+            return sink.context_break();
+        }
+        Ok(true)
+    }
+}
+
+/// The input of a search, split into the units it reports.
+enum Frames<R> {
+    /// Lines read one at a time.
+    Lines(BufReader<R>),
+    /// A whole input, with the regions of lines multi-line matches touch.
+    Regions {
+        hay: Vec<u8>,
+        pos: usize,
+        regions: VecDeque<Range<usize>>,
+    },
+}
+
+/// What ends a line.
+#[derive(Clone, Copy)]
+enum Term {
+    Byte(u8),
+    /// `\n`, `\r\n` or a lone `\r`.
+    Any,
+}
+
+impl Term {
+    /// Returns the end of the line holding hay[at], just past its
+    /// terminator, or the end of hay.
+    fn line_end(self, hay: &[u8], at: usize) -> usize {
+        let Some(i) = hay[at..].iter().position(|&b| self.ends(b)) else {
+            return hay.len();
+        };
+        let end = at + i + 1;
+        match self {
+            Term::Any if hay[end - 1] == b'\r' && hay.get(end) == Some(&b'\n') => end + 1,
+            _ => end,
+        }
+    }
+
+    /// Returns the start of the line holding hay[at].
+    fn line_start(self, hay: &[u8], at: usize) -> usize {
+        hay[..at]
+            .iter()
+            .rposition(|&b| self.ends(b))
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Returns how many line terminators bytes holds.
+    fn count(self, bytes: &[u8]) -> u64 {
+        let mut n = 0;
+        let mut at = 0;
+        while at < bytes.len() {
+            at = self.line_end(bytes, at);
+            n += bytes[..at].last().is_some_and(|&b| self.ends(b)) as u64;
+        }
+        n
+    }
+
+    /// Reports whether b ends a line, or may be the start of a `\r\n` that does.
+    fn ends(self, b: u8) -> bool {
+        match self {
+            Term::Byte(term) => b == term,
+            Term::Any => b == b'\n' || b == b'\r',
+        }
+    }
+
+    /// Reads the next line of rdr into buf with its terminator, returning
+    /// how many bytes were read.
+    fn read_line<R: BufRead>(self, rdr: &mut R, buf: &mut Vec<u8>) -> io::Result<usize> {
+        if let Term::Byte(term) = self {
+            return rdr.read_until(term, buf);
+        }
+        let mut n = 0;
+        loop {
+            let avail = rdr.fill_buf()?;
+            if avail.is_empty() {
+                return Ok(n);
+            }
+            // A `\r` that ended the last buffer may be the start of a `\r\n`.
+            if buf.last() == Some(&b'\r') {
+                if avail[0] == b'\n' {
+                    buf.push(b'\n');
+                    rdr.consume(1);
+                    n += 1;
+                }
+                return Ok(n);
+            }
+            let (used, done) = match avail.iter().position(|&b| self.ends(b)) {
+                Some(i) if avail[i] == b'\r' && i + 1 == avail.len() => (i + 1, false),
+                Some(i) => (self.line_end(avail, i), true),
+                None => (avail.len(), false),
+            };
+            buf.extend_from_slice(&avail[..used]);
+            rdr.consume(used);
+            n += used;
+            if done {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+/// What is known of a unit read by [`Frames::next`].
+enum Unit {
+    /// A line the regex has yet to run over.
+    Line,
+    /// The lines a match touches.
+    Region,
+    /// A line outside any region.
+    Between,
+}
+
+impl<R: Read> Frames<R> {
+    /// Returns the start of the input, for a first look at it.
+    fn peek(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Frames::Lines(rdr) => rdr.fill_buf(),
+            Frames::Regions { hay, .. } => Ok(hay),
+        }
+    }
+
+    /// Reads the next unit into buf, or returns None at the end of input.
+    fn next(&mut self, term: Term, buf: &mut Vec<u8>) -> io::Result<Option<Unit>> {
+        let (hay, pos, regions) = match self {
+            Frames::Lines(rdr) => {
+                return Ok((term.read_line(rdr, buf)? > 0).then_some(Unit::Line));
+            }
+            Frames::Regions { hay, pos, regions } => (hay, pos, regions),
+        };
+        if *pos >= hay.len() {
+            return Ok(None);
+        }
+        let (end, unit) = match regions.front() {
+            Some(r) if r.start == *pos => (r.end, Unit::Region),
+            _ => (term.line_end(hay, *pos), Unit::Between),
+        };
+        if let Unit::Region = unit {
+            regions.pop_front();
+        }
+        buf.extend_from_slice(&hay[*pos..end]);
+        *pos = end;
+        Ok(Some(unit))
+    }
+}
+
+/// Reports whether the match caps, in input starting at byte offset base,
+/// lies within one of only_bytes and is kept by filter, as far as given.
+fn counts(
+    only_bytes: Option<&[Range<u64>]>,
+    filter: Option<&Filter>,
+    base: u64,
+    caps: &Captures<'_>,
+) -> bool {
+    let r = caps.get(0).unwrap().range();
+    let at = base + r.start as u64..base + r.end as u64;
+    only_bytes.map_or(true, |ranges| within(ranges, at)) && filter.map_or(true, |f| f.keeps(caps))
+}
+
+/// Reports whether at lies wholly within one of ranges, which are sorted
+/// and apart.
+fn within(ranges: &[Range<u64>], at: Range<u64>) -> bool {
+    let i = ranges.partition_point(|r| r.end < at.end);
+    ranges.get(i).is_some_and(|r| r.start <= at.start)
+}
+
+/// A matching line found by [`search_file`] or [`search_dir`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    /// The file the line was found in.
+    pub path: PathBuf,
+    /// The 1-based line number.
+    pub line_number: u64,
+    /// The line, without its terminator.
+    pub line: Vec<u8>,
+    /// The byte ranges of every match within `line`.
+    pub spans: Vec<Range<usize>>,
+}
+
+/// Collects matching lines of one file as [`SearchResult`]s.
+struct Collect<'a, F> {
+    path: &'a Path,
+    f: F,
+}
+
+impl<F: FnMut(SearchResult) -> io::Result<bool>> Sink for Collect<'_, F> {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        (self.f)(SearchResult {
+            path: self.path.to_path_buf(),
+            line_number: m.line_number(),
+            line: m.line().to_vec(),
+            spans: m.matches().collect(),
+        })
+    }
+}
+
+/// Returns every matching line of the file at path.
+pub fn search_file(re: &Regex, path: &Path) -> io::Result<Vec<SearchResult>> {
+    let mut results = Vec::new();
+    search_file_with(re, path, |r| {
+        results.push(r);
+        Ok(true)
+    })?;
+    Ok(results)
+}
+
+/// Calls f with every matching line of the file at path until it returns false.
+pub fn search_file_with<F>(re: &Regex, path: &Path, f: F) -> io::Result<()>
+where
+    F: FnMut(SearchResult) -> io::Result<bool>,
+{
+    Searcher::new().search_path(re, path, Collect { path, f })
+}
+
+/// Returns every matching line of every file below root.
+pub fn search_dir(re: &Regex, root: &Path) -> io::Result<Vec<SearchResult>> {
+    let mut results = Vec::new();
+    search_dir_with(re, root, |r| {
+        results.push(r);
+        Ok(())
+    })?;
+    Ok(results)
+}
+
+/// Calls f with every matching line of every file below root.
+pub fn search_dir_with<F>(re: &Regex, root: &Path, mut f: F) -> io::Result<()>
+where
+    F: FnMut(SearchResult) -> io::Result<()>,
+{
+    Walker::new().run(root, |entry| {
+        search_file_with(re, entry.path(), |r| f(r).map(|()| true))?;
+        Ok(true)
+    })
+}
@@ -0,0 +1,416 @@
+//! `--serve`: answering searches over a socket, so an editor can run many
+//! without starting a process for each, and with the patterns it reuses
+//! compiled once.
+//!
+//! Requests and responses are JSON-RPC 2.0 objects, one per line. The
+//! `search` method takes the arguments of a command line as `args` and
+//! answers with the `status` it would exit with and the `output` it would
+//! print; `shutdown` answers and then stops the server. A request cannot
+//! give the flags that run commands or write files, and only the user the
+//! server runs as can connect to its socket.
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"search","params":{"args":["-n","TODO","src"]}}
+//! {"jsonrpc":"2.0","id":1,"result":{"status":0,"output":"src/main.rs:12:// TODO\n"}}
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+
+use anyhow::Result;
+
+use crate::interrupt::interrupted;
+
+/// The JSON-RPC error codes used.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// A search that failed, as the command line would with status 2.
+const SEARCH_FAILED: i32 = -32000;
+
+/// A JSON value; numbers keep their text, so an id is echoed as sent.
+#[derive(Clone, Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Returns the member named key, if this is an object holding one.
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|m| &m.1),
+            _ => None,
+        }
+    }
+
+    /// Appends this value as JSON text to out.
+    fn write(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) => out.push_str(n),
+            Json::String(s) => quote(s, out),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            }
+            Json::Object(members) => {
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    quote(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+}
+
+/// Appends s to out as a quoted JSON string.
+fn quote(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Reads one JSON value from text, which must hold nothing else.
+fn parse(text: &str) -> Option<Json> {
+    let mut p = Parser { text, pos: 0 };
+    let value = p.value()?;
+    p.space();
+    (p.pos == text.len()).then_some(value)
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn space(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    /// Consumes s if the text continues with it.
+    fn eat(&mut self, s: &str) -> bool {
+        let found = self.text[self.pos..].starts_with(s);
+        if found {
+            self.pos += s.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.space();
+        let rest = &self.text[self.pos..];
+        match rest.as_bytes().first()? {
+            b'n' if self.eat("null") => Some(Json::Null),
+            b't' if self.eat("true") => Some(Json::Bool(true)),
+            b'f' if self.eat("false") => Some(Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.space();
+                if !self.eat("]") {
+                    loop {
+                        items.push(self.value()?);
+                        self.space();
+                        if self.eat("]") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Array(items))
+            }
+            b'{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.space();
+                if !self.eat("}") {
+                    loop {
+                        self.space();
+                        let key = self.string()?;
+                        self.space();
+                        if !self.eat(":") {
+                            return None;
+                        }
+                        members.push((key, self.value()?));
+                        self.space();
+                        if self.eat("}") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return None;
+                        }
+                    }
+                }
+                Some(Json::Object(members))
+            }
+            b'-' | b'0'..=b'9' => {
+                let len = rest
+                    .find(|c: char| !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len());
+                rest[..len].parse::<f64>().ok()?;
+                self.pos += len;
+                Some(Json::Number(rest[..len].to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.eat("\"") {
+            return None;
+        }
+        let mut out = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Some(out);
+                }
+                '\\' => {
+                    let c = match chars.next()?.1 {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'u' => {
+                            let hi = unit(&mut chars)?;
+                            let cp = if (0xD800..0xDC00).contains(&hi) {
+                                if chars.next()?.1 != '\\' || chars.next()?.1 != 'u' {
+                                    return None;
+                                }
+                                let lo =
+                                    unit(&mut chars).filter(|lo| (0xDC00..0xE000).contains(lo))?;
+                                0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00)
+                            } else {
+                                hi
+                            };
+                            char::from_u32(cp)?
+                        }
+                        c @ ('"' | '\\' | '/') => c,
+                        _ => return None,
+                    };
+                    out.push(c);
+                }
+                c if (c as u32) < 0x20 => return None,
+                c => out.push(c),
+            }
+        }
+        None
+    }
+}
+
+/// Reads the four hex digits of a `\u` escape.
+fn unit(chars: &mut std::str::CharIndices<'_>) -> Option<u32> {
+    let hex: String = chars.take(4).map(|(_, c)| c).collect();
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 4)
+}
+
+/// Answers requests read from socket, or from stdin onto stdout if it is
+/// `-`, until `shutdown` or Ctrl-C. search runs a command line, returning
+/// its status and output or why it failed.
+pub fn serve<F>(socket: &str, mut search: F) -> Result<()>
+where
+    F: FnMut(&[String]) -> Result<(i32, Vec<u8>), String>,
+{
+    if socket == "-" {
+        session(io::stdin().lock(), io::stdout().lock(), &mut search)?;
+        return Ok(());
+    }
+    listen(socket, search)
+}
+
+#[cfg(unix)]
+fn listen<F>(socket: &str, mut search: F) -> Result<()>
+where
+    F: FnMut(&[String]) -> Result<(i32, Vec<u8>), String>,
+{
+    use std::fs::{self, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let listener = match UnixListener::bind(socket) {
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            // A socket nothing answers on was left by a server that died.
+            if UnixStream::connect(socket).is_ok() {
+                anyhow::bail!("{}: another server is listening there", socket);
+            }
+            fs::remove_file(socket)?;
+            UnixListener::bind(socket)?
+        }
+        res => res.map_err(|e| anyhow::anyhow!("{}: {}", socket, e))?,
+    };
+    // Requests can read any file the server can, so only its user may connect.
+    fs::set_permissions(socket, Permissions::from_mode(0o600))?;
+    let res = (|| {
+        for conn in listener.incoming() {
+            let conn = conn?;
+            let rdr = BufReader::new(conn.try_clone()?);
+            // A client going away ends only its own session.
+            if let Ok(true) = session(rdr, conn, &mut search) {
+                break;
+            }
+            if interrupted() {
+                break;
+            }
+        }
+        Ok(())
+    })();
+    let _ = fs::remove_file(socket);
+    res
+}
+
+#[cfg(not(unix))]
+fn listen<F>(socket: &str, _: F) -> Result<()>
+where
+    F: FnMut(&[String]) -> Result<(i32, Vec<u8>), String>,
+{
+    anyhow::bail!(
+        "{}: sockets need Unix; serve on '-' for stdin and stdout",
+        socket
+    )
+}
+
+/// Answers each line read from rdr on wtr; returns true if it ended with
+/// `shutdown`.
+fn session<R, W, F>(rdr: R, mut wtr: W, search: &mut F) -> io::Result<bool>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(&[String]) -> Result<(i32, Vec<u8>), String>,
+{
+    for line in rdr.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (response, shutdown) = answer(&line, search);
+        if let Some(response) = response {
+            let mut text = String::new();
+            response.write(&mut text);
+            writeln!(wtr, "{}", text)?;
+            wtr.flush()?;
+        }
+        if shutdown {
+            return Ok(true);
+        }
+        if interrupted() {
+            break;
+        }
+    }
+    Ok(false)
+}
+
+/// Returns the response to a request, none for a notification, and
+/// whether it asks to shut down.
+fn answer<F>(line: &str, search: &mut F) -> (Option<Json>, bool)
+where
+    F: FnMut(&[String]) -> Result<(i32, Vec<u8>), String>,
+{
+    let Some(request) = parse(line) else {
+        return (Some(error(Json::Null, PARSE_ERROR, "parse error")), false);
+    };
+    let id = request.get("id").cloned();
+    let reply = |result: Result<Json, (i32, String)>| {
+        let id = id.clone()?;
+        Some(match result {
+            Ok(result) => response(id, "result", result),
+            Err((code, message)) => error(id, code, &message),
+        })
+    };
+    let Some(Json::String(method)) = request.get("method") else {
+        let id = id.unwrap_or(Json::Null);
+        return (Some(error(id, INVALID_REQUEST, "invalid request")), false);
+    };
+    match method.as_str() {
+        "search" => {
+            let args = match request.get("params").and_then(|p| p.get("args")) {
+                Some(Json::Array(items)) => items
+                    .iter()
+                    .map(|item| match item {
+                        Json::String(s) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>(),
+                _ => None,
+            };
+            let Some(args) = args else {
+                let message = "params must hold args, an array of strings".to_string();
+                return (reply(Err((INVALID_PARAMS, message))), false);
+            };
+            let result = search(&args).map_err(|e| (SEARCH_FAILED, e));
+            let result = result.map(|(status, output)| {
+                Json::Object(vec![
+                    ("status".to_string(), Json::Number(status.to_string())),
+                    (
+                        "output".to_string(),
+                        Json::String(String::from_utf8_lossy(&output).into_owned()),
+                    ),
+                ])
+            });
+            (reply(result), false)
+        }
+        "shutdown" => (reply(Ok(Json::Null)), true),
+        _ => {
+            let message = format!("no method {}", method);
+            (reply(Err((METHOD_NOT_FOUND, message))), false)
+        }
+    }
+}
+
+/// Returns a response carrying value as its result or error.
+fn response(id: Json, kind: &str, value: Json) -> Json {
+    Json::Object(vec![
+        ("jsonrpc".to_string(), Json::String("2.0".to_string())),
+        ("id".to_string(), id),
+        (kind.to_string(), value),
+    ])
+}
+
+/// Returns an error response.
+fn error(id: Json, code: i32, message: &str) -> Json {
+    let error = Json::Object(vec![
+        ("code".to_string(), Json::Number(code.to_string())),
+        ("message".to_string(), Json::String(message.to_string())),
+    ]);
+    response(id, "error", error)
+}
@@ -0,0 +1,151 @@
+//! `--tail`: following files as `tail -F` does, from their end, for the
+//! lines appended to them from then on.
+//!
+//! Unlike `--watch`, which looks at files again by name, each file is kept
+//! open, so the lines written to a log just before it is rotated are still
+//! read. A file truncated is read again from its start; one found replaced
+//! by another, once what was written to it is read, gives way to the new
+//! one; one missing is waited for.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Whole lines read from a followed file: bytes `offset..` of it, the
+/// first of them line number `line`.
+pub struct Lines {
+    pub data: Vec<u8>,
+    pub line: u64,
+    pub offset: u64,
+}
+
+/// What happened to a followed file, for the lines read after it to be
+/// told apart from those before.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Event {
+    /// It was created, or could be opened again.
+    Appeared,
+    /// It was cut shorter than what was read of it.
+    Truncated,
+    /// Another file was found at its path.
+    Replaced,
+}
+
+/// A file followed at a path.
+pub struct Followed {
+    path: PathBuf,
+    /// The file open at path, if it could be opened.
+    file: Option<File>,
+    /// Identifies the file open, so one saved in its place is told apart.
+    id: Option<u64>,
+    /// Where the last whole line read ends.
+    done: u64,
+    /// How many whole lines there are before done.
+    lines: u64,
+    terminator: u8,
+}
+
+impl Followed {
+    /// Starts following the file at path, whose lines end with terminator,
+    /// from its last whole line, or from its start once it exists.
+    pub fn new(path: &Path, terminator: u8) -> io::Result<Self> {
+        let mut followed = Followed {
+            path: path.to_path_buf(),
+            file: None,
+            id: None,
+            done: 0,
+            lines: 0,
+            terminator,
+        };
+        if followed.reopen() {
+            let file = followed.file.as_ref().expect("the file is open");
+            let mut rdr = BufReader::new(file);
+            let mut line = Vec::new();
+            while read_line(&mut rdr, terminator, &mut line)? {
+                followed.done += line.len() as u64;
+                followed.lines += 1;
+            }
+        }
+        Ok(followed)
+    }
+
+    /// Returns what happened to the file since it was last looked at, if
+    /// anything did, and the whole lines appended to it since.
+    pub fn poll(&mut self) -> io::Result<(Option<Event>, Option<Lines>)> {
+        let mut event = None;
+        let len = match &self.file {
+            Some(file) => Some(file.metadata()?.len()),
+            None => None,
+        };
+        match len {
+            None if !self.reopen() => return Ok((None, None)),
+            None => event = Some(Event::Appeared),
+            Some(len) if len < self.done => {
+                (self.done, self.lines) = (0, 0);
+                event = Some(Event::Truncated);
+            }
+            Some(_) => {}
+        }
+        let mut lines = self.read()?;
+        // Only once everything written to the old file is read.
+        if lines.is_none() && event.is_none() && self.replaced() && self.reopen() {
+            event = Some(Event::Replaced);
+            lines = self.read()?;
+        }
+        Ok((event, lines))
+    }
+
+    /// Opens the file at path to be read from its start, returning whether
+    /// it could be.
+    fn reopen(&mut self) -> bool {
+        let Ok(file) = File::open(&self.path) else {
+            return false;
+        };
+        self.id = file.metadata().ok().and_then(|md| id(&md));
+        self.file = Some(file);
+        (self.done, self.lines) = (0, 0);
+        true
+    }
+
+    /// Reports whether another file than the one open is at path.
+    fn replaced(&self) -> bool {
+        fs::metadata(&self.path).is_ok_and(|md| id(&md) != self.id)
+    }
+
+    /// Reads the whole lines appended after done.
+    fn read(&mut self) -> io::Result<Option<Lines>> {
+        let file = self.file.as_mut().expect("the file is open");
+        file.seek(SeekFrom::Start(self.done))?;
+        let mut rdr = BufReader::new(file);
+        let (offset, first) = (self.done, self.lines + 1);
+        let mut data = Vec::new();
+        let mut line = Vec::new();
+        while read_line(&mut rdr, self.terminator, &mut line)? {
+            data.extend_from_slice(&line);
+            self.done += line.len() as u64;
+            self.lines += 1;
+        }
+        Ok((!data.is_empty()).then_some(Lines {
+            data,
+            line: first,
+            offset,
+        }))
+    }
+}
+
+/// Reads the next line of rdr into line, returning whether it is whole;
+/// one without its terminator is still being written.
+fn read_line(rdr: &mut impl BufRead, terminator: u8, line: &mut Vec<u8>) -> io::Result<bool> {
+    line.clear();
+    rdr.read_until(terminator, line)?;
+    Ok(line.last() == Some(&terminator))
+}
+
+/// Identifies the file md describes, where the platform says how.
+fn id(md: &fs::Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    let id = Some(std::os::unix::fs::MetadataExt::ino(md));
+    #[cfg(not(unix))]
+    let id = None;
+    id
+}
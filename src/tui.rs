@@ -0,0 +1,438 @@
+//! `--tui`: browsing the lines a search found in the terminal, grouped by
+//! file, with the lines around the one selected shown below the list, and
+//! opening it in `$VISUAL` or `$EDITOR` at its line.
+//!
+//! The terminal is driven through `/dev/tty` with escape sequences, and put
+//! in raw mode by `stty`, so standard output may still be redirected.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+use putao_cf::printer::Printer;
+use putao_cf::searcher::SinkMatch;
+
+use crate::read_lossy;
+
+/// The keys the status line names.
+const HELP: &str = "j/k move  n/p file  enter open  q quit";
+
+/// The lines found in each input, in the order they were found.
+pub struct Results {
+    wtr: Box<dyn Write>,
+    files: Vec<Found>,
+}
+
+/// The lines found in one input.
+struct Found {
+    label: String,
+    hits: Vec<Hit>,
+}
+
+struct Hit {
+    line: u64,
+    /// The 1-based byte column of the first match, or 0 for none.
+    column: usize,
+    text: String,
+}
+
+impl Results {
+    /// Collects lines to browse; what is not a line, such as a count,
+    /// goes to wtr.
+    pub fn new(wtr: Box<dyn Write>) -> Self {
+        Results {
+            wtr,
+            files: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn get_mut(&mut self) -> &mut Box<dyn Write> {
+        &mut self.wtr
+    }
+
+    /// Shows the lines collected until `q` is pressed; does nothing if
+    /// there are none.
+    pub fn browse(&mut self) -> io::Result<()> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
+        let tty = Tty::open()
+            .map_err(|e| io::Error::new(e.kind(), format!("--tui needs a terminal: {}", e)))?;
+        let mut browser = Browser {
+            rows: rows(&self.files),
+            files: &self.files,
+            selected: 1,
+            top: 0,
+            sources: HashMap::new(),
+            status: None,
+        };
+        browser.run(tty)
+    }
+}
+
+impl Printer for Results {
+    fn matched(&mut self, path: Option<&str>, m: &SinkMatch<'_>) -> io::Result<()> {
+        let label = path.unwrap_or("");
+        if self.files.last().map_or(true, |f| f.label != label) {
+            self.files.push(Found {
+                label: label.to_string(),
+                hits: Vec::new(),
+            });
+        }
+        let bytes = m.bytes();
+        let first = bytes.split(|&b| b == b'\n').next().unwrap_or(bytes);
+        let first = first.strip_suffix(b"\r").unwrap_or(first);
+        let hit = Hit {
+            line: m.line_number(),
+            column: m.matches().next().map_or(0, |r| r.start + 1),
+            text: String::from_utf8_lossy(first).into_owned(),
+        };
+        self.files
+            .last_mut()
+            .expect("file just pushed")
+            .hits
+            .push(hit);
+        Ok(())
+    }
+}
+
+/// A row of the list: a file, or one of its lines.
+#[derive(Clone, Copy)]
+enum Row {
+    File(usize),
+    Hit(usize, usize),
+}
+
+fn rows(files: &[Found]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        rows.push(Row::File(i));
+        rows.extend((0..file.hits.len()).map(|j| Row::Hit(i, j)));
+    }
+    rows
+}
+
+/// A key pressed.
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Char(u8),
+}
+
+/// The terminal, in raw mode until dropped.
+struct Tty {
+    file: File,
+    /// The settings to restore, as `stty -g` prints them.
+    saved: String,
+}
+
+impl Tty {
+    fn open() -> io::Result<Tty> {
+        let file = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let saved = Tty::stty(&file, &["-g"])?;
+        let mut tty = Tty {
+            file,
+            saved: saved.trim().to_string(),
+        };
+        tty.enter()?;
+        Ok(tty)
+    }
+
+    /// Runs stty on the terminal, returning what it printed.
+    fn stty(file: &File, args: &[&str]) -> io::Result<String> {
+        let out = Command::new("stty")
+            .args(args)
+            .stdin(file.try_clone()?)
+            .stderr(Stdio::null())
+            .output()?;
+        if !out.status.success() {
+            return Err(io::Error::other("stty failed"));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+    }
+
+    /// Puts the terminal in raw mode, reads waiting at most a tenth of a
+    /// second, and switches to the alternate screen.
+    fn enter(&mut self) -> io::Result<()> {
+        Tty::stty(&self.file, &["raw", "-echo", "min", "0", "time", "1"])?;
+        self.file.write_all(b"\x1b[?1049h\x1b[?25l")
+    }
+
+    /// Undoes [`Tty::enter`].
+    fn leave(&mut self) -> io::Result<()> {
+        self.file.write_all(b"\x1b[?25h\x1b[?1049l")?;
+        Tty::stty(&self.file, &[&self.saved]).map(drop)
+    }
+
+    /// Returns the rows and columns of the terminal.
+    fn size(&self) -> (usize, usize) {
+        let size = Tty::stty(&self.file, &["size"]).unwrap_or_default();
+        let mut parts = size.split_whitespace().map(|n| n.parse().unwrap_or(0));
+        match (parts.next(), parts.next()) {
+            (Some(rows), Some(cols)) if rows > 2 && cols > 0 => (rows, cols),
+            _ => (24, 80),
+        }
+    }
+
+    /// Waits for a key to be pressed.
+    fn key(&mut self) -> io::Result<Key> {
+        let byte = loop {
+            if let Some(byte) = self.byte()? {
+                break byte;
+            }
+        };
+        if byte != 0x1b {
+            return Ok(Key::Char(byte));
+        }
+        let mut seq = Vec::new();
+        while let Some(byte) = self.byte()? {
+            seq.push(byte);
+            if byte.is_ascii_alphabetic() || byte == b'~' {
+                break;
+            }
+        }
+        Ok(match seq.as_slice() {
+            b"[A" | b"OA" => Key::Up,
+            b"[B" | b"OB" => Key::Down,
+            b"[5~" => Key::PageUp,
+            b"[6~" => Key::PageDown,
+            b"[H" | b"OH" | b"[1~" => Key::Home,
+            b"[F" | b"OF" | b"[4~" => Key::End,
+            [] => Key::Char(0x1b),
+            _ => Key::Char(0),
+        })
+    }
+
+    /// Reads a byte, or none if a tenth of a second goes by first.
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        let mut buf = [0];
+        match self.file.read(&mut buf)? {
+            0 => Ok(None),
+            _ => Ok(Some(buf[0])),
+        }
+    }
+}
+
+impl Drop for Tty {
+    fn drop(&mut self) {
+        let _ = self.leave();
+    }
+}
+
+/// What is shown, and where in it the selection is.
+struct Browser<'r> {
+    files: &'r [Found],
+    rows: Vec<Row>,
+    /// The row selected, always a line rather than a file.
+    selected: usize,
+    /// The first row shown.
+    top: usize,
+    /// The lines of each file previewed so far, or none if it cannot be read.
+    sources: HashMap<usize, Option<Vec<String>>>,
+    /// A message shown in place of the help, until the next key.
+    status: Option<String>,
+}
+
+impl Browser<'_> {
+    fn run(&mut self, mut tty: Tty) -> io::Result<()> {
+        loop {
+            let (height, width) = tty.size();
+            let list = (height - 2) / 2;
+            self.scroll(list);
+            let screen = self.draw(height, width, list);
+            tty.file.write_all(&screen)?;
+            tty.file.flush()?;
+            let key = tty.key()?;
+            self.status = None;
+            match key {
+                Key::Char(b'q' | 0x1b | 3) => return Ok(()),
+                Key::Down | Key::Char(b'j' | 14) => self.step(1),
+                Key::Up | Key::Char(b'k' | 16) => self.step(-1),
+                Key::PageDown | Key::Char(b' ' | 6) => self.step(list as isize),
+                Key::PageUp | Key::Char(b'b' | 2) => self.step(-(list as isize)),
+                Key::Home | Key::Char(b'g') => self.step(-(self.rows.len() as isize)),
+                Key::End | Key::Char(b'G') => self.step(self.rows.len() as isize),
+                Key::Char(b'n') => self.file_step(1),
+                Key::Char(b'p') => self.file_step(-1),
+                Key::Char(b'\r' | b'\n' | b'o' | b'e') => self.open(&mut tty)?,
+                Key::Char(_) => {}
+            }
+        }
+    }
+
+    /// Moves the selection by n lines, passing over the rows of files.
+    fn step(&mut self, n: isize) {
+        let hits: Vec<usize> = (0..self.rows.len())
+            .filter(|&i| matches!(self.rows[i], Row::Hit(..)))
+            .collect();
+        let at = hits.iter().position(|&i| i == self.selected).unwrap_or(0);
+        let to = (at as isize + n).clamp(0, hits.len() as isize - 1);
+        self.selected = hits[to as usize];
+    }
+
+    /// Moves the selection to the first line of the nth file from this one.
+    fn file_step(&mut self, n: isize) {
+        let Row::Hit(file, _) = self.rows[self.selected] else {
+            return;
+        };
+        let to = (file as isize + n).clamp(0, self.files.len() as isize - 1) as usize;
+        let row = self
+            .rows
+            .iter()
+            .position(|r| matches!(r, Row::File(f) if *f == to));
+        self.selected = row.map_or(self.selected, |row| row + 1);
+    }
+
+    /// Scrolls so the selection, and the file it is in, are among the list
+    /// rows shown.
+    fn scroll(&mut self, list: usize) {
+        if self.selected < self.top + 1 {
+            self.top = self.selected.saturating_sub(1);
+        }
+        if self.selected >= self.top + list {
+            self.top = self.selected + 1 - list;
+        }
+    }
+
+    fn draw(&mut self, height: usize, width: usize, list: usize) -> Vec<u8> {
+        let mut out = b"\x1b[H".to_vec();
+        for i in self.top..self.top + list {
+            let line = match self.rows.get(i) {
+                Some(&Row::File(f)) => {
+                    let file = &self.files[f];
+                    format!("{} ({})", file.label, file.hits.len())
+                }
+                Some(&Row::Hit(f, h)) => {
+                    let hit = &self.files[f].hits[h];
+                    format!("  {:>5}: {}", hit.line, hit.text)
+                }
+                None => String::new(),
+            };
+            let style = match self.rows.get(i) {
+                _ if i == self.selected => "\x1b[7m",
+                Some(Row::File(_)) => "\x1b[1m",
+                _ => "",
+            };
+            row(&mut out, style, &line, width);
+        }
+        let Row::Hit(f, h) = self.rows[self.selected] else {
+            unreachable!("a line is always selected")
+        };
+        let (label, hit) = (&self.files[f].label, &self.files[f].hits[h]);
+        let at = match hit.column {
+            0 => format!("{}:{}", label, hit.line),
+            column => format!("{}:{}:{}", label, hit.line, column),
+        };
+        row(&mut out, "\x1b[1m", &format!("── {}", at), width);
+        let preview = height - list - 2;
+        let lines = self.sources.entry(f).or_insert_with(|| {
+            read_lossy(label)
+                .ok()
+                .map(|s| s.lines().map(str::to_string).collect())
+        });
+        match lines {
+            Some(lines) => {
+                let at = hit.line as usize - 1;
+                let first = at.saturating_sub(preview / 2);
+                for n in first..first + preview {
+                    let text = lines
+                        .get(n)
+                        .map_or(String::new(), |l| format!("{:>5}: {}", n + 1, l));
+                    let style = if n == at { "\x1b[7m" } else { "" };
+                    row(&mut out, style, &text, width);
+                }
+            }
+            None => {
+                row(
+                    &mut out,
+                    "\x1b[7m",
+                    &format!("{:>5}: {}", hit.line, hit.text),
+                    width,
+                );
+                for _ in 1..preview {
+                    row(&mut out, "", "", width);
+                }
+            }
+        }
+        let count = self.rows[..=self.selected]
+            .iter()
+            .filter(|r| matches!(r, Row::Hit(..)))
+            .count();
+        let total = self.files.iter().map(|f| f.hits.len()).sum::<usize>();
+        let status = self.status.as_deref().unwrap_or(HELP);
+        let status = format!("{}/{}  {}", count, total, status);
+        out.extend_from_slice(b"\x1b[7m");
+        out.extend_from_slice(fit(&status, width).as_bytes());
+        out.extend_from_slice(b"\x1b[K\x1b[m");
+        out
+    }
+
+    /// Opens the selected line in the editor, coming back once it exits.
+    fn open(&mut self, tty: &mut Tty) -> io::Result<()> {
+        let Row::Hit(f, h) = self.rows[self.selected] else {
+            return Ok(());
+        };
+        let (label, hit) = (&self.files[f].label, &self.files[f].hits[h]);
+        let editor = ["VISUAL", "EDITOR"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|e| !e.trim().is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return Ok(());
+        };
+        tty.leave()?;
+        let status = Command::new(program)
+            .args(words)
+            .arg(format!("+{}", hit.line))
+            .arg(label)
+            .stdin(tty.file.try_clone()?)
+            .stdout(tty.file.try_clone()?)
+            .stderr(tty.file.try_clone()?)
+            .status();
+        tty.enter()?;
+        self.status = match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("{} exited with {}", program, status)),
+            Err(e) => Some(format!("cannot run {}: {}", program, e)),
+        };
+        // What the editor changed is shown as it is now.
+        self.sources.remove(&f);
+        Ok(())
+    }
+}
+
+/// Appends text as a row of the screen, cut to width and in style.
+fn row(out: &mut Vec<u8>, style: &str, text: &str, width: usize) {
+    out.extend_from_slice(style.as_bytes());
+    out.extend_from_slice(fit(text, width).as_bytes());
+    out.extend_from_slice(b"\x1b[K\x1b[m\r\n");
+}
+
+/// Returns text with tabs expanded and other control characters shown as
+/// `?`, cut to width characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut n = 0;
+    for c in text.chars() {
+        let (c, times) = match c {
+            '\t' => (' ', 8 - n % 8),
+            c if c.is_control() => ('?', 1),
+            c => (c, 1),
+        };
+        for _ in 0..times {
+            if n == width {
+                return out;
+            }
+            out.push(c);
+            n += 1;
+        }
+    }
+    out
+}
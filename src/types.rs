@@ -0,0 +1,103 @@
+//! File types, each a name for the globs matching its files, as selected
+//! with `--type` and `--type-not`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+
+/// The built-in types and the file name globs of each.
+const DEFAULT_TYPES: &[(&str, &[&str])] = &[
+    ("c", &["*.[chH]", "*.[chH].in", "*.cats"]),
+    ("cmake", &["*.cmake", "CMakeLists.txt"]),
+    (
+        "cpp",
+        &["*.[ChH]", "*.cc", "*.[ch]pp", "*.[ch]xx", "*.hh", "*.inl"],
+    ),
+    ("cs", &["*.cs"]),
+    ("css", &["*.css", "*.scss"]),
+    ("docker", &["*Dockerfile*"]),
+    ("go", &["*.go"]),
+    ("html", &["*.htm", "*.html", "*.ejs"]),
+    ("java", &["*.java", "*.jsp"]),
+    ("js", &["*.js", "*.jsx", "*.mjs", "*.cjs", "*.vue"]),
+    ("json", &["*.json", "composer.lock"]),
+    ("lua", &["*.lua"]),
+    (
+        "make",
+        &["[Gg][Nn][Uu]makefile", "[Mm]akefile", "*.mk", "*.mak"],
+    ),
+    ("markdown", &["*.markdown", "*.md", "*.mdown", "*.mkdn"]),
+    ("md", &["*.markdown", "*.md", "*.mdown", "*.mkdn"]),
+    ("php", &["*.php", "*.php[3457]", "*.phtml"]),
+    ("py", &["*.py", "*.pyi"]),
+    ("rb", &["*.rb", "*.gemspec", "Gemfile", "Rakefile"]),
+    ("rust", &["*.rs"]),
+    (
+        "sh",
+        &["*.sh", "*.bash", "*.zsh", ".bashrc", ".zshrc", ".profile"],
+    ),
+    ("sql", &["*.sql", "*.psql"]),
+    ("swift", &["*.swift"]),
+    ("toml", &["*.toml", "Cargo.lock"]),
+    ("ts", &["*.ts", "*.tsx", "*.cts", "*.mts"]),
+    ("txt", &["*.txt"]),
+    ("xml", &["*.xml", "*.xml.dist"]),
+    ("yaml", &["*.yaml", "*.yml"]),
+];
+
+/// The known file types by name, the built-in ones and those added with
+/// `--type-add`.
+#[derive(Debug)]
+pub struct Types {
+    types: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Types {
+    /// The built-in types.
+    fn default() -> Types {
+        let types = DEFAULT_TYPES
+            .iter()
+            .map(|(name, globs)| {
+                (
+                    name.to_string(),
+                    globs.iter().map(|g| g.to_string()).collect(),
+                )
+            })
+            .collect();
+        Types { types }
+    }
+}
+
+impl Types {
+    /// Adds the glob of a `NAME:GLOB` definition to type NAME, creating it
+    /// if it is new.
+    pub fn add(&mut self, def: &str) -> Result<()> {
+        let Some((name, glob)) = def
+            .split_once(':')
+            .filter(|(n, g)| !n.is_empty() && !g.is_empty())
+        else {
+            bail!("invalid file type definition '{}', expected NAME:GLOB", def);
+        };
+        self.types
+            .entry(name.to_string())
+            .or_default()
+            .push(glob.to_string());
+        Ok(())
+    }
+
+    /// Returns the globs of the type named name.
+    pub fn globs(&self, name: &str) -> Result<&[String]> {
+        self.types
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| anyhow!("unrecognized file type: {}", name))
+    }
+
+    /// Returns the text printed by `--type-list`: each type with its globs.
+    pub fn list(&self) -> String {
+        self.types
+            .iter()
+            .map(|(name, globs)| format!("{}: {}\n", name, globs.join(", ")))
+            .collect()
+    }
+}
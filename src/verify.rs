@@ -0,0 +1,191 @@
+//! `--verify`: checking the lines a search selects against another
+//! engine's, to find patterns the engine here gets wrong.
+//!
+//! The oracle is the system `grep`, given the same patterns and the options
+//! that change what is selected, or with the `oracle` feature, the `regex`
+//! crate, which reads extended patterns only and approximates `-w`.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use anyhow::{bail, Result};
+
+use crate::{Args, Syntax};
+
+/// Another engine to select lines with.
+pub struct Oracle {
+    engine: Engine,
+    /// What ends lines.
+    terminator: u8,
+}
+
+enum Engine {
+    /// The system `grep`, run with these arguments over the input.
+    Grep(Vec<String>),
+    #[cfg(feature = "oracle")]
+    Regex(Lines),
+}
+
+impl Oracle {
+    /// Returns the oracle named name, set up to search as args do.
+    pub fn new(name: &str, args: &Args) -> Result<Oracle> {
+        if args.multiline {
+            bail!("--verify checks lines, so cannot be used with -U");
+        }
+        let engine = match name {
+            "grep" => Engine::Grep(grep_args(args)),
+            #[cfg(feature = "oracle")]
+            "regex" => Engine::Regex(Lines::new(args)?),
+            #[cfg(not(feature = "oracle"))]
+            "regex" => bail!("putao was built without the regex oracle, the 'oracle' feature"),
+            _ => bail!("invalid argument '{}' for '--verify'", name),
+        };
+        let terminator = if args.null_data { 0 } else { b'\n' };
+        Ok(Oracle { engine, terminator })
+    }
+
+    /// Returns what the oracle is called in reports.
+    pub fn name(&self) -> &'static str {
+        match self.engine {
+            Engine::Grep(_) => "grep",
+            #[cfg(feature = "oracle")]
+            Engine::Regex(_) => "regex",
+        }
+    }
+
+    /// Returns the numbers of the lines the oracle selects in data.
+    pub fn select(&self, data: &[u8]) -> io::Result<Vec<u64>> {
+        match &self.engine {
+            Engine::Grep(argv) => grep(argv, data, self.terminator),
+            #[cfg(feature = "oracle")]
+            Engine::Regex(lines) => Ok(lines.select(data, self.terminator)),
+        }
+    }
+
+    /// Returns the lines of data, without their terminators.
+    pub fn lines<'d>(&self, data: &'d [u8]) -> Vec<&'d [u8]> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let data = data.strip_suffix(&[self.terminator]).unwrap_or(data);
+        data.split(|&b| b == self.terminator).collect()
+    }
+}
+
+/// Returns the arguments to `grep` that select lines as args do.
+fn grep_args(args: &Args) -> Vec<String> {
+    let syntax = match args.syntax {
+        _ if args.fixed_strings => "-F",
+        Syntax::Basic => "-G",
+        Syntax::Extended => "-E",
+        Syntax::Perl => "-P",
+    };
+    let mut argv: Vec<String> = ["-n", "-h", "-a", "--color=never", syntax]
+        .map(String::from)
+        .to_vec();
+    let options = [
+        (args.regex().ignores_case(), "-i"),
+        (args.word, "-w"),
+        (args.line_regexp, "-x"),
+        (args.invert_match, "-v"),
+        (args.null_data, "-z"),
+    ];
+    argv.extend(options.iter().filter(|o| o.0).map(|o| o.1.to_string()));
+    if let Some(max) = args.max_count {
+        argv.push(format!("--max-count={}", max));
+    }
+    for pattern in &args.patterns {
+        argv.push("-e".to_string());
+        argv.push(pattern.clone());
+    }
+    argv
+}
+
+/// Runs `grep` with argv over data, returning the numbers of the lines it
+/// printed.
+fn grep(argv: &[String], data: &[u8], end: u8) -> io::Result<Vec<u64>> {
+    let mut child = Command::new("grep")
+        .args(argv)
+        // Patterns and input are read as UTF-8 here, whatever the locale.
+        .env("LC_ALL", "C.UTF-8")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run grep: {}", e)))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // Written on a thread of its own, so grep never waits on its output
+    // being read while data is.
+    let out = thread::scope(|s| {
+        s.spawn(move || {
+            let _ = stdin.write_all(data);
+        });
+        child.wait_with_output()
+    })?;
+    if out.status.code() == Some(2) || out.status.code().is_none() {
+        let err = String::from_utf8_lossy(&out.stderr);
+        let err = err.lines().next().unwrap_or("grep failed");
+        return Err(io::Error::other(err.to_string()));
+    }
+    let numbers = out.stdout.split(|&b| b == end).filter_map(|record| {
+        let digits = record.iter().position(|&b| b == b':')?;
+        std::str::from_utf8(&record[..digits]).ok()?.parse().ok()
+    });
+    Ok(numbers.collect())
+}
+
+/// Selects lines with the `regex` crate.
+#[cfg(feature = "oracle")]
+pub struct Lines {
+    re: regex::bytes::Regex,
+    invert: bool,
+    max_count: Option<u64>,
+}
+
+#[cfg(feature = "oracle")]
+impl Lines {
+    fn new(args: &Args) -> Result<Lines> {
+        if args.syntax == Syntax::Basic && !args.fixed_strings {
+            bail!("the regex oracle reads extended patterns only; give -E");
+        }
+        let patterns: Vec<_> = args
+            .patterns
+            .iter()
+            .map(|p| match args.fixed_strings {
+                true => regex::escape(p),
+                false => p.clone(),
+            })
+            .map(|p| format!("(?:{})", p))
+            .collect();
+        let any = patterns.join("|");
+        let pattern = match (args.line_regexp, args.word) {
+            (true, _) => format!("^(?:{})$", any),
+            // A match with no word character on either side of it.
+            (false, true) => format!(r"(?:^|\W)(?:{})(?:\W|$)", any),
+            (false, false) => any,
+        };
+        let re = regex::bytes::RegexBuilder::new(&pattern)
+            .case_insensitive(args.regex().ignores_case())
+            .build()
+            .map_err(|e| anyhow::anyhow!("the regex oracle cannot read the patterns: {}", e))?;
+        Ok(Lines {
+            re,
+            invert: args.invert_match,
+            max_count: args.max_count,
+        })
+    }
+
+    fn select(&self, data: &[u8], end: u8) -> Vec<u64> {
+        if data.is_empty() {
+            return Vec::new();
+        }
+        let data = data.strip_suffix(&[end]).unwrap_or(data);
+        let lines = data.split(|&b| b == end).zip(1..);
+        let selected = lines
+            .filter(|(line, _)| self.re.is_match(line) != self.invert)
+            .map(|(_, number)| number);
+        let max = self.max_count.map_or(usize::MAX, |n| n as usize);
+        selected.take(max).collect()
+    }
+}
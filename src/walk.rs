@@ -0,0 +1,406 @@
+//! Recursive directory traversal for searching trees of files.
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs::{self, FileType};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ignore::Gitignore;
+
+/// A file or directory visited by a [`Walker`].
+#[derive(Clone, Debug)]
+pub struct Entry {
+    path: PathBuf,
+    depth: usize,
+    file_type: FileType,
+}
+
+impl Entry {
+    /// The path of the entry, starting with the walked root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many directories below the root the entry is; the root itself is 0.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The path of the entry below the root, with `/` between its
+    /// components; empty for the root itself.
+    pub fn relative_path(&self) -> String {
+        let components: Vec<_> = self.path.components().collect();
+        let below = &components[components.len().saturating_sub(self.depth)..];
+        let names: Vec<_> = below
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        names.join("/")
+    }
+
+    /// The type of the entry, after following symlinks if enabled.
+    pub fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    fn error(&self, err: io::Error) -> Error {
+        Error::io(self.path.clone(), err)
+    }
+}
+
+/// A path a [`Walker`] could not read, such as a directory it may not list.
+#[derive(Debug)]
+pub struct Error {
+    path: PathBuf,
+    err: io::Error,
+    /// The directory above that a looping symlink leads back to.
+    ancestor: Option<PathBuf>,
+}
+
+impl Error {
+    fn io(path: PathBuf, err: io::Error) -> Error {
+        Error {
+            path,
+            err,
+            ancestor: None,
+        }
+    }
+
+    /// The path that could not be read.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// What went wrong reading it.
+    pub fn io_error(&self) -> &io::Error {
+        &self.err
+    }
+
+    /// Returns what went wrong, without the path.
+    pub fn into_io_error(self) -> io::Error {
+        self.err
+    }
+
+    /// The directory the path leads back to, if it is a symlink looping to
+    /// one above it; such a path is skipped rather than walked again.
+    pub fn loop_ancestor(&self) -> Option<&Path> {
+        self.ancestor.as_deref()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.err)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        io::Error::new(e.err.kind(), e)
+    }
+}
+
+type Filter = Box<dyn Fn(&Entry) -> bool + Send + Sync>;
+
+/// Walks a directory tree, reporting every regular file to a callback.
+///
+/// ```no_run
+/// use putao_cf::walk::Walker;
+///
+/// let mut walker = Walker::new();
+/// walker.max_depth(Some(2)).filter(|e| !e.path().ends_with("target"));
+/// walker.run("src".as_ref(), |e| {
+///     println!("{}", e.path().display());
+///     Ok(true)
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Walker {
+    max_depth: Option<usize>,
+    follow_links: bool,
+    filters: Vec<Filter>,
+    ignore_files: bool,
+    skip_hidden: bool,
+    sort: Option<Sort>,
+    max_filesize: Option<u64>,
+    /// Rules applying below every root.
+    ignores: Vec<Gitignore>,
+}
+
+/// The order a [`Walker`] visits the entries of each directory in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sort {
+    /// By name.
+    Path,
+    /// Most recently modified first.
+    Modified,
+    /// Smallest first.
+    Size,
+}
+
+/// The names of the ignore files read in each directory, with what git
+/// keeps in `.git/info/exclude`.
+const IGNORE_FILES: &[&str] = &[".git/info/exclude", ".gitignore", ".ignore"];
+
+/// What tells a directory apart however it is reached: its device and
+/// inode on Unix, its canonical path elsewhere.
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    let md = fs::metadata(path)?;
+    Ok((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> io::Result<DirId> {
+    fs::canonicalize(path)
+}
+
+/// What is carried down from the directories above an entry.
+#[derive(Default)]
+struct Above {
+    /// The identities and paths of the directories, when following symlinks.
+    dirs: Vec<(DirId, PathBuf)>,
+    /// The ignore rules in force, with the directory each is relative to.
+    ignores: Vec<(PathBuf, Gitignore)>,
+}
+
+impl Walker {
+    /// Creates a walker with no depth limit that does not follow symlinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits how deep below the root entries are visited.
+    pub fn max_depth(&mut self, depth: Option<usize>) -> &mut Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets whether symlinks are followed instead of skipped; a link back to
+    /// a directory being walked is skipped rather than looping.
+    pub fn follow_links(&mut self, yes: bool) -> &mut Self {
+        self.follow_links = yes;
+        self
+    }
+
+    /// Sets whether `.gitignore` and `.ignore` files, and
+    /// `.git/info/exclude`, are read in every directory walked, leaving out
+    /// the entries below it they ignore.
+    pub fn ignore_files(&mut self, yes: bool) -> &mut Self {
+        self.ignore_files = yes;
+        self
+    }
+
+    /// Sets whether entries below the root whose names start with `.` are
+    /// left out, with everything below them.
+    pub fn skip_hidden(&mut self, yes: bool) -> &mut Self {
+        self.skip_hidden = yes;
+        self
+    }
+
+    /// Visits the entries of each directory in the given order rather than
+    /// the one the file system lists them in.
+    pub fn sort(&mut self, sort: Option<Sort>) -> &mut Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Leaves out files below the root larger than size bytes.
+    pub fn max_filesize(&mut self, size: Option<u64>) -> &mut Self {
+        self.max_filesize = size;
+        self
+    }
+
+    /// Adds ignore rules relative to every root, such as git's global
+    /// excludes file.
+    pub fn add_ignore(&mut self, ignore: Gitignore) -> &mut Self {
+        self.ignores.push(ignore);
+        self
+    }
+
+    /// Adds a predicate every entry below the root must pass; a rejected
+    /// directory is not descended into.
+    pub fn filter<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&Entry) -> bool + Send + Sync + 'static,
+    {
+        self.filters.push(Box::new(f));
+        self
+    }
+
+    /// Walks root, calling f for every file until it returns false; a root
+    /// that is a file is reported as is. The walk stops at the first path
+    /// that cannot be read; symlinks looping back are skipped.
+    pub fn run<F>(&self, root: &Path, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&Entry) -> io::Result<bool>,
+    {
+        let mut first = None;
+        self.run_with_errors(root, |entry| match entry {
+            Ok(entry) => f(entry),
+            Err(e) if e.loop_ancestor().is_some() => Ok(true),
+            Err(e) => {
+                first = Some(e);
+                Ok(false)
+            }
+        })?;
+        first.map_or(Ok(()), |e| Err(e.into()))
+    }
+
+    /// Like [`Walker::run`], but calls f with every path that cannot be
+    /// read, root included, and goes on walking unless it returns false.
+    pub fn run_with_errors<F>(&self, root: &Path, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(Result<&Entry, Error>) -> io::Result<bool>,
+    {
+        let md = match fs::metadata(root) {
+            Ok(md) => md,
+            Err(err) => {
+                return f(Err(Error::io(root.to_path_buf(), err))).map(|_| ());
+            }
+        };
+        let entry = Entry {
+            path: root.to_path_buf(),
+            depth: 0,
+            file_type: md.file_type(),
+        };
+        let mut above = Above::default();
+        for ignore in &self.ignores {
+            above.ignores.push((root.to_path_buf(), ignore.clone()));
+        }
+        self.visit(&entry, &mut f, &mut above).map(|_| ())
+    }
+
+    /// Visits entry and everything below it, returning false once f asks to
+    /// stop.
+    fn visit<F>(&self, entry: &Entry, f: &mut F, above: &mut Above) -> io::Result<bool>
+    where
+        F: FnMut(Result<&Entry, Error>) -> io::Result<bool>,
+    {
+        // A root is reported unless it is a directory, so a named pipe or
+        // device given as one is read; below the root such files are skipped.
+        if entry.file_type.is_file() || (entry.depth == 0 && !entry.file_type.is_dir()) {
+            return f(Ok(entry));
+        }
+        if !entry.file_type.is_dir() || self.max_depth.is_some_and(|d| entry.depth >= d) {
+            return Ok(true);
+        }
+        let (dirs, ignores) = (above.dirs.len(), above.ignores.len());
+        if self.follow_links {
+            let dir = match dir_id(&entry.path) {
+                Ok(dir) => dir,
+                Err(err) => return f(Err(entry.error(err))),
+            };
+            if let Some((_, ancestor)) = above.dirs.iter().find(|(id, _)| *id == dir) {
+                return f(Err(Error {
+                    path: entry.path.clone(),
+                    err: io::Error::other("file system loop found"),
+                    ancestor: Some(ancestor.clone()),
+                }));
+            }
+            above.dirs.push((dir, entry.path.clone()));
+        }
+        if self.ignore_files {
+            for name in IGNORE_FILES {
+                if let Ok(ignore) = Gitignore::from_path(&entry.path.join(name)) {
+                    if !ignore.is_empty() {
+                        above.ignores.push((entry.path.clone(), ignore));
+                    }
+                }
+            }
+        }
+        let more = self.visit_children(entry, f, above);
+        above.dirs.truncate(dirs);
+        above.ignores.truncate(ignores);
+        more
+    }
+
+    /// Reports whether the innermost ignore rule matching entry ignores it.
+    fn ignored(&self, entry: &Entry, above: &Above) -> bool {
+        let is_dir = entry.file_type.is_dir();
+        above.ignores.iter().rev().find_map(|(base, ignore)| {
+            let rel = entry.path.strip_prefix(base).ok()?;
+            let rel: Vec<_> = rel
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            ignore.matched(&rel.join("/"), is_dir)
+        }) == Some(true)
+    }
+
+    /// Reports whether the file at path is over the size limit.
+    fn too_large(&self, path: &Path) -> bool {
+        self.max_filesize
+            .is_some_and(|max| fs::metadata(path).is_ok_and(|md| md.len() > max))
+    }
+
+    /// Visits everything below the directory entry.
+    fn visit_children<F>(&self, entry: &Entry, f: &mut F, above: &mut Above) -> io::Result<bool>
+    where
+        F: FnMut(Result<&Entry, Error>) -> io::Result<bool>,
+    {
+        let listed = fs::read_dir(&entry.path).and_then(|d| d.collect::<io::Result<Vec<_>>>());
+        let mut dents = match listed {
+            Ok(dents) => dents,
+            Err(err) => return f(Err(entry.error(err))),
+        };
+        match self.sort {
+            None => {}
+            Some(Sort::Path) => dents.sort_by_key(|d| d.file_name()),
+            Some(Sort::Modified) => {
+                let modified = |d: &fs::DirEntry| d.metadata().and_then(|m| m.modified()).ok();
+                dents.sort_by_cached_key(|d| Reverse(modified(d)));
+            }
+            Some(Sort::Size) => dents.sort_by_cached_key(|d| d.metadata().map_or(0, |m| m.len())),
+        }
+        for dent in dents {
+            if self.skip_hidden && dent.file_name().as_encoded_bytes().starts_with(b".") {
+                continue;
+            }
+            let path = dent.path();
+            let file_type = dent.file_type().and_then(|t| match t.is_symlink() {
+                true if self.follow_links => fs::metadata(&path).map(|md| md.file_type()),
+                _ => Ok(t),
+            });
+            let file_type = match file_type {
+                Ok(t) if t.is_symlink() => continue,
+                Ok(t) => t,
+                Err(err) => {
+                    if !f(Err(Error::io(path, err)))? {
+                        return Ok(false);
+                    }
+                    continue;
+                }
+            };
+            let child = Entry {
+                path,
+                depth: entry.depth + 1,
+                file_type,
+            };
+            if self.ignored(&child, above) || !self.filters.iter().all(|keep| keep(&child)) {
+                continue;
+            }
+            if file_type.is_file() && self.too_large(&child.path) {
+                continue;
+            }
+            if !self.visit(&child, f, above)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,78 @@
+//! JavaScript bindings via wasm-bindgen, enabled by the `wasm` feature; the
+//! crate in `wasm/` builds them with wasm-pack.
+//!
+//! Offsets reported to JavaScript are in UTF-16 code units, matching
+//! `String.prototype.slice`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::Regex;
+
+/// A compiled pattern, exported to JavaScript as `Regex`.
+#[wasm_bindgen(js_name = Regex)]
+pub struct JsRegex(Regex);
+
+/// A match, exported to JavaScript as `Match`.
+#[wasm_bindgen(js_name = Match)]
+pub struct JsMatch {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Compiles a pattern, throwing an `Error` with the syntax error message.
+#[wasm_bindgen]
+pub fn compile(pattern: &str) -> Result<JsRegex, JsError> {
+    Regex::new(pattern)
+        .map(JsRegex)
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_class = Regex)]
+impl JsRegex {
+    /// Same as [`compile`], usable as `new Regex(pattern)`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(pattern: &str) -> Result<JsRegex, JsError> {
+        compile(pattern)
+    }
+
+    /// Reports whether the pattern matches anywhere in the haystack.
+    #[wasm_bindgen(js_name = isMatch)]
+    pub fn is_match(&self, hay: &str) -> bool {
+        self.0.is_match(hay)
+    }
+
+    /// Returns the leftmost match, or `undefined`.
+    pub fn find(&self, hay: &str) -> Option<JsMatch> {
+        self.0.find(hay).map(|m| JsMatch {
+            start: utf16_len(&hay[..m.start()]),
+            end: utf16_len(&hay[..m.end()]),
+            text: m.as_str().to_string(),
+        })
+    }
+}
+
+#[wasm_bindgen(js_class = Match)]
+impl JsMatch {
+    /// UTF-16 offset where the match starts.
+    #[wasm_bindgen(getter)]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// UTF-16 offset just past the end of the match.
+    #[wasm_bindgen(getter)]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// The matched text.
+    #[wasm_bindgen(getter)]
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
@@ -0,0 +1,131 @@
+//! `--watch`: noticing files that changed since they were last searched.
+//!
+//! Files are polled rather than watched through the OS, so this works the
+//! same everywhere and with nothing more to depend on. A file that only
+//! grew is searched from the end of the last whole line read before, the
+//! way a log is followed; one rewritten, truncated or new is searched whole.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long to wait between looking for changes.
+pub const INTERVAL: Duration = Duration::from_millis(250);
+
+/// How a file changed since it was last looked at.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Whole lines were appended, making up bytes `from..to`, the first of
+    /// them line number `line`.
+    Appended { from: u64, to: u64, line: u64 },
+    /// The file is new or was rewritten, so all of it is to be searched.
+    Whole,
+}
+
+/// What a file looked like when last seen.
+struct Seen {
+    len: u64,
+    modified: Option<SystemTime>,
+    /// Identifies the file itself, so one saved in its place is told apart.
+    id: Option<u64>,
+    /// Where the last whole line ends.
+    done: u64,
+    /// How many whole lines there are before done.
+    lines: u64,
+}
+
+/// The files being watched.
+pub struct Watch {
+    files: HashMap<PathBuf, Seen>,
+    terminator: u8,
+}
+
+impl Watch {
+    /// Watches files whose lines end with terminator.
+    pub fn new(terminator: u8) -> Self {
+        Watch {
+            files: HashMap::new(),
+            terminator,
+        }
+    }
+
+    /// Remembers what the file at path looks like now, as just searched.
+    pub fn record(&mut self, path: &Path) -> io::Result<()> {
+        let md = fs::metadata(path)?;
+        let mut seen = Seen::new(&md);
+        seen.read(File::open(path)?, self.terminator)?;
+        self.files.insert(path.to_path_buf(), seen);
+        Ok(())
+    }
+
+    /// Returns how the file at path changed since last seen, if it did,
+    /// remembering what it looks like now. A file gone is forgotten, and is
+    /// new if it comes back.
+    pub fn change(&mut self, path: &Path) -> io::Result<Option<Change>> {
+        let md = match fs::metadata(path) {
+            Ok(md) => md,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.files.remove(path);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        let now = Seen::new(&md);
+        let (from, line) = match self.files.get(path) {
+            Some(seen) if (now.len, now.modified, now.id) == (seen.len, seen.modified, seen.id) => {
+                return Ok(None)
+            }
+            Some(seen) if now.id == seen.id && now.len >= seen.len => (seen.done, seen.lines + 1),
+            _ => {
+                self.record(path)?;
+                return Ok(Some(Change::Whole));
+            }
+        };
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(from))?;
+        let terminator = self.terminator;
+        let seen = self.files.get_mut(path).expect("file just seen");
+        seen.len = now.len;
+        seen.modified = now.modified;
+        seen.read(file.take(now.len - from), terminator)?;
+        Ok((seen.done > from).then_some(Change::Appended {
+            from,
+            to: seen.done,
+            line,
+        }))
+    }
+}
+
+impl Seen {
+    /// Starts describing a file by its metadata, with no lines read yet.
+    fn new(md: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let id = Some(std::os::unix::fs::MetadataExt::ino(md));
+        #[cfg(not(unix))]
+        let id = None;
+        Seen {
+            len: md.len(),
+            modified: md.modified().ok(),
+            id,
+            done: 0,
+            lines: 0,
+        }
+    }
+
+    /// Moves done past the whole lines read from rdr, which starts there.
+    fn read(&mut self, rdr: impl Read, terminator: u8) -> io::Result<()> {
+        let mut rdr = BufReader::new(rdr);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let n = rdr.read_until(terminator, &mut line)?;
+            if line.last() != Some(&terminator) {
+                return Ok(());
+            }
+            self.done += n as u64;
+            self.lines += 1;
+        }
+    }
+}
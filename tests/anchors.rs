@@ -0,0 +1,82 @@
+//! `^` and `$` inside groups and alternation branches, checked against the
+//! lines GNU grep 3.8 selects for the same patterns.
+
+mod common;
+
+use putao_cf::bytes::Regex;
+use putao_cf::RegexBuilder;
+
+const HAY: &str = "b\nxb\na\n*\nx^b\na$b\n$^\nfoo\nbar\n^bar\n";
+
+/// Returns the numbers of the lines of HAY that pattern selects, read as
+/// a POSIX basic pattern if basic, else as an extended one.
+fn lines(pattern: &str, basic: bool) -> Vec<u64> {
+    let re = RegexBuilder::new(pattern)
+        .basic(basic)
+        .posix(true)
+        .build()
+        .unwrap();
+    common::lines(&Regex::from(re), HAY)
+}
+
+#[test]
+fn extended_anchors_inside_branches() {
+    let cases: &[(&str, &[u64])] = &[
+        ("a|^b", &[1, 3, 6, 9, 10]),
+        ("^a|^b", &[1, 3, 6, 9]),
+        ("(^a)", &[3, 6]),
+        ("x(^a|b)", &[2]),
+        ("(^|x)b", &[1, 2, 9]),
+        ("^(foo|^bar)$", &[8, 9]),
+        ("a$|c", &[3]),
+        ("(a$)", &[3]),
+        ("(a$|b)", &[1, 2, 3, 5, 6, 9, 10]),
+        ("b$|x", &[1, 2, 5, 6]),
+        ("b($|x)", &[1, 2, 5, 6]),
+    ];
+    for (pattern, expected) in cases {
+        assert_eq!(lines(pattern, false), *expected, "pattern {:?}", pattern);
+    }
+}
+
+#[test]
+fn extended_anchors_mid_branch_never_match() {
+    assert!(lines("a^b", false).is_empty());
+    assert!(lines("a$b", false).is_empty());
+}
+
+#[test]
+fn extended_escaped_anchors_are_literal() {
+    assert_eq!(lines(r"\$", false), [6, 7]);
+    assert_eq!(lines(r"\^", false), [5, 7, 10]);
+}
+
+#[test]
+fn extended_repeated_anchor_matches_every_line() {
+    assert_eq!(lines("^*", false), (1..=10).collect::<Vec<_>>());
+}
+
+#[test]
+fn basic_anchors_inside_groups_and_branches() {
+    let cases: &[(&str, &[u64])] = &[
+        (r"a\|^b", &[1, 3, 6, 9, 10]),
+        (r"\(^a\)", &[3, 6]),
+        (r"x\(^a\|b\)", &[2]),
+        (r"\(^\|x\)b", &[1, 2, 9]),
+        (r"^\(foo\|^bar\)$", &[8, 9]),
+        ("b$", &[1, 2, 5, 6]),
+        (r"\(b$\)", &[1, 2, 5, 6]),
+        (r"b\($\|x\)", &[1, 2, 5, 6]),
+    ];
+    for (pattern, expected) in cases {
+        assert_eq!(lines(pattern, true), *expected, "pattern {:?}", pattern);
+    }
+}
+
+#[test]
+fn basic_anchors_elsewhere_are_literal() {
+    assert_eq!(lines("x^b", true), [5]);
+    assert_eq!(lines("a$b", true), [6]);
+    assert_eq!(lines("$^", true), [7]);
+    assert_eq!(lines("^*", true), [4]);
+}
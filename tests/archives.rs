@@ -0,0 +1,78 @@
+//! `--search-archives` reads past GNU long name and pax headers of any
+//! size to the entries they are for.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+/// Returns a tar header for an entry of the given kind, name and size.
+fn header(kind: u8, name: &str, size: usize) -> Vec<u8> {
+    let mut header = vec![0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = kind;
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+    header
+}
+
+/// Appends an entry, its data padded to whole blocks, to tar.
+fn entry(tar: &mut Vec<u8>, kind: u8, name: &str, data: &[u8]) {
+    tar.extend(header(kind, name, data.len()));
+    tar.extend_from_slice(data);
+    tar.resize(tar.len().div_ceil(512) * 512, 0);
+}
+
+/// Returns what putao prints searching a tar archive holding a file after
+/// a pax header of the given size, for hello.
+fn search_after_pax(name: &str, pax_size: usize) -> String {
+    let mut pax = b"30 path=dir/long-name.txt\n".to_vec();
+    pax.resize(pax_size - 1, b'x');
+    pax.push(b'\n');
+    let mut tar = Vec::new();
+    entry(&mut tar, b'x', "pax", &pax);
+    entry(&mut tar, b'0', "short.txt", b"hello\n");
+    tar.extend([0; 1024]);
+    let dir = scratch_dir(name);
+    fs::write(dir.join("a.tar"), tar).unwrap();
+    let out = putao(&dir)
+        .args(["--search-archives", "hello", "a.tar"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "");
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn pax_header_names_the_entry() {
+    let out = search_after_pax("tar-pax", 100);
+    assert_eq!(out, "a.tar!dir/long-name.txt:hello\n");
+}
+
+#[test]
+fn reads_past_an_oversized_pax_header() {
+    let out = search_after_pax("tar-pax-oversized", 3 << 20);
+    assert_eq!(out, "a.tar!dir/long-name.txt:hello\n");
+}
+
+#[test]
+fn reads_past_an_oversized_long_name() {
+    let mut tar = Vec::new();
+    entry(&mut tar, b'L', "././@LongLink", &vec![b'n'; (2 << 20) + 100]);
+    entry(&mut tar, b'0', "short.txt", b"hello\n");
+    entry(&mut tar, b'0', "b.txt", b"hello again\n");
+    tar.extend([0; 1024]);
+    let dir = scratch_dir("tar-long-name");
+    fs::write(dir.join("a.tar"), tar).unwrap();
+    let out = putao(&dir)
+        .args(["--search-archives", "again", "a.tar"])
+        .output()
+        .unwrap();
+    assert_eq!(out.stdout, b"a.tar!b.txt:hello again\n");
+}
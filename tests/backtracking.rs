@@ -0,0 +1,28 @@
+//! Repeated groups give back earlier iterations when what follows them
+//! would otherwise fail to match.
+
+use putao_cf::Regex;
+
+/// Returns the leftmost match of pattern in hay.
+fn find(pattern: &str, hay: &str) -> Option<String> {
+    let re = Regex::new(pattern).unwrap();
+    re.find(hay).map(|m| m.as_str().to_string())
+}
+
+#[test]
+fn counted_group_backtracks_into_earlier_iterations() {
+    assert_eq!(find("(.+){2}", "_1").as_deref(), Some("_1"));
+    assert_eq!(find("(a+){2}b", "aaab").as_deref(), Some("aaab"));
+}
+
+#[test]
+fn starred_group_backtracks_into_earlier_iterations() {
+    assert_eq!(find("(a|ab)+c", "abc").as_deref(), Some("abc"));
+    assert_eq!(find("(a|ab)*c", "ababc").as_deref(), Some("ababc"));
+    assert_eq!(find("(ab|a)+bc", "abc").as_deref(), Some("abc"));
+}
+
+#[test]
+fn optional_group_backtracks() {
+    assert_eq!(find("(ab|a)?bc", "abc").as_deref(), Some("abc"));
+}
@@ -0,0 +1,62 @@
+//! `--changed` searches only lines changed since a revision, which must be
+//! one: git is never handed a base it would read as an option.
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use common::{putao, scratch_dir};
+
+/// Runs git with args in dir, failing the test if it fails.
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// Returns a repository with one commit of a.txt, changed since.
+fn repo(name: &str) -> PathBuf {
+    let dir = scratch_dir(name);
+    git(&dir, &["init", "-q"]);
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    git(&dir, &["add", "a.txt"]);
+    git(&dir, &["commit", "-q", "-m", "init"]);
+    fs::write(dir.join("a.txt"), "one\ntwo one\n").unwrap();
+    dir
+}
+
+#[test]
+fn searches_only_changed_lines() {
+    let dir = repo("changed-lines");
+    let out = putao(&dir).args(["--changed", "one"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "a.txt:two one\n");
+}
+
+#[test]
+fn base_read_as_an_option_is_refused() {
+    let dir = repo("changed-option");
+    let target = dir.join("written");
+    let out = putao(&dir)
+        .arg(format!("--changed=--output={}", target.display()))
+        .arg("one")
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("invalid revision"));
+    assert!(!target.exists());
+}
+
+#[test]
+fn project_config_cannot_give_changed() {
+    let dir = repo("changed-putaorc");
+    fs::write(dir.join(".putaorc"), "--changed=HEAD\n").unwrap();
+    let out = putao(&dir).args(["one", "a.txt"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "one\ntwo one\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--changed is not read"));
+}
@@ -0,0 +1,105 @@
+//! Classes holding ranges and multibyte chars match whole chars by scalar
+//! value, never single bytes of a UTF-8 sequence.
+
+use putao_cf::{ErrorKind, Regex, RegexBuilder};
+
+/// Returns every match of pattern in hay.
+fn all(pattern: &str, hay: &str) -> Vec<String> {
+    let re = Regex::new(pattern).unwrap();
+    re.find_iter(hay).map(|m| m.as_str().to_string()).collect()
+}
+
+#[test]
+fn ascii_ranges() {
+    assert_eq!(all("[0-9]+", "a12b3"), ["12", "3"]);
+    assert_eq!(all("[a-cx-z]", "abdxw"), ["a", "b", "x"]);
+    assert_eq!(all("[^a-z]", "aB1"), ["B", "1"]);
+}
+
+#[test]
+fn non_ascii_ranges() {
+    assert_eq!(all("[À-ÿ]+", "naïve Ünï"), ["ï", "Ü", "ï"]);
+    assert_eq!(all("[一-龥]+", "日本語 and 中文"), ["日本語", "中文"]);
+    assert_eq!(all("[а-я]+", "привет, world"), ["привет"]);
+}
+
+#[test]
+fn ranges_span_encoded_lengths() {
+    // From one-byte through four-byte UTF-8.
+    assert_eq!(all("[~-😀]", "a~é中😀😁"), ["~", "é", "中", "😀"]);
+}
+
+#[test]
+fn multibyte_chars_listed_in_class() {
+    assert_eq!(all("[éü中]", "eéuü中文"), ["é", "ü", "中"]);
+    assert_eq!(all("[^é]", "éaé"), ["a"]);
+}
+
+#[test]
+fn negated_class_matches_whole_char() {
+    assert_eq!(all("[^a-z]", "aé中"), ["é", "中"]);
+    let re = Regex::new("x[^a-z]y").unwrap();
+    assert_eq!(re.find("x中y").map(|m| m.range()), Some(0..5));
+}
+
+#[test]
+fn invalid_utf8_reads_as_replacement_char_per_byte() {
+    let re = putao_cf::bytes::Regex::new("[^a-z]").unwrap();
+    let hay = b"a\xE4\xB8z";
+    assert_eq!(re.find(hay).map(|m| m.range()), Some(1..2));
+    let re = putao_cf::bytes::Regex::new("[À-ÿ]").unwrap();
+    assert!(!re.is_match(b"\xC3"));
+}
+
+#[test]
+fn dash_first_or_last_is_literal() {
+    assert_eq!(all("[-a]", "a-b"), ["a", "-"]);
+    assert_eq!(all("[a-]", "a-b"), ["a", "-"]);
+    assert_eq!(all("[é-]", "é-e"), ["é", "-"]);
+}
+
+#[test]
+fn escaped_range_ends_in_perl_classes() {
+    let re = RegexBuilder::new(r"[\--\/]+").build().unwrap();
+    assert_eq!(re.find("a-./b").map(|m| m.as_str()), Some("-./"));
+}
+
+#[test]
+fn reversed_range_is_an_error() {
+    let err = Regex::new("[z-a]").unwrap_err();
+    assert_eq!(err.kind(), Some(ErrorKind::InvalidClassRange));
+    assert_eq!(err.offset(), Some(1));
+    assert!(Regex::new("[ÿ-À]").is_err());
+}
+
+#[test]
+fn case_insensitive_ranges() {
+    let re = RegexBuilder::new("^[a-f]+$")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    assert!(re.is_match("BadCafe"));
+    let re = RegexBuilder::new("^[à-ÿ]+$")
+        .case_insensitive(true)
+        .unicode(true)
+        .build()
+        .unwrap();
+    assert!(re.is_match("ÉÀü"));
+}
+
+#[test]
+fn class_with_range_displays_as_written() {
+    let ast = putao_cf::ast::parse("[^a-zé-]").unwrap();
+    assert_eq!(ast.to_string(), r"[^é\-a-z]");
+}
+
+#[test]
+fn close_bracket_first_is_a_member() {
+    assert_eq!(all("[]a]+", "b]a]c"), ["]a]"]);
+    assert_eq!(all("[^]a]+", "]bc]"), ["bc"]);
+    let basic = RegexBuilder::new("[]a]\\+").basic(true).build().unwrap();
+    assert_eq!(basic.find("b]a").map(|m| m.as_str()), Some("]a"));
+    let ast = putao_cf::ast::parse("[]a]").unwrap();
+    assert_eq!(putao_cf::ast::parse(&ast.to_string()).unwrap(), ast);
+    assert!(Regex::new("[]").is_err());
+}
@@ -0,0 +1,52 @@
+//! `--colors` specs: the first one naming a part replaces its colors
+//! rather than adding to the defaults.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+/// Returns what putao prints for `foo` in a line holding it, given args.
+fn colored(name: &str, args: &[&str]) -> String {
+    let dir = scratch_dir(name);
+    fs::write(dir.join("a.txt"), "a foo b\n").unwrap();
+    let out = putao(&dir)
+        .arg("--color=always")
+        .args(args)
+        .args(["foo", "a.txt"])
+        .output()
+        .unwrap();
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn default_match_color() {
+    let out = colored("colors-default", &[]);
+    assert_eq!(out, "a \x1b[01;31m\x1b[Kfoo\x1b[m\x1b[K b\n");
+}
+
+#[test]
+fn spec_replaces_default() {
+    let out = colored("colors-replace", &["--colors", "match:fg:yellow"]);
+    assert_eq!(out, "a \x1b[33m\x1b[Kfoo\x1b[m\x1b[K b\n");
+}
+
+#[test]
+fn later_specs_add_to_earlier() {
+    let args = ["--colors", "match:fg:yellow", "--colors", "match:style:bold"];
+    let out = colored("colors-add", &args);
+    assert_eq!(out, "a \x1b[33;1m\x1b[Kfoo\x1b[m\x1b[K b\n");
+}
+
+#[test]
+fn spec_replaces_grep_colors() {
+    let dir = scratch_dir("colors-grep-colors");
+    fs::write(dir.join("a.txt"), "a foo b\n").unwrap();
+    let out = putao(&dir)
+        .env("GREP_COLORS", "ms=01;32")
+        .args(["--color=always", "--colors", "match:bg:blue", "foo", "a.txt"])
+        .output()
+        .unwrap();
+    assert_eq!(out.stdout, b"a \x1b[44m\x1b[Kfoo\x1b[m\x1b[K b\n");
+}
@@ -0,0 +1,46 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, io, process};
+
+use putao_cf::bytes::Regex;
+use putao_cf::searcher::{Searcher, Sink, SinkMatch};
+
+/// Collects the line numbers of matching lines.
+pub struct Lines(pub Vec<u64>);
+
+impl Sink for &mut Lines {
+    fn matched(&mut self, m: &SinkMatch<'_>) -> io::Result<bool> {
+        self.0.push(m.line_number());
+        Ok(true)
+    }
+}
+
+/// Returns the numbers of the lines of hay that re selects.
+pub fn lines(re: &Regex, hay: &str) -> Vec<u64> {
+    let mut lines = Lines(Vec::new());
+    Searcher::new()
+        .search_slice(re, hay.as_bytes(), &mut lines)
+        .unwrap();
+    lines.0
+}
+
+/// Returns a fresh, empty directory for the test named name to work in.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("putao-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Returns a command running putao in dir, reading no user config.
+pub fn putao(dir: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_putao"));
+    cmd.current_dir(dir)
+        .env_remove("PUTAO_CONFIG")
+        .env_remove("GREP_COLORS");
+    cmd
+}
@@ -0,0 +1,77 @@
+//! Cross-checks `is_match` against the `regex` crate on generated patterns
+//! drawn from the syntax both engines agree on.
+
+use proptest::prelude::*;
+
+/// A generated pattern.
+#[derive(Clone, Debug)]
+struct Pat {
+    src: String,
+    /// Whether a quantifier can follow it without wrapping it in a group.
+    atomic: bool,
+}
+
+fn leaf() -> impl Strategy<Value = Pat> {
+    let chars = vec!['a', 'b', 'c', '1', '_', ' '];
+    prop_oneof![
+        prop::sample::select(chars.clone()).prop_map(String::from),
+        Just(".".to_string()),
+        Just(r"\d".to_string()),
+        Just(r"\w".to_string()),
+        prop::sample::subsequence(chars.clone(), 1..=3)
+            .prop_map(|cs| format!("[{}]", cs.into_iter().collect::<String>())),
+        prop::sample::subsequence(chars, 1..=3)
+            .prop_map(|cs| format!("[^{}]", cs.into_iter().collect::<String>())),
+    ]
+    .prop_map(|src| Pat { src, atomic: true })
+}
+
+fn quantifier() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec!["?", "*", "+", "{2}", "{1,}", "{0,2}"])
+}
+
+fn pattern() -> impl Strategy<Value = String> {
+    let pat = leaf().prop_recursive(4, 24, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 1..4).prop_map(|ps| Pat {
+                atomic: ps.len() == 1 && ps[0].atomic,
+                src: ps.into_iter().map(|p| p.src).collect(),
+            }),
+            prop::collection::vec(inner.clone(), 1..3).prop_map(|ps| Pat {
+                atomic: true,
+                src: format!(
+                    "({})",
+                    ps.into_iter().map(|p| p.src).collect::<Vec<_>>().join("|")
+                ),
+            }),
+            (inner, quantifier()).prop_map(|(p, q)| {
+                let src = if p.atomic {
+                    p.src
+                } else {
+                    format!("({})", p.src)
+                };
+                Pat {
+                    src: format!("{}{}", src, q),
+                    atomic: false,
+                }
+            }),
+        ]
+    });
+    (any::<bool>(), pat, any::<bool>()).prop_map(|(start, p, end)| {
+        format!(
+            "{}{}{}",
+            if start { "^" } else { "" },
+            p.src,
+            if end { "$" } else { "" }
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn is_match_agrees_with_regex_crate(pattern in pattern(), hay in "[abc1_ \n-]{0,10}") {
+        let ours = putao_cf::Regex::new(&pattern).unwrap();
+        let oracle = regex::Regex::new(&pattern).unwrap();
+        prop_assert_eq!(ours.is_match(&hay), oracle.is_match(&hay), "pattern {:?}", pattern);
+    }
+}
@@ -0,0 +1,58 @@
+//! Patterns matching the empty string select every line, as in POSIX grep.
+
+mod common;
+
+use putao_cf::bytes::Regex;
+use putao_cf::searcher::Searcher;
+use putao_cf::RegexBuilder;
+
+use common::Lines;
+
+/// Returns the numbers of the lines of hay that pattern selects.
+fn lines(builder: &RegexBuilder, hay: &str) -> Vec<u64> {
+    common::lines(&Regex::from(builder.build().unwrap()), hay)
+}
+
+const HAY: &str = "one\n\nthree\n";
+
+#[test]
+fn empty_pattern_matches_everywhere() {
+    let re = putao_cf::Regex::new("").unwrap();
+    let starts: Vec<_> = re.find_iter("ab").map(|m| m.range()).collect();
+    assert_eq!(starts, [0..0, 1..1, 2..2]);
+    assert_eq!(lines(&RegexBuilder::new(""), HAY), [1, 2, 3]);
+    assert_eq!(lines(RegexBuilder::new("").basic(true), HAY), [1, 2, 3]);
+    assert_eq!(lines(RegexBuilder::new("").literal(true), HAY), [1, 2, 3]);
+}
+
+#[test]
+fn start_anchor_matches_every_line() {
+    let re = putao_cf::Regex::new("^").unwrap();
+    assert_eq!(re.find("ab").map(|m| m.range()), Some(0..0));
+    assert_eq!(lines(&RegexBuilder::new("^"), HAY), [1, 2, 3]);
+    assert_eq!(lines(RegexBuilder::new("^").basic(true), HAY), [1, 2, 3]);
+}
+
+#[test]
+fn end_anchor_matches_every_line() {
+    let re = putao_cf::Regex::new("$").unwrap();
+    assert_eq!(re.find("ab").map(|m| m.range()), Some(2..2));
+    assert_eq!(lines(&RegexBuilder::new("$"), HAY), [1, 2, 3]);
+    assert_eq!(lines(RegexBuilder::new("$").basic(true), HAY), [1, 2, 3]);
+}
+
+#[test]
+fn empty_pattern_among_others_matches_every_line() {
+    assert_eq!(lines(&RegexBuilder::new_many(["x", ""]), HAY), [1, 2, 3]);
+}
+
+#[test]
+fn empty_pattern_inverted_matches_no_line() {
+    let re = Regex::from(RegexBuilder::new("").build().unwrap());
+    let mut lines = Lines(Vec::new());
+    Searcher::new()
+        .invert_match(true)
+        .search_slice(&re, HAY.as_bytes(), &mut lines)
+        .unwrap();
+    assert!(lines.0.is_empty());
+}
@@ -0,0 +1,53 @@
+//! Perl escapes for word boundaries, whitespace, negated classes and
+//! control chars; in Perl syntax, other escaped letters are errors rather
+//! than literals.
+
+use putao_cf::{ErrorKind, Regex, RegexBuilder};
+
+/// Returns every match of pattern in hay.
+fn all(pattern: &str, hay: &str) -> Vec<String> {
+    let re = Regex::new(pattern).unwrap();
+    re.find_iter(hay).map(|m| m.as_str().to_string()).collect()
+}
+
+#[test]
+fn word_boundaries() {
+    assert_eq!(all(r"\bfoo\b", "foo bar"), ["foo"]);
+    assert!(all(r"\bfoo\b", "bfoob").is_empty());
+    assert_eq!(all(r"\Bo\B", "foo"), ["o"]);
+    assert_eq!(all(r"\b\w+\b", "a bc"), ["a", "bc"]);
+}
+
+#[test]
+fn space_and_negated_classes() {
+    assert_eq!(all(r"\s+", "a \t\nb"), [" \t\n"]);
+    assert!(all(r"\s", "sss").is_empty());
+    assert_eq!(all(r"\S+", "ab cd"), ["ab", "cd"]);
+    assert_eq!(all(r"\D+", "a1b2"), ["a", "b"]);
+    assert_eq!(all(r"\W+", "a, b"), [", "]);
+}
+
+#[test]
+fn control_escapes() {
+    assert_eq!(all(r"a\tb", "atb a\tb"), ["a\tb"]);
+    assert_eq!(all(r"[\t\r]+", "a\t\rb"), ["\t\r"]);
+}
+
+#[test]
+fn escapes_in_posix_syntax() {
+    let posix = |pattern| RegexBuilder::new(pattern).posix(true).build().unwrap();
+    let re = posix(r"\bfoo\b");
+    assert!(re.is_match("a foo") && !re.is_match("afoo"));
+    let re = posix(r"\z");
+    assert!(re.is_match("z"));
+}
+
+#[test]
+fn unknown_perl_escapes_are_errors() {
+    for pattern in [r"\z", r"\A", r"\0", r"[\q]"] {
+        let err = Regex::new(pattern).unwrap_err();
+        assert_eq!(err.kind(), Some(ErrorKind::UnsupportedEscape), "{}", pattern);
+    }
+    let err = Regex::new(r"[\s]").unwrap_err();
+    assert_eq!(err.kind(), Some(ErrorKind::UnsupportedClassEscape));
+}
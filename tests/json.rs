@@ -0,0 +1,53 @@
+//! `--json` writes text that is valid UTF-8 as `{"text":...}` and other
+//! bytes as `{"bytes":...}` in base64, never lossily.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+/// Returns the `match` messages putao prints for pattern in a file holding
+/// hay.
+fn matches(name: &str, hay: &[u8], pattern: &str) -> Vec<String> {
+    let dir = scratch_dir(name);
+    fs::write(dir.join("a.txt"), hay).unwrap();
+    let out = putao(&dir)
+        .args(["--json", pattern, "a.txt"])
+        .output()
+        .unwrap();
+    String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .filter(|l| l.starts_with(r#"{"type":"match""#))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn valid_utf8_is_text() {
+    let out = matches("json-text", "é foo\n".as_bytes(), "foo");
+    assert_eq!(out.len(), 1);
+    assert!(out[0].contains(r#""lines":{"text":"é foo\n"}"#), "{}", out[0]);
+    assert!(out[0].contains(r#"{"match":{"text":"foo"},"start":3,"end":6}"#));
+}
+
+#[test]
+fn invalid_utf8_is_base64() {
+    let out = matches("json-bytes", b"a\xffb foo\n", "a.b");
+    assert_eq!(out.len(), 1);
+    assert!(out[0].contains(r#""lines":{"bytes":"Yf9iIGZvbwo="}"#), "{}", out[0]);
+    assert!(out[0].contains(r#"{"match":{"bytes":"Yf9i"},"start":0,"end":3}"#));
+}
+
+#[test]
+fn base64_padding() {
+    for (hay, line) in [
+        (&b"\xff\n"[..], "/wo="),
+        (&b"\xffa\n"[..], "/2EK"),
+        (&b"\xffab\n"[..], "/2FiCg=="),
+    ] {
+        let out = matches("json-padding", hay, "^");
+        assert!(out[0].contains(&format!(r#"{{"bytes":"{}"}}"#, line)), "{}", out[0]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Long repetitions search without running out of room or time, and the
+//! state limit only fails the `try_` methods.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use putao_cf::{Error, Regex, RegexBuilder};
+
+/// Runs f on another thread, failing if it takes longer than secs.
+fn within<T: Send + 'static>(secs: u64, f: impl FnOnce() -> T + Send + 'static) -> T {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || tx.send(f()).unwrap());
+    rx.recv_timeout(Duration::from_secs(secs))
+        .expect("search took too long")
+}
+
+#[test]
+fn group_repetition_over_long_haystack_matches() {
+    let hay = "ab".repeat(100_000);
+    assert!(Regex::new("(a)+").unwrap().is_match(&hay));
+    let re = Regex::new("^(a|b)+$").unwrap();
+    assert_eq!(re.try_is_match(&hay), Ok(true));
+    let caps = re.captures(&hay).unwrap();
+    assert_eq!(caps.get(1).map(|m| m.range()), Some(hay.len() - 1..hay.len()));
+}
+
+#[test]
+fn state_limit_fails_only_try_methods() {
+    let hay = "ab".repeat(1_000) + "xc";
+    let re = RegexBuilder::new("(a|b)+c")
+        .state_limit(1 << 10)
+        .build()
+        .unwrap();
+    assert_eq!(re.try_is_match(&hay), Err(Error::StateLimit { limit: 1 << 10 }));
+    assert!(!re.is_match(&hay));
+
+    let hay = "ab".repeat(10_000);
+    let re = RegexBuilder::new("(a|b)+")
+        .state_limit(1 << 10)
+        .build()
+        .unwrap();
+    assert!(re.try_is_match(&hay).is_err());
+    assert!(re.is_match(&hay));
+    assert_eq!(re.find(&hay).map(|m| m.end()), Some(hay.len()));
+}
+
+#[test]
+fn greedy_single_char_backs_off_over_multibyte_chars() {
+    let re = Regex::new("(.*)é").unwrap();
+    let caps = re.captures("aébéc").unwrap();
+    assert_eq!(&caps[1], "aéb");
+}
+
+#[test]
+fn leading_repetition_is_not_rescanned_from_each_start() {
+    let hay = "a".repeat(200_000);
+    for pattern in ["a*[bc]", "a+?[bc]", "(a|b)*[cd]", "(a|b)+?[cd]"] {
+        let re = Regex::new(pattern).unwrap();
+        let (missing, re2) = (hay.clone(), re.clone());
+        assert!(!within(10, move || re2.is_match(&missing)), "{}", pattern);
+        let ending = hay.clone() + "c";
+        assert_eq!(within(10, move || re.find(&ending).map(|m| m.start())), Some(0));
+    }
+}
+
+#[test]
+fn starts_a_leading_repetition_rules_out_are_only_its_own() {
+    let all = |pattern: &str, hay: &str| -> Vec<String> {
+        let re = Regex::new(pattern).unwrap();
+        re.find_iter(hay).map(|m| m.as_str().to_string()).collect()
+    };
+    assert_eq!(all("(ab)+c", "ababa babc"), ["abc"]);
+    assert_eq!(all("a*b", "aaxab"), ["ab"]);
+    assert_eq!(all("[ab]*?c", "aaxbc"), ["bc"]);
+    // A backreference can tell starts apart, so none are ruled out.
+    assert_eq!(all("(a)*\\1b", "aab"), ["aab"]);
+    let re = Regex::new("a*[bc]").unwrap();
+    assert_eq!(re.shortest_match("aaxb"), Some(4));
+}
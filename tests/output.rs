@@ -0,0 +1,63 @@
+//! `--output` writes results to a file, which is never searched itself,
+//! replacing it once they are all written or, with `--append`, adding to
+//! it.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+#[test]
+fn writes_results_to_the_file() {
+    let dir = scratch_dir("output-write");
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    fs::write(dir.join("out.txt"), "old results\n").unwrap();
+    let out = putao(&dir)
+        .args(["--output", "out.txt", "hello", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(out.stdout.is_empty());
+    let written = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(written, "hello\n");
+}
+
+#[test]
+fn does_not_search_the_output_file() {
+    let dir = scratch_dir("output-excluded");
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    fs::write(dir.join("out.txt"), "hello from before\n").unwrap();
+    for _ in 0..2 {
+        let out = putao(&dir)
+            .args(["-r", "--output", "out.txt", "hello", "."])
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        let written = fs::read_to_string(dir.join("out.txt")).unwrap();
+        assert_eq!(written, "./a.txt:hello\n");
+    }
+}
+
+#[test]
+fn appends_to_the_file() {
+    let dir = scratch_dir("output-append");
+    fs::write(dir.join("a.txt"), "hello\n").unwrap();
+    fs::write(dir.join("out.txt"), "earlier\n").unwrap();
+    for _ in 0..2 {
+        let out = putao(&dir)
+            .args(["-r", "--output", "out.txt", "--append", "hello", "."])
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+    }
+    let written = fs::read_to_string(dir.join("out.txt")).unwrap();
+    assert_eq!(written, "earlier\n./a.txt:hello\n./a.txt:hello\n");
+}
+
+#[test]
+fn append_needs_output() {
+    let dir = scratch_dir("output-append-alone");
+    let out = putao(&dir).args(["--append", "hello", "."]).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+}
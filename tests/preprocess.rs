@@ -0,0 +1,72 @@
+//! Files read through a command, a decompressor or `--pre`, are never
+//! named to it in a way it could read as an option, and it is never left
+//! waiting on what it writes to stderr.
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use common::{putao, scratch_dir};
+
+#[test]
+fn decompresses_a_file_named_like_an_option() {
+    let dir = scratch_dir("pipe-decompress");
+    let mut gzip = Command::new("gzip")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    gzip.stdin.take().unwrap().write_all(b"hello\n").unwrap();
+    let out = gzip.wait_with_output().unwrap();
+    fs::write(dir.join("--help.gz"), out.stdout).unwrap();
+    let out = putao(&dir)
+        .args(["--search-zip", "hello", "--", "--help.gz"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "");
+    assert_eq!(out.stdout, b"hello\n");
+}
+
+#[test]
+fn pre_is_given_a_path_not_an_option() {
+    let dir = scratch_dir("pipe-pre");
+    let pre = dir.join("pre.sh");
+    fs::write(&pre, "#!/bin/sh\nprintf 'arg:%s\\n' \"$1\"\ncat\n").unwrap();
+    fs::set_permissions(&pre, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(dir.join("-x.txt"), "hi\n").unwrap();
+    let out = putao(&dir)
+        .args(["--pre", "./pre.sh", "-e", "arg", "-e", "hi", "--", "-x.txt"])
+        .output()
+        .unwrap();
+    assert_eq!(out.stdout, b"arg:./-x.txt\nhi\n");
+}
+
+#[test]
+fn pre_writing_much_to_stderr_does_not_stall() {
+    let dir = scratch_dir("pipe-stderr");
+    let pre = dir.join("pre.sh");
+    let script = "#!/bin/sh\nhead -c 200000 /dev/zero | tr '\\0' x >&2\ncat\n";
+    fs::write(&pre, script).unwrap();
+    fs::set_permissions(&pre, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(dir.join("a.txt"), "hi\n").unwrap();
+    let cmd = putao(&dir)
+        .args(["--pre", "./pre.sh", "hi", "a.txt"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let id = cmd.id().to_string();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || tx.send(cmd.wait_with_output().unwrap()).unwrap());
+    let Ok(out) = rx.recv_timeout(Duration::from_secs(10)) else {
+        let _ = Command::new("kill").args(["-9", &id]).status();
+        panic!("putao waited on --pre");
+    };
+    assert_eq!(out.stdout, b"hi\n");
+}
@@ -0,0 +1,62 @@
+//! `--preset` patterns match whole addresses, dates and UUIDs, never part
+//! of a longer run of digits, in every syntax.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+/// Returns what `-o --preset name` prints for each line of hay, given
+/// syntax flags.
+fn found(name: &str, syntax: &str, hay: &str) -> String {
+    let dir = scratch_dir(&format!("preset-{}{}", name, syntax));
+    fs::write(dir.join("a.txt"), hay).unwrap();
+    let out = putao(&dir)
+        .args([syntax, "-o", "--preset", name, "a.txt"])
+        .output()
+        .unwrap();
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn ipv4() {
+    for syntax in ["-E", "-G", "-P"] {
+        let hay = "at 10.0.0.255 and 255.255.255.0\n999.1.1.1\n1.2.3.456\n1.2.3\n";
+        assert_eq!(found("ipv4", syntax, hay), "10.0.0.255\n255.255.255.0\n");
+    }
+}
+
+#[test]
+fn iso_date() {
+    for syntax in ["-E", "-G"] {
+        let hay = "on 2024-02-29.\n12024-02-29\n2024-02-291\n2024-13-01\n";
+        assert_eq!(found("iso-date", syntax, hay), "2024-02-29\n");
+    }
+}
+
+#[test]
+fn uuid() {
+    for syntax in ["-E", "-G"] {
+        let hay = concat!(
+            "id=123e4567-e89b-12d3-a456-426614174000;\n",
+            "0123e4567-e89b-12d3-a456-426614174000\n",
+            "123e4567-e89b-12d3-a456-426614174000ff\n",
+        );
+        assert_eq!(found("uuid", syntax, hay), "123e4567-e89b-12d3-a456-426614174000\n");
+    }
+}
+
+#[test]
+fn email() {
+    let hay = "mail a.b+c@example.co.uk today\nno@tld\n";
+    assert_eq!(found("email", "-E", hay), "a.b+c@example.co.uk\n");
+}
+
+#[test]
+fn unknown_preset() {
+    let dir = scratch_dir("preset-unknown");
+    let out = putao(&dir).args(["--preset", "nope", "."]).output().unwrap();
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--preset-list"));
+}
@@ -0,0 +1,50 @@
+//! A project's `.putaorc` gives default flags to searches under it, but
+//! never ones that run commands or write files.
+
+mod common;
+
+use std::fs;
+
+use common::{putao, scratch_dir};
+
+#[test]
+fn reads_flags_from_the_project() {
+    let dir = scratch_dir("putaorc-read");
+    fs::write(dir.join(".putaorc"), "# shared\n--ignore-case\n").unwrap();
+    fs::write(dir.join("a.txt"), "FOO\n").unwrap();
+    let out = putao(&dir).args(["foo", "a.txt"]).output().unwrap();
+    assert_eq!(out.stdout, b"FOO\n");
+}
+
+#[test]
+fn ignores_unsafe_flags() {
+    let dir = scratch_dir("putaorc-unsafe");
+    let rc = "--replace=pwned\n--write\n--backup-suffix=.bak\n--output=out.txt\n--pre=touch\n";
+    fs::write(dir.join(".putaorc"), rc).unwrap();
+    fs::write(dir.join("a.txt"), "foo\n").unwrap();
+    let out = putao(&dir).args(["foo", "a.txt"]).output().unwrap();
+    assert!(out.status.success());
+    // `--replace` only changes what is printed, so it is kept.
+    assert_eq!(out.stdout, b"pwned\n");
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "foo\n");
+    for made in ["a.txt.bak", "out.txt"] {
+        assert!(!dir.join(made).exists(), "{} was written", made);
+    }
+    let err = String::from_utf8_lossy(&out.stderr);
+    for flag in ["--write", "--backup-suffix", "--output", "--pre"] {
+        assert!(err.contains(&format!("{} is not read", flag)), "{}", err);
+    }
+}
+
+#[test]
+fn command_line_still_gives_unsafe_flags() {
+    let dir = scratch_dir("putaorc-command-line");
+    fs::write(dir.join(".putaorc"), "--write\n").unwrap();
+    fs::write(dir.join("a.txt"), "foo\n").unwrap();
+    let out = putao(&dir)
+        .args(["--replace=bar", "--write", "foo", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "bar\n");
+}
@@ -0,0 +1,47 @@
+//! Repetitions of sub-patterns that can match the empty string end rather
+//! than looping at the same position.
+
+use putao_cf::Regex;
+
+/// Returns the leftmost match of pattern in hay.
+fn find(pattern: &str, hay: &str) -> Option<String> {
+    let re = Regex::new(pattern).unwrap();
+    re.find(hay).map(|m| m.as_str().to_string())
+}
+
+#[test]
+fn star_of_nullable_group_terminates() {
+    assert_eq!(find("(a?)*x", "aax").as_deref(), Some("aax"));
+    assert_eq!(find("(a?)*x", "bbb"), None);
+    assert_eq!(find("(a*)*b", "aaac"), None);
+    assert_eq!(find("((a?)*)*c", "aac").as_deref(), Some("aac"));
+}
+
+#[test]
+fn plus_of_nullable_group_terminates() {
+    assert_eq!(find("()+", "abc").as_deref(), Some(""));
+    assert_eq!(find("(a|)+b", "aab").as_deref(), Some("aab"));
+    assert_eq!(find("(a|)+b", "aac"), None);
+    assert_eq!(find("(a*)+$", "aa").as_deref(), Some("aa"));
+}
+
+#[test]
+fn counted_repetition_of_nullable_group_terminates() {
+    assert_eq!(find("(a?){3,}x", "ax").as_deref(), Some("ax"));
+    assert_eq!(find("(a?){2,5}x", "aaax").as_deref(), Some("aaax"));
+    assert_eq!(find("(a?){3,}x", "aay"), None);
+}
+
+#[test]
+fn lazy_repetition_of_nullable_group_terminates() {
+    assert_eq!(find("(a?)*?x", "aax").as_deref(), Some("aax"));
+    assert_eq!(find("(|a)*?b", "aac"), None);
+}
+
+#[test]
+fn empty_iteration_keeps_earlier_captures() {
+    let re = Regex::new("(a|)*b").unwrap();
+    let caps = re.captures("aab").unwrap();
+    assert_eq!(&caps[0], "aab");
+    assert_eq!(&caps[1], "a");
+}
@@ -0,0 +1,70 @@
+//! Compiled patterns round-trip through serde, and loading refuses data
+//! that is not a compiled pattern in the format this version writes.
+
+#![cfg(feature = "serde")]
+
+use putao_cf::{bytes, Regex, RegexBuilder};
+
+/// Returns re serialized as JSON.
+fn json(re: &Regex) -> String {
+    serde_json::to_string(re).unwrap()
+}
+
+#[test]
+fn round_trips() {
+    let re = RegexBuilder::new(r"(?<word>\w+)-(\d+)\1")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    let loaded: Regex = serde_json::from_str(&json(&re)).unwrap();
+    assert_eq!(loaded.as_str(), re.as_str());
+    assert_eq!(loaded.capture_index("word"), Some(1));
+    let caps = loaded.captures("x AB-12ab").unwrap();
+    assert_eq!(&caps[0], "AB-12ab");
+    assert_eq!(&caps[2], "12");
+}
+
+#[test]
+fn round_trips_many_patterns_and_bytes() {
+    let re = RegexBuilder::new_many(["^foo", "bar$"]).build().unwrap();
+    let loaded: Regex = serde_json::from_str(&json(&re)).unwrap();
+    let found: Vec<_> = loaded.find_iter("foo bar").map(|m| m.as_str()).collect();
+    assert_eq!(found, ["foo", "bar"]);
+
+    let re = bytes::Regex::new("a.c").unwrap();
+    let text = serde_json::to_string(&re).unwrap();
+    let loaded: bytes::Regex = serde_json::from_str(&text).unwrap();
+    assert!(loaded.is_match(b"a\xffc"));
+}
+
+#[test]
+fn refuses_another_format() {
+    let text = json(&Regex::new("a").unwrap()).replace(r#""format":1"#, r#""format":99"#);
+    let err = serde_json::from_str::<Regex>(&text).unwrap_err();
+    assert!(err.to_string().contains("unsupported format"), "{}", err);
+}
+
+#[test]
+fn refuses_missing_group_names() {
+    let text = json(&Regex::new("a").unwrap()).replace(r#""names":[null]"#, r#""names":[]"#);
+    assert!(serde_json::from_str::<Regex>(&text).is_err());
+}
+
+#[test]
+fn refuses_groups_out_of_range() {
+    let text = json(&Regex::new(r"(a)\1").unwrap());
+    for (from, to) in [(r#"{"Ref":1}"#, r#"{"Ref":7}"#), (r#"{"Ref":1}"#, r#"{"Ref":0}"#)] {
+        assert!(text.contains(from), "{}", text);
+        let err = serde_json::from_str::<Regex>(&text.replace(from, to)).unwrap_err();
+        assert!(err.to_string().contains("group"), "{}", err);
+    }
+    assert!(text.contains(r#"{"Cap":[1,"#), "{}", text);
+    let text = text.replace(r#"{"Cap":[1,"#, r#"{"Cap":[0,"#);
+    assert!(serde_json::from_str::<Regex>(&text).is_err());
+}
+
+#[test]
+fn refuses_data_that_is_not_a_regex() {
+    assert!(serde_json::from_str::<Regex>(r#"{"pattern":"a"}"#).is_err());
+    assert!(serde_json::from_str::<Regex>("[1,2,3]").is_err());
+}
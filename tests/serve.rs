@@ -0,0 +1,83 @@
+//! `--serve` requests can only search, and only the server's user can
+//! connect to its socket.
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Stdio;
+
+use common::{putao, scratch_dir};
+
+/// Sends each request line to a server on stdin, returning its responses.
+fn serve(dir: &Path, requests: &[&str]) -> String {
+    let mut child = putao(dir)
+        .args(["--serve", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for request in requests {
+        writeln!(stdin, "{}", request).unwrap();
+    }
+    drop(stdin);
+    let out = child.wait_with_output().unwrap();
+    String::from_utf8(out.stdout).unwrap()
+}
+
+#[test]
+fn requests_cannot_run_commands_or_write_files() {
+    let dir = scratch_dir("serve-unsafe");
+    fs::write(dir.join("a.txt"), "one\n").unwrap();
+    let flags = [
+        "--pre=cat",
+        "--extractor=txt=cat",
+        "--write",
+        "--backup-suffix=.bak",
+        "--output=out.txt",
+        "--append",
+    ];
+    for flag in flags {
+        let request = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"search","params":{{"args":["{}","one","a.txt"]}}}}"#,
+            flag
+        );
+        let response = serve(&dir, &[&request]);
+        assert!(response.contains(r#""error""#), "{}: {}", flag, response);
+        assert!(response.contains("cannot give"), "{}: {}", flag, response);
+    }
+    assert!(!dir.join("out.txt").exists());
+    let request = r#"{"jsonrpc":"2.0","id":1,"method":"search","params":{"args":["one","a.txt"]}}"#;
+    assert!(serve(&dir, &[request]).contains(r#""output":"one\n""#));
+}
+
+#[cfg(unix)]
+#[test]
+fn socket_is_private_to_its_user() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::Duration;
+
+    let dir = scratch_dir("serve-socket");
+    let socket = dir.join("putao.sock");
+    let mut child = putao(&dir).arg("--serve").arg(&socket).spawn().unwrap();
+    let mut conn = None;
+    for _ in 0..100 {
+        if let Ok(c) = UnixStream::connect(&socket) {
+            conn = Some(c);
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    let mut conn = conn.expect("server never listened");
+    let mode = fs::metadata(&socket).unwrap().permissions().mode();
+    writeln!(conn, r#"{{"jsonrpc":"2.0","id":1,"method":"shutdown"}}"#).unwrap();
+    let mut response = String::new();
+    BufReader::new(&conn).read_line(&mut response).unwrap();
+    child.wait().unwrap();
+    assert_eq!(mode & 0o777, 0o600);
+}
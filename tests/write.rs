@@ -0,0 +1,90 @@
+//! `--write` rewrites files with their matches replaced: through a
+//! temporary file renamed over the original, keeping its permissions, and
+//! leaving a backup if `--backup-suffix` asks for one.
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+use common::{putao, scratch_dir};
+
+#[test]
+fn replaces_matches_in_place() {
+    let dir = scratch_dir("write-in-place");
+    fs::write(dir.join("a.txt"), "foo one\nbar\nfoo two\n").unwrap();
+    let out = putao(&dir)
+        .args(["--replace=baz", "--write", "foo", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let text = fs::read_to_string(dir.join("a.txt")).unwrap();
+    assert_eq!(text, "baz one\nbar\nbaz two\n");
+}
+
+#[test]
+fn keeps_a_backup_with_the_suffix() {
+    let dir = scratch_dir("write-backup");
+    fs::write(dir.join("a.txt"), "foo\n").unwrap();
+    let out = putao(&dir)
+        .args(["--replace=bar", "--write", "--backup-suffix=.orig", "foo", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "bar\n");
+    assert_eq!(fs::read_to_string(dir.join("a.txt.orig")).unwrap(), "foo\n");
+}
+
+#[test]
+fn renames_a_new_file_over_the_old() {
+    let dir = scratch_dir("write-rename");
+    let path = dir.join("a.txt");
+    fs::write(&path, "foo\n").unwrap();
+    // A file held open keeps what it held before the rewrite.
+    let held = fs::File::open(&path).unwrap();
+    let before = fs::metadata(&path).unwrap().ino();
+    let out = putao(&dir)
+        .args(["--replace=quux", "--write", "foo", "a.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_ne!(fs::metadata(&path).unwrap().ino(), before);
+    assert_eq!(held.metadata().unwrap().len(), 4);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "quux\n");
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.txt"], "no temporary file is left behind");
+}
+
+#[test]
+fn keeps_permissions() {
+    let dir = scratch_dir("write-permissions");
+    let path = dir.join("a.sh");
+    fs::write(&path, "echo foo\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+    let out = putao(&dir)
+        .args(["--replace=bar", "--write", "foo", "a.sh"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "echo bar\n");
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
+
+#[test]
+fn rewrites_the_target_of_a_link() {
+    let dir = scratch_dir("write-link");
+    fs::write(dir.join("a.txt"), "foo\n").unwrap();
+    std::os::unix::fs::symlink("a.txt", dir.join("link.txt")).unwrap();
+    let out = putao(&dir)
+        .args(["--replace=bar", "--write", "foo", "link.txt"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert!(fs::symlink_metadata(dir.join("link.txt")).unwrap().is_symlink());
+    assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "bar\n");
+}
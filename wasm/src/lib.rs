@@ -0,0 +1,4 @@
+//! JavaScript bindings for the putao regex engine: the library's `wasm`
+//! module, built as a cdylib by `wasm-pack build wasm`.
+
+pub use putao_cf::wasm::*;